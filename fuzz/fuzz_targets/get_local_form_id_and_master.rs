@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use mod_mapper::plugin_processor::get_local_form_id_and_master;
+
+fuzz_target!(|input: (u32, Vec<String>)| {
+    let (form_id, masters) = input;
+    let masters: Vec<&str> = masters.iter().map(String::as_str).collect();
+    let _ = get_local_form_id_and_master(form_id, &masters, "fuzz.esp");
+});
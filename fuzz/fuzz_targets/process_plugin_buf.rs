@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use mod_mapper::plugin_processor::process_plugin_buf;
+
+// Any input must either parse or return an error, never panic.
+fuzz_target!(|data: &[u8]| {
+    let _ = process_plugin_buf(data, "fuzz.esp");
+});
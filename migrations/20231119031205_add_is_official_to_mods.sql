@@ -0,0 +1 @@
+ALTER TABLE "mods" ADD COLUMN "is_official" BOOL NOT NULL DEFAULT FALSE;
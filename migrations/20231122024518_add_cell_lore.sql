@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS "cell_lore" (
+    "id" SERIAL PRIMARY KEY NOT NULL,
+    "world_id" INTEGER REFERENCES "worlds"(id) NOT NULL,
+    "x" INTEGER NOT NULL,
+    "y" INTEGER NOT NULL,
+    "name" VARCHAR(255) NOT NULL,
+    "wiki_page" VARCHAR(255),
+    "created_at" timestamp(3) NOT NULL,
+    "updated_at" timestamp(3) NOT NULL
+);
+CREATE UNIQUE INDEX "cell_lore_unique_world_id_x_y" ON "cell_lore" ("world_id", "x", "y");
@@ -0,0 +1 @@
+CREATE INDEX ON plugins (lower(file_name));
@@ -0,0 +1,99 @@
+-- Cells and worlds are unique per game, since games like Skyrim SE and Skyrim VR share master
+-- names (e.g. Skyrim.esm) but not necessarily the same records.
+ALTER TABLE "worlds" ADD COLUMN "game_id" INTEGER REFERENCES "games"(id);
+ALTER TABLE "cells" ADD COLUMN "game_id" INTEGER REFERENCES "games"(id);
+
+DROP INDEX "worlds_unique_form_id_and_master";
+CREATE UNIQUE INDEX "worlds_unique_form_id_master_and_game_id" ON "worlds" ("form_id", "master", "game_id");
+DROP INDEX "cells_unique_form_id_master_and_world_id";
+CREATE UNIQUE INDEX "cells_unique_form_id_master_world_id_and_game_id" ON "cells" ("form_id", "master", "world_id", "game_id") NULLS NOT DISTINCT;
+
+-- Rows that no mod references (e.g. the base game cells from `--backfill-is-base-game`) belong to
+-- Skyrim SE
+INSERT INTO "games" ("name", "nexus_game_id", "created_at", "updated_at")
+    VALUES ('skyrimspecialedition', 1704, now(), now())
+    ON CONFLICT DO NOTHING;
+
+-- Existing rows are kept by Skyrim SE (so the world ids used by the dumps do not change) or else the
+-- first game that references them, and copied for every other game that references them
+UPDATE "worlds" SET "game_id" = "world_games"."game_id"
+    FROM (
+        SELECT "plugin_worlds"."world_id", (array_agg("mods"."game_id" ORDER BY "mods"."game_id" <> "sse"."id", "mods"."game_id"))[1] AS "game_id"
+        FROM "plugin_worlds"
+        JOIN "plugins" ON "plugins"."id" = "plugin_worlds"."plugin_id"
+        JOIN "mods" ON "mods"."id" = "plugins"."mod_id"
+        CROSS JOIN (SELECT "id" FROM "games" WHERE "name" = 'skyrimspecialedition' ORDER BY "id" LIMIT 1) AS "sse"
+        GROUP BY "plugin_worlds"."world_id"
+    ) AS "world_games"
+    WHERE "worlds"."id" = "world_games"."world_id";
+UPDATE "worlds" SET "game_id" = (SELECT "id" FROM "games" WHERE "name" = 'skyrimspecialedition' ORDER BY "id" LIMIT 1)
+    WHERE "game_id" IS NULL;
+
+INSERT INTO "worlds" ("form_id", "master", "game_id", "created_at", "updated_at")
+    SELECT DISTINCT "worlds"."form_id", "worlds"."master", "mods"."game_id", "worlds"."created_at", now()
+    FROM "plugin_worlds"
+    JOIN "plugins" ON "plugins"."id" = "plugin_worlds"."plugin_id"
+    JOIN "mods" ON "mods"."id" = "plugins"."mod_id"
+    JOIN "worlds" ON "worlds"."id" = "plugin_worlds"."world_id"
+    WHERE "mods"."game_id" <> "worlds"."game_id";
+
+UPDATE "plugin_worlds" SET "world_id" = "copies"."id", "updated_at" = now()
+    FROM "plugins", "mods", "worlds" AS "originals", "worlds" AS "copies"
+    WHERE "plugins"."id" = "plugin_worlds"."plugin_id"
+    AND "mods"."id" = "plugins"."mod_id"
+    AND "originals"."id" = "plugin_worlds"."world_id"
+    AND "originals"."game_id" <> "mods"."game_id"
+    AND "copies"."form_id" = "originals"."form_id"
+    AND "copies"."master" = "originals"."master"
+    AND "copies"."game_id" = "mods"."game_id";
+
+UPDATE "cells" SET "game_id" = "cell_games"."game_id"
+    FROM (
+        SELECT "plugin_cells"."cell_id", (array_agg("mods"."game_id" ORDER BY "mods"."game_id" <> "sse"."id", "mods"."game_id"))[1] AS "game_id"
+        FROM "plugin_cells"
+        JOIN "mods" ON "mods"."id" = "plugin_cells"."mod_id"
+        CROSS JOIN (SELECT "id" FROM "games" WHERE "name" = 'skyrimspecialedition' ORDER BY "id" LIMIT 1) AS "sse"
+        GROUP BY "plugin_cells"."cell_id"
+    ) AS "cell_games"
+    WHERE "cells"."id" = "cell_games"."cell_id";
+UPDATE "cells" SET "game_id" = (SELECT "id" FROM "games" WHERE "name" = 'skyrimspecialedition' ORDER BY "id" LIMIT 1)
+    WHERE "game_id" IS NULL;
+
+INSERT INTO "cells" ("form_id", "master", "x", "y", "world_id", "is_persistent", "is_base_game", "game_id", "created_at", "updated_at")
+    SELECT DISTINCT ON ("cells"."id", "mods"."game_id")
+        "cells"."form_id", "cells"."master", "cells"."x", "cells"."y", "cells"."world_id",
+        "cells"."is_persistent", "cells"."is_base_game", "mods"."game_id", "cells"."created_at", now()
+    FROM "plugin_cells"
+    JOIN "mods" ON "mods"."id" = "plugin_cells"."mod_id"
+    JOIN "cells" ON "cells"."id" = "plugin_cells"."cell_id"
+    WHERE "mods"."game_id" <> "cells"."game_id";
+
+UPDATE "plugin_cells" SET "cell_id" = "copies"."id", "updated_at" = now()
+    FROM "mods", "cells" AS "originals", "cells" AS "copies"
+    WHERE "mods"."id" = "plugin_cells"."mod_id"
+    AND "originals"."id" = "plugin_cells"."cell_id"
+    AND "originals"."game_id" <> "mods"."game_id"
+    AND "copies"."form_id" = "originals"."form_id"
+    AND "copies"."master" = "originals"."master"
+    AND "copies"."world_id" IS NOT DISTINCT FROM "originals"."world_id"
+    AND "copies"."game_id" = "mods"."game_id";
+
+-- Point every cell at the copy of its world in the cell's own game
+INSERT INTO "worlds" ("form_id", "master", "game_id", "created_at", "updated_at")
+    SELECT DISTINCT "worlds"."form_id", "worlds"."master", "cells"."game_id", "worlds"."created_at", now()
+    FROM "cells"
+    JOIN "worlds" ON "worlds"."id" = "cells"."world_id"
+    WHERE "cells"."game_id" <> "worlds"."game_id"
+    ON CONFLICT DO NOTHING;
+
+UPDATE "cells" SET "world_id" = "copies"."id", "updated_at" = now()
+    FROM "worlds" AS "originals", "worlds" AS "copies"
+    WHERE "originals"."id" = "cells"."world_id"
+    AND "originals"."game_id" <> "cells"."game_id"
+    AND "copies"."form_id" = "originals"."form_id"
+    AND "copies"."master" = "originals"."master"
+    AND "copies"."game_id" = "cells"."game_id";
+
+ALTER TABLE "worlds" ALTER COLUMN "game_id" SET NOT NULL;
+ALTER TABLE "cells" ALTER COLUMN "game_id" SET NOT NULL;
+CREATE INDEX "cells_game_id" ON "cells" ("game_id");
@@ -0,0 +1,3 @@
+ALTER TABLE "plugins" ADD COLUMN "npc_count" INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE "plugins" ADD COLUMN "quest_count" INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE "plugins" ADD COLUMN "dialogue_count" INTEGER NOT NULL DEFAULT 0;
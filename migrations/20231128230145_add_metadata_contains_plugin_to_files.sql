@@ -0,0 +1 @@
+ALTER TABLE "files" ADD COLUMN "metadata_contains_plugin" BOOLEAN;
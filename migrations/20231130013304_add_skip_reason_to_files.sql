@@ -0,0 +1,2 @@
+-- Why a file was not downloaded even though it may be a MAIN file (e.g. "save_game" or "preset")
+ALTER TABLE "files" ADD COLUMN "skip_reason" TEXT;
@@ -0,0 +1,6 @@
+/* NULL until filled by --backfill-graphql-fields */
+ALTER TABLE "mods" ADD COLUMN "is_adult" BOOLEAN;
+ALTER TABLE "mods" ADD COLUMN "downloads" INTEGER;
+/* exact times, unlike the day-truncated first_upload_at and last_update_at scraped from the mod list */
+ALTER TABLE "mods" ADD COLUMN "nexus_created_at" TIMESTAMP(3);
+ALTER TABLE "mods" ADD COLUMN "nexus_updated_at" TIMESTAMP(3);
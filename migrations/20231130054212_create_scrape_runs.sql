@@ -0,0 +1,12 @@
+-- The progress of each pass of `update` through a game's mod list (the translations pass walks its
+-- own list), so an interrupted pass resumes where it stopped instead of starting over
+CREATE TABLE IF NOT EXISTS "scrape_runs" (
+    "id" SERIAL PRIMARY KEY NOT NULL,
+    "game_id" INTEGER REFERENCES "games"(id) NOT NULL,
+    "include_translations" BOOLEAN NOT NULL,
+    "page" INTEGER NOT NULL,
+    "finished_at" timestamp(3),
+    "created_at" timestamp(3) NOT NULL,
+    "updated_at" timestamp(3) NOT NULL
+);
+CREATE UNIQUE INDEX "scrape_runs_unique_unfinished_game_id_and_include_translations" ON "scrape_runs" ("game_id", "include_translations") WHERE "finished_at" IS NULL;
@@ -0,0 +1,15 @@
+ALTER TABLE "plugins" ADD COLUMN "is_patch" BOOLEAN NOT NULL DEFAULT false;
+
+-- Same heuristic as `plugin_processor::is_patch`
+WITH "community_master_counts" AS (
+    SELECT "id", (
+        SELECT COUNT(*) FROM unnest("masters") AS "master"
+        WHERE lower("master") NOT IN ('skyrim.esm', 'update.esm', 'dawnguard.esm', 'hearthfires.esm', 'dragonborn.esm', '_resourcepack.esl')
+        AND lower("master") NOT LIKE 'cc___sse%'
+    ) AS "count"
+    FROM "plugins"
+)
+UPDATE "plugins" SET "is_patch" = true
+    FROM "community_master_counts"
+    WHERE "plugins"."id" = "community_master_counts"."id"
+    AND ("community_master_counts"."count" >= 2 OR ("community_master_counts"."count" >= 1 AND lower("plugins"."file_name") LIKE '%patch%'));
@@ -0,0 +1,14 @@
+-- When the file first and last appeared in a mod's file list during an update, as opposed to
+-- uploaded_at which is when Nexus says the file was uploaded
+ALTER TABLE "files" ADD COLUMN "first_seen_at" TIMESTAMP(3);
+ALTER TABLE "files" ADD COLUMN "last_seen_at" TIMESTAMP(3);
+
+/* Backfill existing files using the created_at and updated_at timestamps.
+ *
+ * This is approximate since files that were already processed were not updated when they were
+ * seen again.
+ */
+UPDATE "files" SET "first_seen_at" = "created_at", "last_seen_at" = "updated_at";
+
+ALTER TABLE "files" ALTER COLUMN "first_seen_at" SET NOT NULL;
+ALTER TABLE "files" ALTER COLUMN "last_seen_at" SET NOT NULL;
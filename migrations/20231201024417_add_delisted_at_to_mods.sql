@@ -0,0 +1,3 @@
+-- When the mod was first found to be missing from nexus (deleted or hidden by its author or
+-- moderators). Reset to null if the mod reappears.
+ALTER TABLE "mods" ADD COLUMN "delisted_at" TIMESTAMP(3);
@@ -0,0 +1,24 @@
+-- Number of translation mods of the mod, see game_mod::update_translation_counts
+ALTER TABLE "mods" ADD COLUMN "translation_count" INTEGER NOT NULL DEFAULT 0;
+
+WITH "originals" AS (
+    SELECT DISTINCT ON ("mods"."game_id", lower("plugins"."file_name"))
+        "mods"."game_id", lower("plugins"."file_name") AS "file_name", "plugins"."mod_id"
+    FROM "plugins"
+    JOIN "mods" ON "mods"."id" = "plugins"."mod_id"
+    WHERE NOT "mods"."is_translation" AND NOT "mods"."is_official"
+    ORDER BY "mods"."game_id", lower("plugins"."file_name"), "plugins"."mod_id"
+), "translations" AS (
+    SELECT DISTINCT "mods"."game_id", lower("plugins"."file_name") AS "file_name", "plugins"."mod_id"
+    FROM "plugins"
+    JOIN "mods" ON "mods"."id" = "plugins"."mod_id"
+    WHERE "mods"."is_translation"
+), "counts" AS (
+    SELECT "originals"."mod_id", COUNT(DISTINCT "translations"."mod_id") AS "count"
+    FROM "originals"
+    JOIN "translations" ON "translations"."game_id" = "originals"."game_id" AND "translations"."file_name" = "originals"."file_name"
+    GROUP BY "originals"."mod_id"
+)
+UPDATE "mods" SET "translation_count" = "counts"."count"
+    FROM "counts"
+    WHERE "mods"."id" = "counts"."mod_id";
@@ -0,0 +1,4 @@
+-- Set when the mod ran out of its time budget during an update, so the rest of its files were left
+-- for the next update to process. Reset when all of the mod's files are processed.
+ALTER TABLE "mods" ADD COLUMN "files_deferred_at" TIMESTAMP(3);
+ALTER TABLE "mods" ADD COLUMN "deferred_file_count" INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,12 @@
+-- Links between a mod and its port to another game (usually a Skyrim Special Edition conversion
+-- of a Legendary Edition mod), found by matching plugin hashes or the author and name of the mods
+CREATE TABLE IF NOT EXISTS "mod_ports" (
+    "id" SERIAL PRIMARY KEY NOT NULL,
+    "original_mod_id" INTEGER REFERENCES "mods"(id) NOT NULL,
+    "port_mod_id" INTEGER REFERENCES "mods"(id) NOT NULL,
+    "match_reason" VARCHAR(255) NOT NULL,
+    "created_at" timestamp(3) NOT NULL,
+    "updated_at" timestamp(3) NOT NULL
+);
+CREATE UNIQUE INDEX "mod_ports_unique_original_mod_id_and_port_mod_id" ON "mod_ports" ("original_mod_id", "port_mod_id");
+CREATE INDEX "mod_ports_port_mod_id" ON "mod_ports" ("port_mod_id");
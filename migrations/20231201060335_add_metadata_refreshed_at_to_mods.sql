@@ -0,0 +1,4 @@
+-- Set when the mod's description and thumbnail were last refreshed from the API by the background
+-- metadata refresh, which cycles through the mods refreshed (or updated) longest ago first
+ALTER TABLE "mods" ADD COLUMN "metadata_refreshed_at" TIMESTAMP(3);
+CREATE INDEX "mods_metadata_refreshed_at_or_updated_at" ON "mods" ((COALESCE("metadata_refreshed_at", "updated_at")));
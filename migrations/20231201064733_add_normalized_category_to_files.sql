@@ -0,0 +1,4 @@
+-- The category of the file mapped to one of a fixed set of values (see `FileCategory`), since the
+-- raw `category` names from the API vary in spelling and language. Filled in for existing files by
+-- --backfill-normalized-categories.
+ALTER TABLE "files" ADD COLUMN "normalized_category" VARCHAR(255);
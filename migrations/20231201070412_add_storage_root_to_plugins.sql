@@ -0,0 +1,3 @@
+-- The storage root (see `plugin_storage`) the plugin was written to disk in. NULL for plugins
+-- written before roots were configurable, which are in the default `plugins` folder.
+ALTER TABLE "plugins" ADD COLUMN "storage_root" TEXT;
@@ -0,0 +1,3 @@
+-- When the plugin file was moved to cold storage by --tier-plugins, compressed, into the folder in
+-- `storage_root`. NULL while it is in a regular storage root.
+ALTER TABLE "plugins" ADD COLUMN "tiered_at" timestamp(3);
@@ -0,0 +1,3 @@
+-- Which extractor got the plugins out of the file's archive (see `ExtractorUsed`), or "none" when
+-- it was downloaded but couldn't be extracted. NULL for files that were never extracted.
+ALTER TABLE "files" ADD COLUMN "extractor_used" TEXT;
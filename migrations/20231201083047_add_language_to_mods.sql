@@ -0,0 +1,3 @@
+-- The language of the mod as Nexus reports it (e.g. "English"), filled in from the GraphQL API by
+-- --backfill-graphql-fields. NULL until the mod has been fetched from the GraphQL API.
+ALTER TABLE "mods" ADD COLUMN "language" TEXT;
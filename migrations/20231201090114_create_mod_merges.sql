@@ -0,0 +1,11 @@
+-- Duplicate rows of a mod merged into another row by --merge-mods. The removed row is deleted, so
+-- its game and nexus mod id are kept here so its dumped files can be deleted too.
+CREATE TABLE IF NOT EXISTS "mod_merges" (
+    "id" SERIAL PRIMARY KEY NOT NULL,
+    "kept_mod_id" INTEGER REFERENCES "mods"(id) NOT NULL,
+    "removed_mod_id" INTEGER NOT NULL,
+    "removed_game_id" INTEGER REFERENCES "games"(id) NOT NULL,
+    "removed_nexus_mod_id" INTEGER NOT NULL,
+    "created_at" timestamp(3) NOT NULL
+);
+CREATE INDEX "mod_merges_kept_mod_id" ON "mod_merges" ("kept_mod_id");
@@ -0,0 +1,12 @@
+-- The mod categories of each game with their canonical English names from the API, since the mod
+-- list names categories in the language of the scraping session
+CREATE TABLE IF NOT EXISTS "categories" (
+    "id" SERIAL PRIMARY KEY NOT NULL,
+    "game_id" INTEGER REFERENCES "games"(id) NOT NULL,
+    "nexus_category_id" INTEGER NOT NULL,
+    "name" VARCHAR(255) NOT NULL,
+    "parent_nexus_category_id" INTEGER,
+    "created_at" timestamp(3) NOT NULL,
+    "updated_at" timestamp(3) NOT NULL
+);
+CREATE UNIQUE INDEX "categories_unique_game_id_and_nexus_category_id" ON "categories" ("game_id", "nexus_category_id");
@@ -0,0 +1,8 @@
+-- Backfills that must only change rows once (like converting mods.last_updated_files_at to UTC)
+-- record that they ran here, so running them again does nothing
+CREATE TABLE IF NOT EXISTS "backfill_runs" (
+    "id" SERIAL PRIMARY KEY NOT NULL,
+    "name" VARCHAR(255) NOT NULL,
+    "created_at" timestamp(3) NOT NULL
+);
+CREATE UNIQUE INDEX "backfill_runs_unique_name" ON "backfill_runs" ("name");
@@ -1,7 +1,13 @@
 #!/bin/bash
 export $(grep -v '^#' .env | xargs -d '\n')
 mkdir -p backups
-zip -r -9 backups/plugins.zip plugins -DF --out backups/plugins-update.zip
+for plugin_root in ${MODMAPPER_PLUGIN_ROOTS//:/ }; do
+    name=$(basename "$plugin_root")
+    zip -r -9 "backups/$name.zip" "$plugin_root" -DF --out "backups/$name-update.zip"
+done
+if [ -z "$MODMAPPER_PLUGIN_ROOTS" ]; then
+    zip -r -9 backups/plugins.zip plugins -DF --out backups/plugins-update.zip
+fi
 pg_dump -h localhost -U modmapper -Fc modmapper > backups/modmapper-$(date +'%Y-%m-%d').dump
 find backups/modmapper-*.dump -mtime +30 -type f -delete
 rclone sync backups ${BACKUP_SERVER_REMOTE}:${BACKUP_SERVER_BUCKET}
\ No newline at end of file
@@ -3,12 +3,13 @@ if [ -f cells/edits.json ]; then
     last_update_time=$(date -r cells/edits.json +'%Y-%m-%dT%H:%M:%S')
 fi
 mkdir -p logs
-./target/release/mod-mapper -g skyrimspecialedition &>> logs/modmapper.log
-./target/release/mod-mapper -g skyrim &>> logs/modmapper.log
+./target/release/mod-mapper -g skyrimspecialedition -g skyrim &>> logs/modmapper.log
+./target/release/mod-mapper --match-mod-ports &>> logs/modmapper.log
 mkdir -p cells
 mkdir -p mods
 mkdir -p files
 mkdir -p plugins_data
+mkdir -p plugin_names_data
 if [ -n "$last_update_time" ]; then
     ./target/release/mod-mapper -e cells/edits.json &>> logs/modmapper.log
     ./target/release/mod-mapper -c cells &>> logs/modmapper.log
@@ -16,8 +17,12 @@ if [ -n "$last_update_time" ]; then
     ./target/release/mod-mapper -s mods/skyrim/mod_search_index.json -g skyrim &>> logs/modmapper.log
     ./target/release/mod-mapper -M mods/mod_cell_counts.json &>> logs/modmapper.log
     ./target/release/mod-mapper -G mods/games.json &>> logs/modmapper.log
+    ./target/release/mod-mapper --category-stats mods/category_stats.json &>> logs/modmapper.log
+    ./target/release/mod-mapper --delisted-mods mods/delisted_mods.json &>> logs/modmapper.log
+    ./target/release/mod-mapper --mod-merges mods/mod_merges.json &>> logs/modmapper.log
     ./target/release/mod-mapper -m mods -u "$last_update_time" &>> logs/modmapper.log
     ./target/release/mod-mapper -P plugins_data -u "$last_update_time" &>> logs/modmapper.log
+    ./target/release/mod-mapper --plugin-file-name-data plugin_names_data -u "$last_update_time" &>> logs/modmapper.log
     ./target/release/mod-mapper -F files -u "$last_update_time" &>> logs/modmapper.log
 else
     ./target/release/mod-mapper -e cells/edits.json &>> logs/modmapper.log
@@ -26,7 +31,11 @@ else
     ./target/release/mod-mapper -s mods/skyrim/mod_search_index.json -g skyrim &>> logs/modmapper.log
     ./target/release/mod-mapper -M mods/mod_cell_counts.json &>> logs/modmapper.log
     ./target/release/mod-mapper -G mods/games.json &>> logs/modmapper.log
+    ./target/release/mod-mapper --category-stats mods/category_stats.json &>> logs/modmapper.log
+    ./target/release/mod-mapper --delisted-mods mods/delisted_mods.json &>> logs/modmapper.log
+    ./target/release/mod-mapper --mod-merges mods/mod_merges.json &>> logs/modmapper.log
     ./target/release/mod-mapper -m mods &>> logs/modmapper.log
     ./target/release/mod-mapper -P plugins_data &>> logs/modmapper.log
+    ./target/release/mod-mapper --plugin-file-name-data plugin_names_data &>> logs/modmapper.log
     ./target/release/mod-mapper -F files &>> logs/modmapper.log
 fi
\ No newline at end of file
@@ -0,0 +1,97 @@
+//! Downloaded archives kept on disk for a while after they are extracted, keyed by the database id
+//! of their file, so that retrying a failed extraction (or extracting again after an extractor is
+//! fixed) doesn't need another download. Archives are only kept once a retention is configured.
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::{Duration, SystemTime};
+use tokio::fs::File;
+use tracing::{debug, info};
+
+use crate::temp_dir;
+
+struct ArchiveCache {
+    dir: PathBuf,
+    retention: Duration,
+}
+
+static ARCHIVE_CACHE: RwLock<Option<ArchiveCache>> = RwLock::new(None);
+
+/// Keeps downloaded archives in the `archives` folder of the temp dir (see `temp_dir`) for
+/// `retention`. Call this at startup, after the temp dir is set.
+pub fn configure(retention: Duration) -> Result<()> {
+    let dir = temp_dir::get().join("archives");
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("Failed to create archive cache {}", dir.display()))?;
+    *ARCHIVE_CACHE
+        .write()
+        .expect("archive cache lock is not poisoned") = Some(ArchiveCache { dir, retention });
+    Ok(())
+}
+
+fn config() -> Option<(PathBuf, Duration)> {
+    ARCHIVE_CACHE
+        .read()
+        .expect("archive cache lock is not poisoned")
+        .as_ref()
+        .map(|cache| (cache.dir.clone(), cache.retention))
+}
+
+/// Path the archive of the file is kept at in the cache folder `dir`
+pub fn archive_path(dir: &Path, file_id: i32) -> PathBuf {
+    dir.join(format!("{}.archive", file_id))
+}
+
+pub fn is_expired(modified: SystemTime, now: SystemTime, retention: Duration) -> bool {
+    now.duration_since(modified)
+        .map_or(false, |age| age > retention)
+}
+
+/// Where to download the archive of the file to, if archives are being kept
+pub fn download_path(file_id: i32) -> Option<PathBuf> {
+    config().map(|(dir, _)| archive_path(&dir, file_id))
+}
+
+/// Opens the kept archive of the file, unless there is none or it has expired
+pub async fn open(file_id: i32) -> Result<Option<File>> {
+    let (dir, retention) = match config() {
+        Some(config) => config,
+        None => return Ok(None),
+    };
+    let path = archive_path(&dir, file_id);
+    let modified = match tokio::fs::metadata(&path).await {
+        Ok(metadata) => metadata.modified()?,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    if is_expired(modified, SystemTime::now(), retention) {
+        return Ok(None);
+    }
+    debug!(path = %path.display(), "opening kept archive");
+    Ok(Some(File::open(&path).await?))
+}
+
+/// Removes archives (and interrupted downloads) in `dir` older than `retention`. Returns how many
+/// were removed.
+pub fn prune_dir(dir: &Path, retention: Duration) -> Result<usize> {
+    let now = SystemTime::now();
+    let mut removed = 0;
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_file() && is_expired(metadata.modified()?, now, retention) {
+            std::fs::remove_file(entry.path())?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// Removes expired archives from the cache, if archives are being kept
+pub fn prune() -> Result<()> {
+    if let Some((dir, retention)) = config() {
+        let removed = prune_dir(&dir, retention)?;
+        info!(removed, dir = %dir.display(), "removed expired archives");
+    }
+    Ok(())
+}
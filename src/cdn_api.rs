@@ -0,0 +1,80 @@
+use anyhow::{Context, Result};
+use reqwest::Client;
+use serde_json::json;
+use std::env;
+use std::str::FromStr;
+use tracing::{info, instrument};
+
+/// Cloudflare accepts at most this many urls per purge request
+const CLOUDFLARE_PURGE_BATCH_SIZE: usize = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CdnProvider {
+    Cloudflare,
+    Fastly,
+}
+
+impl FromStr for CdnProvider {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cloudflare" => Ok(CdnProvider::Cloudflare),
+            "fastly" => Ok(CdnProvider::Fastly),
+            _ => Err(format!("invalid cdn provider: {}", s)),
+        }
+    }
+}
+
+/// Purges the urls from the Cloudflare zone in `CLOUDFLARE_ZONE_ID` using the token in
+/// `CLOUDFLARE_API_TOKEN`
+#[instrument(skip(client, urls), fields(num_urls = urls.len()))]
+async fn purge_cloudflare(client: &Client, urls: &[String]) -> Result<()> {
+    let zone_id = env::var("CLOUDFLARE_ZONE_ID").context("CLOUDFLARE_ZONE_ID is not set")?;
+    let token = env::var("CLOUDFLARE_API_TOKEN").context("CLOUDFLARE_API_TOKEN is not set")?;
+    for batch in urls.chunks(CLOUDFLARE_PURGE_BATCH_SIZE) {
+        let res = client
+            .post(format!(
+                "https://api.cloudflare.com/client/v4/zones/{}/purge_cache",
+                zone_id
+            ))
+            .bearer_auth(&token)
+            .json(&json!({ "files": batch }))
+            .send()
+            .await?
+            .error_for_status()
+            .context("Failed to purge urls from Cloudflare")?;
+        info!(status = %res.status(), num_urls = batch.len(), "purged urls from Cloudflare");
+    }
+    Ok(())
+}
+
+/// Purges the urls from Fastly one at a time (Fastly has no batch url purge) using the token in
+/// `FASTLY_API_TOKEN`
+#[instrument(skip(client, urls), fields(num_urls = urls.len()))]
+async fn purge_fastly(client: &Client, urls: &[String]) -> Result<()> {
+    let token = env::var("FASTLY_API_TOKEN").context("FASTLY_API_TOKEN is not set")?;
+    for url in urls {
+        let cached_url = url
+            .trim_start_matches("https://")
+            .trim_start_matches("http://");
+        client
+            .post(format!("https://api.fastly.com/purge/{}", cached_url))
+            .header("Fastly-Key", &token)
+            .send()
+            .await?
+            .error_for_status()
+            .with_context(|| format!("Failed to purge {} from Fastly", url))?;
+    }
+    info!("purged urls from Fastly");
+    Ok(())
+}
+
+pub async fn purge(client: &Client, provider: CdnProvider, urls: &[String]) -> Result<()> {
+    if urls.is_empty() {
+        return Ok(());
+    }
+    match provider {
+        CdnProvider::Cloudflare => purge_cloudflare(client, urls).await,
+        CdnProvider::Fastly => purge_fastly(client, urls).await,
+    }
+}
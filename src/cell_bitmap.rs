@@ -0,0 +1,85 @@
+//! Compact encoding of a set of exterior cells as a bitset over the Skyrim worldspace grid, which
+//! is much smaller than a list of coordinates for mods that edit a large part of the map.
+//!
+//! The grid covers the cells from `grid::MIN_CELL_X` and `grid::MIN_CELL_Y` up to (but not
+//! including) `grid::MAX_CELL_X` and `grid::MAX_CELL_Y`. Bit
+//! `(y - MIN_CELL_Y) * WIDTH + (x - MIN_CELL_X)` is set for every cell (x, y) in the set, with bits
+//! numbered from the least significant bit of the first byte. The bytes are base64 encoded.
+//!
+//! The bitmap is a fixed size, so mods that edit only a few cells are smaller as a list of
+//! coordinates; `encode_if_smaller` only encodes the cells when the bitmap is smaller.
+use anyhow::{anyhow, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+use crate::grid::{MAX_CELL_X, MAX_CELL_Y, MIN_CELL_X, MIN_CELL_Y};
+
+pub const WIDTH: usize = (MAX_CELL_X - MIN_CELL_X) as usize;
+pub const HEIGHT: usize = (MAX_CELL_Y - MIN_CELL_Y) as usize;
+const BYTE_LENGTH: usize = (WIDTH * HEIGHT + 7) / 8;
+
+fn bit_index(x: i32, y: i32) -> Option<usize> {
+    if (MIN_CELL_X..MAX_CELL_X).contains(&x) && (MIN_CELL_Y..MAX_CELL_Y).contains(&y) {
+        Some((y - MIN_CELL_Y) as usize * WIDTH + (x - MIN_CELL_X) as usize)
+    } else {
+        None
+    }
+}
+
+/// Returns the base64 encoded bitset of the cells, and the cells that are outside the grid so
+/// couldn't be encoded.
+pub fn encode(cells: &[(i32, i32)]) -> (String, Vec<(i32, i32)>) {
+    let mut bytes = vec![0u8; BYTE_LENGTH];
+    let mut outside = vec![];
+    for &(x, y) in cells {
+        match bit_index(x, y) {
+            Some(index) => bytes[index / 8] |= 1 << (index % 8),
+            None => outside.push((x, y)),
+        }
+    }
+    (STANDARD.encode(bytes), outside)
+}
+
+/// Returns the cells set in a bitset made by `encode`, sorted by y then x
+pub fn decode(bitmap: &str) -> Result<Vec<(i32, i32)>> {
+    let bytes = STANDARD.decode(bitmap)?;
+    if bytes.len() != BYTE_LENGTH {
+        return Err(anyhow!(
+            "cell bitmap is {} bytes instead of {}",
+            bytes.len(),
+            BYTE_LENGTH
+        ));
+    }
+    Ok((0..WIDTH * HEIGHT)
+        .filter(|index| bytes[index / 8] & (1 << (index % 8)) != 0)
+        .map(|index| {
+            (
+                (index % WIDTH) as i32 + MIN_CELL_X,
+                (index / WIDTH) as i32 + MIN_CELL_Y,
+            )
+        })
+        .collect())
+}
+
+/// Length of the cells written as a JSON list of `{"x": x, "y": y}` objects, like in mod dumps
+fn coordinate_list_len(cells: &[(i32, i32)]) -> usize {
+    serde_json::to_string(
+        &cells
+            .iter()
+            .map(|&(x, y)| serde_json::json!({ "x": x, "y": y }))
+            .collect::<Vec<_>>(),
+    )
+    .map(|json| json.len())
+    .unwrap_or(0)
+}
+
+/// Like `encode`, but returns `None` when the bitmap and the list of cells outside the grid would
+/// be no smaller than the list of all the cells
+pub fn encode_if_smaller(cells: &[(i32, i32)]) -> Option<(String, Vec<(i32, i32)>)> {
+    let (bitmap, outside) = encode(cells);
+    if bitmap.len() + coordinate_list_len(&outside) < coordinate_list_len(cells) {
+        Some((bitmap, outside))
+    } else {
+        None
+    }
+}
@@ -0,0 +1,92 @@
+//! Guesses how likely a mod is to edit cells on the map, so that `update` can process the mods
+//! most likely to show up on the map first when API quota or time is limited. The mod list scraper
+//! doesn't get a mod's tags, so this only goes by the category and any file metadata checked in
+//! earlier updates.
+
+/// Words in the names of categories whose mods usually add or edit locations
+const LOCATION_CATEGORY_WORDS: &[&str] = &[
+    "location",
+    "town",
+    "village",
+    "city",
+    "cities",
+    "dungeon",
+    "building",
+    "castle",
+    "home",
+    "house",
+    "landscape",
+    "environment",
+    "world",
+];
+
+/// Words in the names of categories whose mods sometimes place things in the world
+const WORLD_CATEGORY_WORDS: &[&str] = &[
+    "quest",
+    "adventure",
+    "npc",
+    "follower",
+    "companion",
+    "creature",
+    "overhaul",
+    "immersion",
+    "gameplay",
+    "patch",
+];
+
+/// Words in the names of categories whose mods are almost always assets without cell edits
+const ASSET_CATEGORY_WORDS: &[&str] = &[
+    "texture",
+    "model",
+    "animation",
+    "interface",
+    "audio",
+    "sound",
+    "music",
+    "voice",
+    "preset",
+    "enb",
+    "reshade",
+    "body",
+    "face",
+    "hair",
+    "visual",
+    "utilit",
+    "screenshot",
+    "save",
+];
+
+fn category_score(category_name: &str) -> i32 {
+    let category_name = category_name.to_lowercase();
+    let has_word = |words: &[&str]| words.iter().any(|word| category_name.contains(word));
+    if has_word(LOCATION_CATEGORY_WORDS) {
+        3
+    } else if has_word(WORLD_CATEGORY_WORDS) {
+        1
+    } else if has_word(ASSET_CATEGORY_WORDS) {
+        -2
+    } else {
+        0
+    }
+}
+
+/// Higher scores are more likely to have cell edits. `files_checked` is the number of the mod's
+/// files whose metadata was checked for plugins and `files_with_plugin` how many of them had one.
+pub fn score(category_name: Option<&str>, files_checked: i64, files_with_plugin: i64) -> i32 {
+    let metadata_score = if files_with_plugin > 0 {
+        2
+    } else if files_checked > 0 {
+        -3
+    } else {
+        0
+    };
+    category_name.map(category_score).unwrap_or(0) + metadata_score
+}
+
+/// Sorts a page of mods from the highest score to the lowest, keeping the listed order of mods
+/// with the same score. `update` saves its progress through the mod list a page at a time, so mods
+/// are only ever reordered within the page they were listed on: a likely location mod on page 2 is
+/// still processed after every mod on page 1.
+pub fn sort_page<T>(mods: &mut [T], score: impl Fn(&T) -> i32) {
+    mods.sort_by_key(|db_mod| std::cmp::Reverse(score(db_mod)));
+}
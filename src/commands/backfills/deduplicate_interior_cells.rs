@@ -39,7 +39,7 @@ pub async fn deduplicate_interior_cells(pool: &sqlx::Pool<sqlx::Postgres>) -> Re
                 master
             FROM cells
             WHERE world_id IS NULL
-            GROUP BY (form_id, master)
+            GROUP BY (form_id, master, game_id)
             HAVING COUNT(*) > 1
             LIMIT $1
             "#,
@@ -79,6 +79,9 @@ pub async fn deduplicate_interior_cells(pool: &sqlx::Pool<sqlx::Postgres>) -> Re
                 .map(|cell| cell.id)
                 .collect::<Vec<_>>();
 
+            // All of the updates for one duplicate cell are applied together or not at all
+            let mut tx = pool.begin().await?;
+
             // First, I need to fix-up any duplicated plugin_cells rows caused by broken
             // plugins that have multiple cells with the same form_id. For these duplicate
             // plugin_cells with the same plugin_id, I just arbitrarily choose one and delete
@@ -100,7 +103,7 @@ pub async fn deduplicate_interior_cells(pool: &sqlx::Pool<sqlx::Postgres>) -> Re
                 "#,
                 &duplicate_ids
             )
-            .execute(pool)
+            .execute(&mut *tx)
             .await?;
             info!(
                 "deleted {} duplicate plugin_cells from broken plugins",
@@ -116,7 +119,7 @@ pub async fn deduplicate_interior_cells(pool: &sqlx::Pool<sqlx::Postgres>) -> Re
                 chosen_cell.id,
                 &duplicate_ids
             )
-            .execute(pool)
+            .execute(&mut *tx)
             .await?;
             info!("updated {} plugin_cells", update.rows_affected());
 
@@ -127,8 +130,9 @@ pub async fn deduplicate_interior_cells(pool: &sqlx::Pool<sqlx::Postgres>) -> Re
                 chosen_cell.id,
                 &duplicate_ids
             )
-            .execute(pool)
+            .execute(&mut *tx)
             .await?;
+            tx.commit().await?;
             info!("deleted {} cells", delete.rows_affected());
         }
         page += 1;
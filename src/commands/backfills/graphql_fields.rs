@@ -0,0 +1,122 @@
+use anyhow::Result;
+use reqwest::header::{HeaderMap, HeaderValue};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use tracing::{info, info_span, warn, Instrument};
+
+use crate::models::{game, game_mod};
+use crate::nexus_api::graphql::{self, GRAPHQL_MODS_PAGE_SIZE};
+use crate::nexus_api::{RateLimiter, USER_AGENT};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Fills the columns that the HTML scraper never provided (adult flag, download count, language,
+/// and exact upload and update times) for every mod saved so far by re-fetching the mods from the GraphQL
+/// API in batches. Mods the API no longer returns are marked as delisted.
+pub async fn backfill_graphql_fields(pool: &sqlx::Pool<sqlx::Postgres>) -> Result<()> {
+    let mut headers = HeaderMap::new();
+    headers.insert("user-agent", HeaderValue::from_static(USER_AGENT));
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .connect_timeout(CONNECT_TIMEOUT)
+        .default_headers(headers)
+        .build()?;
+    let rate_limiter = RateLimiter::default();
+
+    let games = game::get_all(pool).await?;
+    let game_names: HashMap<i32, &str> = games
+        .iter()
+        .map(|game| (game.id, game.name.as_str()))
+        .collect();
+    let game_ids: HashMap<&str, i32> = games
+        .iter()
+        .map(|game| (game.name.as_str(), game.id))
+        .collect();
+
+    let mut last_id = None;
+    let mut updated = 0;
+    let mut delisted = 0;
+    loop {
+        let mods = game_mod::batched_get(pool, GRAPHQL_MODS_PAGE_SIZE as i64, last_id).await?;
+        if mods.is_empty() {
+            break;
+        }
+        last_id = mods.last().map(|m| m.id);
+        let batch_span = info_span!("batch", ?last_id);
+        async {
+            // official content mods are not on nexus
+            let ids: Vec<(&str, i32)> = mods
+                .iter()
+                .filter(|m| !m.is_official)
+                .filter_map(|m| {
+                    game_names
+                        .get(&m.game_id)
+                        .map(|game_name| (*game_name, m.nexus_mod_id))
+                })
+                .collect();
+            if ids.is_empty() {
+                return Ok(());
+            }
+            let graphql::GraphQLMods {
+                mods: graphql_mods,
+                invalid,
+            } = graphql::get_mods(&client, &rate_limiter, &ids).await?;
+            // mods returned in a shape we couldn't parse are still on nexus
+            let returned: HashSet<(&str, i32)> = graphql_mods
+                .iter()
+                .map(|graphql_mod| (graphql_mod.game.domain_name.as_str(), graphql_mod.mod_id))
+                .chain(
+                    invalid
+                        .iter()
+                        .map(|(game_name, mod_id)| (game_name.as_str(), *mod_id)),
+                )
+                .collect();
+            let delisted_ids: Vec<i32> = mods
+                .iter()
+                .filter(|m| !m.is_official)
+                .filter(|m| {
+                    game_names
+                        .get(&m.game_id)
+                        .map(|game_name| !returned.contains(&(*game_name, m.nexus_mod_id)))
+                        .unwrap_or(false)
+                })
+                .map(|m| m.id)
+                .collect();
+            if !delisted_ids.is_empty() {
+                warn!(
+                    requested = ids.len(),
+                    returned = graphql_mods.len(),
+                    "some mods are no longer on nexus"
+                );
+                delisted += game_mod::batched_update_delisted(pool, &delisted_ids)
+                    .await?
+                    .len();
+            }
+
+            let mut graphql_mods_by_game: HashMap<i32, Vec<graphql::GraphQLMod>> = HashMap::new();
+            for graphql_mod in graphql_mods {
+                match game_ids.get(graphql_mod.game.domain_name.as_str()) {
+                    Some(game_id) => graphql_mods_by_game
+                        .entry(*game_id)
+                        .or_default()
+                        .push(graphql_mod),
+                    None => warn!(
+                        domain_name = %graphql_mod.game.domain_name,
+                        "GraphQL API returned a mod for an unknown game"
+                    ),
+                }
+            }
+            for (game_id, graphql_mods) in graphql_mods_by_game {
+                updated += game_mod::batched_update_from_graphql(pool, game_id, &graphql_mods)
+                    .await?
+                    .len();
+            }
+            info!(updated, delisted, "updated mods from GraphQL API");
+            Ok::<(), anyhow::Error>(())
+        }
+        .instrument(batch_span)
+        .await?;
+    }
+    Ok(())
+}
@@ -7,7 +7,9 @@ use skyrim_cell_dump::Plugin;
 use tracing::info;
 
 use crate::models::cell::{self, UnsavedCell};
+use crate::models::game;
 use crate::models::world::{self, UnsavedWorld};
+use crate::nexus_api::{SSE_GAME_ID, SSE_GAME_NAME};
 use crate::plugin_processor::get_local_form_id_and_master;
 
 pub async fn backfill_is_base_game(pool: &sqlx::Pool<sqlx::Postgres>) -> Result<()> {
@@ -17,6 +19,9 @@ pub async fn backfill_is_base_game(pool: &sqlx::Pool<sqlx::Postgres>) -> Result<
         serde_json::from_reader(reader).context("failed to deserialize data/skyrim.json")?;
     let file_name = "Skyrim.esm";
     let masters: Vec<&str> = plugin.header.masters.iter().map(|s| s.borrow()).collect();
+    let mut tx = pool.begin().await?;
+    // data/skyrim.json is a dump of the Skyrim SE Skyrim.esm
+    let game = game::insert(&mut *tx, SSE_GAME_NAME, SSE_GAME_ID).await?;
     let base_worlds: Vec<UnsavedWorld> = plugin
         .worlds
         .iter()
@@ -24,10 +29,14 @@ pub async fn backfill_is_base_game(pool: &sqlx::Pool<sqlx::Postgres>) -> Result<
             let (form_id, master) =
                 get_local_form_id_and_master(world.form_id, &masters, file_name)
                     .expect("form_id to be a valid i32");
-            UnsavedWorld { form_id, master }
+            UnsavedWorld {
+                form_id,
+                master,
+                game_id: game.id,
+            }
         })
         .collect();
-    let db_worlds = world::batched_insert(pool, &base_worlds).await?;
+    let db_worlds = world::batched_insert(&mut *tx, &base_worlds).await?;
     info!("Upserted {} Skyrim.esm base worlds", db_worlds.len());
     let base_cells: Vec<UnsavedCell> = plugin
         .cells
@@ -57,10 +66,12 @@ pub async fn backfill_is_base_game(pool: &sqlx::Pool<sqlx::Postgres>) -> Result<
                 world_id,
                 is_persistent: cell.is_persistent,
                 is_base_game: true, // the whole point of this function
+                game_id: game.id,
             }
         })
         .collect();
-    let db_cells = cell::batched_insert(pool, &base_cells).await?;
+    let db_cells = cell::batched_insert(&mut *tx, &base_cells).await?;
+    tx.commit().await?;
     info!("Upserted {} Skyrim.esm base cells", db_cells.len());
     // This works for exterior cells, but there's a bug with the unique index on cells that
     // creates duplicate interior cells. To fix that, I need to upgrade postgres to
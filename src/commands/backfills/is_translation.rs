@@ -4,7 +4,7 @@ use tokio::time::sleep;
 use tracing::{debug, info, info_span};
 
 use crate::nexus_api::{SSE_GAME_ID, SSE_GAME_NAME};
-use crate::nexus_scraper;
+use crate::nexus_scraper::{self, ModsSortField, SortDirection};
 
 const REQUEST_TIMEOUT: Duration = Duration::from_secs(7200); // 2 hours
 const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
@@ -13,6 +13,8 @@ struct UpdatedMods {
     id: i32,
 }
 
+/// Marks every mod in the translations mod list as a translation. The list is read oldest upload
+/// first, so that mods updated while the backfill runs don't shift the pages it hasn't read yet.
 pub async fn backfill_is_translation(pool: &sqlx::Pool<sqlx::Postgres>) -> Result<()> {
     let mut page = 0;
     let mut has_next_page = true;
@@ -25,8 +27,16 @@ pub async fn backfill_is_translation(pool: &sqlx::Pool<sqlx::Postgres>) -> Resul
     while has_next_page {
         let page_span = info_span!("page", page);
         let _page_span = page_span.enter();
-        let mod_list_resp =
-            nexus_scraper::get_mod_list_page(&client, page, SSE_GAME_NAME, SSE_GAME_ID, true).await?;
+        let mod_list_resp = nexus_scraper::get_mod_list_page(
+            &client,
+            page,
+            SSE_GAME_NAME,
+            SSE_GAME_ID,
+            true,
+            ModsSortField::CreatedAt,
+            SortDirection::Asc,
+        )
+        .await?;
         let scraped = mod_list_resp.scrape_mods()?;
         let scraped_ids: Vec<i32> = scraped.mods.iter().map(|m| m.nexus_mod_id).collect();
 
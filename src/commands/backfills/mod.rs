@@ -1,7 +1,13 @@
 pub mod deduplicate_interior_cells;
+pub mod graphql_fields;
 pub mod is_translation;
 pub mod is_base_game;
+pub mod normalized_categories;
+pub mod utc_dates;
 
 pub use deduplicate_interior_cells::deduplicate_interior_cells;
+pub use graphql_fields::backfill_graphql_fields;
 pub use is_translation::backfill_is_translation;
 pub use is_base_game::backfill_is_base_game;
+pub use normalized_categories::backfill_normalized_categories;
+pub use utc_dates::backfill_utc_dates;
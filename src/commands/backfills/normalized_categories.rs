@@ -0,0 +1,27 @@
+use anyhow::Result;
+use tracing::{info, warn};
+
+use crate::models::file;
+use crate::nexus_api::files::FileCategory;
+
+/// Fills in the normalized_category column of the files table from the raw category names, which
+/// are mapped with the same rules used for new files
+pub async fn backfill_normalized_categories(pool: &sqlx::Pool<sqlx::Postgres>) -> Result<()> {
+    let categories = file::get_distinct_categories(pool).await?;
+    for category in categories {
+        let normalized_category = FileCategory::from_name(&category);
+        if normalized_category.is_none() {
+            warn!(category = %category, "unrecognized file category, leaving it unnormalized");
+        }
+        let updated =
+            file::update_normalized_category_by_category(pool, &category, normalized_category)
+                .await?;
+        info!(
+            category = %category,
+            normalized_category = normalized_category.map(|category| category.as_str()),
+            updated,
+            "normalized file category"
+        );
+    }
+    Ok(())
+}
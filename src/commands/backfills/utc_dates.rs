@@ -0,0 +1,67 @@
+use anyhow::{Context, Result};
+use tracing::info;
+
+/// Normalizes the mod date columns that `update` compares to UTC.
+///
+/// Scraped `last_update_at` and `first_upload_at` dates are truncated to the start of their day
+/// (official content mods keep their file modified times). `last_updated_files_at` values were
+/// saved in `source_time_zone` (the time zone of the database session that wrote them) and are
+/// converted to UTC. That conversion is not idempotent, so it is recorded in `backfill_runs` and
+/// skipped when the backfill is run again.
+pub async fn backfill_utc_dates(
+    pool: &sqlx::Pool<sqlx::Postgres>,
+    source_time_zone: &str,
+) -> Result<()> {
+    let mut tx = pool.begin().await?;
+
+    let truncated = sqlx::query!(
+        "UPDATE mods
+            SET last_update_at = date_trunc('day', last_update_at),
+                first_upload_at = date_trunc('day', first_upload_at)
+            WHERE is_official = false
+            AND (
+                last_update_at <> date_trunc('day', last_update_at)
+                OR first_upload_at <> date_trunc('day', first_upload_at)
+            )"
+    )
+    .execute(&mut *tx)
+    .await
+    .context("Failed to truncate mod dates to UTC day boundaries")?
+    .rows_affected();
+    info!(
+        truncated,
+        "truncated scraped mod dates to UTC day boundaries"
+    );
+
+    let first_conversion = sqlx::query_scalar!(
+        "INSERT INTO backfill_runs (name, created_at)
+            VALUES ('utc_dates', now())
+            ON CONFLICT (name) DO NOTHING
+            RETURNING id"
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .context("Failed to record utc_dates backfill run")?
+    .is_some();
+    if !first_conversion {
+        info!("mod last_updated_files_at was already converted to UTC, skipping");
+    } else if !source_time_zone.eq_ignore_ascii_case("UTC") {
+        let converted = sqlx::query!(
+            "UPDATE mods
+                SET last_updated_files_at = (last_updated_files_at AT TIME ZONE $1) AT TIME ZONE 'UTC'
+                WHERE last_updated_files_at IS NOT NULL",
+            source_time_zone,
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to convert mod last_updated_files_at to UTC")?
+        .rows_affected();
+        info!(
+            converted,
+            source_time_zone, "converted mod last_updated_files_at to UTC"
+        );
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
@@ -0,0 +1,191 @@
+//! Shell completions and a man page generated from the argh definition of the command line, so
+//! they never fall out of date with the flags.
+use argh::{CommandInfoWithArgs, FlagInfo, FlagInfoKind};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl FromStr for Shell {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bash" => Ok(Shell::Bash),
+            "zsh" => Ok(Shell::Zsh),
+            "fish" => Ok(Shell::Fish),
+            _ => Err(format!("invalid shell: {}", s)),
+        }
+    }
+}
+
+fn visible_flags<'a>(info: &'a CommandInfoWithArgs<'a>) -> impl Iterator<Item = &'a FlagInfo<'a>> {
+    info.flags.iter().filter(|flag| !flag.hidden)
+}
+
+fn long_name<'a>(flag: &FlagInfo<'a>) -> &'a str {
+    flag.long.trim_start_matches('-')
+}
+
+fn arg_name<'a>(flag: &FlagInfo<'a>) -> Option<&'a str> {
+    match flag.kind {
+        FlagInfoKind::Switch => None,
+        FlagInfoKind::Option { arg_name } => Some(arg_name),
+    }
+}
+
+/// argh descriptions keep the line breaks of the doc comments they come from
+fn one_line(description: &str) -> String {
+    description
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+fn bash(info: &CommandInfoWithArgs, bin_name: &str) -> String {
+    let function_name = format!("_{}", bin_name.replace('-', "_"));
+    let mut words = vec![];
+    let mut options = vec![];
+    for flag in visible_flags(info) {
+        let mut names = vec![format!("--{}", long_name(flag))];
+        if let Some(short) = flag.short {
+            names.push(format!("-{}", short));
+        }
+        if arg_name(flag).is_some() {
+            options.extend(names.iter().cloned());
+        }
+        words.extend(names);
+    }
+    format!(
+        r#"{function_name}() {{
+    local cur prev
+    cur="${{COMP_WORDS[COMP_CWORD]}}"
+    prev="${{COMP_WORDS[COMP_CWORD-1]}}"
+    case "$prev" in
+        {options})
+            COMPREPLY=($(compgen -f -- "$cur"))
+            return
+            ;;
+    esac
+    COMPREPLY=($(compgen -W "{words}" -- "$cur"))
+}}
+complete -F {function_name} {bin_name}
+"#,
+        function_name = function_name,
+        options = options.join("|"),
+        words = words.join(" "),
+        bin_name = bin_name,
+    )
+}
+
+fn zsh_escape(description: &str) -> String {
+    one_line(description)
+        .replace('\\', "\\\\")
+        .replace('\'', "'\\''")
+        .replace('[', "\\[")
+        .replace(']', "\\]")
+        .replace(':', "\\:")
+}
+
+fn zsh(info: &CommandInfoWithArgs, bin_name: &str) -> String {
+    let mut specs = vec![];
+    for flag in visible_flags(info) {
+        let description = zsh_escape(flag.description);
+        let arg = arg_name(flag)
+            .map(|arg_name| format!(":{}:_files", arg_name))
+            .unwrap_or_default();
+        let repeat = if matches!(flag.optionality, argh::Optionality::Repeating) {
+            "*"
+        } else {
+            ""
+        };
+        specs.push(format!(
+            "    '{}--{}[{}]{}'",
+            repeat,
+            long_name(flag),
+            description,
+            arg
+        ));
+        if let Some(short) = flag.short {
+            specs.push(format!(
+                "    '{}-{}[{}]{}'",
+                repeat, short, description, arg
+            ));
+        }
+    }
+    format!(
+        "#compdef {}\n\n_arguments \\\n{}\n",
+        bin_name,
+        specs.join(" \\\n")
+    )
+}
+
+fn fish(info: &CommandInfoWithArgs, bin_name: &str) -> String {
+    let mut lines = vec![];
+    for flag in visible_flags(info) {
+        let mut line = format!("complete -c {} -l {}", bin_name, long_name(flag));
+        if let Some(short) = flag.short {
+            line.push_str(&format!(" -s {}", short));
+        }
+        if arg_name(flag).is_some() {
+            line.push_str(" -r");
+        }
+        line.push_str(&format!(
+            " -d '{}'",
+            one_line(flag.description)
+                .replace('\\', "\\\\")
+                .replace('\'', "\\'")
+        ));
+        lines.push(line);
+    }
+    lines.join("\n") + "\n"
+}
+
+/// Returns the completion script for `shell`
+pub fn completions(info: &CommandInfoWithArgs, bin_name: &str, shell: Shell) -> String {
+    match shell {
+        Shell::Bash => bash(info, bin_name),
+        Shell::Zsh => zsh(info, bin_name),
+        Shell::Fish => fish(info, bin_name),
+    }
+}
+
+fn roff_escape(text: &str) -> String {
+    let text = one_line(text).replace('\\', "\\e").replace('-', "\\-");
+    if text.starts_with('.') || text.starts_with('\'') {
+        format!("\\&{}", text)
+    } else {
+        text
+    }
+}
+
+/// Returns a man page (in roff) listing every flag
+pub fn man_page(info: &CommandInfoWithArgs, bin_name: &str) -> String {
+    let mut page = format!(
+        ".TH {} 1\n.SH NAME\n{} \\- {}\n.SH SYNOPSIS\n.B {}\n[\\fIOPTIONS\\fR]\n.SH OPTIONS\n",
+        bin_name.to_uppercase(),
+        roff_escape(bin_name),
+        roff_escape(info.description),
+        roff_escape(bin_name),
+    );
+    for flag in visible_flags(info) {
+        let mut names = vec![];
+        if let Some(short) = flag.short {
+            names.push(format!("\\fB\\-{}\\fR", short));
+        }
+        names.push(format!("\\fB\\-\\-{}\\fR", roff_escape(long_name(flag))));
+        let mut heading = names.join(", ");
+        if let Some(arg_name) = arg_name(flag) {
+            heading.push_str(&format!(" \\fI{}\\fR", roff_escape(arg_name)));
+        }
+        page.push_str(&format!(
+            ".TP\n{}\n{}\n",
+            heading,
+            roff_escape(flag.description)
+        ));
+    }
+    page
+}
@@ -0,0 +1,161 @@
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use tokio::fs::{create_dir_all, File};
+use tokio::io::AsyncWriteExt;
+use tracing::info;
+
+use crate::models::cell::{self, PluginCellForDiff};
+use crate::models::plugin::{self, Plugin};
+use crate::models::{format_radix, game, game_mod};
+
+/// The parts of a plugin that identify which version was diffed
+#[derive(Debug, Serialize)]
+pub struct DiffedPlugin {
+    /// base 36, like the plugin hashes in the dumps
+    pub hash: String,
+    pub file_name: String,
+    pub file_path: String,
+    pub file_id: i32,
+    pub mod_id: i32,
+}
+
+impl From<&Plugin> for DiffedPlugin {
+    fn from(plugin: &Plugin) -> Self {
+        DiffedPlugin {
+            hash: format_radix(plugin.hash as u64, 36),
+            file_name: plugin.file_name.clone(),
+            file_path: plugin.file_path.clone(),
+            file_id: plugin.file_id,
+            mod_id: plugin.mod_id,
+        }
+    }
+}
+
+/// A cell both plugins edit under different editor ids
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct ChangedCell {
+    #[serde(flatten)]
+    pub cell: PluginCellForDiff,
+    pub old_editor_id: Option<String>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct CellDiff {
+    pub added: Vec<PluginCellForDiff>,
+    pub removed: Vec<PluginCellForDiff>,
+    pub changed: Vec<ChangedCell>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PluginDiff {
+    pub old: DiffedPlugin,
+    pub new: DiffedPlugin,
+    #[serde(flatten)]
+    pub cells: CellDiff,
+}
+
+/// Compares the cells edited by two versions of a plugin. A cell edited by both is only listed as
+/// changed when its editor id differs, since the stored plugin cells don't record anything else
+/// about the edit. Every list is ordered by cell id.
+pub fn diff_cells(old: Vec<PluginCellForDiff>, mut new: Vec<PluginCellForDiff>) -> CellDiff {
+    let mut old: BTreeMap<i32, PluginCellForDiff> =
+        old.into_iter().map(|cell| (cell.cell_id, cell)).collect();
+    let mut diff = CellDiff::default();
+    new.sort_by_key(|cell| cell.cell_id);
+    new.dedup_by_key(|cell| cell.cell_id);
+    for cell in new {
+        match old.remove(&cell.cell_id) {
+            None => diff.added.push(cell),
+            Some(old_cell) if old_cell.editor_id != cell.editor_id => {
+                diff.changed.push(ChangedCell {
+                    cell,
+                    old_editor_id: old_cell.editor_id,
+                })
+            }
+            Some(_) => {}
+        }
+    }
+    diff.removed = old.into_values().collect();
+    diff
+}
+
+/// Parses a plugin hash written in base 36, like the hashes in the dumps
+pub fn parse_hash(hash: &str) -> Result<i64> {
+    Ok(u64::from_str_radix(hash.trim(), 36)
+        .with_context(|| format!("invalid plugin hash: {}", hash))? as i64)
+}
+
+async fn diff(
+    pool: &sqlx::Pool<sqlx::Postgres>,
+    old: &Plugin,
+    new: &Plugin,
+    dir: &str,
+) -> Result<()> {
+    let old_cells = cell::get_by_plugin_id(pool, old.id).await?;
+    let new_cells = cell::get_by_plugin_id(pool, new.id).await?;
+    let diff = PluginDiff {
+        old: old.into(),
+        new: new.into(),
+        cells: diff_cells(old_cells, new_cells),
+    };
+
+    let dir = Path::new(dir).join("plugin_diffs");
+    create_dir_all(&dir).await?;
+    let path = dir.join(format!("{}_{}.json", diff.old.hash, diff.new.hash));
+    let mut file = File::create(&path).await?;
+    file.write_all(serde_json::to_string_pretty(&diff)?.as_bytes())
+        .await?;
+    info!(
+        added = diff.cells.added.len(),
+        removed = diff.cells.removed.len(),
+        changed = diff.cells.changed.len(),
+        "wrote plugin diff to {}",
+        path.display()
+    );
+    Ok(())
+}
+
+/// Writes the cells added, removed, and changed between two plugins (given as base 36 hashes) to
+/// `<dir>/plugin_diffs/<old_hash>_<new_hash>.json`.
+pub async fn diff_plugins(
+    pool: &sqlx::Pool<sqlx::Postgres>,
+    old_hash: &str,
+    new_hash: &str,
+    dir: &str,
+) -> Result<()> {
+    let old = plugin::get_by_hash(pool, parse_hash(old_hash)?)
+        .await?
+        .ok_or_else(|| anyhow!("no plugin with hash {}", old_hash))?;
+    let new = plugin::get_by_hash(pool, parse_hash(new_hash)?)
+        .await?
+        .ok_or_else(|| anyhow!("no plugin with hash {}", new_hash))?;
+    diff(pool, &old, &new, dir).await
+}
+
+/// Diffs the latest two distinct versions of the plugin named `file_name` in a mod, going by when
+/// the files containing them were uploaded.
+pub async fn diff_plugin_versions(
+    pool: &sqlx::Pool<sqlx::Postgres>,
+    game_name: &str,
+    nexus_mod_id: i32,
+    file_name: &str,
+    dir: &str,
+) -> Result<()> {
+    let game_id = game::get_id_by_name(pool, game_name).await?;
+    let db_mod = game_mod::get_by_nexus_mod_id(pool, game_id, nexus_mod_id)
+        .await?
+        .ok_or_else(|| anyhow!("no mod with nexus id {} in {}", nexus_mod_id, game_name))?;
+    let mut versions = plugin::get_versions_by_file_name(pool, db_mod.id, file_name).await?;
+    // the same plugin is often re-uploaded unchanged in later files
+    versions.dedup_by_key(|plugin| plugin.hash);
+    match versions.as_slice() {
+        [.., old, new] => diff(pool, old, new, dir).await,
+        _ => Err(anyhow!(
+            "mod {} has fewer than two versions of {}",
+            nexus_mod_id,
+            file_name
+        )),
+    }
+}
@@ -1,33 +1,276 @@
-use anyhow::Result;
-use reqwest::Client;
-use std::fs::File;
+use anyhow::{anyhow, Result};
+use reqwest::{Client, StatusCode};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Duration;
-use tokio::time::sleep;
-use tracing::info;
+use tokio::sync::Mutex;
+use tokio::time::{sleep, Instant};
+use tracing::{info, warn};
 
-pub async fn download_tiles(dir: &str) -> Result<()> {
-    let client = Client::builder().build()?;
-    for z in 10..18 {
-        for x in 0..2_u32.pow(z - 9) {
-            for y in 0..2_u32.pow(z - 9) {
-                sleep(Duration::from_millis(100)).await;
-                let url = format!(
-                    "https://maps.uesp.net/srmap/color/zoom{z}/skyrim-{x}-{y}-{z}.jpg",
-                    z = z,
-                    x = x,
-                    y = y
-                );
-                let resp = client.get(&url).send().await?;
-                if resp.status().is_success() {
-                    info!(z = z, x = x, y = y, "fetched tile from {}", url);
-                    std::fs::create_dir_all(format!("{}/{z}/{x}", dir, z = z, x = x))?;
-                    let mut out =
-                        File::create(format!("{}/{z}/{x}/{y}.jpg", dir, z = z, x = x, y = y))?;
-                    let bytes = resp.bytes().await?;
-                    std::io::copy(&mut bytes.as_ref(), &mut out)?;
+use crate::grid::tiles_per_side;
+pub use crate::grid::{is_valid_tile, ZOOM_LEVELS};
+
+/// Minimum time between requests to UESP so that we don't overload their server
+const FETCH_INTERVAL: Duration = Duration::from_millis(100);
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long a tile UESP answered with a 404 is served as missing without asking UESP again
+pub const MISSING_TILE_TTL: Duration = Duration::from_secs(5 * 60);
+
+pub fn tile_url(z: u32, x: u32, y: u32) -> String {
+    format!(
+        "https://maps.uesp.net/srmap/color/zoom{z}/skyrim-{x}-{y}-{z}.jpg",
+        z = z,
+        x = x,
+        y = y
+    )
+}
+
+pub fn tile_path(dir: &Path, z: u32, x: u32, y: u32) -> PathBuf {
+    dir.join(z.to_string())
+        .join(x.to_string())
+        .join(format!("{}.jpg", y))
+}
+
+/// Parses a tile path like `10/0/1.jpg` into (z, x, y), returning `None` for paths that aren't a
+/// valid tile.
+pub fn parse_tile_path(path: &str) -> Option<(u32, u32, u32)> {
+    let mut parts = path.split('/');
+    let z = parts.next()?.parse().ok()?;
+    let x = parts.next()?.parse().ok()?;
+    let y = parts.next()?.strip_suffix(".jpg")?.parse().ok()?;
+    if parts.next().is_some() || !is_valid_tile(z, x, y) {
+        return None;
+    }
+    Some((z, x, y))
+}
+
+/// How far from the end of a JPEG to look for the end of image marker. Some encoders write
+/// padding or other trailing bytes after it.
+pub const JPEG_END_SEARCH_LEN: usize = 64;
+
+/// Whether `bytes` looks like a whole JPEG: it starts with the start of image marker and has the
+/// end of image marker near the end, which a download cut off part way through won't have.
+pub fn is_valid_jpeg(bytes: &[u8]) -> bool {
+    if bytes.len() <= 4 || !bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        return false;
+    }
+    let tail = &bytes[bytes.len().saturating_sub(JPEG_END_SEARCH_LEN).max(3)..];
+    tail.windows(2).any(|marker| marker == [0xFF, 0xD9])
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TileState {
+    Valid,
+    Missing,
+    /// Saved, but empty or not a whole JPEG (see `is_valid_jpeg`)
+    Corrupt,
+}
+
+/// Tiles UESP recently answered with a 404, so that requests for tiles it doesn't have aren't
+/// forwarded to it every time
+#[derive(Debug)]
+pub struct MissingTiles {
+    ttl: Duration,
+    seen_at: std::sync::Mutex<HashMap<(u32, u32, u32), Instant>>,
+}
+
+impl MissingTiles {
+    pub fn new(ttl: Duration) -> Self {
+        MissingTiles {
+            ttl,
+            seen_at: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn insert(&self, tile: (u32, u32, u32), now: Instant) {
+        self.seen_at
+            .lock()
+            .expect("missing tiles lock is not poisoned")
+            .insert(tile, now);
+    }
+
+    /// Whether the tile was missing within the last `ttl`, forgetting it once that has passed
+    pub fn contains(&self, tile: (u32, u32, u32), now: Instant) -> bool {
+        let mut seen_at = self
+            .seen_at
+            .lock()
+            .expect("missing tiles lock is not poisoned");
+        match seen_at.get(&tile) {
+            Some(&at) if now.saturating_duration_since(at) < self.ttl => true,
+            Some(_) => {
+                seen_at.remove(&tile);
+                false
+            }
+            None => false,
+        }
+    }
+}
+
+/// UESP map tiles saved in `dir` in the same `{z}/{x}/{y}.jpg` layout the map frontend requests.
+pub struct TileCache {
+    dir: PathBuf,
+    client: Client,
+    /// When the next request to UESP may start
+    next_fetch_at: std::sync::Mutex<Option<Instant>>,
+    /// Held while a tile is read and fetched, so concurrent requests for the same tile only fetch
+    /// it once while requests for other tiles go ahead
+    tile_locks: std::sync::Mutex<HashMap<(u32, u32, u32), Arc<Mutex<()>>>>,
+    missing: MissingTiles,
+}
+
+impl TileCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self> {
+        Ok(TileCache {
+            dir: dir.into(),
+            client: Client::builder()
+                .timeout(FETCH_TIMEOUT)
+                .connect_timeout(CONNECT_TIMEOUT)
+                .build()?,
+            next_fetch_at: std::sync::Mutex::new(None),
+            tile_locks: std::sync::Mutex::new(HashMap::new()),
+            missing: MissingTiles::new(MISSING_TILE_TTL),
+        })
+    }
+
+    /// Waits for the next free slot to request a tile in, so that requests start at least
+    /// `FETCH_INTERVAL` apart without waiting on each other to finish
+    async fn wait_for_fetch_slot(&self) {
+        let wait = {
+            let mut next_fetch_at = self
+                .next_fetch_at
+                .lock()
+                .expect("fetch slot lock is not poisoned");
+            let now = Instant::now();
+            let fetch_at = next_fetch_at.map_or(now, |next_fetch_at| next_fetch_at.max(now));
+            *next_fetch_at = Some(fetch_at + FETCH_INTERVAL);
+            fetch_at - now
+        };
+        sleep(wait).await;
+    }
+
+    /// Downloads the tile from UESP and saves it, returning `None` if UESP doesn't have it or sends
+    /// back something that isn't a whole JPEG.
+    pub async fn fetch(&self, z: u32, x: u32, y: u32) -> Result<Option<Vec<u8>>> {
+        self.wait_for_fetch_slot().await;
+        let url = tile_url(z, x, y);
+        let resp = self.client.get(&url).send().await?;
+        if resp.status() == StatusCode::NOT_FOUND {
+            self.missing.insert((z, x, y), Instant::now());
+            return Ok(None);
+        }
+        if !resp.status().is_success() {
+            return Ok(None);
+        }
+        info!(z = z, x = x, y = y, "fetched tile from {}", url);
+        let bytes = resp.bytes().await?.to_vec();
+        if !is_valid_jpeg(&bytes) {
+            warn!(
+                z,
+                x,
+                y,
+                bytes = bytes.len(),
+                "fetched invalid tile, not saving it"
+            );
+            return Ok(None);
+        }
+        let path = tile_path(&self.dir, z, x, y);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&path, &bytes).await?;
+        Ok(Some(bytes))
+    }
+
+    async fn read(&self, z: u32, x: u32, y: u32) -> Result<(TileState, Option<Vec<u8>>)> {
+        match tokio::fs::read(tile_path(&self.dir, z, x, y)).await {
+            Ok(bytes) if is_valid_jpeg(&bytes) => Ok((TileState::Valid, Some(bytes))),
+            Ok(_) => Ok((TileState::Corrupt, None)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                Ok((TileState::Missing, None))
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Checks the saved tile without fetching it
+    pub async fn check(&self, z: u32, x: u32, y: u32) -> Result<TileState> {
+        Ok(self.read(z, x, y).await?.0)
+    }
+
+    /// Returns the saved tile, fetching it from UESP if it hasn't been saved yet or is corrupt.
+    /// Tiles UESP answered with a 404 in the last `MISSING_TILE_TTL` aren't fetched again.
+    pub async fn get(&self, z: u32, x: u32, y: u32) -> Result<Option<Vec<u8>>> {
+        let tile_lock = self
+            .tile_locks
+            .lock()
+            .expect("tile locks lock is not poisoned")
+            .entry((z, x, y))
+            .or_default()
+            .clone();
+        let result = async {
+            let _tile_guard = tile_lock.lock().await;
+            match self.read(z, x, y).await? {
+                (TileState::Valid, bytes) => Ok(bytes),
+                _ if self.missing.contains((z, x, y), Instant::now()) => Ok(None),
+                (TileState::Corrupt, _) => {
+                    warn!(z, x, y, "saved tile is corrupt, fetching it again");
+                    self.fetch(z, x, y).await
                 }
+                (TileState::Missing, _) => self.fetch(z, x, y).await,
             }
         }
+        .await;
+        let mut tile_locks = self
+            .tile_locks
+            .lock()
+            .expect("tile locks lock is not poisoned");
+        // Only this request and the map still hold the lock, so no one is waiting on it
+        if Arc::strong_count(&tile_lock) == 2 {
+            tile_locks.remove(&(z, x, y));
+        }
+        result
+    }
+}
+
+fn all_tiles() -> impl Iterator<Item = (u32, u32, u32)> {
+    ZOOM_LEVELS.flat_map(|z| {
+        (0..tiles_per_side(z)).flat_map(move |x| (0..tiles_per_side(z)).map(move |y| (z, x, y)))
+    })
+}
+
+/// Downloads every tile that isn't saved in `dir` yet, replacing saved tiles that are corrupt
+pub async fn download_tiles(dir: &str) -> Result<()> {
+    let cache = TileCache::new(dir)?;
+    for (z, x, y) in all_tiles() {
+        cache.get(z, x, y).await?;
+    }
+    Ok(())
+}
+
+/// Checks every tile saved in `dir` without downloading anything, failing if any are corrupt so
+/// that it can be used in scripts. Running `download_tiles` on the folder repairs them.
+pub async fn verify_tiles(dir: &str) -> Result<()> {
+    let cache = TileCache::new(dir)?;
+    let (mut valid, mut missing, mut corrupt) = (0, 0, 0);
+    for (z, x, y) in all_tiles() {
+        match cache.check(z, x, y).await? {
+            TileState::Valid => valid += 1,
+            TileState::Missing => missing += 1,
+            TileState::Corrupt => {
+                warn!(path = %tile_path(Path::new(dir), z, x, y).display(), "corrupt tile");
+                corrupt += 1;
+            }
+        }
+    }
+    info!(valid, missing, corrupt, "verified tiles in {}", dir);
+    if corrupt > 0 {
+        return Err(anyhow!(
+            "{} corrupt tiles in {}, run --download-tiles to replace them",
+            corrupt,
+            dir
+        ));
     }
     Ok(())
 }
@@ -0,0 +1,46 @@
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::Write;
+use tracing::info;
+
+use crate::commands::enrich_cell_lore::TAMRIEL_FORM_ID;
+use crate::models::game_mod::{self, CategoryCellStats};
+use crate::models::{game, world};
+use crate::nexus_api::SSE_GAME_NAME;
+use crate::provenance;
+
+/// Writes the number of mods, cells edited, and average cells edited per mod of each mod category,
+/// grouped by game name, so the site can show which categories make the most world edits.
+pub async fn dump_category_stats(
+    pool: &sqlx::Pool<sqlx::Postgres>,
+    path: &str,
+    include_translations: bool,
+) -> Result<()> {
+    let game_id_to_name: HashMap<_, _> = game::get_all(pool)
+        .await?
+        .into_iter()
+        .map(|game| (game.id, game.name))
+        .collect();
+    let game_id = game::get_id_by_name(pool, SSE_GAME_NAME).await?;
+    let world_id = world::get_id(pool, TAMRIEL_FORM_ID, "Skyrim.esm", game_id)
+        .await
+        .context("Tamriel is missing from the worlds table")?;
+    let stats =
+        game_mod::get_category_cell_stats(pool, "Skyrim.esm", world_id, include_translations)
+            .await?;
+    let mut stats_by_game: BTreeMap<&str, Vec<&CategoryCellStats>> = BTreeMap::new();
+    for category_stats in &stats {
+        if let Some(game_name) = game_id_to_name.get(&category_stats.game_id) {
+            stats_by_game
+                .entry(game_name)
+                .or_default()
+                .push(category_stats);
+        }
+    }
+    info!("writing stats for {} categories to {}", stats.len(), path);
+    let mut file = File::create(path)?;
+    write!(file, "{}", serde_json::to_string(&stats_by_game)?)?;
+    provenance::write_manifest(path, stats.len())?;
+    Ok(())
+}
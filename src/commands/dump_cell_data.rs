@@ -1,30 +1,25 @@
 use anyhow::Result;
-use sqlx::postgres::PgPoolOptions;
-use std::env;
 use std::fs::create_dir_all;
 use std::path::Path;
 use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
 use tracing::{debug, info};
 
+use crate::db;
+use crate::grid;
 use crate::models::cell;
+use crate::provenance;
 
 pub async fn dump_cell_data(dir: &str) -> Result<()> {
-    let mut pool = PgPoolOptions::new()
-        .max_connections(5)
-        .connect(&env::var("DATABASE_URL")?)
-        .await?;
+    let mut pool = db::connect().await?;
     let mut cell_count = 0;
-    for x in -77..75 {
-        for y in -50..44 {
+    for x in grid::MIN_CELL_X..grid::MAX_CELL_X {
+        for y in grid::MIN_CELL_Y..grid::MAX_CELL_Y {
             if cell_count % 5 == 0 {
                 // There's a weird issue that slows down this query after 5 iterations. Recreating the
                 // connection pool seems to fix it. I don't know why.
                 info!("reconnecting to database");
-                pool = PgPoolOptions::new()
-                    .max_connections(5)
-                    .connect(&env::var("DATABASE_URL")?)
-                    .await?;
+                pool = db::connect().await?;
             }
             if let Ok(data) = cell::get_cell_data(&pool, "Skyrim.esm", 1, x, y, true).await {
                 let path = format!("{}/{}", &dir, x);
@@ -39,7 +34,7 @@ pub async fn dump_cell_data(dir: &str) -> Result<()> {
                     path.display()
                 );
                 let mut file = File::create(path).await?;
-                file.write_all(serde_json::to_string(&data)?.as_bytes())
+                file.write_all(provenance::to_json(&data)?.as_bytes())
                     .await?;
                 cell_count += 1;
             }
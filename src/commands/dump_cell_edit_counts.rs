@@ -1,16 +1,166 @@
 use anyhow::Result;
-use std::collections::HashMap;
+use chrono::NaiveDateTime;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::Write;
 use tracing::{debug, info};
 
+use crate::grid;
 use crate::models::cell;
+use crate::models::plugin::{self, PluginForFamily};
+use crate::provenance;
 
-pub async fn dump_cell_edit_counts(pool: &sqlx::Pool<sqlx::Postgres>, path: &str) -> Result<()> {
+fn find(parents: &mut HashMap<i32, i32>, mod_id: i32) -> i32 {
+    let parent = *parents.entry(mod_id).or_insert(mod_id);
+    if parent == mod_id {
+        return mod_id;
+    }
+    let root = find(parents, parent);
+    parents.insert(mod_id, root);
+    root
+}
+
+fn union(parents: &mut HashMap<i32, i32>, a: i32, b: i32) {
+    let a = find(parents, a);
+    let b = find(parents, b);
+    // The oldest mod (lowest id) is the root so families are stable between dumps
+    if a < b {
+        parents.insert(b, a);
+    } else if b < a {
+        parents.insert(a, b);
+    }
+}
+
+/// Groups mods into families of an original mod plus the mods that only exist because of it, and
+/// returns the id every mod's family is represented by. Mods are in the same family if they:
+///
+/// * contain a plugin with the same hash
+/// * are a translation of a plugin with the same file name
+/// * are a patch (see `plugin_processor::is_patch`) of one of its masters (only the first master
+///   another mod provides, so a patch between two mods doesn't merge them into one family)
+///
+/// Official mods are never part of a family, otherwise everything would be in the base game's.
+pub fn mod_families(plugins: &[PluginForFamily]) -> HashMap<i32, i32> {
+    let mut parents = HashMap::new();
+    let mut mods_by_hash: HashMap<i64, i32> = HashMap::new();
+    let mut mods_by_file_name: HashMap<&str, i32> = HashMap::new();
+    for plugin in plugins.iter().filter(|plugin| !plugin.is_official) {
+        find(&mut parents, plugin.mod_id);
+        let mod_id = *mods_by_hash.entry(plugin.hash).or_insert(plugin.mod_id);
+        union(&mut parents, mod_id, plugin.mod_id);
+        if !plugin.is_translation {
+            let mod_id = mods_by_file_name
+                .entry(plugin.file_name.as_str())
+                .or_insert(plugin.mod_id);
+            *mod_id = (*mod_id).min(plugin.mod_id);
+        }
+    }
+    for plugin in plugins.iter().filter(|plugin| !plugin.is_official) {
+        if plugin.is_translation {
+            if let Some(&mod_id) = mods_by_file_name.get(plugin.file_name.as_str()) {
+                union(&mut parents, mod_id, plugin.mod_id);
+            }
+        }
+        if plugin.is_patch {
+            if let Some(&mod_id) = plugin
+                .masters
+                .iter()
+                .filter_map(|master| mods_by_file_name.get(master.to_lowercase().as_str()))
+                .find(|&&mod_id| mod_id != plugin.mod_id)
+            {
+                union(&mut parents, mod_id, plugin.mod_id);
+            }
+        }
+    }
+    let mod_ids: Vec<i32> = parents.keys().copied().collect();
+    mod_ids
+        .into_iter()
+        .map(|mod_id| (mod_id, find(&mut parents, mod_id)))
+        .collect()
+}
+
+/// Counts each mod family (see `mod_families`) that edits a cell once
+async fn count_family_edits(
+    pool: &sqlx::Pool<sqlx::Postgres>,
+    include_translations: bool,
+    include_patches: bool,
+    uploaded_before: Option<NaiveDateTime>,
+    language: Option<&str>,
+) -> Result<HashMap<(i32, i32), i64>> {
+    let families = mod_families(&plugin::get_all_for_families(pool).await?);
+    let mut counts = HashMap::new();
+    for cell in cell::get_mod_ids_by_cell(
+        pool,
+        "Skyrim.esm",
+        1,
+        include_translations,
+        include_patches,
+        uploaded_before,
+        language,
+    )
+    .await?
+    {
+        if let (Some(x), Some(y), Some(mod_ids)) = (cell.x, cell.y, cell.mod_ids) {
+            let count = mod_ids
+                .iter()
+                .map(|mod_id| families.get(mod_id).unwrap_or(mod_id))
+                .collect::<HashSet<_>>()
+                .len();
+            counts.insert((x, y), count as i64);
+        }
+    }
+    Ok(counts)
+}
+
+/// With `dedup_aggressive`, each mod family is only counted once per cell, which also folds the
+/// translations and patches that aren't excluded into the mods they belong to. With
+/// `uploaded_before`, only files uploaded before then are counted, giving the map as it was at that
+/// time. With `language`, only mods in that language are counted (plus mods whose language isn't
+/// known yet), so heavily translated mods aren't counted once per language.
+pub async fn dump_cell_edit_counts(
+    pool: &sqlx::Pool<sqlx::Postgres>,
+    path: &str,
+    include_translations: bool,
+    include_patches: bool,
+    dedup_aggressive: bool,
+    uploaded_before: Option<NaiveDateTime>,
+    language: Option<&str>,
+) -> Result<()> {
+    let family_edit_counts = if dedup_aggressive {
+        Some(
+            count_family_edits(
+                pool,
+                include_translations,
+                include_patches,
+                uploaded_before,
+                language,
+            )
+            .await?,
+        )
+    } else {
+        None
+    };
     let mut cell_mod_edit_counts = HashMap::new();
-    for x in -77..75 {
-        for y in -50..44 {
-            if let Some(count) = cell::count_mod_edits(pool, "Skyrim.esm", 1, x, y).await? {
+    for x in grid::MIN_CELL_X..grid::MAX_CELL_X {
+        for y in grid::MIN_CELL_Y..grid::MAX_CELL_Y {
+            let count = match &family_edit_counts {
+                Some(counts) => Some(counts.get(&(x, y)).copied().unwrap_or(0)),
+                None => {
+                    cell::count_mod_edits(
+                        pool,
+                        "Skyrim.esm",
+                        1,
+                        x,
+                        y,
+                        include_translations,
+                        include_patches,
+                        uploaded_before,
+                        language,
+                    )
+                    .await?
+                }
+            };
+            if let Some(count) = count {
                 debug!(x = x, y = y, count = count, "read cell edit count");
                 cell_mod_edit_counts.insert(format!("{},{}", x, y), count);
             }
@@ -23,5 +173,6 @@ pub async fn dump_cell_edit_counts(pool: &sqlx::Pool<sqlx::Postgres>, path: &str
     );
     let mut file = File::create(path)?;
     write!(file, "{}", serde_json::to_string(&cell_mod_edit_counts)?)?;
+    provenance::write_manifest(path, cell_mod_edit_counts.len())?;
     Ok(())
 }
@@ -1,8 +1,10 @@
+use crate::db;
+use crate::grid;
 use crate::models::cell::{self, CellFileEditCount};
+use crate::provenance;
 use anyhow::Result;
 use chrono::{Duration, NaiveDateTime, Months};
-use sqlx::postgres::PgPoolOptions;
-use std::{collections::HashMap, env, str::FromStr};
+use std::{collections::HashMap, str::FromStr};
 use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
 use tracing::{debug, info};
@@ -31,11 +33,12 @@ pub async fn dump_cell_edit_counts_over_time(
     end_date: NaiveDateTime,
     time_step: TimeStep,
     path: &str,
+    include_translations: bool,
+    include_patches: bool,
+    by_first_seen: bool,
+    language: Option<&str>,
 ) -> Result<()> {
-    let mut pool = PgPoolOptions::new()
-        .max_connections(5)
-        .connect(&env::var("DATABASE_URL")?)
-        .await?;
+    let mut pool = db::connect().await?;
     let mut i = 0;
     let mut current_date = start_date;
     while current_date <= end_date {
@@ -43,10 +46,7 @@ pub async fn dump_cell_edit_counts_over_time(
             // There's a weird issue that slows down this query after 5 iterations. Recreating the
             // connection pool seems to fix it. I don't know why.
             info!("reconnecting to database");
-            pool = PgPoolOptions::new()
-                .max_connections(5)
-                .connect(&env::var("DATABASE_URL")?)
-                .await?;
+            pool = db::connect().await?;
         }
         let next_date = match &time_step {
             TimeStep::Day => current_date + Duration::days(1),
@@ -54,11 +54,20 @@ pub async fn dump_cell_edit_counts_over_time(
             TimeStep::Month => current_date.checked_add_months(Months::new(1)).unwrap(),
         };
         let mut cell_file_edit_counts = HashMap::new();
-        let counts =
-            cell::count_file_edits_in_time_range(&pool, "Skyrim.esm", 1, current_date, next_date)
-                .await?;
-        for x in -77..75 {
-            for y in -50..44 {
+        let counts = cell::count_file_edits_in_time_range(
+            &pool,
+            "Skyrim.esm",
+            1,
+            current_date,
+            next_date,
+            include_translations,
+            include_patches,
+            by_first_seen,
+            language,
+        )
+        .await?;
+        for x in grid::MIN_CELL_X..grid::MAX_CELL_X {
+            for y in grid::MIN_CELL_Y..grid::MAX_CELL_Y {
                 let count: Option<&CellFileEditCount> = counts.iter().find(|c| c.x.unwrap() == x && c.y.unwrap() == y);
                 let count = count.map(|c| c.count).unwrap_or(Some(0)).unwrap();
                 debug!(x = x, y = y, count = count, "read cell edit count");
@@ -78,6 +87,7 @@ pub async fn dump_cell_edit_counts_over_time(
         );
         let mut file = File::create(&file_name).await?;
         file.write_all(serde_json::to_string(&cell_file_edit_counts)?.as_bytes()).await?;
+        provenance::write_manifest(&file_name, cell_file_edit_counts.len())?;
 
         current_date = next_date;
         i += 1;
@@ -0,0 +1,74 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use std::path::Path;
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+use tracing::info;
+use walkdir::WalkDir;
+
+use crate::cdn_api::{self, CdnProvider};
+
+/// Parses a `<dir>=<url>` pair naming a dump folder and the url its files are served from
+pub fn parse_dump_url(dump_url: &str) -> Result<(String, String)> {
+    let (dir, url) = dump_url
+        .split_once('=')
+        .ok_or_else(|| anyhow!("dump url {} is not in the form <dir>=<url>", dump_url))?;
+    Ok((dir.to_string(), url.trim_end_matches('/').to_string()))
+}
+
+/// Returns the urls of every file in the dump folders that was written after `since` (or of every
+/// file if `since` is not given).
+pub fn changed_urls(
+    dump_urls: &[(String, String)],
+    since: Option<NaiveDateTime>,
+) -> Result<Vec<String>> {
+    let mut urls = vec![];
+    for (dir, base_url) in dump_urls {
+        for entry in WalkDir::new(dir)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file())
+        {
+            if let Some(since) = since {
+                let modified_at = DateTime::<Utc>::from(entry.metadata()?.modified()?).naive_utc();
+                if modified_at <= since {
+                    continue;
+                }
+            }
+            let relative_path = entry.path().strip_prefix(Path::new(dir))?;
+            let relative_path = relative_path
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            urls.push(format!("{}/{}", base_url, relative_path));
+        }
+    }
+    urls.sort();
+    Ok(urls)
+}
+
+/// Writes the urls of dumped files changed since `since` to `path`, one per line, so the static
+/// site's CDN cache can be invalidated for them. If `purge` is given, the urls are also purged
+/// from that CDN (run this after the dumps are synced to the static server).
+pub async fn dump_changed_urls(
+    path: &str,
+    dump_urls: &[String],
+    since: Option<NaiveDateTime>,
+    purge: Option<CdnProvider>,
+) -> Result<()> {
+    let dump_urls = dump_urls
+        .iter()
+        .map(|dump_url| parse_dump_url(dump_url))
+        .collect::<Result<Vec<(String, String)>>>()?;
+    let urls = changed_urls(&dump_urls, since)?;
+    let mut file = File::create(path).await?;
+    file.write_all(urls.join("\n").as_bytes()).await?;
+    info!("dumped {} changed urls", urls.len());
+
+    if let Some(provider) = purge {
+        let client = reqwest::Client::new();
+        cdn_api::purge(&client, provider, &urls).await?;
+    }
+    Ok(())
+}
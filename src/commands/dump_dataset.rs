@@ -0,0 +1,341 @@
+use anyhow::{Context, Result};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use futures::TryStreamExt;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use tracing::info;
+
+use crate::provenance::{self, Provenance};
+
+/// A table of the dataset, dumped to `{name}.csv.gz` from `query` and documented in `SCHEMA.md`
+pub struct DatasetTable {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// The columns `query` selects, in order, with their descriptions
+    pub columns: &'static [(&'static str, &'static str)],
+    pub query: &'static str,
+}
+
+/// The tables of a dataset release. Internal bookkeeping (processing state, storage locations,
+/// content previews) is left out.
+pub const DATASET_TABLES: &[DatasetTable] = &[
+    DatasetTable {
+        name: "games",
+        description: "Games on Nexus Mods that mods were scraped for.",
+        columns: &[
+            ("id", "Dataset id of the game"),
+            (
+                "name",
+                "Nexus Mods domain name of the game (e.g. `skyrimspecialedition`)",
+            ),
+            ("nexus_game_id", "Nexus Mods id of the game"),
+        ],
+        query: "SELECT id, name, nexus_game_id FROM games ORDER BY id",
+    },
+    DatasetTable {
+        name: "mods",
+        description: "Mods listed on Nexus Mods, including delisted mods.",
+        columns: &[
+            ("id", "Dataset id of the mod"),
+            ("game_id", "The `games.id` the mod is for"),
+            (
+                "nexus_mod_id",
+                "Nexus Mods id of the mod, unique within its game",
+            ),
+            ("name", "Name of the mod"),
+            ("author_name", "Name of the mod's author"),
+            ("author_id", "Nexus Mods id of the mod's author"),
+            (
+                "category_name",
+                "Nexus Mods category of the mod as scraped, which may be localized (see `categories`)",
+            ),
+            ("category_id", "Nexus Mods id of the category"),
+            ("description", "Short description of the mod"),
+            ("language", "Language of the mod, if Nexus Mods reports one"),
+            (
+                "is_translation",
+                "Whether the mod is a translation of another mod",
+            ),
+            (
+                "is_official",
+                "Whether the mod is official content (e.g. Creation Club)",
+            ),
+            ("is_adult", "Whether the mod is marked as adult content"),
+            (
+                "downloads",
+                "Unique downloads of the mod when it was last scraped",
+            ),
+            ("first_upload_at", "UTC day the mod was first uploaded"),
+            ("last_update_at", "UTC day the mod was last updated"),
+            (
+                "delisted_at",
+                "When the mod was found to be removed from Nexus Mods",
+            ),
+        ],
+        query: "SELECT id, game_id, nexus_mod_id, name, author_name, author_id, category_name,
+            category_id, description, language, is_translation, is_official, is_adult, downloads,
+            first_upload_at, last_update_at, delisted_at
+            FROM mods ORDER BY id",
+    },
+    DatasetTable {
+        name: "categories",
+        description: "Mod categories of each game, with their English names.",
+        columns: &[
+            ("game_id", "The `games.id` the category is in"),
+            ("nexus_category_id", "Nexus Mods id of the category (`mods.category_id`)"),
+            ("name", "English name of the category"),
+            (
+                "parent_nexus_category_id",
+                "Nexus Mods id of the parent category, empty for top level categories",
+            ),
+        ],
+        query: "SELECT game_id, nexus_category_id, name, parent_nexus_category_id
+            FROM categories ORDER BY game_id, nexus_category_id",
+    },
+    DatasetTable {
+        name: "files",
+        description: "Files uploaded to mods.",
+        columns: &[
+            ("id", "Dataset id of the file"),
+            ("mod_id", "The `mods.id` the file belongs to"),
+            ("nexus_file_id", "Nexus Mods id of the file"),
+            ("name", "Name of the file"),
+            ("file_name", "File name of the archive"),
+            (
+                "category",
+                "Nexus Mods category of the file (e.g. `MAIN`, `OPTIONAL`)",
+            ),
+            ("version", "Version of the file"),
+            (
+                "mod_version",
+                "Version of the mod the file was uploaded for",
+            ),
+            ("size", "Size of the archive in bytes"),
+            ("uploaded_at", "When the file was uploaded"),
+            ("has_plugin", "Whether plugins were found in the file"),
+        ],
+        query: "SELECT id, mod_id, nexus_file_id, name, file_name, category, version, mod_version,
+            size, uploaded_at, has_plugin
+            FROM files ORDER BY id",
+    },
+    DatasetTable {
+        name: "plugins",
+        description: "Plugins (`.esp`, `.esm`, and `.esl` files) found in files.",
+        columns: &[
+            ("id", "Dataset id of the plugin"),
+            ("mod_id", "The `mods.id` the plugin belongs to"),
+            ("file_id", "The `files.id` the plugin was found in"),
+            ("name", "Name of the plugin from its header"),
+            ("file_name", "File name of the plugin"),
+            ("hash", "Seahash of the plugin's contents"),
+            ("version", "Version of the plugin's header"),
+            ("size", "Size of the plugin in bytes"),
+            ("author", "Author from the plugin's header"),
+            ("description", "Description from the plugin's header"),
+            ("masters", "Master files the plugin depends on"),
+            ("npc_count", "Number of NPC records in the plugin"),
+            ("quest_count", "Number of quest records in the plugin"),
+            ("dialogue_count", "Number of dialogue records in the plugin"),
+            ("is_patch", "Whether the plugin only patches other plugins"),
+        ],
+        query: "SELECT id, mod_id, file_id, name, file_name, hash, version, size, author,
+            description, masters, npc_count, quest_count, dialogue_count, is_patch
+            FROM plugins ORDER BY id",
+    },
+    DatasetTable {
+        name: "worlds",
+        description: "Worldspaces defined or edited by plugins.",
+        columns: &[
+            ("id", "Dataset id of the world"),
+            ("game_id", "The `games.id` the world is in"),
+            (
+                "form_id",
+                "Form id of the world, without the master's load order index",
+            ),
+            ("master", "File name of the plugin that defines the world"),
+        ],
+        query: "SELECT id, game_id, form_id, master FROM worlds ORDER BY id",
+    },
+    DatasetTable {
+        name: "cells",
+        description: "Cells edited by plugins. Interior cells have no world or coordinates.",
+        columns: &[
+            ("id", "Dataset id of the cell"),
+            ("game_id", "The `games.id` the cell is in"),
+            (
+                "form_id",
+                "Form id of the cell, without the master's load order index",
+            ),
+            ("master", "File name of the plugin that defines the cell"),
+            (
+                "world_id",
+                "The `worlds.id` the cell is in, empty for interior cells",
+            ),
+            ("x", "X grid coordinate of the cell"),
+            ("y", "Y grid coordinate of the cell"),
+            (
+                "is_persistent",
+                "Whether the cell is the world's persistent cell",
+            ),
+            (
+                "is_base_game",
+                "Whether the cell is defined by the base game",
+            ),
+        ],
+        query: "SELECT id, game_id, form_id, master, world_id, x, y, is_persistent, is_base_game
+            FROM cells ORDER BY id",
+    },
+    DatasetTable {
+        name: "plugin_cells",
+        description: "Which plugins edit which cells.",
+        columns: &[
+            ("plugin_id", "The `plugins.id` that edits the cell"),
+            ("cell_id", "The `cells.id` edited"),
+            ("file_id", "The `files.id` of the plugin"),
+            ("mod_id", "The `mods.id` of the plugin"),
+            ("editor_id", "Editor id the plugin gives the cell"),
+        ],
+        query: "SELECT plugin_id, cell_id, file_id, mod_id, editor_id
+            FROM plugin_cells ORDER BY id",
+    },
+    DatasetTable {
+        name: "plugin_worlds",
+        description: "Which plugins edit which worldspaces.",
+        columns: &[
+            ("plugin_id", "The `plugins.id` that edits the world"),
+            ("world_id", "The `worlds.id` edited"),
+            ("editor_id", "Editor id the plugin gives the world"),
+        ],
+        query: "SELECT plugin_id, world_id, editor_id FROM plugin_worlds ORDER BY id",
+    },
+];
+
+/// Name of the folder a release of the dataset is assembled in
+pub fn dataset_dir_name(version: &str) -> String {
+    format!("modmapper-dataset-{}", version)
+}
+
+/// The default version of a release: the UTC day it was generated
+pub fn default_version(provenance: Provenance) -> String {
+    provenance.generated_at.format("%Y-%m-%d").to_string()
+}
+
+/// `SCHEMA.md` of a release, describing the format and every column of each table
+pub fn schema_markdown(version: &str, tables: &[DatasetTable]) -> String {
+    let mut schema = format!("# Modmapper dataset {}\n\n", version);
+    schema.push_str(
+        "Each table is a gzip compressed CSV file with a header row. Empty values are NULL. \
+        Times are UTC, formatted `YYYY-MM-DD HH:MM:SS`. Arrays are formatted as Postgres array \
+        literals (e.g. `{Skyrim.esm,Update.esm}`). `manifest.json` has the row count and SHA-256 \
+        checksum of each table.\n",
+    );
+    for table in tables {
+        schema.push_str(&format!(
+            "\n## {}.csv.gz\n\n{}\n\n| Column | Description |\n| --- | --- |\n",
+            table.name, table.description
+        ));
+        for (column, description) in table.columns {
+            schema.push_str(&format!("| `{}` | {} |\n", column, description));
+        }
+    }
+    schema
+}
+
+#[derive(Debug, Serialize)]
+struct DatasetTableManifest<'a> {
+    file: String,
+    rows: i64,
+    bytes: u64,
+    sha256: String,
+    columns: Vec<&'a str>,
+}
+
+#[derive(Debug, Serialize)]
+struct DatasetManifest<'a> {
+    version: &'a str,
+    #[serde(flatten)]
+    provenance: Provenance,
+    tables: Vec<DatasetTableManifest<'a>>,
+}
+
+fn sha256_file(path: &Path) -> Result<String> {
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Assembles a release of the dataset in `{dir}/modmapper-dataset-{version}`: every table in
+/// `DATASET_TABLES` as a compressed CSV, a `SCHEMA.md` describing them, the license at
+/// `license_path` as `LICENSE`, and a `manifest.json` with row counts and checksums. The tables are
+/// read in one snapshot, so they are consistent with each other even while an update is running.
+pub async fn dump_dataset(
+    pool: &sqlx::Pool<sqlx::Postgres>,
+    dir: &str,
+    version: Option<&str>,
+    license_path: &str,
+) -> Result<()> {
+    let provenance = provenance::current();
+    let version = version
+        .map(|version| version.to_string())
+        .unwrap_or_else(|| default_version(provenance));
+    let release_dir = Path::new(dir).join(dataset_dir_name(&version));
+    std::fs::create_dir_all(&release_dir)?;
+
+    let mut tx = pool.begin().await?;
+    sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ, READ ONLY")
+        .execute(&mut *tx)
+        .await?;
+    let mut tables = vec![];
+    for table in DATASET_TABLES {
+        let file = format!("{}.csv.gz", table.name);
+        let path = release_dir.join(&file);
+        let rows: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM ({}) AS t", table.query))
+            .fetch_one(&mut *tx)
+            .await
+            .with_context(|| format!("Failed to count rows of dataset table {}", table.name))?;
+        info!(rows, path = %path.display(), "writing dataset table");
+        let mut encoder = GzEncoder::new(File::create(&path)?, Compression::default());
+        let mut copy = (&mut *tx)
+            .copy_out_raw(&format!(
+                "COPY ({}) TO STDOUT WITH (FORMAT csv, HEADER)",
+                table.query
+            ))
+            .await
+            .with_context(|| format!("Failed to copy dataset table {}", table.name))?;
+        while let Some(chunk) = copy.try_next().await? {
+            encoder.write_all(&chunk)?;
+        }
+        drop(copy);
+        encoder.finish()?;
+        tables.push(DatasetTableManifest {
+            file,
+            rows,
+            bytes: std::fs::metadata(&path)?.len(),
+            sha256: sha256_file(&path)?,
+            columns: table.columns.iter().map(|(column, _)| *column).collect(),
+        });
+    }
+    tx.commit().await?;
+
+    std::fs::write(
+        release_dir.join("SCHEMA.md"),
+        schema_markdown(&version, DATASET_TABLES),
+    )?;
+    std::fs::copy(license_path, release_dir.join("LICENSE"))
+        .with_context(|| format!("Failed to copy dataset license {}", license_path))?;
+    let manifest = DatasetManifest {
+        version: &version,
+        provenance,
+        tables,
+    };
+    std::fs::write(
+        release_dir.join("manifest.json"),
+        serde_json::to_string_pretty(&manifest)?,
+    )?;
+    info!(path = %release_dir.display(), "assembled dataset release");
+    Ok(())
+}
@@ -0,0 +1,48 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
+use tracing::info;
+
+use crate::commands::enrich_cell_lore::TAMRIEL_FORM_ID;
+use crate::models::game_mod::{self, DelistedModWithCells};
+use crate::models::{game, world};
+use crate::nexus_api::SSE_GAME_NAME;
+use crate::provenance;
+
+#[derive(Serialize)]
+struct DelistedModWithGameName<'a> {
+    #[serde(flatten)]
+    delisted_mod: &'a DelistedModWithCells,
+    game_name: Option<&'a str>,
+}
+
+/// Writes the mods that were removed from nexus but are still indexed, with the cells they edit,
+/// so their footprint on the map isn't lost with them.
+pub async fn dump_delisted_mods(pool: &sqlx::Pool<sqlx::Postgres>, path: &str) -> Result<()> {
+    let game_id_to_name: HashMap<_, _> = game::get_all(pool)
+        .await?
+        .into_iter()
+        .map(|game| (game.id, game.name))
+        .collect();
+    let game_id = game::get_id_by_name(pool, SSE_GAME_NAME).await?;
+    let world_id = world::get_id(pool, TAMRIEL_FORM_ID, "Skyrim.esm", game_id)
+        .await
+        .context("Tamriel is missing from the worlds table")?;
+    let delisted_mods = game_mod::get_delisted_with_cells(pool, "Skyrim.esm", world_id).await?;
+    let delisted_mods: Vec<DelistedModWithGameName> = delisted_mods
+        .iter()
+        .map(|delisted_mod| DelistedModWithGameName {
+            delisted_mod,
+            game_name: game_id_to_name
+                .get(&delisted_mod.game_id)
+                .map(String::as_str),
+        })
+        .collect();
+    info!("writing {} delisted mods to {}", delisted_mods.len(), path);
+    let mut file = File::create(path)?;
+    write!(file, "{}", serde_json::to_string(&delisted_mods)?)?;
+    provenance::write_manifest(path, delisted_mods.len())?;
+    Ok(())
+}
@@ -1,19 +1,16 @@
 use anyhow::Result;
 use chrono::NaiveDateTime;
-use sqlx::postgres::PgPoolOptions;
-use std::env;
 use std::path::Path;
 use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
 use tracing::{debug, info};
 
+use crate::db;
 use crate::models::file;
+use crate::provenance;
 
 pub async fn dump_file_data(dir: &str, updated_after: Option<NaiveDateTime>) -> Result<()> {
-    let mut pool = PgPoolOptions::new()
-        .max_connections(5)
-        .connect(&env::var("DATABASE_URL")?)
-        .await?;
+    let mut pool = db::connect().await?;
     let mut file_count = 0;
     let mut page = 1;
     let page_size = 20;
@@ -23,10 +20,7 @@ pub async fn dump_file_data(dir: &str, updated_after: Option<NaiveDateTime>) ->
             // There's a weird issue that slows down this query after 5 iterations. Recreating the
             // connection pool seems to fix it. I don't know why.
             info!("reconnecting to database");
-            pool = PgPoolOptions::new()
-                .max_connections(5)
-                .connect(&env::var("DATABASE_URL")?)
-                .await?;
+            pool = db::connect().await?;
         }
         let files =
             file::batched_get_with_cells(&pool, page_size, last_id, "Skyrim.esm", 1, updated_after)
@@ -45,8 +39,8 @@ pub async fn dump_file_data(dir: &str, updated_after: Option<NaiveDateTime>) ->
                 path.display()
             );
             let mut file = File::create(path).await?;
-            file.write_all(serde_json::to_string(&file_with_cells)?.as_bytes())
-                .await?;
+            let json = provenance::to_json(&file_with_cells)?;
+            file.write_all(json.as_bytes()).await?;
             last_id = Some(file_with_cells.id);
             file_count += 1;
         }
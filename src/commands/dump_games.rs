@@ -1,14 +1,38 @@
 use anyhow::Result;
+use serde::Serialize;
 use std::fs::File;
 use std::io::Write;
 use tracing::info;
 
-use crate::models::game;
+use crate::models::game::{self, Game, GameStats};
+use crate::nexus_api::get_canonical_game_name;
+use crate::provenance;
 
+#[derive(Serialize)]
+struct GameWithCanonicalName<'a> {
+    #[serde(flatten)]
+    game: &'a Game,
+    canonical_name: &'a str,
+    #[serde(flatten)]
+    stats: Option<&'a GameStats>,
+}
+
+/// Writes every game with its canonical name and totals of its mods, files, plugins, and edited
+/// cells, so the site can show per-game stats without loading the other dumps.
 pub async fn dump_games(pool: &sqlx::Pool<sqlx::Postgres>, path: &str) -> Result<()> {
     let games = game::get_all(pool).await?;
+    let stats = game::get_stats(pool).await?;
+    let games: Vec<GameWithCanonicalName> = games
+        .iter()
+        .map(|game| GameWithCanonicalName {
+            game,
+            canonical_name: get_canonical_game_name(&game.name),
+            stats: stats.iter().find(|stats| stats.game_id == game.id),
+        })
+        .collect();
     info!("writing {} games to {}", games.len(), path);
     let mut file = File::create(path)?;
     write!(file, "{}", serde_json::to_string(&games)?)?;
+    provenance::write_manifest(path, games.len())?;
     Ok(())
 }
@@ -1,18 +1,46 @@
 use anyhow::Result;
-use sqlx::postgres::PgPoolOptions;
+use serde::Serialize;
 use std::collections::HashMap;
-use std::env;
 use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
 use tracing::{debug, info};
 
-use crate::models::game_mod;
+use crate::db;
+use crate::models::game_mod::{self, ModCellCount};
+use crate::provenance;
 
-pub async fn dump_mod_cell_counts(path: &str) -> Result<()> {
-    let mut pool = PgPoolOptions::new()
-        .max_connections(5)
-        .connect(&env::var("DATABASE_URL")?)
-        .await?;
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+pub enum CellCounts {
+    Exterior(Option<i64>),
+    ByType {
+        exterior: Option<i64>,
+        interior: Option<i64>,
+    },
+}
+
+impl CellCounts {
+    pub fn new(mod_cell_count: &ModCellCount, by_type: bool) -> Self {
+        if by_type {
+            CellCounts::ByType {
+                exterior: mod_cell_count.cells,
+                interior: mod_cell_count.interior_cells,
+            }
+        } else {
+            CellCounts::Exterior(mod_cell_count.cells)
+        }
+    }
+}
+
+/// Writes the number of exterior cells each mod edits, keyed by nexus mod id. With `by_type`, each
+/// mod's count is an object with its `exterior` and `interior` cell counts instead, which tells a
+/// dungeon mod apart from one that edits as many wilderness cells.
+pub async fn dump_mod_cell_counts(
+    path: &str,
+    include_translations: bool,
+    by_type: bool,
+) -> Result<()> {
+    let mut pool = db::connect().await?;
     let mut page = 1;
     let page_size = 100;
     let mut last_id = None;
@@ -22,13 +50,17 @@ pub async fn dump_mod_cell_counts(path: &str) -> Result<()> {
             // There's a weird issue that slows down this query after 5 iterations. Recreating the
             // connection pool seems to fix it. I don't know why.
             info!("reconnecting to database");
-            pool = PgPoolOptions::new()
-                .max_connections(5)
-                .connect(&env::var("DATABASE_URL")?)
-                .await?;
+            pool = db::connect().await?;
         }
-        let mod_cell_counts =
-            game_mod::batched_get_cell_counts(&pool, page_size, last_id, "Skyrim.esm", 1).await?;
+        let mod_cell_counts = game_mod::batched_get_cell_counts(
+            &pool,
+            page_size,
+            last_id,
+            "Skyrim.esm",
+            1,
+            include_translations,
+        )
+        .await?;
         if mod_cell_counts.is_empty() {
             break;
         }
@@ -37,9 +69,13 @@ pub async fn dump_mod_cell_counts(path: &str) -> Result<()> {
                 page = page,
                 nexus_mod_id = mod_cell_count.nexus_mod_id,
                 count = mod_cell_count.cells.unwrap_or(0),
+                interior_count = mod_cell_count.interior_cells.unwrap_or(0),
                 "read mod cell count"
             );
-            counts.insert(mod_cell_count.nexus_mod_id, mod_cell_count.cells);
+            counts.insert(
+                mod_cell_count.nexus_mod_id,
+                CellCounts::new(&mod_cell_count, by_type),
+            );
             last_id = Some(mod_cell_count.nexus_mod_id);
         }
         info!("dumped page {}", page);
@@ -49,5 +85,6 @@ pub async fn dump_mod_cell_counts(path: &str) -> Result<()> {
     let mut file = File::create(path).await?;
     file.write_all(serde_json::to_string(&counts)?.as_bytes())
         .await?;
+    provenance::write_manifest(path, counts.len())?;
     Ok(())
 }
@@ -1,21 +1,63 @@
 use anyhow::Result;
 use chrono::NaiveDateTime;
-use sqlx::postgres::PgPoolOptions;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::env;
 use std::path::Path;
 use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
 use tracing::{debug, info};
 
+use crate::cell_bitmap;
+use crate::db;
 use crate::models::game;
-use crate::models::game_mod;
+use crate::models::game_mod::{self, ModWithCellsAndFiles};
+use crate::provenance;
 
-pub async fn dump_mod_data(dir: &str, updated_after: Option<NaiveDateTime>) -> Result<()> {
-    let mut pool = PgPoolOptions::new()
-        .max_connections(5)
-        .connect(&env::var("DATABASE_URL")?)
-        .await?;
+#[derive(Deserialize)]
+struct CellCoords {
+    x: i32,
+    y: i32,
+}
+
+#[derive(Serialize)]
+struct ModWithCellBitmap<'a> {
+    #[serde(flatten)]
+    mod_with_cells: &'a ModWithCellsAndFiles,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cell_bitmap: Option<String>,
+}
+
+/// Replaces the mod's list of cells with only the cells that are outside the grid and returns the
+/// bitmap of the rest (see `cell_bitmap`), if that is smaller than the list. Otherwise the list of
+/// cells is left as is.
+fn take_cell_bitmap(mod_with_cells: &mut ModWithCellsAndFiles) -> Result<Option<String>> {
+    let cells: Vec<CellCoords> = match &mod_with_cells.cells {
+        Some(cells) => serde_json::from_value(cells.clone())?,
+        None => vec![],
+    };
+    let cells: Vec<(i32, i32)> = cells.into_iter().map(|cell| (cell.x, cell.y)).collect();
+    let (bitmap, outside) = match cell_bitmap::encode_if_smaller(&cells) {
+        Some(encoded) => encoded,
+        None => return Ok(None),
+    };
+    mod_with_cells.cells = Some(serde_json::to_value(
+        outside
+            .into_iter()
+            .map(|(x, y)| serde_json::json!({ "x": x, "y": y }))
+            .collect::<Vec<_>>(),
+    )?);
+    Ok(Some(bitmap))
+}
+
+/// With `cell_bitmaps`, each mod's cells are written as a base64 `cell_bitmap` instead of a list
+/// of coordinates, leaving only cells outside the grid in `cells`, for the mods where the bitmap
+/// is smaller.
+pub async fn dump_mod_data(
+    dir: &str,
+    updated_after: Option<NaiveDateTime>,
+    cell_bitmaps: bool,
+) -> Result<()> {
+    let mut pool = db::connect().await?;
     let mut mod_count = 0;
     let mut page = 1;
     let page_size = 20;
@@ -30,10 +72,7 @@ pub async fn dump_mod_data(dir: &str, updated_after: Option<NaiveDateTime>) -> R
             // There's a weird issue that slows down this query after 5 iterations. Recreating the
             // connection pool seems to fix it. I don't know why.
             info!("reconnecting to database");
-            pool = PgPoolOptions::new()
-                .max_connections(5)
-                .connect(&env::var("DATABASE_URL")?)
-                .await?;
+            pool = db::connect().await?;
         }
         let mods = game_mod::batched_get_with_cells_and_files(
             &pool,
@@ -47,7 +86,7 @@ pub async fn dump_mod_data(dir: &str, updated_after: Option<NaiveDateTime>) -> R
         if mods.is_empty() {
             break;
         }
-        for mod_with_cells in mods {
+        for mut mod_with_cells in mods {
             let path = Path::new(&dir).join(
                 game_id_to_name
                     .get(&mod_with_cells.game_id)
@@ -61,9 +100,17 @@ pub async fn dump_mod_data(dir: &str, updated_after: Option<NaiveDateTime>) -> R
                 "dumping mod data to {}",
                 path.display()
             );
+            let json = if cell_bitmaps {
+                let cell_bitmap = take_cell_bitmap(&mut mod_with_cells)?;
+                provenance::to_json(&ModWithCellBitmap {
+                    mod_with_cells: &mod_with_cells,
+                    cell_bitmap,
+                })?
+            } else {
+                provenance::to_json(&mod_with_cells)?
+            };
             let mut file = File::create(path).await?;
-            file.write_all(serde_json::to_string(&mod_with_cells)?.as_bytes())
-                .await?;
+            file.write_all(json.as_bytes()).await?;
             last_id = Some(mod_with_cells.id);
             mod_count += 1;
         }
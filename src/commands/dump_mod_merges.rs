@@ -0,0 +1,18 @@
+use anyhow::Result;
+use std::fs::File;
+use std::io::Write;
+use tracing::info;
+
+use crate::models::mod_merge;
+use crate::provenance;
+
+/// Writes every mod merged into another by `merge_mods`, with the game and nexus mod id of both,
+/// so the dumped files of removed mods can be deleted (or redirected to the kept mod).
+pub async fn dump_mod_merges(pool: &sqlx::Pool<sqlx::Postgres>, path: &str) -> Result<()> {
+    let mod_merges = mod_merge::get_all_with_games(pool).await?;
+    info!("writing {} mod merges to {}", mod_merges.len(), path);
+    let mut file = File::create(path)?;
+    write!(file, "{}", serde_json::to_string(&mod_merges)?)?;
+    provenance::write_manifest(path, mod_merges.len())?;
+    Ok(())
+}
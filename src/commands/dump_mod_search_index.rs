@@ -1,41 +1,119 @@
 use anyhow::Result;
 use serde::Serialize;
-use sqlx::postgres::PgPoolOptions;
-use std::env;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::str::FromStr;
 use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
 use tracing::{debug, info};
 
+use crate::db;
 use crate::models::game;
 use crate::models::game_mod;
+use crate::nexus_api::get_canonical_game_name;
+use crate::provenance;
+
+/// How to split the search index into smaller files that clients can load one at a time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchIndexSharding {
+    /// One shard per first letter of the mod name (`a` to `z`, and `_` for everything else)
+    Letter,
+    /// Shards of at most this many mods, in the order they were indexed
+    Chunk(usize),
+}
+
+impl FromStr for SearchIndexSharding {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "letter" => Ok(SearchIndexSharding::Letter),
+            _ => match s.parse::<usize>() {
+                Ok(size) if size > 0 => Ok(SearchIndexSharding::Chunk(size)),
+                _ => Err(format!(
+                    "invalid search index sharding: {} (expected \"letter\" or a chunk size)",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+/// Returns the key of the shard the `index`th mod in the search index, named `name`, goes in
+pub fn shard_key(index: usize, name: &str, sharding: SearchIndexSharding) -> String {
+    match sharding {
+        SearchIndexSharding::Letter => match name.chars().next() {
+            Some(c) if c.is_ascii_alphabetic() => c.to_ascii_lowercase().to_string(),
+            _ => "_".to_string(),
+        },
+        SearchIndexSharding::Chunk(size) => (index / size).to_string(),
+    }
+}
+
+#[derive(Serialize)]
+struct SearchIndexShard {
+    key: String,
+    /// File name relative to the index of shards
+    file: String,
+    count: usize,
+}
+
+#[derive(Serialize)]
+struct SearchIndexShards {
+    shards: Vec<SearchIndexShard>,
+}
 
 #[derive(Serialize)]
 struct ModForSearchIdTranslated {
     name: String,
     id: i32,
+    /// Only set for mods from an aliased game domain that was merged into this game's index
+    #[serde(skip_serializing_if = "Option::is_none")]
+    game: Option<String>,
+    /// Left out when 0 to keep the index small
+    #[serde(skip_serializing_if = "is_zero")]
+    translation_count: i32,
 }
 
-pub async fn dump_mod_search_index(game: &str, path: &str) -> Result<()> {
-    let mut pool = PgPoolOptions::new()
-        .max_connections(5)
-        .connect(&env::var("DATABASE_URL")?)
-        .await?;
+fn is_zero(count: &i32) -> bool {
+    *count == 0
+}
+
+/// Writes the search index of every mod in `game` to `path`. With `sharding`, the index is also split
+/// into `{name}_{key}.json` shards next to `path`, listed in `{name}_shards.json`.
+pub async fn dump_mod_search_index(
+    game: &str,
+    path: &str,
+    include_translations: bool,
+    sharding: Option<SearchIndexSharding>,
+) -> Result<()> {
+    let mut pool = db::connect().await?;
     let mut page = 1;
     let mut search_index = vec![];
     let page_size = 20;
     let mut last_id = None;
-    let game_id = game::get_id_by_name(&pool, game).await?;
+    // Include mods from any game domains that are aliases of this game
+    let game_id_to_name: HashMap<_, _> = game::get_all(&pool)
+        .await?
+        .into_iter()
+        .filter(|db_game| get_canonical_game_name(&db_game.name) == game)
+        .map(|db_game| (db_game.id, db_game.name))
+        .collect();
+    let game_ids: Vec<i32> = game_id_to_name.keys().copied().collect();
     loop {
         if page % 5 == 0 {
             // There's a weird issue that slows down this query after 5 iterations. Recreating the
             // connection pool seems to fix it. I don't know why.
             info!("reconnecting to database");
-            pool = PgPoolOptions::new()
-                .max_connections(5)
-                .connect(&env::var("DATABASE_URL")?)
-                .await?;
+            pool = db::connect().await?;
         }
-        let mods = game_mod::batched_get_for_search(&pool, game_id, page_size, last_id).await?;
+        let mods = game_mod::batched_get_for_search(
+            &pool,
+            &game_ids,
+            page_size,
+            last_id,
+            include_translations,
+        )
+        .await?;
         if mods.is_empty() {
             break;
         }
@@ -45,9 +123,18 @@ pub async fn dump_mod_search_index(game: &str, path: &str) -> Result<()> {
                 nexus_mod_id = mod_for_search.nexus_mod_id,
                 "read mod name for search index"
             );
+            let game_name = game_id_to_name
+                .get(&mod_for_search.game_id)
+                .expect("valid mod.game_id");
             search_index.push(ModForSearchIdTranslated {
                 name: mod_for_search.name,
                 id: mod_for_search.nexus_mod_id,
+                game: if game_name != game {
+                    Some(game_name.clone())
+                } else {
+                    None
+                },
+                translation_count: mod_for_search.translation_count,
             });
             last_id = Some(mod_for_search.id);
         }
@@ -61,5 +148,60 @@ pub async fn dump_mod_search_index(game: &str, path: &str) -> Result<()> {
     let mut file = File::create(path).await?;
     file.write_all(serde_json::to_string(&search_index)?.as_bytes())
         .await?;
+    provenance::write_manifest(path, search_index.len())?;
+    if let Some(sharding) = sharding {
+        write_shards(path, search_index, sharding).await?;
+    }
+    Ok(())
+}
+
+async fn write_shards(
+    path: &str,
+    search_index: Vec<ModForSearchIdTranslated>,
+    sharding: SearchIndexSharding,
+) -> Result<()> {
+    let path = Path::new(path);
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    let stem = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("mod_search_index");
+    let mut shards: BTreeMap<String, Vec<ModForSearchIdTranslated>> = BTreeMap::new();
+    for (index, mod_for_search) in search_index.into_iter().enumerate() {
+        shards
+            .entry(shard_key(index, &mod_for_search.name, sharding))
+            .or_default()
+            .push(mod_for_search);
+    }
+    let mut shard_index = vec![];
+    for (key, shard) in shards {
+        let file_name = format!("{}_{}.json", stem, key);
+        let shard_path = dir.join(&file_name);
+        info!(
+            "writing {} mod names for search index shard to {}",
+            shard.len(),
+            shard_path.display()
+        );
+        let mut file = File::create(&shard_path).await?;
+        file.write_all(serde_json::to_string(&shard)?.as_bytes())
+            .await?;
+        provenance::write_manifest(&shard_path, shard.len())?;
+        shard_index.push(SearchIndexShard {
+            key,
+            file: file_name,
+            count: shard.len(),
+        });
+    }
+    let shard_index_path = dir.join(format!("{}_shards.json", stem));
+    info!(
+        "writing {} search index shards to {}",
+        shard_index.len(),
+        shard_index_path.display()
+    );
+    let json = provenance::to_json(&SearchIndexShards {
+        shards: shard_index,
+    })?;
+    let mut file = File::create(&shard_index_path).await?;
+    file.write_all(json.as_bytes()).await?;
     Ok(())
 }
@@ -1,20 +1,17 @@
 use anyhow::Result;
 use chrono::NaiveDateTime;
-use sqlx::postgres::PgPoolOptions;
-use std::env;
 use std::fs::create_dir_all;
 use std::path::Path;
 use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
 use tracing::{debug, info};
 
+use crate::db;
 use crate::models::{format_radix, plugin};
+use crate::provenance;
 
 pub async fn dump_plugin_data(dir: &str, updated_after: Option<NaiveDateTime>) -> Result<()> {
-    let mut pool = PgPoolOptions::new()
-        .max_connections(5)
-        .connect(&env::var("DATABASE_URL")?)
-        .await?;
+    let mut pool = db::connect().await?;
     let mut plugin_count = 0;
     let mut page: u32 = 1;
     let page_size = 20;
@@ -24,10 +21,7 @@ pub async fn dump_plugin_data(dir: &str, updated_after: Option<NaiveDateTime>) -
             // There's a weird issue that slows down this query after 5 iterations. Recreating the
             // connection pool seems to fix it. I don't know why.
             info!("reconnecting to database");
-            pool = PgPoolOptions::new()
-                .max_connections(5)
-                .connect(&env::var("DATABASE_URL")?)
-                .await?;
+            pool = db::connect().await?;
         }
         let plugins = plugin::batched_get_by_hash_with_mods(
             &pool,
@@ -52,7 +46,7 @@ pub async fn dump_plugin_data(dir: &str, updated_after: Option<NaiveDateTime>) -
                 path.display()
             );
             let mut file = File::create(path).await?;
-            let json_val = serde_json::to_string(&plugin)?;
+            let json_val = provenance::to_json(&plugin)?;
             file.write_all(json_val.as_bytes()).await?;
             last_hash = Some(plugin.hash);
             plugin_count += 1;
@@ -0,0 +1,64 @@
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use std::fs::create_dir_all;
+use std::path::Path;
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+use tracing::{debug, info};
+
+use crate::db;
+use crate::models::plugin;
+use crate::provenance;
+
+pub async fn dump_plugin_file_name_data(
+    dir: &str,
+    updated_after: Option<NaiveDateTime>,
+) -> Result<()> {
+    let mut pool = db::connect().await?;
+    let mut file_name_count = 0;
+    let mut page: u32 = 1;
+    let page_size = 20;
+    let mut last_file_name = None;
+    loop {
+        if page % 5 == 0 {
+            // There's a weird issue that slows down this query after 5 iterations. Recreating the
+            // connection pool seems to fix it. I don't know why.
+            info!("reconnecting to database");
+            pool = db::connect().await?;
+        }
+        let plugins = plugin::batched_get_by_file_name_with_mods(
+            &pool,
+            page_size,
+            last_file_name.as_deref(),
+            updated_after,
+        )
+        .await?;
+        if plugins.is_empty() {
+            break;
+        }
+        for plugin in plugins {
+            let file_name = plugin
+                .file_name
+                .clone()
+                .expect("plugins are grouped by non-null file_name");
+            let path = Path::new(&dir);
+            create_dir_all(path)?;
+            let path = path.join(format!("{}.json", file_name));
+            debug!(
+                page = page,
+                file_name = %file_name,
+                "dumping plugin file name data to {}",
+                path.display()
+            );
+            let mut file = File::create(path).await?;
+            let json_val = provenance::to_json(&plugin)?;
+            file.write_all(json_val.as_bytes()).await?;
+            last_file_name = Some(file_name);
+            file_name_count += 1;
+        }
+        info!("dumped page {}", page);
+        page += 1;
+    }
+    info!("dumped {} plugin file name data files", file_name_count);
+    Ok(())
+}
@@ -0,0 +1,50 @@
+use anyhow::{Context, Result};
+use reqwest::Client;
+use tokio::time::sleep;
+use tracing::{debug, info};
+
+use crate::grid;
+use crate::models::{cell_lore, game, world};
+use crate::nexus_api::{SSE_GAME_NAME, USER_AGENT};
+use crate::uesp_api::{self, REQUEST_INTERVAL};
+
+/// Form id of the Tamriel worldspace in Skyrim.esm
+pub const TAMRIEL_FORM_ID: i32 = 0x3C;
+
+/// Saves the most prominent UESP map location in each exterior cell of Tamriel to the
+/// `cell_lore` table so that cell dumps can show a name instead of just coordinates.
+pub async fn enrich_cell_lore(pool: &sqlx::Pool<sqlx::Postgres>) -> Result<()> {
+    let client = Client::builder().user_agent(USER_AGENT).build()?;
+    // The base game worlds are saved by `--backfill-is-base-game` under Skyrim SE
+    let game_id = game::get_id_by_name(pool, SSE_GAME_NAME).await?;
+    let world_id = world::get_id(pool, TAMRIEL_FORM_ID, "Skyrim.esm", game_id)
+        .await
+        .context("Tamriel is missing from the worlds table, run --backfill-is-base-game first")?;
+    let mut lore_count = 0;
+    for x in grid::MIN_CELL_X..grid::MAX_CELL_X {
+        for y in grid::MIN_CELL_Y..grid::MAX_CELL_Y {
+            sleep(REQUEST_INTERVAL).await;
+            let locations = uesp_api::get_cell_locations(&client, x, y).await?;
+            // Lower display levels are shown at further zoom levels on the UESP map
+            if let Some(location) = locations
+                .iter()
+                .min_by_key(|location| location.display_level)
+            {
+                debug!(x = x, y = y, name = %location.name, "read cell lore");
+                cell_lore::insert(
+                    pool,
+                    world_id,
+                    x,
+                    y,
+                    &location.name,
+                    location.wiki_page.as_deref(),
+                )
+                .await?;
+                lore_count += 1;
+            }
+        }
+        info!("enriched all rows in x: {}", x);
+    }
+    info!("saved lore for {} cells", lore_count);
+    Ok(())
+}
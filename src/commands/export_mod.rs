@@ -0,0 +1,79 @@
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::path::Path;
+use tokio::fs::{create_dir_all, write, File};
+use tokio::io::AsyncWriteExt;
+use tracing::{info, warn};
+
+use crate::models::{cell, file, game, game_mod, plugin};
+use crate::plugin_storage;
+
+async fn write_json<T: Serialize>(dir: &Path, name: &str, data: &T) -> Result<()> {
+    let path = dir.join(name);
+    info!("writing {}", path.display());
+    let mut file = File::create(path).await?;
+    file.write_all(serde_json::to_string_pretty(data)?.as_bytes())
+        .await?;
+    Ok(())
+}
+
+/// Writes everything saved about one mod (the mod, its files, plugins, and the cells its plugins
+/// edit) to `<dir>/<game>/<nexus_mod_id>/`. The plugins themselves are copied from whichever
+/// storage root (or cold storage) they are in to `plugins/` in there, laid out like a storage root.
+pub async fn export_mod(
+    pool: &sqlx::Pool<sqlx::Postgres>,
+    game_name: &str,
+    nexus_mod_id: i32,
+    dir: &str,
+) -> Result<()> {
+    let game_id = game::get_id_by_name(pool, game_name).await?;
+    let db_mod = game_mod::get_by_nexus_mod_id(pool, game_id, nexus_mod_id)
+        .await?
+        .ok_or_else(|| anyhow!("no mod with nexus id {} in {}", nexus_mod_id, game_name))?;
+    let files = file::get_by_mod_id(pool, db_mod.id).await?;
+    let plugins = plugin::get_by_mod_id(pool, db_mod.id).await?;
+    let cells = cell::get_by_mod_id(pool, db_mod.id).await?;
+
+    let dir = Path::new(dir)
+        .join(game_name)
+        .join(nexus_mod_id.to_string());
+    create_dir_all(&dir).await?;
+    write_json(&dir, "mod.json", &db_mod).await?;
+    write_json(&dir, "files.json", &files).await?;
+    write_json(&dir, "plugins.json", &plugins).await?;
+    write_json(&dir, "cells.json", &cells).await?;
+
+    let mut copied_plugins = 0;
+    for db_plugin in &plugins {
+        let db_file = match files.iter().find(|db_file| db_file.id == db_plugin.file_id) {
+            Some(db_file) => db_file,
+            None => continue,
+        };
+        let relative_path = plugin_storage::relative_path(
+            game_name,
+            nexus_mod_id,
+            db_file.nexus_file_id,
+            &db_plugin.file_path,
+        );
+        match plugin_storage::read(db_plugin.storage_root.as_deref(), &relative_path)? {
+            Some(plugin_buf) => {
+                let export_path = dir.join("plugins").join(&relative_path);
+                if let Some(parent) = export_path.parent() {
+                    create_dir_all(parent).await?;
+                }
+                write(&export_path, plugin_buf).await?;
+                copied_plugins += 1;
+            }
+            None => warn!(path = %relative_path.display(), "plugin is not in any storage root"),
+        }
+    }
+    info!(
+        files = files.len(),
+        plugins = plugins.len(),
+        copied_plugins,
+        cells = cells.len(),
+        "exported mod to {}",
+        dir.display()
+    );
+    Ok(())
+}
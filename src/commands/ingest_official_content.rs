@@ -0,0 +1,132 @@
+//! Creation Club (and Anniversary Edition) content is not hosted on nexus, so it is ingested from
+//! the plugins in a local game install's Data folder instead. Each plugin becomes its own
+//! "official content" mod (with `is_official = true`) under a synthetic negative nexus id so it
+//! can never collide with a real nexus mod.
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::collections::HashMap;
+use tracing::{info, info_span, Instrument};
+use walkdir::WalkDir;
+
+use crate::models::file::{self, UnsavedFile};
+use crate::models::game;
+use crate::models::game_mod;
+use crate::nexus_api::files::FileCategory;
+use crate::nexus_api::get_game_id;
+use crate::plugin_processor::process_plugin;
+
+const OFFICIAL_AUTHOR_NAME: &str = "Bethesda Softworks";
+const OFFICIAL_CATEGORY_NAME: &str = "Creation Club";
+/// The titles of the official content plugins, bundled so the command runs from any directory
+const MANIFEST: &str = include_str!("../../data/creation_club.json");
+
+#[derive(Debug, Deserialize)]
+struct ManifestEntry {
+    file_name: String,
+    name: String,
+}
+
+fn official_content_id(file_name: &str) -> i32 {
+    let hash = seahash::hash(file_name.to_lowercase().as_bytes());
+    -((hash % i32::MAX as u64) as i32) - 1
+}
+
+fn is_plugin_file_name(file_name: &str) -> bool {
+    file_name.ends_with(".esp") || file_name.ends_with(".esm") || file_name.ends_with(".esl")
+}
+
+pub async fn ingest_official_content(
+    pool: &sqlx::Pool<sqlx::Postgres>,
+    game_name: &str,
+    dir: &str,
+) -> Result<()> {
+    let manifest: Vec<ManifestEntry> =
+        serde_json::from_str(MANIFEST).context("failed to deserialize data/creation_club.json")?;
+    let titles: HashMap<String, String> = manifest
+        .into_iter()
+        .map(|entry| (entry.file_name.to_lowercase(), entry.name))
+        .collect();
+
+    let game_id = get_game_id(game_name).ok_or_else(|| anyhow!("unknown game {}", game_name))?;
+    let game = game::insert(pool, game_name, game_id).await?;
+
+    let mut plugin_count = 0;
+    for entry in WalkDir::new(dir)
+        .max_depth(1)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+    {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let lowercase_file_name = file_name.to_lowercase();
+        if !is_plugin_file_name(&lowercase_file_name)
+            || !(lowercase_file_name.starts_with("cc") || titles.contains_key(&lowercase_file_name))
+        {
+            continue;
+        }
+        let plugin_span = info_span!("plugin", name = ?file_name);
+        async {
+            let name = titles
+                .get(&lowercase_file_name)
+                .map(String::as_str)
+                .unwrap_or(&file_name);
+            let nexus_id = official_content_id(&file_name);
+            let modified_at = DateTime::<Utc>::from(entry.metadata()?.modified()?).naive_utc();
+            let mut plugin_buf = std::fs::read(entry.path())?;
+
+            let db_mod = game_mod::insert(
+                pool,
+                name,
+                nexus_id,
+                OFFICIAL_AUTHOR_NAME,
+                0,
+                Some(OFFICIAL_CATEGORY_NAME),
+                None,
+                None,
+                None,
+                game.id,
+                false,
+                true,
+                modified_at,
+                modified_at,
+            )
+            .await?;
+            let db_file = file::insert(
+                pool,
+                &UnsavedFile {
+                    name,
+                    file_name: &file_name,
+                    nexus_file_id: nexus_id,
+                    mod_id: db_mod.id,
+                    category: Some("MAIN"),
+                    normalized_category: Some(FileCategory::Main),
+                    version: None,
+                    mod_version: None,
+                    size: plugin_buf.len() as i64,
+                    uploaded_at: modified_at,
+                },
+            )
+            .await?;
+
+            info!("processing official content plugin from local game install");
+            process_plugin(
+                &mut plugin_buf,
+                pool,
+                &db_file,
+                &db_mod,
+                &file_name,
+                game_name,
+            )
+            .await?;
+            file::update_downloaded_at(pool, db_file.id).await?;
+            game_mod::update_last_updated_files_at(pool, db_mod.id).await?;
+            Ok::<(), anyhow::Error>(())
+        }
+        .instrument(plugin_span)
+        .await?;
+        plugin_count += 1;
+    }
+    info!("ingested {} official content plugins", plugin_count);
+    Ok(())
+}
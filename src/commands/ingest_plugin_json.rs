@@ -0,0 +1,46 @@
+use anyhow::{anyhow, Context, Result};
+use std::path::Path;
+use tracing::info;
+
+use crate::hooks;
+use crate::models::{file, game, game_mod};
+use crate::plugin_processor::{process_plugin_json, save_plugin};
+
+/// Saves a plugin dumped to JSON by skyrim-cell-dump (e.g. on a machine without the extraction
+/// toolchain) as a plugin of the already scraped file with the given nexus file id. The plugin's
+/// file name is the JSON file name without the `.json` extension (e.g. `Foo.esp.json` is saved as
+/// `Foo.esp`).
+pub async fn ingest_plugin_json(
+    pool: &sqlx::Pool<sqlx::Postgres>,
+    game_name: &str,
+    path: &str,
+    nexus_file_id: i32,
+) -> Result<()> {
+    let game_id = game::get_id_by_name(pool, game_name).await?;
+    let db_file = file::get_by_nexus_file_id(pool, game_id, nexus_file_id)
+        .await?
+        .ok_or_else(|| anyhow!("no file with nexus id {} in {}", nexus_file_id, game_name))?;
+    let db_mod = game_mod::get(pool, db_file.mod_id)
+        .await?
+        .ok_or_else(|| anyhow!("file {} has no mod", db_file.id))?;
+
+    let file_name = Path::new(path)
+        .file_name()
+        .ok_or_else(|| anyhow!("plugin json path {} does not end in a file name", path))?
+        .to_string_lossy();
+    let file_name = file_name.strip_suffix(".json").unwrap_or(&file_name);
+    let json_buf = tokio::fs::read(path).await?;
+    let plugin = process_plugin_json(&json_buf, file_name)
+        .with_context(|| format!("failed to deserialize {}", path))?;
+    save_plugin(pool, &plugin, &db_file, &db_mod, file_name, None).await?;
+    hooks::plugin_parsed(&db_mod, &db_file, &plugin);
+    info!(
+        num_worlds = plugin.worlds.len(),
+        num_cells = plugin.cells.len(),
+        "saved {} to file {} of mod {}",
+        file_name,
+        db_file.nexus_file_id,
+        db_mod.nexus_mod_id
+    );
+    Ok(())
+}
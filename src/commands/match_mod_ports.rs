@@ -0,0 +1,113 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use tracing::info;
+
+use crate::models::game;
+use crate::models::game_mod::{self, ModForPortMatching};
+use crate::models::mod_port::{self, UnsavedModPort};
+use crate::nexus_api::PORT_GAMES;
+
+/// Words that ports add to (or originals drop from) the name of a mod, e.g. "Foo SSE" or
+/// "Foo - Special Edition Port"
+const EDITION_WORDS: &[&str] = &[
+    "se",
+    "sse",
+    "le",
+    "ae",
+    "special",
+    "legendary",
+    "anniversary",
+    "edition",
+    "port",
+    "ported",
+    "version",
+    "conversion",
+];
+
+/// Lowercases the name and strips punctuation and edition words so that an original and its port
+/// normalize to the same string
+pub fn normalize_mod_name(name: &str) -> String {
+    name.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty() && !EDITION_WORDS.contains(word))
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// Pairs each port with the originals by the same author that have the same normalized name, as
+/// (original mod id, port mod id)
+pub fn match_by_author_and_name(
+    originals: &[ModForPortMatching],
+    ports: &[ModForPortMatching],
+) -> Vec<(i32, i32)> {
+    let mut originals_by_key: HashMap<(i32, String), Vec<i32>> = HashMap::new();
+    for original in originals {
+        let name = normalize_mod_name(&original.name);
+        if !name.is_empty() {
+            originals_by_key
+                .entry((original.author_id, name))
+                .or_default()
+                .push(original.id);
+        }
+    }
+    let mut matches = vec![];
+    for port in ports {
+        let key = (port.author_id, normalize_mod_name(&port.name));
+        if let Some(original_ids) = originals_by_key.get(&key) {
+            for original_id in original_ids {
+                matches.push((*original_id, port.id));
+            }
+        }
+    }
+    matches
+}
+
+/// Links mods to their ports in other games (e.g. SSE conversions of LE mods) in the `mod_ports`
+/// table, first by shared plugins and then by the same author and name, so mod dumps can link
+/// between the versions.
+pub async fn match_mod_ports(pool: &sqlx::Pool<sqlx::Postgres>) -> Result<()> {
+    let game_ids: HashMap<_, _> = game::get_all(pool)
+        .await?
+        .into_iter()
+        .map(|game| (game.name, game.id))
+        .collect();
+    for (original_game_name, port_game_name) in PORT_GAMES {
+        let (original_game_id, port_game_id) = match (
+            game_ids.get(*original_game_name),
+            game_ids.get(*port_game_name),
+        ) {
+            (Some(original_game_id), Some(port_game_id)) => (*original_game_id, *port_game_id),
+            _ => {
+                info!(
+                    "skipping ports from {} to {}, both games have not been scraped",
+                    original_game_name, port_game_name
+                );
+                continue;
+            }
+        };
+
+        let hash_matches =
+            mod_port::insert_by_plugin_hash(pool, original_game_id, port_game_id).await?;
+        info!(
+            "linked {} ports from {} to {} by plugin hash",
+            hash_matches, original_game_name, port_game_name
+        );
+
+        let originals = game_mod::get_for_port_matching(pool, original_game_id).await?;
+        let ports = game_mod::get_for_port_matching(pool, port_game_id).await?;
+        let unsaved_mod_ports: Vec<UnsavedModPort> = match_by_author_and_name(&originals, &ports)
+            .into_iter()
+            .map(|(original_mod_id, port_mod_id)| UnsavedModPort {
+                original_mod_id,
+                port_mod_id,
+                match_reason: "author_and_name",
+            })
+            .collect();
+        let name_matches = mod_port::batched_insert(pool, &unsaved_mod_ports).await?;
+        info!(
+            "linked {} ports from {} to {} by author and name",
+            name_matches, original_game_name, port_game_name
+        );
+    }
+    Ok(())
+}
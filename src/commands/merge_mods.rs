@@ -0,0 +1,209 @@
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use tracing::{info, warn};
+
+use crate::models::{game, game_mod, mod_merge};
+use crate::plugin_storage;
+
+/// Merges the duplicate mod row `remove_id` into `keep_id`: its files, plugins, plugin cells, and
+/// ports are moved to the kept mod, metadata the kept mod is missing is copied over, and the
+/// removed row is deleted and recorded in `mod_merges` (see `dump_mod_merges`). Files both mods
+/// have are only kept once, with the kept mod's plugins. Saved plugins are stored under their
+/// mod's nexus mod id (see `plugin_storage`), so the removed mod's plugins are moved on disk to
+/// where the kept mod's plugins are.
+pub async fn merge_mods(
+    pool: &sqlx::Pool<sqlx::Postgres>,
+    keep_id: i32,
+    remove_id: i32,
+) -> Result<()> {
+    if keep_id == remove_id {
+        return Err(anyhow!("cannot merge mod {} into itself", keep_id));
+    }
+    let mut tx = pool.begin().await?;
+    let kept_mod = game_mod::get(&mut *tx, keep_id)
+        .await?
+        .ok_or_else(|| anyhow!("mod {} to keep does not exist", keep_id))?;
+    let removed_mod = game_mod::get(&mut *tx, remove_id)
+        .await?
+        .ok_or_else(|| anyhow!("mod {} to remove does not exist", remove_id))?;
+    if kept_mod.nexus_mod_id != removed_mod.nexus_mod_id {
+        warn!(
+            kept_nexus_mod_id = kept_mod.nexus_mod_id,
+            removed_nexus_mod_id = removed_mod.nexus_mod_id,
+            "merging mods with different nexus mod ids"
+        );
+    }
+
+    let game_names: HashMap<i32, String> = game::get_all(&mut *tx)
+        .await?
+        .into_iter()
+        .map(|game| (game.id, game.name))
+        .collect();
+    let removed_plugins = sqlx::query!(
+        "SELECT plugins.file_path, plugins.storage_root, files.nexus_file_id
+        FROM plugins
+        JOIN files ON files.id = plugins.file_id
+        WHERE plugins.mod_id = $1",
+        remove_id,
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let duplicate_file_ids = sqlx::query_scalar!(
+        "SELECT id FROM files
+        WHERE mod_id = $2
+        AND nexus_file_id IN (SELECT nexus_file_id FROM files WHERE mod_id = $1)",
+        keep_id,
+        remove_id,
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    if !duplicate_file_ids.is_empty() {
+        sqlx::query!(
+            "DELETE FROM plugin_worlds
+            WHERE plugin_id IN (SELECT id FROM plugins WHERE file_id = ANY($1))",
+            &duplicate_file_ids,
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "DELETE FROM plugin_cells WHERE file_id = ANY($1)",
+            &duplicate_file_ids,
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "DELETE FROM plugins WHERE file_id = ANY($1)",
+            &duplicate_file_ids,
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!("DELETE FROM files WHERE id = ANY($1)", &duplicate_file_ids)
+            .execute(&mut *tx)
+            .await?;
+        info!(
+            count = duplicate_file_ids.len(),
+            "deleted files both mods have from the removed mod"
+        );
+    }
+
+    let files = sqlx::query!(
+        "UPDATE files SET mod_id = $1, updated_at = now() WHERE mod_id = $2",
+        keep_id,
+        remove_id,
+    )
+    .execute(&mut *tx)
+    .await?;
+    let plugins = sqlx::query!(
+        "UPDATE plugins SET mod_id = $1, updated_at = now() WHERE mod_id = $2",
+        keep_id,
+        remove_id,
+    )
+    .execute(&mut *tx)
+    .await?;
+    let plugin_cells = sqlx::query!(
+        "UPDATE plugin_cells SET mod_id = $1, updated_at = now() WHERE mod_id = $2",
+        keep_id,
+        remove_id,
+    )
+    .execute(&mut *tx)
+    .await?;
+    info!(
+        files = files.rows_affected(),
+        plugins = plugins.rows_affected(),
+        plugin_cells = plugin_cells.rows_affected(),
+        "moved rows to the kept mod"
+    );
+
+    // Links between the two mods, and links the kept mod already has, would be duplicates
+    sqlx::query!(
+        "DELETE FROM mod_ports
+        WHERE (original_mod_id = $2 AND (
+            port_mod_id = $1 OR
+            port_mod_id IN (SELECT port_mod_id FROM mod_ports WHERE original_mod_id = $1)
+        ))
+        OR (port_mod_id = $2 AND (
+            original_mod_id = $1 OR
+            original_mod_id IN (SELECT original_mod_id FROM mod_ports WHERE port_mod_id = $1)
+        ))",
+        keep_id,
+        remove_id,
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!(
+        "UPDATE mod_ports SET original_mod_id = $1, updated_at = now() WHERE original_mod_id = $2",
+        keep_id,
+        remove_id,
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!(
+        "UPDATE mod_ports SET port_mod_id = $1, updated_at = now() WHERE port_mod_id = $2",
+        keep_id,
+        remove_id,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "UPDATE mods AS kept_mods SET
+            category_name = COALESCE(kept_mods.category_name, removed_mods.category_name),
+            category_id = COALESCE(kept_mods.category_id, removed_mods.category_id),
+            description = COALESCE(kept_mods.description, removed_mods.description),
+            thumbnail_link = COALESCE(kept_mods.thumbnail_link, removed_mods.thumbnail_link),
+            is_adult = COALESCE(kept_mods.is_adult, removed_mods.is_adult),
+            downloads = GREATEST(kept_mods.downloads, removed_mods.downloads),
+            language = COALESCE(kept_mods.language, removed_mods.language),
+            nexus_created_at = COALESCE(kept_mods.nexus_created_at, removed_mods.nexus_created_at),
+            nexus_updated_at = GREATEST(kept_mods.nexus_updated_at, removed_mods.nexus_updated_at),
+            first_upload_at = LEAST(kept_mods.first_upload_at, removed_mods.first_upload_at),
+            last_update_at = GREATEST(kept_mods.last_update_at, removed_mods.last_update_at),
+            updated_at = now()
+        FROM mods AS removed_mods
+        WHERE kept_mods.id = $1 AND removed_mods.id = $2",
+        keep_id,
+        remove_id,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    mod_merge::update_kept_mod_id(&mut *tx, remove_id, keep_id).await?;
+    mod_merge::insert(&mut *tx, keep_id, &removed_mod).await?;
+    sqlx::query!("DELETE FROM mods WHERE id = $1", remove_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    info!(
+        keep_id,
+        remove_id,
+        nexus_mod_id = kept_mod.nexus_mod_id,
+        "merged mods"
+    );
+
+    let mut moved = 0;
+    let mut missing = 0;
+    for removed_plugin in removed_plugins {
+        let relative_path = |db_mod: &game_mod::Mod| {
+            plugin_storage::relative_path(
+                game_names.get(&db_mod.game_id).expect("valid mod.game_id"),
+                db_mod.nexus_mod_id,
+                removed_plugin.nexus_file_id,
+                &removed_plugin.file_path,
+            )
+        };
+        let from = relative_path(&removed_mod);
+        if plugin_storage::relocate(
+            removed_plugin.storage_root.as_deref(),
+            &from,
+            &relative_path(&kept_mod),
+        )? {
+            moved += 1;
+        } else {
+            warn!(path = %from.display(), "plugin of the removed mod is not in any storage root");
+            missing += 1;
+        }
+    }
+    info!(moved, missing, "moved plugins of the removed mod on disk");
+    Ok(())
+}
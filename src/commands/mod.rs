@@ -1,24 +1,57 @@
 pub mod backfills;
+pub mod completions;
+pub mod diff_plugins;
 pub mod download_tiles;
+pub mod dump_changed_urls;
+pub mod dump_category_stats;
 pub mod dump_cell_data;
 pub mod dump_cell_edit_counts;
 pub mod dump_cell_edit_counts_over_time;
+pub mod dump_dataset;
+pub mod dump_delisted_mods;
 pub mod dump_file_data;
 pub mod dump_games;
 pub mod dump_mod_cell_counts;
 pub mod dump_mod_data;
+pub mod dump_mod_merges;
 pub mod dump_mod_search_index;
 pub mod dump_plugin_data;
+pub mod dump_plugin_file_name_data;
+pub mod enrich_cell_lore;
+pub mod export_mod;
+pub mod ingest_official_content;
+pub mod ingest_plugin_json;
+pub mod match_mod_ports;
+pub mod merge_mods;
+pub mod refresh_metadata;
+pub mod serve;
+pub mod tier_plugins;
 pub mod update;
 
-pub use download_tiles::download_tiles;
+pub use diff_plugins::{diff_plugin_versions, diff_plugins};
+pub use download_tiles::{download_tiles, verify_tiles};
+pub use dump_changed_urls::dump_changed_urls;
+pub use dump_category_stats::dump_category_stats;
 pub use dump_cell_data::dump_cell_data;
 pub use dump_cell_edit_counts::dump_cell_edit_counts;
 pub use dump_cell_edit_counts_over_time::{dump_cell_edit_counts_over_time, TimeStep};
+pub use dump_dataset::dump_dataset;
+pub use dump_delisted_mods::dump_delisted_mods;
 pub use dump_file_data::dump_file_data;
 pub use dump_games::dump_games;
 pub use dump_mod_cell_counts::dump_mod_cell_counts;
 pub use dump_mod_data::dump_mod_data;
-pub use dump_mod_search_index::dump_mod_search_index;
+pub use dump_mod_merges::dump_mod_merges;
+pub use dump_mod_search_index::{dump_mod_search_index, SearchIndexSharding};
 pub use dump_plugin_data::dump_plugin_data;
-pub use update::update;
+pub use dump_plugin_file_name_data::dump_plugin_file_name_data;
+pub use enrich_cell_lore::enrich_cell_lore;
+pub use export_mod::export_mod;
+pub use ingest_official_content::ingest_official_content;
+pub use ingest_plugin_json::ingest_plugin_json;
+pub use match_mod_ports::match_mod_ports;
+pub use merge_mods::merge_mods;
+pub use refresh_metadata::refresh_metadata;
+pub use serve::serve;
+pub use tier_plugins::tier_plugins;
+pub use update::{update, update_games, ModIdRange, UpdateOptions};
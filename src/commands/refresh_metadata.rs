@@ -0,0 +1,112 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::time::sleep;
+use tracing::{info, warn};
+
+use crate::commands::update::update_graphql_fields;
+use crate::models::game;
+use crate::models::game_mod;
+use crate::nexus_api::{self, RateLimiter, USER_AGENT};
+
+/// Hourly API requests left to `update` before the refresh stops for the hour
+pub const QUOTA_RESERVE: i32 = 100;
+/// Time between refreshes, so the refresh never competes with `update` for the quota
+const REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+const PAGE_SIZE: i64 = 50;
+
+/// How long to wait for the quota to reset before the next refresh, if the last known hourly quota
+/// is down to the reserve
+pub fn quota_wait(quota: Option<(i32, DateTime<Utc>)>, now: DateTime<Utc>) -> Option<Duration> {
+    match quota {
+        Some((remaining, reset)) if remaining <= QUOTA_RESERVE && reset > now => {
+            Some((reset - now).to_std().unwrap_or_default())
+        }
+        _ => None,
+    }
+}
+
+/// Refreshes the name, description, thumbnail, and the fields only the GraphQL API has (see
+/// `update_graphql_fields`) of every listed mod from the API forever, cycling through the mods
+/// refreshed longest ago first and only using the hourly quota `update` leaves over.
+pub async fn refresh_metadata(pool: &sqlx::Pool<sqlx::Postgres>) -> Result<()> {
+    let client = Client::builder().user_agent(USER_AGENT).build()?;
+    let rate_limiter = RateLimiter::default();
+    loop {
+        let game_names: HashMap<_, _> = game::get_all(pool)
+            .await?
+            .into_iter()
+            .map(|game| (game.id, game.name))
+            .collect();
+        let mods = game_mod::get_for_metadata_refresh(pool, PAGE_SIZE).await?;
+        if mods.is_empty() {
+            sleep(REFRESH_INTERVAL).await;
+            continue;
+        }
+        let mut nexus_mod_ids_by_game: HashMap<i32, Vec<i32>> = HashMap::new();
+        for db_mod in &mods {
+            nexus_mod_ids_by_game
+                .entry(db_mod.game_id)
+                .or_default()
+                .push(db_mod.nexus_mod_id);
+        }
+        for (game_id, nexus_mod_ids) in &nexus_mod_ids_by_game {
+            if let Some(game_name) = game_names.get(game_id) {
+                update_graphql_fields(
+                    pool,
+                    &client,
+                    &rate_limiter,
+                    game_name,
+                    *game_id,
+                    nexus_mod_ids,
+                )
+                .await;
+            }
+        }
+        for db_mod in mods {
+            if let Some(wait) = quota_wait(rate_limiter.hourly_quota(), Utc::now()) {
+                info!(duration = ?wait, "hourly quota is down to the reserve, pausing metadata refresh");
+                sleep(wait).await;
+            }
+            sleep(REFRESH_INTERVAL).await;
+
+            let game_name = match game_names.get(&db_mod.game_id) {
+                Some(game_name) => game_name,
+                None => {
+                    game_mod::update_metadata_refreshed_at(pool, db_mod.id).await?;
+                    continue;
+                }
+            };
+            let refreshed = match nexus_api::game_mod::get(
+                &client,
+                &rate_limiter,
+                game_name,
+                db_mod.nexus_mod_id,
+            )
+            .await
+            {
+                Ok(mod_resp) => match mod_resp.extract_data() {
+                    Ok(mod_data) => {
+                        game_mod::update_from_api_response(pool, &db_mod, &mod_data).await?;
+                        true
+                    }
+                    Err(err) => {
+                        warn!(error = %err, nexus_mod_id = db_mod.nexus_mod_id, "failed to parse mod metadata");
+                        false
+                    }
+                },
+                Err(err) => {
+                    warn!(error = %err, nexus_mod_id = db_mod.nexus_mod_id, "failed to refresh mod metadata");
+                    false
+                }
+            };
+            if refreshed {
+                info!(nexus_mod_id = db_mod.nexus_mod_id, "refreshed mod metadata");
+            } else {
+                game_mod::update_metadata_refreshed_at(pool, db_mod.id).await?;
+            }
+        }
+    }
+}
@@ -0,0 +1,314 @@
+use anyhow::Result;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{header, Body, Method, Request, Response, Server, StatusCode};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::time::{sleep, timeout};
+use tracing::{error, info, warn};
+
+use crate::commands::download_tiles::{parse_tile_path, TileCache};
+use crate::commands::enrich_cell_lore::TAMRIEL_FORM_ID;
+use crate::commands::refresh_metadata;
+use crate::commands::update::UpdateOptions;
+use crate::commands::update_games;
+use crate::grid;
+use crate::heatmap::{self, Region};
+use crate::models::{cell, game, world};
+use crate::nexus_api::schema_drift::{self, SchemaDriftMetrics};
+use crate::nexus_api::SSE_GAME_NAME;
+use crate::plugin_queue::{self, PluginQueueMetrics};
+use crate::status::{Stage, Status, StatusSnapshot};
+
+const DATABASE_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Serialize)]
+struct HealthResponse {
+    /// Only checked for readiness, so a slow database doesn't get a live process restarted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    database: Option<bool>,
+    #[serde(flatten)]
+    status: StatusSnapshot,
+    plugin_queue: PluginQueueMetrics,
+    graphql_schema_drift: SchemaDriftMetrics,
+}
+
+async fn check_database(pool: &sqlx::Pool<sqlx::Postgres>) -> bool {
+    matches!(
+        timeout(
+            DATABASE_CHECK_TIMEOUT,
+            sqlx::query("SELECT 1").execute(pool)
+        )
+        .await,
+        Ok(Ok(_))
+    )
+}
+
+fn json_response<T: Serialize>(status_code: StatusCode, body: &T) -> Response<Body> {
+    let mut res = Response::new(Body::from(
+        serde_json::to_string(body).expect("response body serializes to json"),
+    ));
+    *res.status_mut() = status_code;
+    res.headers_mut().insert(
+        header::CONTENT_TYPE,
+        header::HeaderValue::from_static("application/json"),
+    );
+    res
+}
+
+fn not_found() -> Response<Body> {
+    let mut res = Response::new(Body::from("not found"));
+    *res.status_mut() = StatusCode::NOT_FOUND;
+    res
+}
+
+async fn tile_response(tile_cache: &TileCache, tile_path: &str) -> Response<Body> {
+    let (z, x, y) = match parse_tile_path(tile_path) {
+        Some(tile) => tile,
+        None => return not_found(),
+    };
+    match tile_cache.get(z, x, y).await {
+        Ok(Some(bytes)) => {
+            let mut res = Response::new(Body::from(bytes));
+            let headers = res.headers_mut();
+            headers.insert(
+                header::CONTENT_TYPE,
+                header::HeaderValue::from_static("image/jpeg"),
+            );
+            // UESP's tiles rarely change, so let browsers and CDNs keep them
+            headers.insert(
+                header::CACHE_CONTROL,
+                header::HeaderValue::from_static("public, max-age=604800"),
+            );
+            res
+        }
+        Ok(None) => not_found(),
+        Err(err) => {
+            error!(error = %err, z, x, y, "failed to get tile");
+            let mut res = Response::new(Body::from("failed to get tile"));
+            *res.status_mut() = StatusCode::BAD_GATEWAY;
+            res
+        }
+    }
+}
+
+/// The id of Tamriel in the worlds table, if it has been saved by `--backfill-is-base-game` yet
+async fn get_tamriel_id(pool: &sqlx::Pool<sqlx::Postgres>) -> Option<i32> {
+    let world_id = async {
+        let game_id = game::get_id_by_name(pool, SSE_GAME_NAME).await?;
+        world::get_id(pool, TAMRIEL_FORM_ID, "Skyrim.esm", game_id).await
+    };
+    match world_id.await {
+        Ok(world_id) => Some(world_id),
+        Err(err) => {
+            warn!(error = %err, "Tamriel is missing from the worlds table, not serving heatmaps");
+            None
+        }
+    }
+}
+
+async fn heatmap_response(
+    pool: &sqlx::Pool<sqlx::Postgres>,
+    world_id: Option<i32>,
+    region: Region,
+) -> Response<Body> {
+    let world_id = match world_id {
+        Some(world_id) => world_id,
+        None => return not_found(),
+    };
+    let rendered = async {
+        let counts: HashMap<(i32, i32), i64> = cell::count_mod_edits_in_area(
+            pool,
+            "Skyrim.esm",
+            world_id,
+            region.min(),
+            region.max(),
+            false,
+        )
+        .await?
+        .into_iter()
+        .filter_map(|cell| Some(((cell.x?, cell.y?), cell.count?)))
+        .collect();
+        heatmap::render(region, &counts)
+    };
+    match rendered.await {
+        Ok(bytes) => {
+            let mut res = Response::new(Body::from(bytes));
+            let headers = res.headers_mut();
+            headers.insert(
+                header::CONTENT_TYPE,
+                header::HeaderValue::from_static("image/png"),
+            );
+            // edit counts only change when an update finishes
+            headers.insert(
+                header::CACHE_CONTROL,
+                header::HeaderValue::from_static("public, max-age=3600"),
+            );
+            res
+        }
+        Err(err) => {
+            error!(error = %err, ?region, "failed to render heatmap");
+            let mut res = Response::new(Body::from("failed to render heatmap"));
+            *res.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+            res
+        }
+    }
+}
+
+async fn handle(
+    req: Request<Body>,
+    pool: sqlx::Pool<sqlx::Postgres>,
+    status: Arc<Status>,
+    tile_cache: Option<Arc<TileCache>>,
+    world_id: Option<i32>,
+) -> Result<Response<Body>, Infallible> {
+    match (req.method(), req.uri().path()) {
+        // Liveness: the process is responsive, the body reports what it is currently doing
+        (&Method::GET, "/healthz") => {
+            let body = HealthResponse {
+                database: None,
+                status: status.snapshot(),
+                plugin_queue: plugin_queue::metrics(),
+                graphql_schema_drift: schema_drift::metrics(),
+            };
+            Ok(json_response(StatusCode::OK, &body))
+        }
+        // Readiness: the process can reach the database
+        (&Method::GET, "/readyz") => {
+            let database = check_database(&pool).await;
+            let body = HealthResponse {
+                database: Some(database),
+                status: status.snapshot(),
+                plugin_queue: plugin_queue::metrics(),
+                graphql_schema_drift: schema_drift::metrics(),
+            };
+            let status_code = if database {
+                StatusCode::OK
+            } else {
+                StatusCode::SERVICE_UNAVAILABLE
+            };
+            Ok(json_response(status_code, &body))
+        }
+        // UESP map tiles, so the map frontend can load everything from one origin
+        (&Method::GET, path) if path.starts_with("/tiles/") => match tile_cache {
+            Some(tile_cache) => Ok(tile_response(&tile_cache, &path["/tiles/".len()..]).await),
+            None => Ok(not_found()),
+        },
+        // Worldspace bounds and map tiles of a cell, so the map frontend doesn't redo the math
+        (&Method::GET, path) if path.starts_with("/grid/") => {
+            match grid::parse_cell_path(&path["/grid/".len()..]) {
+                Some((x, y)) => Ok(json_response(StatusCode::OK, &grid::cell_grid(x, y))),
+                None => Ok(not_found()),
+            }
+        }
+        // Mod edit heatmaps of the cells around a cell, for link previews
+        (&Method::GET, path) if path.starts_with("/heatmap/") => {
+            match heatmap::parse_heatmap_path(&path["/heatmap/".len()..], req.uri().query()) {
+                Some(region) => Ok(heatmap_response(&pool, world_id, region).await),
+                None => Ok(not_found()),
+            }
+        }
+        _ => Ok(not_found()),
+    }
+}
+
+/// Runs `update_games` forever, waiting `interval` between runs, while serving health endpoints on
+/// `addr`. With a `tile_dir`, UESP map tiles are also proxied at `/tiles/{z}/{x}/{y}.jpg` and
+/// cached in that folder. Heatmaps of the mod edits around a cell are served at
+/// `/heatmap/{x}/{y}.png?radius={radius}` if Tamriel was saved by `--backfill-is-base-game` before
+/// the server started, and the worldspace bounds and map tiles of a cell at
+/// `/grid/{x}/{y}`. With `refresh_metadata`, mod metadata is refreshed in
+/// the background with the API quota updates leave over. Returns once the process is asked to
+/// stop with ctrl-c or SIGTERM.
+pub async fn serve(
+    pool: &sqlx::Pool<sqlx::Postgres>,
+    addr: SocketAddr,
+    game_names: &[String],
+    options: &UpdateOptions,
+    interval: Duration,
+    tile_dir: Option<&str>,
+    refresh_metadata: bool,
+) -> Result<()> {
+    let status = Arc::new(Status::default());
+    let tile_cache = tile_dir.map(TileCache::new).transpose()?.map(Arc::new);
+    let world_id = get_tamriel_id(pool).await;
+
+    let server_pool = pool.clone();
+    let server_status = status.clone();
+    let make_service = make_service_fn(move |_conn| {
+        let pool = server_pool.clone();
+        let status = server_status.clone();
+        let tile_cache = tile_cache.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                handle(
+                    req,
+                    pool.clone(),
+                    status.clone(),
+                    tile_cache.clone(),
+                    world_id,
+                )
+            }))
+        }
+    });
+    let server = Server::try_bind(&addr)?.serve(make_service);
+    info!(%addr, "serving health endpoints");
+    tokio::spawn(async move {
+        if let Err(err) = server.await {
+            error!(error = %err, "health server failed");
+        }
+    });
+
+    if refresh_metadata {
+        let pool = pool.clone();
+        info!("refreshing mod metadata in the background");
+        tokio::spawn(async move {
+            if let Err(err) = refresh_metadata::refresh_metadata(&pool).await {
+                error!(error = %err, "metadata refresh failed");
+            }
+        });
+    }
+
+    let shutdown = shutdown_signal();
+    tokio::pin!(shutdown);
+    loop {
+        tokio::select! {
+            result = update_games(pool, game_names, options, &status) => match result {
+                Ok(_) => {
+                    status.record_successful_scrape();
+                    info!("update finished");
+                }
+                Err(err) => {
+                    error!(error = %err, "update failed");
+                }
+            },
+            result = &mut shutdown => {
+                info!("shutting down");
+                return result;
+            }
+        }
+        status.set_stage(Stage::Idle);
+        info!(duration = ?interval, "sleeping until next update");
+        tokio::select! {
+            _ = sleep(interval) => {}
+            result = &mut shutdown => {
+                info!("shutting down");
+                return result;
+            }
+        }
+    }
+}
+
+/// Resolves when the process is asked to stop with ctrl-c or SIGTERM
+async fn shutdown_signal() -> Result<()> {
+    let mut terminate = signal(SignalKind::terminate())?;
+    tokio::select! {
+        result = tokio::signal::ctrl_c() => result?,
+        _ = terminate.recv() => {}
+    }
+    Ok(())
+}
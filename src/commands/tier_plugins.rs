@@ -0,0 +1,71 @@
+use anyhow::Result;
+use chrono::{Duration, NaiveDateTime, Utc};
+use std::path::PathBuf;
+use tokio::task::spawn_blocking;
+use tracing::{debug, info, warn};
+
+use crate::models::plugin;
+use crate::plugin_storage;
+
+const PAGE_SIZE: i64 = 1000;
+
+/// The time before which a mod must have last been updated for its plugins to be tiered
+pub fn tier_cutoff(now: NaiveDateTime, years: u32) -> NaiveDateTime {
+    now - Duration::days(365 * years as i64)
+}
+
+/// Moves the plugins of mods that haven't been updated on Nexus in `years` years from their
+/// storage root into `cold_dir`, gzipped, and saves `cold_dir` as their root. Plugins are only
+/// removed from their old root once the compressed copy is written and saved.
+pub async fn tier_plugins(
+    pool: &sqlx::Pool<sqlx::Postgres>,
+    cold_dir: &str,
+    years: u32,
+) -> Result<()> {
+    let updated_before = tier_cutoff(Utc::now().naive_utc(), years);
+    info!(
+        cold_dir,
+        %updated_before,
+        "moving plugins of mods last updated before cutoff to cold storage"
+    );
+    let mut last_id = None;
+    let mut tiered = 0;
+    let mut missing = 0;
+    loop {
+        let page =
+            plugin::batched_get_for_tiering(pool, updated_before, PAGE_SIZE, last_id).await?;
+        if page.is_empty() {
+            break;
+        }
+        last_id = page.last().map(|db_plugin| db_plugin.id);
+        for db_plugin in page {
+            let relative_path = plugin_storage::relative_path(
+                &db_plugin.game_name,
+                db_plugin.nexus_mod_id,
+                db_plugin.nexus_file_id,
+                &db_plugin.file_path,
+            );
+            let path =
+                match plugin_storage::resolve(db_plugin.storage_root.as_deref(), &relative_path) {
+                    Some(path) => path,
+                    None => {
+                        warn!(path = %relative_path.display(), "plugin is not in any storage root");
+                        missing += 1;
+                        continue;
+                    }
+                };
+            let cold_root = PathBuf::from(cold_dir);
+            let source = path.clone();
+            let cold_path = spawn_blocking(move || {
+                plugin_storage::compress(&source, &cold_root, &relative_path)
+            })
+            .await??;
+            plugin::update_tiered(pool, db_plugin.id, cold_dir).await?;
+            tokio::fs::remove_file(&path).await?;
+            debug!(from = %path.display(), to = %cold_path.display(), "moved plugin to cold storage");
+            tiered += 1;
+        }
+    }
+    info!(tiered, missing, "finished moving plugins to cold storage");
+    Ok(())
+}
@@ -1,351 +1,879 @@
-use anyhow::Result;
-use chrono::{NaiveDateTime, NaiveTime};
+use anyhow::{anyhow, Result};
+use chrono::{NaiveDate, NaiveDateTime};
+use futures::future::join_all;
 use humansize::{format_size_i, DECIMAL};
 use reqwest::StatusCode;
 use reqwest::header::{HeaderMap, HeaderValue};
 use std::collections::HashSet;
 use std::io::SeekFrom;
-use std::time::Duration;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
 use tokio::io::{AsyncReadExt, AsyncSeekExt};
 use tokio::time::sleep;
-use tracing::{debug, info, info_span, warn};
+use tracing::{debug, error, info, info_span, instrument, warn, Instrument};
 
-use crate::extractors::{self, extract_with_7zip, extract_with_compress_tools, extract_with_unrar};
+use crate::archive_cache;
+use crate::cell_relevance;
+use crate::extractors::{
+    self, extract_with_7zip, extract_with_compress_tools, extract_with_unrar, ExtractorUsed,
+};
+use crate::file_filter::skip_reason;
+use crate::hooks;
+use crate::models::category;
 use crate::models::file;
-use crate::models::game;
+use crate::models::game::{self, Game};
+use crate::models::scrape_run;
 use crate::models::{game_mod, game_mod::UnsavedMod};
-use crate::nexus_api::{self, get_game_id, USER_AGENT};
-use crate::nexus_scraper;
+use crate::nexus_api::files::{ApiFile, FileCategory};
+use crate::nexus_api::graphql::{self, GRAPHQL_MODS_PAGE_SIZE};
+use crate::nexus_api::{self, get_game_id, RateLimiter, USER_AGENT};
+use crate::nexus_scraper::{self, utc_day_start, ModsSortField, SortDirection};
+use crate::status::{GameStatus, Stage, Status};
 
 const REQUEST_TIMEOUT: Duration = Duration::from_secs(7200); // 2 hours
 const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
 
+/// Inclusive range of nexus mod ids, written `<start>-<end>`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModIdRange {
+    pub start: i32,
+    pub end: i32,
+}
+
+impl ModIdRange {
+    pub fn contains(&self, nexus_mod_id: i32) -> bool {
+        self.start <= nexus_mod_id && nexus_mod_id <= self.end
+    }
+}
+
+impl FromStr for ModIdRange {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid mod id range: {} (expected <start>-<end>)", s);
+        let (start, end) = s.split_once('-').ok_or_else(invalid)?;
+        let start = start.trim().parse::<i32>().map_err(|_| invalid())?;
+        let end = end.trim().parse::<i32>().map_err(|_| invalid())?;
+        if start > end {
+            return Err(invalid());
+        }
+        Ok(ModIdRange { start, end })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UpdateOptions {
+    /// Page of the mod list to start from instead of resuming the last interrupted run
+    pub start_page: Option<usize>,
+    /// Last page of the mod list to scrape
+    pub end_page: Option<usize>,
+    /// Only create or update mods with a nexus mod id in this range
+    pub mod_id_range: Option<ModIdRange>,
+    /// Keep scraping even after many pages in a row without updated mods
+    pub full: bool,
+    /// Don't check file metadata for plugins before downloading
+    pub skip_metadata: bool,
+    /// Files of a mod that takes longer than this are deferred: the mod is left unprocessed and
+    /// the next update starts by continuing with the files it didn't get to
+    pub mod_time_budget: Option<Duration>,
+    /// Process the mods of each page in order of how likely they are to edit cells (see
+    /// `cell_relevance`) instead of the order they are listed in. Mods are only reordered within
+    /// their page, so the pages are still processed in the order they are listed in.
+    pub prioritize: bool,
+    /// After scraping the mod list, update this many of the mods whose files were processed
+    /// longest before their last update on Nexus (see `update_stale_mods`)
+    pub stale_mod_budget: Option<usize>,
+}
+
+fn build_client() -> Result<reqwest::Client> {
+    let mut headers = HeaderMap::new();
+    headers.insert("user-agent", HeaderValue::from_static(USER_AGENT));
+    Ok(reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .connect_timeout(CONNECT_TIMEOUT)
+        .default_headers(headers)
+        .build()?)
+}
+
+/// Saves the fields of the mods of the game that only the GraphQL API has (language, adult flag,
+/// download count, and exact upload and update times). The mods are still usable without them, so
+/// failures are only logged. Official content mods are not on nexus, so don't pass them.
+pub async fn update_graphql_fields(
+    pool: &sqlx::Pool<sqlx::Postgres>,
+    client: &reqwest::Client,
+    rate_limiter: &RateLimiter,
+    game_name: &str,
+    game_id: i32,
+    nexus_mod_ids: &[i32],
+) {
+    let ids: Vec<(&str, i32)> = nexus_mod_ids
+        .iter()
+        .map(|nexus_mod_id| (game_name, *nexus_mod_id))
+        .collect();
+    for ids in ids.chunks(GRAPHQL_MODS_PAGE_SIZE) {
+        let updated = async {
+            let graphql_mods = graphql::get_mods(client, rate_limiter, ids).await?;
+            game_mod::batched_update_from_graphql(pool, game_id, &graphql_mods.mods).await
+        }
+        .await;
+        if let Err(err) = updated {
+            warn!(error = %err, "failed to update mods from GraphQL API");
+        }
+    }
+}
+
+/// Scraped update dates have no time of day, so a mod whose files were processed on the same UTC
+/// day it was last updated may have been updated again after processing. Only mods processed on a
+/// later day than their last update are known to be up to date.
+fn processed_after_last_update(
+    last_updated_files_at: NaiveDateTime,
+    last_update_at: NaiveDate,
+) -> bool {
+    last_updated_files_at.date() > last_update_at
+}
+
+/// Saves the game's mod categories with the English names the API gives them, which dumps use in
+/// place of the names scraped from the mod list (localized in the language of the session)
+async fn update_categories(
+    pool: &sqlx::Pool<sqlx::Postgres>,
+    client: &reqwest::Client,
+    rate_limiter: &RateLimiter,
+    game: &Game,
+) -> Result<()> {
+    let game_resp = nexus_api::game::get(client, rate_limiter, &game.name).await?;
+    let categories = category::batched_upsert(pool, game.id, &game_resp.categories()?).await?;
+    info!(count = categories.len(), "updated categories");
+    debug!(duration = ?game_resp.wait, "sleeping");
+    sleep(game_resp.wait).await;
+    Ok(())
+}
+
+/// Runs `update` for every game at once, sharing one rate limiter between them. A game that fails
+/// does not stop the others.
+pub async fn update_games(
+    pool: &sqlx::Pool<sqlx::Postgres>,
+    game_names: &[String],
+    options: &UpdateOptions,
+    status: &Status,
+) -> Result<()> {
+    archive_cache::prune()?;
+    status.set_stage(Stage::Updating);
+    let rate_limiter = RateLimiter::default();
+    let rate_limiter = &rate_limiter;
+    let results = join_all(game_names.iter().map(|game_name| {
+        let game_status = status.game(game_name);
+        async move {
+            let result = update(pool, game_name, options, rate_limiter, &game_status).await;
+            game_status.set_stage(Stage::Idle);
+            result
+        }
+        .instrument(info_span!("game", name = %game_name))
+    }))
+    .await;
+    let mut failed_games = vec![];
+    for (game_name, result) in game_names.iter().zip(results) {
+        if let Err(err) = result {
+            error!(game_name = %game_name, error = %err, "update failed");
+            failed_games.push(game_name.as_str());
+        }
+    }
+    let updated = game_mod::update_translation_counts(pool).await?;
+    info!(updated, "updated mod translation counts");
+    if failed_games.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!("update failed for {}", failed_games.join(", ")))
+    }
+}
+
+/// Scrapes the mod list of the game twice, once without and once with translations. Each pass
+/// resumes from the page its last interrupted run stopped at unless `options.start_page` is given.
+///
+/// A pass bounded by `options.end_page` or `options.mod_id_range` re-runs a segment of a scrape: it
+/// starts at `options.start_page` (or page 1) and leaves the game's scrape run alone, so an
+/// interrupted full pass still resumes where it stopped afterwards.
 pub async fn update(
     pool: &sqlx::Pool<sqlx::Postgres>,
-    start_page: usize,
     game_name: &str,
-    full: bool,
+    options: &UpdateOptions,
+    rate_limiter: &RateLimiter,
+    status: &GameStatus,
 ) -> Result<()> {
+    let UpdateOptions {
+        start_page,
+        end_page,
+        mod_id_range,
+        full,
+        prioritize,
+        ..
+    } = *options;
+    let game_id = get_game_id(game_name).expect("valid game name");
+    let game = game::insert(pool, game_name, game_id).await?;
+    update_deferred_mods(pool, game_name, game.id, options, rate_limiter, status).await?;
+
     for include_translations in [false, true] {
-        let mut page = start_page;
         let mut has_next_page = true;
         let mut pages_with_no_updates = 0;
 
-        let mut headers = HeaderMap::new();
-        headers.insert("user-agent", HeaderValue::from_static(USER_AGENT));
-        let client = reqwest::Client::builder()
-            .timeout(REQUEST_TIMEOUT)
-            .connect_timeout(CONNECT_TIMEOUT)
-            .default_headers(headers)
-            .build()?;
+        let client = build_client()?;
 
-        let game_id = get_game_id(game_name).expect("valid game name");
-        let game = game::insert(pool, game_name, game_id).await?;
+        if !include_translations {
+            if let Err(err) = update_categories(pool, &client, rate_limiter, &game).await {
+                warn!(error = %err, "failed to update categories");
+            }
+        }
+
+        let bounded = end_page.is_some() || mod_id_range.is_some();
+        let scrape_run = if bounded {
+            info!(
+                start_page = start_page.unwrap_or(1),
+                ?end_page,
+                ?mod_id_range,
+                include_translations,
+                "scraping a segment of the mod list"
+            );
+            None
+        } else {
+            Some(
+                match (
+                    start_page,
+                    scrape_run::get_unfinished(pool, game.id, include_translations).await?,
+                ) {
+                    (None, Some(scrape_run)) => {
+                        info!(
+                            page = scrape_run.page,
+                            include_translations, "resuming interrupted scrape run"
+                        );
+                        scrape_run
+                    }
+                    (start_page, _) => {
+                        scrape_run::start(
+                            pool,
+                            game.id,
+                            include_translations,
+                            start_page.unwrap_or(1) as i32,
+                        )
+                        .await?
+                    }
+                },
+            )
+        };
+        let mut page = scrape_run
+            .as_ref()
+            .map_or(start_page.unwrap_or(1), |scrape_run| {
+                scrape_run.page as usize
+            });
 
         while has_next_page {
-            if !full && pages_with_no_updates >= 50 {
+            if end_page.map_or(false, |end_page| page > end_page) {
+                info!(?end_page, "reached the last page to scrape");
+                break;
+            }
+            // A segment is scraped to its end: a mod id range filters out most of the mods on each
+            // page, so pages without updates don't mean the rest of the list is up to date
+            if !full && !bounded && pages_with_no_updates >= 50 {
                 warn!("No updates found for 50 pages in a row, aborting");
                 break;
             }
 
             let page_span = info_span!("page", page, game_name, include_translations);
-            let _page_span = page_span.enter();
-            let mod_list_resp = nexus_scraper::get_mod_list_page(
-                &client,
-                page,
-                game_name,
-                game.nexus_game_id,
-                include_translations,
-            )
-            .await?;
-            let scraped = mod_list_resp.scrape_mods()?;
+            // Other games are updated on this thread while this one awaits, so the span is
+            // attached to the future instead of entered
+            async {
+                status.set_stage(Stage::ScrapingModList);
+                let mod_list_resp = nexus_scraper::get_mod_list_page(
+                    &client,
+                    page,
+                    game_name,
+                    game.nexus_game_id,
+                    include_translations,
+                    ModsSortField::UpdatedAt,
+                    SortDirection::Desc,
+                )
+                .await?;
+                let scraped = mod_list_resp.scrape_mods()?;
 
-            has_next_page = scraped.has_next_page;
-            let processed_mods = game_mod::bulk_get_last_updated_by_nexus_mod_ids(
-                pool,
-                game.id,
-                &scraped
+                has_next_page = scraped.has_next_page;
+                let processed_mods = game_mod::bulk_get_last_updated_by_nexus_mod_ids(
+                    pool,
+                    game.id,
+                    &scraped
+                        .mods
+                        .iter()
+                        .map(|scraped_mod| scraped_mod.nexus_mod_id)
+                        .collect::<Vec<i32>>(),
+                )
+                .await?;
+                let mods_to_create_or_update: Vec<UnsavedMod> = scraped
                     .mods
                     .iter()
-                    .map(|scraped_mod| scraped_mod.nexus_mod_id)
-                    .collect::<Vec<i32>>(),
-            )
-            .await?;
-            let mods_to_create_or_update: Vec<UnsavedMod> = scraped
-                .mods
-                .iter()
-                .filter(|scraped_mod| {
-                    if let Some(processed_mod) = processed_mods.iter().find(|processed_mod| {
-                        processed_mod.nexus_mod_id == scraped_mod.nexus_mod_id
-                    }) {
-                        if processed_mod.last_updated_files_at
-                            > NaiveDateTime::new(
-                                scraped_mod.last_update_at,
-                                NaiveTime::from_hms(0, 0, 0),
-                            )
-                        {
-                            return false;
+                    .filter(|scraped_mod| {
+                        if let Some(mod_id_range) = mod_id_range {
+                            if !mod_id_range.contains(scraped_mod.nexus_mod_id) {
+                                return false;
+                            }
                         }
-                    }
-                    true
-                })
-                .map(|scraped_mod| UnsavedMod {
-                    name: scraped_mod.name,
-                    nexus_mod_id: scraped_mod.nexus_mod_id,
-                    author_name: scraped_mod.author_name,
-                    author_id: scraped_mod.author_id,
-                    category_name: scraped_mod.category_name,
-                    category_id: scraped_mod.category_id,
-                    description: scraped_mod.desc,
-                    thumbnail_link: scraped_mod.thumbnail_link,
-                    game_id: game.id,
-                    is_translation: include_translations,
-                    last_update_at: NaiveDateTime::new(
-                        scraped_mod.last_update_at,
-                        NaiveTime::from_hms(0, 0, 0),
-                    ),
-                    first_upload_at: NaiveDateTime::new(
-                        scraped_mod.first_upload_at,
-                        NaiveTime::from_hms(0, 0, 0),
-                    ),
-                })
-                .collect();
-
-            let mods = game_mod::batched_insert(pool, &mods_to_create_or_update).await?;
-
-            if mods.is_empty() {
-                pages_with_no_updates += 1;
-            } else {
-                pages_with_no_updates = 0;
-            }
-
-            for db_mod in mods {
-                let mod_span = info_span!("mod", name = ?&db_mod.name, id = &db_mod.nexus_mod_id);
-                let _mod_span = mod_span.enter();
-                let files_resp =
-                    nexus_api::files::get(&client, game_name, db_mod.nexus_mod_id).await?;
-
-                debug!(duration = ?files_resp.wait, "sleeping");
-                sleep(files_resp.wait).await;
-
-                // Filter out replaced/deleted files (indicated by null category) and archived files
-                let files = files_resp
-                    .files()?
-                    .into_iter()
-                    .filter(|file| match file.category {
-                        None => {
-                            info!(
-                                name = file.file_name,
-                                id = file.file_id,
-                                "skipping file with no category"
-                            );
-                            false
+                        if let Some(processed_mod) = processed_mods.iter().find(|processed_mod| {
+                            processed_mod.nexus_mod_id == scraped_mod.nexus_mod_id
+                        }) {
+                            if processed_after_last_update(
+                                processed_mod.last_updated_files_at,
+                                scraped_mod.last_update_at,
+                            ) {
+                                return false;
+                            }
                         }
-                        Some(category) if category == "ARCHIVED" => false,
-                        Some(_) => true,
-                    });
-
-                let processed_file_ids: HashSet<i32> =
-                    file::get_processed_nexus_file_ids_by_mod_id(pool, db_mod.id)
-                        .await?
-                        .into_iter()
-                        .collect();
+                        true
+                    })
+                    .map(|scraped_mod| UnsavedMod {
+                        name: scraped_mod.name,
+                        nexus_mod_id: scraped_mod.nexus_mod_id,
+                        author_name: scraped_mod.author_name,
+                        author_id: scraped_mod.author_id,
+                        category_name: scraped_mod.category_name,
+                        category_id: scraped_mod.category_id,
+                        description: scraped_mod.desc,
+                        thumbnail_link: scraped_mod.thumbnail_link,
+                        game_id: game.id,
+                        is_translation: include_translations,
+                        last_update_at: utc_day_start(scraped_mod.last_update_at),
+                        first_upload_at: utc_day_start(scraped_mod.first_upload_at),
+                    })
+                    .collect();
 
-                for api_file in files {
-                    let file_span =
-                        info_span!("file", name = &api_file.file_name, id = &api_file.file_id,);
-                    let _file_span = file_span.enter();
-
-                    if processed_file_ids.contains(&(api_file.file_id as i32)) {
-                        info!("skipping file already present and processed in database");
-                        continue;
-                    }
-                    let db_file = file::insert(
+                let mut mods = game_mod::batched_insert(pool, &mods_to_create_or_update).await?;
+                let nexus_mod_ids: Vec<i32> = mods.iter().map(|m| m.nexus_mod_id).collect();
+                update_graphql_fields(
+                    pool,
+                    &client,
+                    rate_limiter,
+                    game_name,
+                    game.id,
+                    &nexus_mod_ids,
+                )
+                .await;
+                if prioritize {
+                    let metadata_plugin_counts = file::get_metadata_plugin_counts_by_mod_ids(
                         pool,
-                        &file::UnsavedFile {
-                            name: api_file.name,
-                            file_name: api_file.file_name,
-                            nexus_file_id: api_file.file_id as i32,
-                            mod_id: db_mod.id,
-                            category: api_file.category,
-                            version: api_file.version,
-                            mod_version: api_file.mod_version,
-                            size: api_file.size,
-                            uploaded_at: api_file.uploaded_at,
-                        },
+                        &mods.iter().map(|m| m.id).collect::<Vec<i32>>(),
                     )
                     .await?;
-
-                    let mut checked_metadata = false;
-                    match nexus_api::metadata::contains_plugin(&client, &api_file).await {
-                        Ok(contains_plugin) => {
-                            if let Some(contains_plugin) = contains_plugin {
-                                checked_metadata = true;
-                                if !contains_plugin {
-                                    info!(
-                                        "file metadata does not contain a plugin, skip downloading"
-                                    );
-                                    file::update_has_plugin(pool, db_file.id, false).await?;
-                                    continue;
-                                }
-                            } else {
-                                warn!("file has no metadata link, continuing with download");
-                            }
-                        }
-                        Err(err) => {
-                            warn!(error = %err, "error retreiving metadata for file, continuing with download");
-                        }
+                    let score = |db_mod: &game_mod::Mod| {
+                        let counts = metadata_plugin_counts
+                            .iter()
+                            .find(|counts| counts.mod_id == db_mod.id);
+                        cell_relevance::score(
+                            db_mod.category_name.as_deref(),
+                            counts.and_then(|c| c.checked_count).unwrap_or(0),
+                            counts.and_then(|c| c.with_plugin_count).unwrap_or(0),
+                        )
                     };
+                    cell_relevance::sort_page(&mut mods, score);
+                }
+
+                if mods.is_empty() {
+                    pages_with_no_updates += 1;
+                } else {
+                    pages_with_no_updates = 0;
+                }
 
-                    let humanized_size = format_size_i(api_file.size, DECIMAL);
-                    info!(size = %humanized_size, "decided to download file");
-                    let download_link_resp = nexus_api::download_link::get(
+                for db_mod in mods {
+                    update_mod(
+                        pool,
                         &client,
+                        rate_limiter,
                         game_name,
-                        db_mod.nexus_mod_id,
-                        api_file.file_id,
+                        db_mod,
+                        options,
+                        status,
                     )
-                    .await;
-                    if let Err(err) = &download_link_resp {
-                        if let Some(reqwest_err) = err.downcast_ref::<reqwest::Error>() {
-                            if reqwest_err.status() == Some(StatusCode::NOT_FOUND) {
-                                warn!(
-                                    status = ?reqwest_err.status(),
-                                    "failed to get download link for file, skipping file"
-                                );
-                                file::update_has_download_link(pool, db_file.id, false).await?;
-                                continue;
-                            }
-                        }
-                    }
-                    let download_link_resp = download_link_resp?;
+                    .await?;
+                }
 
-                    let mut tokio_file = match download_link_resp.download_file(&client).await {
-                        Ok(file) => {
-                            info!(bytes = api_file.size, "download finished");
-                            file::update_downloaded_at(pool, db_file.id).await?;
-                            file
-                        }
-                        Err(err) => {
-                            warn!(error = %err, "failed all attempts at downloading file, skipping file");
-                            continue;
-                        }
-                    };
+                page += 1;
+                if let Some(scrape_run) = &scrape_run {
+                    scrape_run::update_page(pool, scrape_run.id, page as i32).await?;
+                }
+                debug!(?page, ?has_next_page, "sleeping 1 second");
+                sleep(Duration::from_secs(1)).await;
+                Ok::<(), anyhow::Error>(())
+            }
+            .instrument(page_span)
+            .await?;
+        }
+        if let Some(scrape_run) = &scrape_run {
+            scrape_run::finish(pool, scrape_run.id).await?;
+        }
+    }
+    if let Some(budget) = options.stale_mod_budget {
+        update_stale_mods(pool, game_name, budget, options, rate_limiter, status).await?;
+    }
 
-                    let mut initial_bytes = [0; 8];
-                    tokio_file.seek(SeekFrom::Start(0)).await?;
-                    if let Err(err) = tokio_file.read_exact(&mut initial_bytes).await {
-                        warn!(error = %err, "failed to read initial bytes, skipping file");
-                        file::update_unable_to_extract_plugins(pool, db_file.id, true).await?;
-                        continue;
-                    }
-                    let kind = match infer::get(&initial_bytes) {
-                        Some(kind) => kind,
-                        None => {
-                            warn!(initial_bytes = ?initial_bytes, "unable to determine file type of archive, skipping file");
-                            file::update_unable_to_extract_plugins(pool, db_file.id, true).await?;
-                            continue;
-                        }
-                    };
-                    info!(
-                        mime_type = kind.mime_type(),
-                        "inferred mime_type of downloaded archive"
-                    );
-
-                    match kind.mime_type() {
-                        "application/vnd.rar" => {
-                            info!("downloaded archive is RAR archive, attempt to uncompress entire archive");
-                            // Use unrar to uncompress the entire .rar file to avoid bugs with compress_tools uncompressing certain .rar files:
-                            // https://github.com/libarchive/libarchive/issues/373, https://github.com/libarchive/libarchive/issues/1426
-                            tokio_file.seek(SeekFrom::Start(0)).await?;
-                            let mut file = tokio_file.try_clone().await?.into_std().await;
-                            match extract_with_unrar(
-                                &mut file,
-                                pool,
-                                &db_file,
-                                &db_mod,
-                                game_name,
-                                checked_metadata,
-                            )
-                            .await
-                            {
-                                Ok(_) => Ok(()),
-                                Err(err) => {
-                                    // unrar failed to extract rar file (e.g. archive has unicode filenames)
-                                    // Attempt to uncompress the archive using `7z` unix command instead
-                                    warn!(error = %err, "failed to extract file with unrar, extracting whole archive with 7z instead");
-                                    extract_with_7zip(
-                                        &mut file,
-                                        pool,
-                                        &db_file,
-                                        &db_mod,
-                                        game_name,
-                                        checked_metadata,
-                                    )
-                                    .await
-                                }
-                            }?;
-                        }
-                        _ => {
-                            tokio_file.seek(SeekFrom::Start(0)).await?;
-                            let mut file = tokio_file.try_clone().await?.into_std().await;
-
-                            match extract_with_compress_tools(
-                                &mut file, pool, &db_file, &db_mod, game_name,
-                            )
-                            .await
-                            {
-                                Ok(_) => Ok(()),
-                                Err(err) => {
-                                    if err
-                                        .downcast_ref::<extractors::compress_tools::ExtractorError>(
-                                        )
-                                        .is_some()
-                                        && (kind.mime_type() == "application/zip"
-                                            || kind.mime_type() == "application/x-7z-compressed")
-                                    {
-                                        // compress_tools or libarchive failed to extract zip/7z file (e.g. archive is deflate64 compressed)
-                                        // Attempt to uncompress the archive using `7z` unix command instead
-                                        warn!(error = %err, "failed to extract file with compress_tools, extracting whole archive with 7z instead");
-                                        extract_with_7zip(
-                                            &mut file,
-                                            pool,
-                                            &db_file,
-                                            &db_mod,
-                                            game_name,
-                                            checked_metadata,
-                                        )
-                                        .await
-                                    } else if kind.mime_type()
-                                        == "application/vnd.microsoft.portable-executable"
-                                    {
-                                        // we tried to extract this .exe file, but it's not an archive so there's nothing we can do
-                                        warn!("archive is an .exe file that cannot be extracted, skipping file");
-                                        file::update_unable_to_extract_plugins(
-                                            pool, db_file.id, true,
-                                        )
-                                        .await?;
-                                        continue;
-                                    } else {
-                                        Err(err)
-                                    }
-                                }
-                            }?;
-                        }
-                    }
+    Ok(())
+}
 
-                    debug!(duration = ?download_link_resp.wait, "sleeping");
-                    sleep(download_link_resp.wait).await;
-                }
+/// Finishes the mods that an earlier update deferred files of (see
+/// `UpdateOptions::mod_time_budget`) before anything else, since the mod list scrape only gets
+/// back to them if they show up again.
+/// A mod that fails is logged and left deferred for the next run. With `options.mod_id_range`, only
+/// mods in the range are finished.
+async fn update_deferred_mods(
+    pool: &sqlx::Pool<sqlx::Postgres>,
+    game_name: &str,
+    game_id: i32,
+    options: &UpdateOptions,
+    rate_limiter: &RateLimiter,
+    status: &GameStatus,
+) -> Result<()> {
+    let mods: Vec<game_mod::Mod> = game_mod::get_deferred(pool, game_id)
+        .await?
+        .into_iter()
+        .filter(|db_mod| {
+            options.mod_id_range.map_or(true, |mod_id_range| {
+                mod_id_range.contains(db_mod.nexus_mod_id)
+            })
+        })
+        .collect();
+    if mods.is_empty() {
+        return Ok(());
+    }
+    info!(count = mods.len(), "finishing mods with deferred files");
+    let client = build_client()?;
+    for db_mod in mods {
+        let nexus_mod_id = db_mod.nexus_mod_id;
+        if let Err(err) = update_mod(
+            pool,
+            &client,
+            rate_limiter,
+            game_name,
+            db_mod,
+            options,
+            status,
+        )
+        .await
+        {
+            error!(nexus_mod_id, error = %err, "failed to finish mod with deferred files");
+        }
+    }
+    Ok(())
+}
+
+/// Updates up to `budget` mods of the game whose files are the most out of date: processed
+/// longest before (or never since) the mod was last updated on Nexus. The mod list is scraped in
+/// order of last update and gives up after many pages without updates, so this catches the mods
+/// it misses, like mods whose updates were only seen in a metadata refresh. A mod that fails to
+/// update is logged and skipped so that it doesn't hold up the rest.
+pub async fn update_stale_mods(
+    pool: &sqlx::Pool<sqlx::Postgres>,
+    game_name: &str,
+    budget: usize,
+    options: &UpdateOptions,
+    rate_limiter: &RateLimiter,
+    status: &GameStatus,
+) -> Result<()> {
+    let client = build_client()?;
+    let game_id = game::get_id_by_name(pool, game_name).await?;
+    let mods = game_mod::get_stale(pool, game_id, budget as i64).await?;
+    info!(count = mods.len(), budget, "updating stale mods");
+    for db_mod in mods {
+        let nexus_mod_id = db_mod.nexus_mod_id;
+        if let Err(err) = update_mod(
+            pool,
+            &client,
+            rate_limiter,
+            game_name,
+            db_mod,
+            options,
+            status,
+        )
+        .await
+        {
+            error!(nexus_mod_id, error = %err, "failed to update stale mod");
+        }
+    }
+    Ok(())
+}
+
+/// Fetches the files of the mod and processes the ones that aren't in the database yet: checking
+/// their metadata for plugins, downloading them, and extracting their plugins. Marks the mod as
+/// processed unless files were deferred by `options.mod_time_budget`.
+#[instrument(name = "mod", skip_all, fields(name = ?db_mod.name, id = db_mod.nexus_mod_id))]
+async fn update_mod(
+    pool: &sqlx::Pool<sqlx::Postgres>,
+    client: &reqwest::Client,
+    rate_limiter: &RateLimiter,
+    game_name: &str,
+    db_mod: game_mod::Mod,
+    options: &UpdateOptions,
+    status: &GameStatus,
+) -> Result<()> {
+    let UpdateOptions {
+        skip_metadata,
+        mod_time_budget,
+        ..
+    } = *options;
+    status.set_stage(Stage::FetchingFiles);
+    let files_resp =
+        nexus_api::files::get(client, rate_limiter, game_name, db_mod.nexus_mod_id).await?;
+
+    debug!(duration = ?files_resp.wait, "sleeping");
+    status.set_stage(Stage::RateLimitWait);
+    sleep(files_resp.wait).await;
+
+    // Filter out replaced/deleted files (indicated by null category) and archived files
+    let files = files_resp
+        .files()?
+        .into_iter()
+        .filter(|file| match file.category {
+            None => {
+                info!(
+                    name = file.file_name,
+                    id = file.file_id,
+                    "skipping file with no category"
+                );
+                false
+            }
+            Some(_) => file.normalized_category != Some(FileCategory::Archived),
+        })
+        .collect::<Vec<_>>();
+
+    let processed_file_ids: HashSet<i32> =
+        file::get_processed_nexus_file_ids_by_mod_id(pool, db_mod.id)
+            .await?
+            .into_iter()
+            .collect();
+    file::update_last_seen_at(
+        pool,
+        db_mod.id,
+        &files
+            .iter()
+            .map(|file| file.file_id as i32)
+            .collect::<Vec<i32>>(),
+    )
+    .await?;
+
+    let mod_started_at = Instant::now();
+    let mut deferred_file_count = 0;
+    for api_file in files {
+        let file_span = info_span!("file", name = &api_file.file_name, id = &api_file.file_id,);
+        if processed_file_ids.contains(&(api_file.file_id as i32)) {
+            file_span.in_scope(|| info!("skipping file already present and processed in database"));
+            continue;
+        }
+        if let Some(mod_time_budget) = mod_time_budget {
+            if mod_started_at.elapsed() > mod_time_budget {
+                deferred_file_count += 1;
+                continue;
+            }
+        }
+        update_file(
+            pool,
+            client,
+            rate_limiter,
+            game_name,
+            &db_mod,
+            api_file,
+            skip_metadata,
+            status,
+        )
+        .instrument(file_span)
+        .await?;
+    }
+
+    if deferred_file_count > 0 {
+        warn!(
+            deferred_file_count,
+            elapsed = ?mod_started_at.elapsed(),
+            "mod exceeded its time budget, deferring remaining files to the next update"
+        );
+        game_mod::update_deferred_files(pool, db_mod.id, deferred_file_count).await?;
+        return Ok(());
+    }
+    let db_mod = game_mod::update_last_updated_files_at(pool, db_mod.id).await?;
+    hooks::mod_processed(&db_mod);
+    Ok(())
+}
+
+/// Saves the file and, unless its metadata or name shows it has no plugins, downloads it and
+/// extracts its plugins
+async fn update_file(
+    pool: &sqlx::Pool<sqlx::Postgres>,
+    client: &reqwest::Client,
+    rate_limiter: &RateLimiter,
+    game_name: &str,
+    db_mod: &game_mod::Mod,
+    api_file: ApiFile<'_>,
+    skip_metadata: bool,
+    status: &GameStatus,
+) -> Result<()> {
+    let db_file = file::insert(
+        pool,
+        &file::UnsavedFile {
+            name: api_file.name,
+            file_name: api_file.file_name,
+            nexus_file_id: api_file.file_id as i32,
+            mod_id: db_mod.id,
+            category: api_file.category,
+            normalized_category: api_file.normalized_category,
+            version: api_file.version,
+            mod_version: api_file.mod_version,
+            size: api_file.size,
+            uploaded_at: api_file.uploaded_at,
+        },
+    )
+    .await?;
 
-                game_mod::update_last_updated_files_at(pool, db_mod.id).await?;
+    status.set_stage(Stage::CheckingMetadata);
+    let (contains_plugin, content_preview) = if skip_metadata {
+        (None, None)
+    } else if let Some(contains_plugin) = db_file.metadata_contains_plugin {
+        debug!(contains_plugin, "using cached file metadata check");
+        let content_preview = db_file
+            .content_preview
+            .clone()
+            .and_then(|value| serde_json::from_value(value).ok());
+        (Some(contains_plugin), content_preview)
+    } else {
+        match nexus_api::metadata::get_content_preview(client, rate_limiter, &api_file).await {
+            Ok(Some(content_preview)) => {
+                let contains_plugin =
+                    file::update_content_preview(pool, db_file.id, &content_preview)
+                        .await?
+                        .metadata_contains_plugin;
+                (contains_plugin, Some(content_preview))
+            }
+            Ok(None) => {
+                warn!("file has no metadata link, continuing with download");
+                (None, None)
+            }
+            Err(err) => {
+                warn!(error = %err, "error retreiving metadata for file, continuing with download");
+                (None, None)
+            }
+        }
+    };
+    let checked_metadata = contains_plugin.is_some();
+    if contains_plugin != Some(true) {
+        if let Some(reason) = skip_reason(
+            api_file.name,
+            api_file.file_name,
+            content_preview.as_deref(),
+        ) {
+            info!(
+                reason = reason.as_str(),
+                "file looks like a save game or preset, skip downloading"
+            );
+            file::update_skip_reason(pool, db_file.id, reason.as_str()).await?;
+            return Ok(());
+        }
+    }
+    if contains_plugin == Some(false) {
+        info!("file metadata does not contain a plugin, skip downloading");
+        file::update_has_plugin(pool, db_file.id, false).await?;
+        return Ok(());
+    }
+
+    let (mut tokio_file, wait) = match archive_cache::open(db_file.id).await? {
+        Some(tokio_file) => {
+            info!("reusing archive kept from an earlier download");
+            (tokio_file, Duration::ZERO)
+        }
+        None => {
+            let humanized_size = format_size_i(api_file.size, DECIMAL);
+            info!(size = %humanized_size, "decided to download file");
+            status.set_stage(Stage::Downloading);
+            let download_link_resp = nexus_api::download_link::get(
+                client,
+                rate_limiter,
+                game_name,
+                db_mod.nexus_mod_id,
+                api_file.file_id,
+            )
+            .await;
+            if let Err(err) = &download_link_resp {
+                if let Some(reqwest_err) = err.downcast_ref::<reqwest::Error>() {
+                    if reqwest_err.status() == Some(StatusCode::NOT_FOUND) {
+                        warn!(
+                            status = ?reqwest_err.status(),
+                            "failed to get download link for file, skipping file"
+                        );
+                        file::update_has_download_link(pool, db_file.id, false).await?;
+                        hooks::file_failed(db_mod, &db_file, err);
+                        return Ok(());
+                    }
+                }
             }
+            let download_link_resp = download_link_resp?;
 
-            page += 1;
-            debug!(?page, ?has_next_page, "sleeping 1 second");
-            sleep(Duration::from_secs(1)).await;
+            let download = match archive_cache::download_path(db_file.id) {
+                Some(path) => download_link_resp.download_file_to(client, &path).await,
+                None => download_link_resp.download_file(client).await,
+            };
+            let tokio_file = match download {
+                Ok(file) => {
+                    info!(bytes = api_file.size, "download finished");
+                    file
+                }
+                Err(err) => {
+                    warn!(error = %err, "failed all attempts at downloading file, skipping file");
+                    hooks::file_failed(db_mod, &db_file, &err);
+                    return Ok(());
+                }
+            };
+            (tokio_file, download_link_resp.wait)
         }
+    };
+
+    status.set_stage(Stage::Extracting);
+    extract_archive(
+        pool,
+        &mut tokio_file,
+        &db_file,
+        db_mod,
+        game_name,
+        checked_metadata,
+    )
+    .await?;
+
+    debug!(duration = ?wait, "sleeping");
+    status.set_stage(Stage::RateLimitWait);
+    sleep(wait).await;
+    Ok(())
+}
+
+/// Extracts the plugins of the downloaded (or kept, see `archive_cache`) archive of the file and
+/// marks the file as downloaded. Files are only marked once their archive is extracted, or turns
+/// out to be something plugins can't be extracted from, so that a failed extraction is retried by
+/// the next update instead of the file counting as processed.
+pub async fn extract_archive(
+    pool: &sqlx::Pool<sqlx::Postgres>,
+    tokio_file: &mut tokio::fs::File,
+    db_file: &file::File,
+    db_mod: &game_mod::Mod,
+    game_name: &str,
+    checked_metadata: bool,
+) -> Result<()> {
+    extract_archive_plugins(
+        pool,
+        tokio_file,
+        db_file,
+        db_mod,
+        game_name,
+        checked_metadata,
+    )
+    .await?;
+    file::update_downloaded_at(pool, db_file.id).await?;
+    Ok(())
+}
+
+async fn extract_archive_plugins(
+    pool: &sqlx::Pool<sqlx::Postgres>,
+    tokio_file: &mut tokio::fs::File,
+    db_file: &file::File,
+    db_mod: &game_mod::Mod,
+    game_name: &str,
+    checked_metadata: bool,
+) -> Result<()> {
+    let mut initial_bytes = [0; 8];
+    tokio_file.seek(SeekFrom::Start(0)).await?;
+    if let Err(err) = tokio_file.read_exact(&mut initial_bytes).await {
+        warn!(error = %err, "failed to read initial bytes, skipping file");
+        file::update_unable_to_extract_plugins(pool, db_file.id, true).await?;
+        file::update_extractor_used(pool, db_file.id, ExtractorUsed::None.as_str()).await?;
+        hooks::file_failed(db_mod, db_file, &anyhow!(err));
+        return Ok(());
     }
+    let kind = match infer::get(&initial_bytes) {
+        Some(kind) => kind,
+        None => {
+            warn!(initial_bytes = ?initial_bytes, "unable to determine file type of archive, skipping file");
+            file::update_unable_to_extract_plugins(pool, db_file.id, true).await?;
+            file::update_extractor_used(pool, db_file.id, ExtractorUsed::None.as_str()).await?;
+            hooks::file_failed(
+                db_mod,
+                db_file,
+                &anyhow!("unable to determine file type of archive"),
+            );
+            return Ok(());
+        }
+    };
+    info!(
+        mime_type = kind.mime_type(),
+        "inferred mime_type of downloaded archive"
+    );
+    let extractor_used = match kind.mime_type() {
+        "application/vnd.rar" => {
+            info!("downloaded archive is RAR archive, attempt to uncompress entire archive");
+            // Use unrar to uncompress the entire .rar file to avoid bugs with compress_tools uncompressing certain .rar files:
+            // https://github.com/libarchive/libarchive/issues/373, https://github.com/libarchive/libarchive/issues/1426
+            tokio_file.seek(SeekFrom::Start(0)).await?;
+            let mut file = tokio_file.try_clone().await?.into_std().await;
+            match extract_with_unrar(
+                &mut file,
+                pool,
+                db_file,
+                db_mod,
+                game_name,
+                checked_metadata,
+            )
+            .await
+            {
+                Ok(extractor_used) => Ok(extractor_used),
+                Err(err) => {
+                    // unrar failed to extract rar file (e.g. archive has unicode filenames)
+                    // Attempt to uncompress the archive using `7z` unix command instead
+                    warn!(error = %err, "failed to extract file with unrar, extracting whole archive with 7z instead");
+                    extract_with_7zip(
+                        &mut file,
+                        pool,
+                        db_file,
+                        db_mod,
+                        game_name,
+                        checked_metadata,
+                    )
+                    .await
+                }
+            }?
+        }
+        _ => {
+            tokio_file.seek(SeekFrom::Start(0)).await?;
+            let mut file = tokio_file.try_clone().await?.into_std().await;
 
+            match extract_with_compress_tools(&mut file, pool, db_file, db_mod, game_name).await {
+                Ok(extractor_used) => Ok(extractor_used),
+                Err(err) => {
+                    if err
+                        .downcast_ref::<extractors::compress_tools::ExtractorError>()
+                        .is_some()
+                        && (kind.mime_type() == "application/zip"
+                            || kind.mime_type() == "application/x-7z-compressed")
+                    {
+                        // compress_tools or libarchive failed to extract zip/7z file (e.g. archive is deflate64 compressed)
+                        // Attempt to uncompress the archive using `7z` unix command instead
+                        warn!(error = %err, "failed to extract file with compress_tools, extracting whole archive with 7z instead");
+                        extract_with_7zip(
+                            &mut file,
+                            pool,
+                            db_file,
+                            db_mod,
+                            game_name,
+                            checked_metadata,
+                        )
+                        .await
+                    } else if kind.mime_type() == "application/vnd.microsoft.portable-executable" {
+                        // we tried to extract this .exe file, but it's not an archive so there's nothing we can do
+                        warn!("archive is an .exe file that cannot be extracted, skipping file");
+                        file::update_unable_to_extract_plugins(pool, db_file.id, true).await?;
+                        file::update_extractor_used(pool, db_file.id, ExtractorUsed::None.as_str())
+                            .await?;
+                        hooks::file_failed(db_mod, db_file, &err);
+                        return Ok(());
+                    } else {
+                        Err(err)
+                    }
+                }
+            }?
+        }
+    };
+    file::update_extractor_used(pool, db_file.id, extractor_used.as_str()).await?;
     Ok(())
 }
@@ -0,0 +1,75 @@
+//! Connections to the database at `DATABASE_URL`. With a schema set, every connection uses only
+//! that schema as its search_path, so every query reads and writes that schema's tables instead of
+//! the ones in `public`. This lets a staging run share a database with production and be cleaned
+//! up with `DROP SCHEMA <schema> CASCADE`.
+use anyhow::{anyhow, Context, Result};
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+use sqlx::{Executor, PgPool};
+use std::env;
+use std::str::FromStr;
+use std::sync::RwLock;
+use tracing::info;
+
+static SCHEMA: RwLock<Option<String>> = RwLock::new(None);
+
+/// Schema names are interpolated into `CREATE SCHEMA` and the search_path, so only plain
+/// identifiers are allowed
+pub fn is_valid_schema_name(schema: &str) -> bool {
+    schema.len() <= 63
+        && schema
+            .chars()
+            .next()
+            .map_or(false, |c| c.is_ascii_lowercase() || c == '_')
+        && schema
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// Uses `schema` for every connection opened after this call
+pub fn set_schema(schema: &str) -> Result<()> {
+    if !is_valid_schema_name(schema) {
+        return Err(anyhow!(
+            "invalid schema name {}, must be lowercase letters, digits, and underscores",
+            schema
+        ));
+    }
+    *SCHEMA.write().expect("schema lock is not poisoned") = Some(schema.to_string());
+    Ok(())
+}
+
+pub fn schema() -> Option<String> {
+    SCHEMA.read().expect("schema lock is not poisoned").clone()
+}
+
+pub fn connect_options() -> Result<PgConnectOptions> {
+    let options = PgConnectOptions::from_str(&env::var("DATABASE_URL")?)
+        .context("Failed to parse DATABASE_URL")?;
+    Ok(match schema() {
+        Some(schema) => options.options([("search_path", schema)]),
+        None => options,
+    })
+}
+
+pub async fn connect() -> Result<PgPool> {
+    Ok(PgPoolOptions::new()
+        .max_connections(5)
+        .connect_with(connect_options()?)
+        .await?)
+}
+
+/// Creates the schema if it doesn't exist yet and runs any migrations it is missing, so a staging
+/// run can start from an empty schema. Does nothing without a schema set, since migrations of
+/// `public` are run with `sqlx migrate`.
+pub async fn prepare_schema(pool: &PgPool) -> Result<()> {
+    if let Some(schema) = schema() {
+        pool.execute(format!("CREATE SCHEMA IF NOT EXISTS \"{}\"", schema).as_str())
+            .await
+            .context("Failed to create schema")?;
+        sqlx::migrate!("./migrations")
+            .run(pool)
+            .await
+            .context("Failed to run migrations in schema")?;
+        info!(schema = %schema, "using schema");
+    }
+    Ok(())
+}
@@ -0,0 +1,224 @@
+//! A small Discord bot (with the `discord` feature) that answers questions about the map in chat:
+//!
+//! * `!cell <x> <y>` lists the mods that edit a Skyrim exterior cell
+//! * `!mod <nexus_mod_id> [game]` summarizes a mod and how many cells it edits
+//!
+//! Parsing and replies don't depend on the Discord client, so they are built without the feature.
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+
+use crate::commands::enrich_cell_lore::TAMRIEL_FORM_ID;
+use crate::models::cell::{self, CellData, PluginCellWithCell};
+use crate::models::game_mod::{self, Mod};
+use crate::models::{game, world};
+use crate::nexus_api::{GAME_NAMES, SSE_GAME_NAME};
+
+/// How many mods a `!cell` reply names before summarizing the rest
+pub const MAX_LISTED_MODS: usize = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Query {
+    Cell {
+        x: i32,
+        y: i32,
+    },
+    Mod {
+        nexus_mod_id: i32,
+        game_name: String,
+    },
+    /// A bot command with missing or invalid arguments
+    Help,
+}
+
+pub const HELP: &str =
+    "Try `!cell <x> <y>` (e.g. `!cell 5 -12`) or `!mod <nexus mod id> [game]` (e.g. `!mod 12345`)";
+
+/// Returns the query in a message, or `None` if the message isn't meant for the bot
+pub fn parse_query(content: &str) -> Option<Query> {
+    let mut words = content.split_whitespace();
+    let query = match words.next()? {
+        "!cell" => match (words.next(), words.next()) {
+            (Some(x), Some(y)) => match (x.parse(), y.parse()) {
+                (Ok(x), Ok(y)) => Query::Cell { x, y },
+                _ => Query::Help,
+            },
+            _ => Query::Help,
+        },
+        "!mod" => {
+            let nexus_mod_id = words.next().and_then(|id| id.parse().ok());
+            let game_name = words.next().unwrap_or(SSE_GAME_NAME).to_lowercase();
+            match nexus_mod_id {
+                Some(nexus_mod_id) if GAME_NAMES.contains(&game_name.as_str()) => Query::Mod {
+                    nexus_mod_id,
+                    game_name,
+                },
+                _ => Query::Help,
+            }
+        }
+        _ => return None,
+    };
+    if words.next().is_some() {
+        return Some(Query::Help);
+    }
+    Some(query)
+}
+
+/// Reply to `!cell` for a cell no mods edit (`cell_data` is `None`) or the mods editing it
+pub fn cell_reply(x: i32, y: i32, cell_data: Option<&CellData>) -> String {
+    let cell_data = match cell_data {
+        Some(cell_data) => cell_data,
+        None => return format!("No mods edit cell {}, {}", x, y),
+    };
+    let name = match &cell_data.lore_name {
+        Some(name) => format!("Cell {}, {} ({})", x, y, name),
+        None => format!("Cell {}, {}", x, y),
+    };
+    let mods = cell_data
+        .mods
+        .as_ref()
+        .and_then(|mods| mods.as_array())
+        .cloned()
+        .unwrap_or_default();
+    let mut names: Vec<String> = mods
+        .iter()
+        .take(MAX_LISTED_MODS)
+        .filter_map(|game_mod| {
+            let name = game_mod.get("name")?.as_str()?;
+            let nexus_mod_id = game_mod.get("nexus_mod_id")?.as_i64()?;
+            Some(format!("{} ({})", name, nexus_mod_id))
+        })
+        .collect();
+    if mods.len() > MAX_LISTED_MODS {
+        names.push(format!("and {} more", mods.len() - MAX_LISTED_MODS));
+    }
+    format!(
+        "{} is edited by {} mods in {} plugins: {}",
+        name,
+        cell_data.mods_count.unwrap_or(0),
+        cell_data.plugins_count.unwrap_or(0),
+        names.join(", ")
+    )
+}
+
+/// Reply to `!mod` with the number of distinct exterior cells the mod's plugins edit
+pub fn mod_reply(db_mod: &Mod, cells: &[PluginCellWithCell]) -> String {
+    let exterior_cells = cells
+        .iter()
+        .filter_map(|cell| {
+            Some((
+                cell.x?,
+                cell.y?,
+                cell.world_form_id?,
+                cell.world_master.as_deref()?,
+            ))
+        })
+        .collect::<HashSet<_>>()
+        .len();
+    format!(
+        "{} by {} ({}) edits {} exterior cells",
+        db_mod.name,
+        db_mod.author_name,
+        db_mod.category_name.as_deref().unwrap_or("uncategorized"),
+        exterior_cells
+    )
+}
+
+/// Answers a query from the database
+pub async fn answer(pool: &sqlx::Pool<sqlx::Postgres>, query: &Query) -> Result<String> {
+    match query {
+        Query::Cell { x, y } => {
+            let game_id = game::get_id_by_name(pool, SSE_GAME_NAME).await?;
+            let world_id = world::get_id(pool, TAMRIEL_FORM_ID, "Skyrim.esm", game_id)
+                .await
+                .context("Tamriel is missing from the worlds table")?;
+            let mods_count =
+                cell::count_mod_edits(pool, "Skyrim.esm", world_id, *x, *y, true, true, None, None)
+                    .await?;
+            if mods_count.unwrap_or(0) == 0 {
+                return Ok(cell_reply(*x, *y, None));
+            }
+            let cell_data =
+                cell::get_cell_data(pool, "Skyrim.esm", world_id, *x, *y, false).await?;
+            Ok(cell_reply(*x, *y, Some(&cell_data)))
+        }
+        Query::Mod {
+            nexus_mod_id,
+            game_name,
+        } => {
+            let game_id = game::get_id_by_name(pool, game_name).await?;
+            match game_mod::get_by_nexus_mod_id(pool, game_id, *nexus_mod_id).await? {
+                Some(db_mod) => {
+                    let cells = cell::get_by_mod_id(pool, db_mod.id).await?;
+                    Ok(mod_reply(&db_mod, &cells))
+                }
+                None => Ok(format!("No mod with id {} in {}", nexus_mod_id, game_name)),
+            }
+        }
+        Query::Help => Ok(HELP.to_string()),
+    }
+}
+
+#[cfg(feature = "discord")]
+mod client {
+    use serenity::all::{Client, Context, EventHandler, GatewayIntents, Message, Ready};
+    use serenity::async_trait;
+    use tracing::{info, warn};
+
+    use super::{answer, parse_query};
+
+    pub struct Handler {
+        pub pool: sqlx::Pool<sqlx::Postgres>,
+    }
+
+    #[async_trait]
+    impl EventHandler for Handler {
+        async fn message(&self, ctx: Context, msg: Message) {
+            if msg.author.bot {
+                return;
+            }
+            let query = match parse_query(&msg.content) {
+                Some(query) => query,
+                None => return,
+            };
+            let reply = match answer(&self.pool, &query).await {
+                Ok(reply) => reply,
+                Err(err) => {
+                    warn!(error = %err, ?query, "failed to answer discord query");
+                    "Something went wrong looking that up".to_string()
+                }
+            };
+            if let Err(err) = msg.channel_id.say(&ctx.http, reply).await {
+                warn!(error = %err, "failed to send discord reply");
+            }
+        }
+
+        async fn ready(&self, _ctx: Context, ready: Ready) {
+            info!(user = %ready.user.name, "connected to discord");
+        }
+    }
+
+    pub async fn client(pool: sqlx::Pool<sqlx::Postgres>, token: &str) -> anyhow::Result<Client> {
+        let intents = GatewayIntents::GUILD_MESSAGES
+            | GatewayIntents::DIRECT_MESSAGES
+            | GatewayIntents::MESSAGE_CONTENT;
+        Ok(Client::builder(token, intents)
+            .event_handler(Handler { pool })
+            .await?)
+    }
+}
+
+/// Runs the bot until it is disconnected, logging in with the `DISCORD_TOKEN` env var
+#[cfg(feature = "discord")]
+pub async fn run(pool: &sqlx::Pool<sqlx::Postgres>) -> Result<()> {
+    let token = std::env::var("DISCORD_TOKEN").context("DISCORD_TOKEN is not set")?;
+    let mut client = client::client(pool.clone(), &token).await?;
+    client.start().await?;
+    Ok(())
+}
+
+#[cfg(not(feature = "discord"))]
+pub async fn run(_pool: &sqlx::Pool<sqlx::Postgres>) -> Result<()> {
+    Err(anyhow::anyhow!(
+        "mod-mapper was built without the discord feature"
+    ))
+}
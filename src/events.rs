@@ -0,0 +1,236 @@
+//! Publishes mod, file, and plugin processing events to NATS (with the `nats` feature) or Kafka
+//! (with the `kafka` feature) so that downstream consumers can react as soon as data is saved
+//! instead of waiting for the next dump.
+//!
+//! Events are collected by hooks (see `crate::hooks`) and sent from a background task, so a slow
+//! or unreachable broker never blocks the update.
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+use crate::hooks::{self, Hooks};
+use crate::models::file::File;
+use crate::models::format_radix;
+use crate::models::game_mod::Mod;
+use crate::plugin_processor::ParsedPlugin;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    ModProcessed {
+        mod_id: i32,
+        game_id: i32,
+        nexus_mod_id: i32,
+    },
+    PluginParsed {
+        mod_id: i32,
+        file_id: i32,
+        nexus_mod_id: i32,
+        nexus_file_id: i32,
+        file_name: String,
+        /// base 36, like the plugin hashes in the dumps
+        hash: String,
+        num_worlds: usize,
+        num_cells: usize,
+    },
+    FileFailed {
+        mod_id: i32,
+        file_id: i32,
+        nexus_mod_id: i32,
+        nexus_file_id: i32,
+        error: String,
+    },
+}
+
+impl Event {
+    /// NATS subject or Kafka topic the event is published to
+    pub fn subject(&self) -> &'static str {
+        match self {
+            Event::ModProcessed { .. } => "modmapper.mod_processed",
+            Event::PluginParsed { .. } => "modmapper.plugin_parsed",
+            Event::FileFailed { .. } => "modmapper.file_failed",
+        }
+    }
+
+    /// Messages for the same mod share a key so Kafka keeps them in order
+    #[cfg(feature = "kafka")]
+    fn key(&self) -> String {
+        match self {
+            Event::ModProcessed { mod_id, .. }
+            | Event::PluginParsed { mod_id, .. }
+            | Event::FileFailed { mod_id, .. } => mod_id.to_string(),
+        }
+    }
+}
+
+type SharedSender = Arc<Mutex<Option<UnboundedSender<Event>>>>;
+
+struct EventHooks {
+    sender: SharedSender,
+}
+
+impl EventHooks {
+    fn send(&self, event: Event) {
+        let sender = self
+            .sender
+            .lock()
+            .expect("event sender lock is not poisoned");
+        // events after `Events::close` are dropped on purpose, only a crashed publisher is logged
+        if let Some(sender) = sender.as_ref() {
+            if sender.send(event).is_err() {
+                warn!("event publisher has stopped, dropping event");
+            }
+        }
+    }
+}
+
+impl Hooks for EventHooks {
+    fn on_mod_processed(&self, db_mod: &Mod) {
+        self.send(Event::ModProcessed {
+            mod_id: db_mod.id,
+            game_id: db_mod.game_id,
+            nexus_mod_id: db_mod.nexus_mod_id,
+        });
+    }
+
+    fn on_plugin_parsed(&self, db_mod: &Mod, db_file: &File, plugin: &ParsedPlugin) {
+        self.send(Event::PluginParsed {
+            mod_id: db_mod.id,
+            file_id: db_file.id,
+            nexus_mod_id: db_mod.nexus_mod_id,
+            nexus_file_id: db_file.nexus_file_id,
+            file_name: plugin.file_name.clone(),
+            hash: format_radix(plugin.hash, 36),
+            num_worlds: plugin.worlds.len(),
+            num_cells: plugin.cells.len(),
+        });
+    }
+
+    fn on_file_failed(&self, db_mod: &Mod, db_file: &File, error: &anyhow::Error) {
+        self.send(Event::FileFailed {
+            mod_id: db_mod.id,
+            file_id: db_file.id,
+            nexus_mod_id: db_mod.nexus_mod_id,
+            nexus_file_id: db_file.nexus_file_id,
+            error: error.to_string(),
+        });
+    }
+}
+
+pub enum Publisher {
+    #[cfg(feature = "nats")]
+    Nats(async_nats::Client),
+    #[cfg(feature = "kafka")]
+    Kafka(rdkafka::producer::FutureProducer),
+}
+
+impl Publisher {
+    /// Connects to the broker at `url`, either `nats://host:port` or `kafka://host:port[,host:port]`
+    pub async fn connect(url: &str) -> Result<Publisher> {
+        match url.split_once("://") {
+            #[cfg(feature = "nats")]
+            Some(("nats", _)) => Ok(Publisher::Nats(async_nats::connect(url).await?)),
+            #[cfg(feature = "kafka")]
+            Some(("kafka", brokers)) => Ok(Publisher::Kafka(
+                rdkafka::ClientConfig::new()
+                    .set("bootstrap.servers", brokers)
+                    .create()?,
+            )),
+            #[cfg(not(feature = "nats"))]
+            Some(("nats", _)) => Err(anyhow!("mod-mapper was built without the nats feature")),
+            #[cfg(not(feature = "kafka"))]
+            Some(("kafka", _)) => Err(anyhow!("mod-mapper was built without the kafka feature")),
+            _ => Err(anyhow!(
+                "events url must start with nats:// or kafka://, got {}",
+                url
+            )),
+        }
+    }
+
+    #[allow(unused_variables)]
+    async fn publish(&self, event: &Event) -> Result<()> {
+        let payload = serde_json::to_vec(event)?;
+        match *self {
+            #[cfg(feature = "nats")]
+            Publisher::Nats(ref client) => {
+                client
+                    .publish(event.subject().to_string(), payload.into())
+                    .await?;
+                Ok(())
+            }
+            #[cfg(feature = "kafka")]
+            Publisher::Kafka(ref producer) => {
+                let key = event.key();
+                producer
+                    .send(
+                        rdkafka::producer::FutureRecord::to(event.subject())
+                            .key(&key)
+                            .payload(&payload),
+                        std::time::Duration::from_secs(5),
+                    )
+                    .await
+                    .map_err(|(err, _)| err)?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Waits for the messages the client buffers to reach the broker
+    async fn flush(&self) -> Result<()> {
+        match *self {
+            #[cfg(feature = "nats")]
+            Publisher::Nats(ref client) => {
+                client.flush().await?;
+                Ok(())
+            }
+            #[cfg(feature = "kafka")]
+            Publisher::Kafka(ref producer) => {
+                use rdkafka::producer::Producer;
+                producer.flush(std::time::Duration::from_secs(5))?;
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Handle to the background task publishing events
+pub struct Events {
+    sender: SharedSender,
+    task: JoinHandle<()>,
+}
+
+impl Events {
+    /// Stops collecting events and waits for the ones already collected to be published and
+    /// flushed to the broker. The hooks stay registered but silently drop any later events.
+    pub async fn close(self) -> Result<()> {
+        self.sender
+            .lock()
+            .expect("event sender lock is not poisoned")
+            .take();
+        self.task.await?;
+        Ok(())
+    }
+}
+
+/// Registers hooks that publish every event with `publisher` from a background task
+pub fn register(publisher: Publisher) -> Events {
+    let (sender, mut receiver) = unbounded_channel::<Event>();
+    let sender = Arc::new(Mutex::new(Some(sender)));
+    hooks::register(EventHooks {
+        sender: sender.clone(),
+    });
+    let task = tokio::spawn(async move {
+        while let Some(event) = receiver.recv().await {
+            if let Err(err) = publisher.publish(&event).await {
+                warn!(error = %err, subject = event.subject(), "failed to publish event");
+            }
+        }
+        if let Err(err) = publisher.flush().await {
+            warn!(error = %err, "failed to flush published events");
+        }
+    });
+    Events { sender, task }
+}
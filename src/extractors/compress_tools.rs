@@ -7,9 +7,10 @@ use std::io::Seek;
 use std::io::SeekFrom;
 use tracing::{info, info_span};
 
+use super::ExtractorUsed;
 use crate::models::file::File;
 use crate::models::game_mod::Mod;
-use crate::plugin_processor::process_plugin;
+use crate::plugin_queue::process_plugins;
 
 #[derive(Debug)]
 pub struct ExtractorError;
@@ -89,22 +90,16 @@ pub async fn extract_with_compress_tools(
     db_file: &File,
     db_mod: &Mod,
     game_name: &str,
-) -> Result<()> {
-    let extractor = Extractor::new(file);
-    for plugin in extractor.into_iter() {
-        let (file_path, mut plugin_buf) = plugin?;
-        let plugin_span = info_span!("plugin", name = ?file_path);
-        let _plugin_span = plugin_span.enter();
-        let safe_file_path = file_path.replace('\\', "/");
-        process_plugin(
-            &mut plugin_buf,
-            pool,
-            db_file,
-            db_mod,
-            &safe_file_path,
-            game_name,
-        )
-        .await?;
-    }
-    Ok(())
+) -> Result<ExtractorUsed> {
+    let mut file = file.try_clone()?;
+    process_plugins(pool, db_file, db_mod, game_name, move |plugins| {
+        let extractor = Extractor::new(&mut file);
+        for plugin in extractor.into_iter() {
+            let (file_path, plugin_buf) = plugin?;
+            plugins.send(file_path.replace('\\', "/"), plugin_buf)?;
+        }
+        Ok(())
+    })
+    .await?;
+    Ok(ExtractorUsed::CompressTools)
 }
@@ -5,3 +5,24 @@ pub mod unrar;
 pub use self::compress_tools::extract_with_compress_tools;
 pub use self::unrar::extract_with_unrar;
 pub use seven_zip::extract_with_7zip;
+
+/// Which extractor got the plugins out of a file's archive, saved as `files.extractor_used`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtractorUsed {
+    CompressTools,
+    SevenZip,
+    Unrar,
+    /// The archive was downloaded but couldn't be extracted
+    None,
+}
+
+impl ExtractorUsed {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExtractorUsed::CompressTools => "compress_tools",
+            ExtractorUsed::SevenZip => "7zip",
+            ExtractorUsed::Unrar => "unrar",
+            ExtractorUsed::None => "none",
+        }
+    }
+}
@@ -1,13 +1,15 @@
 use anyhow::Result;
 use std::io::{Seek, SeekFrom};
 use std::process::Command;
-use tempfile::tempdir;
-use tracing::{info, info_span, warn};
+use tempfile::tempdir_in;
+use tracing::{info, warn};
 use walkdir::WalkDir;
 
+use super::ExtractorUsed;
 use crate::models::game_mod::Mod;
 use crate::models::{file, file::File};
-use crate::plugin_processor::process_plugin;
+use crate::plugin_queue::process_plugins;
+use crate::temp_dir;
 
 pub async fn extract_with_7zip(
     file: &mut std::fs::File,
@@ -16,9 +18,9 @@ pub async fn extract_with_7zip(
     db_mod: &Mod,
     game_name: &str,
     checked_metadata: bool,
-) -> Result<()> {
+) -> Result<ExtractorUsed> {
     file.seek(SeekFrom::Start(0))?;
-    let temp_dir = tempdir()?;
+    let temp_dir = tempdir_in(temp_dir::get())?;
     let temp_file_path = temp_dir.path().join("download.zip");
     let mut temp_file = std::fs::File::create(&temp_file_path)?;
     std::io::copy(file, &mut temp_file)?;
@@ -36,38 +38,35 @@ pub async fn extract_with_7zip(
     if !status.success() && !checked_metadata {
         warn!("failed to extract archive and server has no metadata, skipping file");
         file::update_unable_to_extract_plugins(pool, db_file.id, true).await?;
-        return Ok(());
+        return Ok(ExtractorUsed::None);
     }
 
-    for entry in WalkDir::new(&extracted_path)
-        .contents_first(true)
-        .into_iter()
-        .filter_entry(|e| {
-            if e.file_type().is_dir() {
-                return false;
-            }
-            if let Some(extension) = e.path().extension() {
-                extension == "esp" || extension == "esm" || extension == "esl"
-            } else {
-                false
-            }
-        })
-    {
-        let entry = entry?;
-        let file_path = entry.path();
-        let plugin_span = info_span!("plugin", name = ?file_path);
-        let _plugin_span = plugin_span.enter();
-        info!("processing uncompressed file from downloaded archive");
-        let mut plugin_buf = std::fs::read(extracted_path.join(file_path))?;
-        process_plugin(
-            &mut plugin_buf,
-            pool,
-            db_file,
-            db_mod,
-            &file_path.to_string_lossy(),
-            game_name,
-        )
-        .await?;
-    }
-    Ok(())
+    process_plugins(pool, db_file, db_mod, game_name, move |plugins| {
+        for entry in WalkDir::new(&extracted_path)
+            .contents_first(true)
+            .into_iter()
+            .filter_entry(|e| {
+                if e.file_type().is_dir() {
+                    return false;
+                }
+                if let Some(extension) = e.path().extension() {
+                    extension == "esp" || extension == "esm" || extension == "esl"
+                } else {
+                    false
+                }
+            })
+        {
+            let entry = entry?;
+            let file_path = entry.path();
+            info!(
+                ?file_path,
+                "reading uncompressed file from downloaded archive"
+            );
+            let plugin_buf = std::fs::read(extracted_path.join(file_path))?;
+            plugins.send(file_path.to_string_lossy().to_string(), plugin_buf)?;
+        }
+        Ok(())
+    })
+    .await?;
+    Ok(ExtractorUsed::SevenZip)
 }
@@ -1,11 +1,13 @@
 use anyhow::Result;
-use tempfile::tempdir;
+use tempfile::tempdir_in;
 use tracing::{error, info, warn};
 use unrar::Archive;
 
+use super::ExtractorUsed;
 use crate::models::file::{self, File};
 use crate::models::game_mod::Mod;
-use crate::plugin_processor::process_plugin;
+use crate::plugin_queue::process_plugins;
+use crate::temp_dir;
 
 pub async fn extract_with_unrar(
     file: &mut std::fs::File,
@@ -14,8 +16,8 @@ pub async fn extract_with_unrar(
     db_mod: &Mod,
     game_name: &str,
     checked_metadata: bool,
-) -> Result<()> {
-    let temp_dir = tempdir()?;
+) -> Result<ExtractorUsed> {
+    let temp_dir = tempdir_in(temp_dir::get())?;
     let temp_file_path = temp_dir.path().join("download.rar");
     let mut temp_file = std::fs::File::create(&temp_file_path)?;
     std::io::copy(file, &mut temp_file)?;
@@ -38,7 +40,7 @@ pub async fn extract_with_unrar(
             if !checked_metadata {
                 warn!("failed to read archive and server has no metadata, skipping file");
                 file::update_unable_to_extract_plugins(pool, db_file.id, true).await?;
-                return Ok(());
+                return Ok(ExtractorUsed::None);
             } else {
                 error!("failed to read archive, but server had metadata");
                 panic!("failed to read archive, but server had metadata");
@@ -59,32 +61,29 @@ pub async fn extract_with_unrar(
             Err(err) => {
                 warn!(error = %err, "failed to extract with unrar");
                 file::update_unable_to_extract_plugins(pool, db_file.id, true).await?;
-                return Ok(());
+                return Ok(ExtractorUsed::None);
             }
             Ok(extract) => extract,
         };
         if let Err(err) = extract.process() {
             warn!(error = %err, "failed to extract with unrar");
             file::update_unable_to_extract_plugins(pool, db_file.id, true).await?;
-            return Ok(());
+            return Ok(ExtractorUsed::None);
         }
 
-        for file_path in plugin_file_paths.iter() {
-            info!(
-                ?file_path,
-                "processing uncompressed file from downloaded archive"
-            );
-            let mut plugin_buf = std::fs::read(temp_dir.path().join(file_path))?;
-            process_plugin(
-                &mut plugin_buf,
-                pool,
-                db_file,
-                db_mod,
-                &file_path.to_string_lossy(),
-                game_name,
-            )
-            .await?;
-        }
+        let extracted_path = temp_dir.path().to_path_buf();
+        process_plugins(pool, db_file, db_mod, game_name, move |plugins| {
+            for file_path in plugin_file_paths.iter() {
+                info!(
+                    ?file_path,
+                    "reading uncompressed file from downloaded archive"
+                );
+                let plugin_buf = std::fs::read(extracted_path.join(file_path))?;
+                plugins.send(file_path.to_string_lossy().to_string(), plugin_buf)?;
+            }
+            Ok(())
+        })
+        .await?;
     }
-    Ok(())
+    Ok(ExtractorUsed::Unrar)
 }
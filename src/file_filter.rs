@@ -0,0 +1,110 @@
+//! Heuristics for files that are categorized as mod files on nexus but are actually save games or
+//! character/body presets, which never contain plugins and would only waste a download and
+//! extraction.
+use crate::nexus_api::metadata::{contains_plugin, ContentPreviewEntry};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    SaveGame,
+    Preset,
+}
+
+impl SkipReason {
+    /// Value saved in the `skip_reason` column of the files table
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SkipReason::SaveGame => "save_game",
+            SkipReason::Preset => "preset",
+        }
+    }
+}
+
+const SAVE_GAME_EXTENSIONS: [&str; 2] = [".ess", ".skse"];
+const PRESET_EXTENSIONS: [&str; 2] = [".jslot", ".slp"];
+/// BodySlide presets are plain xml files, so they are only recognized in these folders
+const PRESET_DIRECTORIES: [&str; 2] = ["sliderpresets", "slidergroups"];
+/// Files that are commonly packaged alongside saves and presets
+const IGNORED_EXTENSIONS: [&str; 7] = [".txt", ".md", ".pdf", ".jpg", ".jpeg", ".png", ".url"];
+
+const SAVE_GAME_NAMES: [&str; 3] = ["savegame", "save game", "save file"];
+const PRESET_NAMES: [&str; 3] = ["bodyslide preset", "slider preset", "racemenu preset"];
+
+fn ends_with_any(name: &str, extensions: &[&str]) -> bool {
+    extensions.iter().any(|extension| name.ends_with(extension))
+}
+
+/// Lowercased paths of every file in the content preview
+fn file_paths(entries: &[ContentPreviewEntry], parent: &str, paths: &mut Vec<String>) {
+    for entry in entries {
+        let path = format!("{}/{}", parent, entry.name.to_lowercase());
+        if entry.is_directory {
+            file_paths(&entry.children, &path, paths);
+        } else {
+            paths.push(path);
+        }
+    }
+}
+
+fn is_preset_path(path: &str) -> bool {
+    ends_with_any(path, &PRESET_EXTENSIONS)
+        || (path.ends_with(".xml")
+            && path
+                .split('/')
+                .any(|directory| PRESET_DIRECTORIES.contains(&directory)))
+}
+
+fn skip_reason_from_content_preview(entries: &[ContentPreviewEntry]) -> Option<SkipReason> {
+    let mut paths = vec![];
+    file_paths(entries, "", &mut paths);
+    let paths: Vec<&String> = paths
+        .iter()
+        .filter(|path| !ends_with_any(path, &IGNORED_EXTENSIONS))
+        .collect();
+    if paths.is_empty() {
+        None
+    } else if paths
+        .iter()
+        .all(|path| ends_with_any(path, &SAVE_GAME_EXTENSIONS))
+    {
+        Some(SkipReason::SaveGame)
+    } else if paths.iter().all(|path| is_preset_path(path)) {
+        Some(SkipReason::Preset)
+    } else {
+        None
+    }
+}
+
+fn skip_reason_from_names(name: &str, file_name: &str) -> Option<SkipReason> {
+    let name = name.to_lowercase();
+    let file_name = file_name.to_lowercase();
+    if ends_with_any(&file_name, &SAVE_GAME_EXTENSIONS)
+        || SAVE_GAME_NAMES
+            .iter()
+            .any(|save_game_name| name.contains(save_game_name))
+    {
+        Some(SkipReason::SaveGame)
+    } else if ends_with_any(&file_name, &PRESET_EXTENSIONS)
+        || PRESET_NAMES
+            .iter()
+            .any(|preset_name| name.contains(preset_name))
+    {
+        Some(SkipReason::Preset)
+    } else {
+        None
+    }
+}
+
+/// Decides whether a file should be skipped without downloading it. The archive's content preview
+/// is trusted over the file's name when it is available, and files whose preview lists a plugin
+/// are never skipped.
+pub fn skip_reason(
+    name: &str,
+    file_name: &str,
+    content_preview: Option<&[ContentPreviewEntry]>,
+) -> Option<SkipReason> {
+    match content_preview {
+        Some(entries) if contains_plugin(entries) => None,
+        Some(entries) if !entries.is_empty() => skip_reason_from_content_preview(entries),
+        _ => skip_reason_from_names(name, file_name),
+    }
+}
@@ -0,0 +1,160 @@
+//! Conversions between exterior cell coordinates, worldspace units, and the z/x/y map tiles UESP
+//! serves (and `download_tiles` saves), for the Skyrim worldspace.
+//!
+//! Cell (x, y) covers the worldspace units from (x * 4096, y * 4096) up to the next cell, with y
+//! growing north. The tiles cover a square of 128 by 128 cells centered on cell (0, 0), split into
+//! 2^(z - 9) by 2^(z - 9) tiles at zoom level z, with tile (0, 0) in the north west corner.
+use serde::Serialize;
+
+/// Width and height of an exterior cell in worldspace units
+pub const CELL_SIZE: i32 = 4096;
+/// Zoom levels UESP has tiles for
+pub const ZOOM_LEVELS: std::ops::Range<u32> = 10..18;
+/// Western and southern edge of the tiled map in worldspace units
+pub const MAP_MIN: i32 = -64 * CELL_SIZE;
+/// Width and height of the tiled map in worldspace units
+pub const MAP_SIZE: i32 = 128 * CELL_SIZE;
+/// Western edge of the exterior cells that are dumped and counted, which reach past the tiled map
+pub const MIN_CELL_X: i32 = -77;
+/// Exclusive
+pub const MAX_CELL_X: i32 = 75;
+/// Southern edge of the exterior cells that are dumped and counted
+pub const MIN_CELL_Y: i32 = -50;
+/// Exclusive
+pub const MAX_CELL_Y: i32 = 44;
+
+/// A rectangle of the worldspace, from `left` and `bottom` up to (but not including) `right` and
+/// `top`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct WorldBounds {
+    pub left: i32,
+    pub bottom: i32,
+    pub right: i32,
+    pub top: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Tile {
+    pub z: u32,
+    pub x: u32,
+    pub y: u32,
+}
+
+pub fn cell_bounds(x: i32, y: i32) -> WorldBounds {
+    WorldBounds {
+        left: x * CELL_SIZE,
+        bottom: y * CELL_SIZE,
+        right: (x + 1) * CELL_SIZE,
+        top: (y + 1) * CELL_SIZE,
+    }
+}
+
+/// The cell the worldspace position is in
+pub fn world_to_cell(world_x: i32, world_y: i32) -> (i32, i32) {
+    (world_x.div_euclid(CELL_SIZE), world_y.div_euclid(CELL_SIZE))
+}
+
+/// Whether the cell is inside the tiled map
+pub fn is_on_map(x: i32, y: i32) -> bool {
+    let cells = MAP_SIZE / CELL_SIZE;
+    let min = MAP_MIN / CELL_SIZE;
+    (min..min + cells).contains(&x) && (min..min + cells).contains(&y)
+}
+
+/// Tiles along each side of the map at zoom level `z`
+pub fn tiles_per_side(z: u32) -> u32 {
+    2_u32.pow(z - 9)
+}
+
+pub fn is_valid_tile(z: u32, x: u32, y: u32) -> bool {
+    ZOOM_LEVELS.contains(&z) && x < tiles_per_side(z) && y < tiles_per_side(z)
+}
+
+/// Width and height of a tile at zoom level `z` in worldspace units
+pub fn tile_size(z: u32) -> i32 {
+    MAP_SIZE / tiles_per_side(z) as i32
+}
+
+/// The tile at zoom level `z` the worldspace position is in, if it is on the map
+pub fn world_to_tile(z: u32, world_x: i32, world_y: i32) -> Option<Tile> {
+    if !ZOOM_LEVELS.contains(&z) {
+        return None;
+    }
+    let size = tile_size(z) as i64;
+    let x = (world_x as i64 - MAP_MIN as i64).div_euclid(size);
+    let y = ((MAP_MIN as i64 + MAP_SIZE as i64) - world_y as i64 - 1).div_euclid(size);
+    let tiles = tiles_per_side(z) as i64;
+    if (0..tiles).contains(&x) && (0..tiles).contains(&y) {
+        Some(Tile {
+            z,
+            x: x as u32,
+            y: y as u32,
+        })
+    } else {
+        None
+    }
+}
+
+/// The tile at zoom level `z` the cell is in. At the highest zoom levels a cell spans several
+/// tiles, and this is the one its south west corner is in.
+pub fn cell_to_tile(z: u32, x: i32, y: i32) -> Option<Tile> {
+    if !is_on_map(x, y) {
+        return None;
+    }
+    let bounds = cell_bounds(x, y);
+    world_to_tile(z, bounds.left, bounds.bottom)
+}
+
+pub fn tile_bounds(tile: Tile) -> WorldBounds {
+    let size = tile_size(tile.z);
+    let left = MAP_MIN + tile.x as i32 * size;
+    let top = MAP_MIN + MAP_SIZE - tile.y as i32 * size;
+    WorldBounds {
+        left,
+        bottom: top - size,
+        right: left + size,
+        top,
+    }
+}
+
+/// The south west and north east cells of the cells a tile covers
+pub fn tile_cells(tile: Tile) -> ((i32, i32), (i32, i32)) {
+    let bounds = tile_bounds(tile);
+    (
+        world_to_cell(bounds.left, bounds.bottom),
+        world_to_cell(bounds.right - 1, bounds.top - 1),
+    )
+}
+
+/// The cell drawn at `column` and `row` of an image of cells with north up, whose top left cell is
+/// `top_left`
+pub fn image_cell(top_left: (i32, i32), column: u32, row: u32) -> (i32, i32) {
+    (top_left.0 + column as i32, top_left.1 - row as i32)
+}
+
+/// Parses a cell path like `-3/12` into (x, y)
+pub fn parse_cell_path(path: &str) -> Option<(i32, i32)> {
+    let (x, y) = path.split_once('/')?;
+    Some((x.parse().ok()?, y.parse().ok()?))
+}
+
+/// Where a cell is in the worldspace and on the map tiles at each zoom level
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct CellGrid {
+    pub x: i32,
+    pub y: i32,
+    pub bounds: WorldBounds,
+    /// Empty for cells off the tiled map
+    pub tiles: Vec<Tile>,
+}
+
+/// The grid positions of the cell. Cells off the tiled map (see `is_on_map`) still have bounds
+/// but no tiles.
+pub fn cell_grid(x: i32, y: i32) -> CellGrid {
+    CellGrid {
+        x,
+        y,
+        bounds: cell_bounds(x, y),
+        tiles: ZOOM_LEVELS.filter_map(|z| cell_to_tile(z, x, y)).collect(),
+    }
+}
@@ -0,0 +1,113 @@
+//! Small PNG heatmaps of how many mods edit each cell in a square of the Skyrim worldspace, served
+//! in serve mode for link previews and embeds where the full map can't be shown.
+//!
+//! North is up, so the top row of the image is the highest y of the region.
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+
+use crate::grid;
+
+pub const DEFAULT_RADIUS: i32 = 5;
+pub const MAX_RADIUS: i32 = 20;
+/// Pixels per cell
+pub const CELL_SIZE: u32 = 16;
+
+const BACKGROUND: [u8; 3] = [32, 32, 32];
+const COLD: [u8; 3] = [255, 230, 80];
+const HOT: [u8; 3] = [200, 20, 20];
+const CENTER_BORDER: [u8; 3] = [255, 255, 255];
+
+/// The square of cells `radius` cells out from (x, y) in every direction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    pub x: i32,
+    pub y: i32,
+    pub radius: i32,
+}
+
+impl Region {
+    pub fn min(&self) -> (i32, i32) {
+        (self.x - self.radius, self.y - self.radius)
+    }
+
+    pub fn max(&self) -> (i32, i32) {
+        (self.x + self.radius, self.y + self.radius)
+    }
+
+    /// Cells along each side
+    pub fn size(&self) -> u32 {
+        (self.radius * 2 + 1) as u32
+    }
+}
+
+/// Parses the `{x}/{y}.png` part of a heatmap path plus the `radius` from its query string (if
+/// any), e.g. `-3/12.png` and `radius=8`.
+pub fn parse_heatmap_path(path: &str, query: Option<&str>) -> Option<Region> {
+    let (x, y) = grid::parse_cell_path(path.strip_suffix(".png")?)?;
+    let mut radius = DEFAULT_RADIUS;
+    for pair in query.unwrap_or_default().split('&') {
+        if let Some(("radius", value)) = pair.split_once('=') {
+            radius = value.parse().ok()?;
+        }
+    }
+    if !(0..=MAX_RADIUS).contains(&radius) {
+        return None;
+    }
+    Some(Region { x, y, radius })
+}
+
+/// Color of a cell edited by `count` mods when the most edited cell in the image has `max`.
+/// Counts are log scaled so a few very popular cells don't wash out the rest.
+pub fn color(count: i64, max: i64) -> [u8; 3] {
+    if count <= 0 || max <= 0 {
+        return BACKGROUND;
+    }
+    let t = if max == 1 {
+        1.0
+    } else {
+        ((count.min(max) as f64).ln() / (max as f64).ln()).clamp(0.0, 1.0)
+    };
+    let mut rgb = [0; 3];
+    for (channel, (cold, hot)) in rgb.iter_mut().zip(COLD.iter().zip(HOT.iter())) {
+        *channel = (*cold as f64 + (*hot as f64 - *cold as f64) * t).round() as u8;
+    }
+    rgb
+}
+
+/// Renders the region as an RGB PNG, `CELL_SIZE` pixels per cell, with a border around the
+/// center cell. `counts` are mod counts keyed by cell (x, y); missing cells have no edits.
+pub fn render(region: Region, counts: &HashMap<(i32, i32), i64>) -> Result<Vec<u8>> {
+    let size = region.size() * CELL_SIZE;
+    let max = counts.values().copied().max().unwrap_or(0);
+    let top_left = (region.x - region.radius, region.y + region.radius);
+    let mut pixels = vec![0u8; (size * size * 3) as usize];
+    for row in 0..region.size() {
+        for column in 0..region.size() {
+            let (x, y) = grid::image_cell(top_left, column, row);
+            let fill = color(counts.get(&(x, y)).copied().unwrap_or(0), max);
+            for dy in 0..CELL_SIZE {
+                for dx in 0..CELL_SIZE {
+                    let on_edge = dx == 0 || dy == 0 || dx == CELL_SIZE - 1 || dy == CELL_SIZE - 1;
+                    let rgb = if on_edge && (x, y) == (region.x, region.y) {
+                        CENTER_BORDER
+                    } else {
+                        fill
+                    };
+                    let index =
+                        (((row * CELL_SIZE + dy) * size + column * CELL_SIZE + dx) * 3) as usize;
+                    pixels[index..index + 3].copy_from_slice(&rgb);
+                }
+            }
+        }
+    }
+
+    let mut bytes = vec![];
+    let mut encoder = png::Encoder::new(&mut bytes, size, size);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&pixels))
+        .map_err(|err| anyhow!("failed to encode heatmap: {}", err))?;
+    Ok(bytes)
+}
@@ -0,0 +1,55 @@
+//! Callbacks that users of the library can register to run their own code at points in the update
+//! pipeline (e.g. to push processed data to a message queue) without patching `update`.
+//!
+//! Hooks run synchronously on the task doing the processing, so slow work should be handed off to
+//! a channel or spawned task.
+use std::sync::{Arc, RwLock};
+
+use crate::models::file::File;
+use crate::models::game_mod::Mod;
+use crate::plugin_processor::ParsedPlugin;
+
+/// Every method defaults to doing nothing, so implementors only override the hooks they need.
+pub trait Hooks: Send + Sync {
+    /// Called after all of a mod's files have been processed
+    fn on_mod_processed(&self, _db_mod: &Mod) {}
+
+    /// Called after a plugin has been parsed and saved to the database
+    fn on_plugin_parsed(&self, _db_mod: &Mod, _db_file: &File, _plugin: &ParsedPlugin) {}
+
+    /// Called when a file is skipped because it could not be downloaded or extracted
+    fn on_file_failed(&self, _db_mod: &Mod, _db_file: &File, _error: &anyhow::Error) {}
+}
+
+static HOOKS: RwLock<Vec<Arc<dyn Hooks>>> = RwLock::new(Vec::new());
+
+/// Registers hooks to be called for the rest of the process's lifetime, after any hooks
+/// registered before them.
+pub fn register(hooks: impl Hooks + 'static) {
+    HOOKS
+        .write()
+        .expect("hooks lock is not poisoned")
+        .push(Arc::new(hooks));
+}
+
+fn registered() -> Vec<Arc<dyn Hooks>> {
+    HOOKS.read().expect("hooks lock is not poisoned").clone()
+}
+
+pub(crate) fn mod_processed(db_mod: &Mod) {
+    for hooks in registered() {
+        hooks.on_mod_processed(db_mod);
+    }
+}
+
+pub(crate) fn plugin_parsed(db_mod: &Mod, db_file: &File, plugin: &ParsedPlugin) {
+    for hooks in registered() {
+        hooks.on_plugin_parsed(db_mod, db_file, plugin);
+    }
+}
+
+pub(crate) fn file_failed(db_mod: &Mod, db_file: &File, error: &anyhow::Error) {
+    for hooks in registered() {
+        hooks.on_file_failed(db_mod, db_file, error);
+    }
+}
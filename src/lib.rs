@@ -0,0 +1,23 @@
+pub mod archive_cache;
+pub mod cdn_api;
+pub mod cell_bitmap;
+pub mod cell_relevance;
+pub mod commands;
+pub mod db;
+pub mod discord;
+pub mod events;
+pub mod extractors;
+pub mod file_filter;
+pub mod grid;
+pub mod heatmap;
+pub mod hooks;
+pub mod models;
+pub mod nexus_api;
+pub mod nexus_scraper;
+pub mod plugin_processor;
+pub mod plugin_queue;
+pub mod plugin_storage;
+pub mod provenance;
+pub mod status;
+pub mod temp_dir;
+pub mod uesp_api;
@@ -1,43 +1,74 @@
 use anyhow::Result;
-use argh::FromArgs;
+use argh::{ArgsInfo, FromArgs};
 use chrono::{NaiveDate, NaiveDateTime, Utc};
 use dotenv::dotenv;
-use sqlx::postgres::PgPoolOptions;
 use std::env;
+use std::net::SocketAddr;
+use std::time::Duration;
 
-mod commands;
-mod extractors;
-mod models;
-mod nexus_api;
-mod nexus_scraper;
-mod plugin_processor;
-
-use commands::{
-    backfills::backfill_is_base_game, backfills::backfill_is_translation,
-    backfills::deduplicate_interior_cells, download_tiles, dump_cell_data, dump_cell_edit_counts,
-    dump_cell_edit_counts_over_time, dump_file_data, dump_games, dump_mod_cell_counts,
-    dump_mod_data, dump_mod_search_index, dump_plugin_data, update, TimeStep,
+use mod_mapper::archive_cache;
+use mod_mapper::cdn_api::CdnProvider;
+use mod_mapper::commands::completions::{completions, man_page, Shell};
+use mod_mapper::commands::{
+    backfills::backfill_graphql_fields, backfills::backfill_is_base_game,
+    backfills::backfill_is_translation, backfills::backfill_normalized_categories,
+    backfills::backfill_utc_dates, backfills::deduplicate_interior_cells, diff_plugin_versions,
+    diff_plugins, download_tiles, dump_category_stats, dump_cell_data, dump_cell_edit_counts,
+    dump_cell_edit_counts_over_time, dump_changed_urls, dump_dataset, dump_delisted_mods,
+    dump_file_data, dump_games, dump_mod_cell_counts, dump_mod_data, dump_mod_merges,
+    dump_mod_search_index, dump_plugin_data, dump_plugin_file_name_data, enrich_cell_lore,
+    export_mod, ingest_official_content, ingest_plugin_json, match_mod_ports, merge_mods, serve,
+    tier_plugins, update_games, verify_tiles, ModIdRange, SearchIndexSharding, TimeStep,
+    UpdateOptions,
 };
+use mod_mapper::db;
+use mod_mapper::discord;
+use mod_mapper::events::{self, Publisher};
+use mod_mapper::nexus_api::{GAME_NAMES, SSE_GAME_NAME};
+use mod_mapper::plugin_queue;
+use mod_mapper::plugin_storage::{self, Placement};
+use mod_mapper::provenance;
+use mod_mapper::status::Status;
+use mod_mapper::temp_dir;
 
-#[derive(FromArgs)]
+#[derive(FromArgs, ArgsInfo)]
 /// Downloads every mod off nexus mods, parses CELL and WRLD data from plugins in each, and saves the da&ta to the database.
 struct Args {
-    #[argh(option, short = 'p', default = "1")]
-    /// the page number to start scraping for mods on nexus mods
-    page: usize,
-
-    #[argh(
-        option,
-        short = 'g',
-        default = "String::from(\"skyrimspecialedition\")"
-    )]
-    /// name of nexus game to scrape (e.g. "skyrim" or "skyrimspecialedition")
-    game: String,
+    #[argh(option, short = 'p')]
+    /// the page number to start scraping for mods on nexus mods (defaults to resuming the last
+    /// interrupted scrape or else page 1)
+    page_start: Option<usize>,
+
+    #[argh(option)]
+    /// the last page number to scrape for mods on nexus mods. Re-runs just that segment of the mod
+    /// list, leaving an interrupted scrape to resume where it stopped.
+    page_end: Option<usize>,
+
+    #[argh(option)]
+    /// only create or update mods with nexus mod ids in this inclusive range (e.g. "1000-2000").
+    /// Re-runs a segment of the mod list like --page-end, without stopping after 50 pages of no
+    /// updates.
+    mod_id_range: Option<ModIdRange>,
+
+    #[argh(option, short = 'g')]
+    /// name of nexus game to scrape (e.g. "skyrim" or "skyrimspecialedition", defaults to
+    /// "skyrimspecialedition"). Can be repeated to update several games at once, other commands
+    /// use the first game.
+    game: Vec<String>,
+
+    #[argh(switch)]
+    /// update every supported game at once
+    all_games: bool,
 
     #[argh(switch, short = 'f')]
     /// enable full scrape of all pages, rather than stopping after 50 pages of no updates
     full: bool,
 
+    /// download every file without first checking its content preview for plugins (saves requests
+    /// when the API quota is tight)
+    #[argh(switch)]
+    skip_metadata: bool,
+
     /// file to output the cell mod edit counts as json
     #[argh(option, short = 'e')]
     dump_edits: Option<String>,
@@ -52,6 +83,11 @@ struct Args {
     #[argh(option, short = 'T')]
     time_step: Option<TimeStep>,
 
+    /// when dumping cell edits over time, group files by when they were first seen during an
+    /// update instead of when they were uploaded to Nexus
+    #[argh(switch)]
+    edits_by_first_seen: bool,
+
     /// folder to output all cell data as json files
     #[argh(option, short = 'c')]
     cell_data: Option<String>,
@@ -60,30 +96,84 @@ struct Args {
     #[argh(option, short = 'm')]
     mod_data: Option<String>,
 
+    /// when dumping mod data, write each mod's cells as a base64 bitset over the worldspace grid
+    /// in cell_bitmap instead of a list of coordinates, if the bitset is smaller
+    #[argh(switch)]
+    cell_bitmaps: bool,
+
     /// file to output all mod titles and ids as a json search index
     #[argh(option, short = 's')]
     mod_search_index: Option<String>,
 
+    /// also split the mod search index into shards by first "letter" of the mod name or into
+    /// chunks of this many mods, listed in a <name>_shards.json file next to the index
+    #[argh(option)]
+    search_index_shards: Option<SearchIndexSharding>,
+
     /// file to output all mod cell edit counts and ids as a json index
     #[argh(option, short = 'M')]
     mod_cell_counts: Option<String>,
 
+    /// when dumping mod cell counts, write each mod's count as an object with its separate
+    /// "exterior" and "interior" cell counts instead of only its exterior cell count
+    #[argh(switch)]
+    cell_counts_by_type: bool,
+
     /// folder to output all plugin data as json files
     #[argh(option, short = 'P')]
     plugin_data: Option<String>,
 
+    /// folder to output all plugin data grouped by lowercased plugin file name as json files
+    #[argh(option)]
+    plugin_file_name_data: Option<String>,
+
     /// folder to output all files data as json files
     #[argh(option, short = 'F')]
     file_data: Option<String>,
 
+    /// file to output the number of mods, cells edited, and average cells edited per mod of each
+    /// mod category as json
+    #[argh(option)]
+    category_stats: Option<String>,
+
     /// file to output all the game data as json
     #[argh(option, short = 'G')]
     game_data: Option<String>,
 
-    /// folder to output all map tile images downloaded from the UESP wiki
+    /// file to output the mods that were removed from nexus but are still indexed, with the cells
+    /// they edit, as json (run backfill_graphql_fields first to find removed mods)
+    #[argh(option)]
+    delisted_mods: Option<String>,
+
+    /// file to output the mods merged into another mod by --merge-mod-remove as json, so their
+    /// dumped mod files can be deleted
+    #[argh(option)]
+    mod_merges: Option<String>,
+
+    /// folder to assemble a versioned release of the dataset in: every table as a compressed CSV
+    /// with a schema description, license, and manifest (dataset_license option required with this
+    /// option)
+    #[argh(option)]
+    dump_dataset: Option<String>,
+
+    /// version of the dataset release assembled by --dump-dataset (defaults to today's UTC date)
+    #[argh(option)]
+    dataset_version: Option<String>,
+
+    /// license file to include in the dataset release assembled by --dump-dataset
+    #[argh(option)]
+    dataset_license: Option<String>,
+
+    /// folder to output all map tile images downloaded from the UESP wiki. Tiles already in the
+    /// folder are kept unless they are corrupt.
     #[argh(option, short = 't')]
     download_tiles: Option<String>,
 
+    /// folder of map tiles saved by --download-tiles to check for corrupt tiles (empty or not a
+    /// whole JPEG) without downloading anything. Exits with an error if any are found.
+    #[argh(option)]
+    verify_tiles: Option<String>,
+
     /// backfill the is_translation column in the mods table
     #[argh(switch)]
     backfill_is_translation: bool,
@@ -92,30 +182,306 @@ struct Args {
     #[argh(switch)]
     backfill_is_base_game: bool,
 
+    /// backfill the normalized_category column in the files table from the raw category names
+    #[argh(switch)]
+    backfill_normalized_categories: bool,
+
+    /// truncate scraped mod dates to UTC day boundaries and convert last_updated_files_at from the
+    /// given time zone it was saved in to UTC (pass "UTC" to only truncate). The conversion is
+    /// skipped if it already ran.
+    #[argh(option)]
+    backfill_utc_dates: Option<String>,
+
+    /// backfill the is_adult, downloads, language, nexus_created_at, and nexus_updated_at columns
+    /// in the mods table from the nexus GraphQL API
+    #[argh(switch)]
+    backfill_graphql_fields: bool,
+
     /// deduplicate the interior cells with same form_id and master
     #[argh(switch)]
     deduplicate_interior_cells: bool,
 
+    /// save names of exterior cells from the UESP map to the cell_lore table for cell dumps
+    #[argh(switch)]
+    enrich_cell_lore: bool,
+
+    /// link mods to their ports in other games (e.g. SSE conversions of LE mods) by shared plugins
+    /// or the same author and name, for the mod dumps
+    #[argh(switch)]
+    match_mod_ports: bool,
+
+    /// run a Discord bot answering "!cell <x> <y>" and "!mod <nexus_mod_id>" in chat, logging in
+    /// with the DISCORD_TOKEN env var. Requires building with the "discord" feature.
+    #[argh(switch)]
+    discord_bot: bool,
+
+    /// folder of a local game install's Data directory to ingest Creation Club plugins from as
+    /// official content mods
+    #[argh(option)]
+    ingest_official_content: Option<String>,
+
+    /// file path of a plugin dumped to json by skyrim-cell-dump (named like "Foo.esp.json") to save
+    /// as a plugin of the file given by --nexus-file-id
+    #[argh(option)]
+    ingest_plugin_json: Option<String>,
+
+    /// nexus file id of an already scraped file (in the game given by --game) to link an
+    /// ingested plugin json to
+    #[argh(option)]
+    nexus_file_id: Option<i32>,
+
+    /// run updates continuously and serve /healthz and /readyz endpoints on this address (e.g.
+    /// "0.0.0.0:8080")
+    #[argh(option)]
+    serve: Option<SocketAddr>,
+
+    /// nexus mod id of a mod (in the game given by --game) to export with all of its files,
+    /// plugins, and cells as json files
+    #[argh(option)]
+    export_mod: Option<i32>,
+
+    /// two base 36 plugin hashes, old and new, separated by a comma (e.g. "5eqlb3,1ye9hm1") to
+    /// write the cells added, removed, and changed between them to --out
+    #[argh(option)]
+    diff_plugins: Option<String>,
+
+    /// file name of a plugin (e.g. "Foo.esp") in the mod given by --nexus-mod-id to diff the latest
+    /// two versions of, writing the cells added, removed, and changed to --out
+    #[argh(option)]
+    diff_plugin_file_name: Option<String>,
+
+    /// nexus mod id of a mod (in the game given by --game) containing the plugin given by
+    /// --diff-plugin-file-name
+    #[argh(option)]
+    nexus_mod_id: Option<i32>,
+
+    /// id (in the database, not on nexus) of a duplicate mod row to merge into the mod given by
+    /// --merge-mod-keep. Its files, plugins, and cells are moved to the kept mod and it is deleted.
+    #[argh(option)]
+    merge_mod_remove: Option<i32>,
+
+    /// id (in the database) of the mod to merge the mod given by --merge-mod-remove into
+    #[argh(option)]
+    merge_mod_keep: Option<i32>,
+
+    /// folder to write exports to
+    #[argh(option, default = "String::from(\"exports\")")]
+    out: String,
+
+    /// seconds to wait between update runs in serve mode
+    #[argh(option, default = "3600")]
+    update_interval: u64,
+
+    /// seconds a mod's files can take to process before the rest of its files are deferred to the
+    /// next update, which finishes deferred mods before scraping the mod list
+    #[argh(option)]
+    mod_time_budget: Option<u64>,
+
+    /// process the mods on each page of the mod list in order of how likely they are to edit
+    /// cells (going by category and earlier file metadata checks). Mods are only reordered within
+    /// their page, not across pages.
+    #[argh(switch)]
+    prioritize: bool,
+
+    /// after scraping the mod list of each game, update this many of its mods whose files were
+    /// processed longest before their last update on nexus
+    #[argh(option)]
+    stale_mod_budget: Option<usize>,
+
+    /// folder to cache UESP map tiles in when proxying them at /tiles/{z}/{x}/{y}.jpg in serve
+    /// mode (can be the folder download_tiles saved tiles to)
+    #[argh(option)]
+    tile_cache: Option<String>,
+
+    /// in serve mode, also refresh mod names, descriptions, and thumbnails from the API in the
+    /// background, oldest first, using the hourly API quota left over by updates
+    #[argh(switch)]
+    refresh_metadata: bool,
+
+    /// folder to download and extract archives in instead of the system temp folder (can also be
+    /// set with the MODMAPPER_TEMP_DIR environment variable)
+    #[argh(option)]
+    temp_dir: Option<String>,
+
+    /// hours to keep downloaded archives for in the "archives" folder of the temp folder, so
+    /// files whose extraction failed are extracted again on the next update without another
+    /// download (by default archives are removed as soon as they are extracted)
+    #[argh(option)]
+    keep_archives_hours: Option<u64>,
+
+    /// how many plugins extracted from archives can wait to be saved to the database before
+    /// extraction pauses, bounding memory use when the database is slow
+    #[argh(option, default = "plugin_queue::DEFAULT_CAPACITY")]
+    plugin_queue_size: usize,
+
+    /// folder to write processed plugins to (default "plugins", or the colon-separated folders in
+    /// the MODMAPPER_PLUGIN_ROOTS env var). Can be repeated to spread plugins over several disks.
+    #[argh(option)]
+    plugin_root: Vec<String>,
+
+    /// how plugins are spread over --plugin-root folders: "fill-first" (the default) fills them
+    /// in order, "balanced" writes to the one with the most free space
+    #[argh(option, default = "Placement::FillFirst")]
+    plugin_placement: Placement,
+
+    /// folder to move the plugins of mods not updated in --tier-after-years years to, gzipped,
+    /// freeing up the plugin storage roots. Tiered plugins are still found when reading plugins.
+    #[argh(option)]
+    tier_plugins: Option<String>,
+
+    /// years since a mod was last updated on nexus before --tier-plugins moves its plugins
+    #[argh(option, default = "3")]
+    tier_after_years: u32,
+
+    /// postgres schema to read and write all tables in instead of "public" (can also be set with
+    /// the MODMAPPER_SCHEMA environment variable). The schema is created and migrated if needed,
+    /// so staging runs can share the production database and be dropped afterwards.
+    #[argh(option)]
+    schema: Option<String>,
+
+    /// leave translation mods out of the mod search index, mod cell counts, category stats, and
+    /// cell edit counts
+    #[argh(switch)]
+    exclude_translations: bool,
+
+    /// leave plugins that look like compatibility patches between other mods out of cell edit
+    /// counts, so the cells of the patched mods are not counted twice
+    #[argh(switch)]
+    exclude_patches: bool,
+
+    /// when dumping cell edit counts (and over time), only count mods in this language as Nexus
+    /// reports it (e.g. "English"), so translations uploaded as their own mods aren't counted once
+    /// per language. Mods whose language isn't known yet (see backfill_graphql_fields) are counted.
+    #[argh(option)]
+    edits_language: Option<String>,
+
+    /// when dumping cell edit counts, count a mod together with its patches, translations, and
+    /// reuploads of the same plugins once per cell
+    #[argh(switch)]
+    dedup_aggressive: bool,
+
+    /// when dumping cell edit counts, only count files uploaded before this date (e.g.
+    /// "2020-01-01") to get the map as it was then
+    #[argh(option)]
+    as_of: Option<NaiveDate>,
+
     /// when dumping data, only dump data for mods or files that have been updated since this date
     #[argh(option, short = 'u')]
     updated_after: Option<NaiveDateTime>,
+
+    /// publish mod, file, and plugin events from update and serve to this NATS
+    /// ("nats://host:port") or Kafka ("kafka://host:port") url. Requires building with the
+    /// "nats" or "kafka" feature.
+    #[argh(option)]
+    events_url: Option<String>,
+
+    /// file to output the urls of dumped files written since --updated-after to, one per line
+    /// (requires --dump-url)
+    #[argh(option)]
+    changed_urls: Option<String>,
+
+    /// dump folder and the url it is served from for --changed-urls (e.g.
+    /// "mods=https://mods.modmapper.com"). Can be repeated.
+    #[argh(option)]
+    dump_url: Vec<String>,
+
+    /// purge the changed urls from this CDN ("cloudflare" or "fastly") after writing them. Reads
+    /// CLOUDFLARE_ZONE_ID and CLOUDFLARE_API_TOKEN or FASTLY_API_TOKEN from the environment.
+    #[argh(option)]
+    purge_cdn: Option<CdnProvider>,
+
+    /// print a completion script for the given shell ("bash", "zsh", or "fish")
+    #[argh(option)]
+    completions: Option<Shell>,
+
+    /// print a man page documenting every option
+    #[argh(switch)]
+    help_all: bool,
 }
 
-#[tokio::main]
-pub async fn main() -> Result<()> {
+const BIN_NAME: &str = "mod-mapper";
+
+pub fn main() -> Result<()> {
     dotenv().ok();
 
     tracing_subscriber::fmt::init();
 
-    let pool = PgPoolOptions::new()
-        .max_connections(5)
-        .connect(&env::var("DATABASE_URL")?)
-        .await?;
+    let mut args: Args = argh::from_env();
+    if let Some(shell) = args.completions {
+        print!("{}", completions(&Args::get_args_info(), BIN_NAME, shell));
+        return Ok(());
+    }
+    if args.help_all {
+        print!("{}", man_page(&Args::get_args_info(), BIN_NAME));
+        return Ok(());
+    }
 
-    let args: Args = argh::from_env();
+    // The environment is set up before the runtime starts its worker threads, since changing it
+    // while other threads may be reading it isn't safe
+    if let Some(dir) = args
+        .temp_dir
+        .take()
+        .or_else(|| env::var("MODMAPPER_TEMP_DIR").ok())
+    {
+        temp_dir::set(dir)?;
+    }
+
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?
+        .block_on(run(args))
+}
+
+async fn run(args: Args) -> Result<()> {
+    if let Some(hours) = args.keep_archives_hours {
+        archive_cache::configure(Duration::from_secs(hours * 60 * 60))?;
+    }
+
+    plugin_queue::set_capacity(args.plugin_queue_size);
+
+    let plugin_roots: Vec<String> = if args.plugin_root.is_empty() {
+        env::var("MODMAPPER_PLUGIN_ROOTS")
+            .unwrap_or_default()
+            .split(':')
+            .filter(|root| !root.is_empty())
+            .map(String::from)
+            .collect()
+    } else {
+        args.plugin_root
+    };
+    if !plugin_roots.is_empty() {
+        plugin_storage::configure(plugin_roots, args.plugin_placement)?;
+    }
+
+    if let Some(schema) = args.schema.or_else(|| env::var("MODMAPPER_SCHEMA").ok()) {
+        db::set_schema(&schema)?;
+    }
+
+    let pool = db::connect().await?;
+    db::prepare_schema(&pool).await?;
+
+    let games: Vec<String> = if args.all_games {
+        GAME_NAMES.iter().map(|game| game.to_string()).collect()
+    } else if args.game.is_empty() {
+        vec![SSE_GAME_NAME.to_string()]
+    } else {
+        args.game.clone()
+    };
+    let game = &games[0];
+    provenance::init(&pool).await?;
 
     if let Some(path) = args.dump_edits {
-        return dump_cell_edit_counts(&pool, &path).await;
+        return dump_cell_edit_counts(
+            &pool,
+            &path,
+            !args.exclude_translations,
+            !args.exclude_patches,
+            args.dedup_aggressive,
+            args.as_of
+                .map(|date| date.and_hms_opt(0, 0, 0).expect("midnight is a valid time")),
+            args.edits_language.as_deref(),
+        )
+        .await;
     }
     if let Some(path) = args.dump_edits_over_time {
         if let Some(time_step) = args.time_step {
@@ -127,6 +493,10 @@ pub async fn main() -> Result<()> {
                 Utc::now().naive_utc(),
                 time_step,
                 &path,
+                !args.exclude_translations,
+                !args.exclude_patches,
+                args.edits_by_first_seen,
+                args.edits_language.as_deref(),
             )
             .await;
         } else {
@@ -137,35 +507,152 @@ pub async fn main() -> Result<()> {
         return dump_cell_data(&dir).await;
     }
     if let Some(dir) = args.mod_data {
-        return dump_mod_data(&dir, args.updated_after).await;
+        return dump_mod_data(&dir, args.updated_after, args.cell_bitmaps).await;
     }
     if let Some(path) = args.mod_search_index {
-        return dump_mod_search_index(&args.game, &path).await;
+        return dump_mod_search_index(
+            game,
+            &path,
+            !args.exclude_translations,
+            args.search_index_shards,
+        )
+        .await;
     }
     if let Some(path) = args.mod_cell_counts {
-        return dump_mod_cell_counts(&path).await;
+        return dump_mod_cell_counts(&path, !args.exclude_translations, args.cell_counts_by_type)
+            .await;
     }
     if let Some(path) = args.plugin_data {
         return dump_plugin_data(&path, args.updated_after).await;
     }
+    if let Some(path) = args.plugin_file_name_data {
+        return dump_plugin_file_name_data(&path, args.updated_after).await;
+    }
     if let Some(path) = args.file_data {
         return dump_file_data(&path, args.updated_after).await;
     }
     if let Some(path) = args.game_data {
         return dump_games(&pool, &path).await;
     }
+    if let Some(path) = args.category_stats {
+        return dump_category_stats(&pool, &path, !args.exclude_translations).await;
+    }
+    if let Some(path) = args.delisted_mods {
+        return dump_delisted_mods(&pool, &path).await;
+    }
+    if let Some(path) = args.mod_merges {
+        return dump_mod_merges(&pool, &path).await;
+    }
+    if let Some(dir) = args.dump_dataset {
+        if let Some(license_path) = args.dataset_license {
+            return dump_dataset(&pool, &dir, args.dataset_version.as_deref(), &license_path).await;
+        } else {
+            panic!("dataset_license option required with dump_dataset option");
+        }
+    }
+    if let Some(path) = args.changed_urls {
+        return dump_changed_urls(&path, &args.dump_url, args.updated_after, args.purge_cdn).await;
+    }
     if let Some(dir) = args.download_tiles {
         return download_tiles(&dir).await;
     }
+    if let Some(dir) = args.verify_tiles {
+        return verify_tiles(&dir).await;
+    }
     if args.backfill_is_translation {
         return backfill_is_translation(&pool).await;
     }
+    if args.backfill_graphql_fields {
+        return backfill_graphql_fields(&pool).await;
+    }
     if args.backfill_is_base_game {
         return backfill_is_base_game(&pool).await;
     }
+    if args.backfill_normalized_categories {
+        return backfill_normalized_categories(&pool).await;
+    }
+    if let Some(source_time_zone) = args.backfill_utc_dates {
+        return backfill_utc_dates(&pool, &source_time_zone).await;
+    }
     if args.deduplicate_interior_cells {
         return deduplicate_interior_cells(&pool).await;
     }
-
-    update(&pool, args.page, &args.game, args.full).await
+    if args.enrich_cell_lore {
+        return enrich_cell_lore(&pool).await;
+    }
+    if args.match_mod_ports {
+        return match_mod_ports(&pool).await;
+    }
+    if let Some(cold_dir) = args.tier_plugins {
+        return tier_plugins(&pool, &cold_dir, args.tier_after_years).await;
+    }
+    if args.discord_bot {
+        return discord::run(&pool).await;
+    }
+    if let Some(path) = args.ingest_plugin_json {
+        if let Some(nexus_file_id) = args.nexus_file_id {
+            return ingest_plugin_json(&pool, game, &path, nexus_file_id).await;
+        } else {
+            panic!("nexus_file_id option required with ingest_plugin_json option");
+        }
+    }
+    if let Some(nexus_mod_id) = args.export_mod {
+        return export_mod(&pool, game, nexus_mod_id, &args.out).await;
+    }
+    if let Some(hashes) = args.diff_plugins {
+        if let Some((old_hash, new_hash)) = hashes.split_once(',') {
+            return diff_plugins(&pool, old_hash, new_hash, &args.out).await;
+        } else {
+            panic!("diff_plugins option must be two hashes separated by a comma");
+        }
+    }
+    if let Some(file_name) = args.diff_plugin_file_name {
+        if let Some(nexus_mod_id) = args.nexus_mod_id {
+            return diff_plugin_versions(&pool, game, nexus_mod_id, &file_name, &args.out).await;
+        } else {
+            panic!("nexus_mod_id option required with diff_plugin_file_name option");
+        }
+    }
+    if let Some(remove_id) = args.merge_mod_remove {
+        if let Some(keep_id) = args.merge_mod_keep {
+            return merge_mods(&pool, keep_id, remove_id).await;
+        } else {
+            panic!("merge_mod_keep option required with merge_mod_remove option");
+        }
+    }
+    if let Some(dir) = args.ingest_official_content {
+        return ingest_official_content(&pool, game, &dir).await;
+    }
+    let events = match &args.events_url {
+        Some(url) => Some(events::register(Publisher::connect(url).await?)),
+        None => None,
+    };
+    let update_options = UpdateOptions {
+        start_page: args.page_start,
+        end_page: args.page_end,
+        mod_id_range: args.mod_id_range,
+        full: args.full,
+        skip_metadata: args.skip_metadata,
+        mod_time_budget: args.mod_time_budget.map(Duration::from_secs),
+        prioritize: args.prioritize,
+        stale_mod_budget: args.stale_mod_budget,
+    };
+    let result = if let Some(addr) = args.serve {
+        serve(
+            &pool,
+            addr,
+            &games,
+            &update_options,
+            Duration::from_secs(args.update_interval),
+            args.tile_cache.as_deref(),
+            args.refresh_metadata,
+        )
+        .await
+    } else {
+        update_games(&pool, &games, &update_options, &Status::default()).await
+    };
+    if let Some(events) = events {
+        events.close().await?;
+    }
+    result
 }
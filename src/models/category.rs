@@ -0,0 +1,91 @@
+use anyhow::{Context, Result};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::collections::HashMap;
+use tracing::instrument;
+
+use crate::nexus_api::game::ApiCategory;
+
+/// A mod category of a game, with its canonical English name
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Category {
+    pub id: i32,
+    pub game_id: i32,
+    pub nexus_category_id: i32,
+    pub name: String,
+    pub parent_nexus_category_id: Option<i32>,
+    pub updated_at: NaiveDateTime,
+    pub created_at: NaiveDateTime,
+}
+
+#[instrument(level = "debug", skip(executor, categories))]
+pub async fn batched_upsert(
+    executor: impl sqlx::PgExecutor<'_>,
+    game_id: i32,
+    categories: &[ApiCategory<'_>],
+) -> Result<Vec<Category>> {
+    let mut nexus_category_ids: Vec<i32> = vec![];
+    let mut names: Vec<&str> = vec![];
+    let mut parent_nexus_category_ids: Vec<Option<i32>> = vec![];
+    for category in categories {
+        nexus_category_ids.push(category.category_id);
+        names.push(category.name);
+        parent_nexus_category_ids.push(category.parent_category_id);
+    }
+    // sqlx doesn't understand arrays of Options with the query_as! macro
+    sqlx::query_as(
+        r#"INSERT INTO categories
+            (game_id, nexus_category_id, name, parent_nexus_category_id, created_at, updated_at)
+            SELECT $1, *, now(), now() FROM UNNEST($2::int[], $3::text[], $4::int[])
+            ON CONFLICT (game_id, nexus_category_id) DO UPDATE
+            SET (name, parent_nexus_category_id, updated_at) =
+            (EXCLUDED.name, EXCLUDED.parent_nexus_category_id, now())
+            RETURNING *"#,
+    )
+    .bind(game_id)
+    .bind(&nexus_category_ids)
+    .bind(&names)
+    .bind(&parent_nexus_category_ids)
+    .fetch_all(executor)
+    .await
+    .context("Failed to upsert categories")
+}
+
+#[instrument(level = "debug", skip(executor))]
+pub async fn get_all(executor: impl sqlx::PgExecutor<'_>) -> Result<Vec<Category>> {
+    sqlx::query_as!(Category, "SELECT * FROM categories")
+        .fetch_all(executor)
+        .await
+        .context("Failed to get categories")
+}
+
+/// Canonical category names by game id and nexus category id
+pub async fn get_names(executor: impl sqlx::PgExecutor<'_>) -> Result<HashMap<(i32, i32), String>> {
+    Ok(get_all(executor)
+        .await?
+        .into_iter()
+        .map(|category| {
+            (
+                (category.game_id, category.nexus_category_id),
+                category.name,
+            )
+        })
+        .collect())
+}
+
+/// The category name to dump for a mod and, if it differs, the (possibly localized) name it was
+/// scraped with. Mods in a category missing from the categories table keep their scraped name.
+pub fn canonical_category_name(
+    scraped_name: Option<String>,
+    canonical_name: Option<&str>,
+) -> (Option<String>, Option<String>) {
+    match canonical_name {
+        Some(canonical_name) => {
+            let localized_name =
+                scraped_name.filter(|scraped_name| scraped_name.as_str() != canonical_name);
+            (Some(canonical_name.to_string()), localized_name)
+        }
+        None => (scraped_name, None),
+    }
+}
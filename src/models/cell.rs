@@ -1,7 +1,7 @@
 use anyhow::{Context, Result};
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
-use sqlx::FromRow;
+use sqlx::{Acquire, FromRow};
 use tracing::instrument;
 
 use super::BATCH_SIZE;
@@ -16,6 +16,7 @@ pub struct Cell {
     pub world_id: Option<i32>,
     pub is_persistent: bool,
     pub is_base_game: bool,
+    pub game_id: i32,
     pub updated_at: NaiveDateTime,
     pub created_at: NaiveDateTime,
 }
@@ -29,6 +30,7 @@ pub struct UnsavedCell<'a> {
     pub world_id: Option<i32>,
     pub is_persistent: bool,
     pub is_base_game: bool,
+    pub game_id: i32,
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
@@ -41,11 +43,43 @@ pub struct CellData {
     pub files_count: Option<i64>,
     pub mods_count: Option<i64>,
     pub mods: Option<serde_json::Value>,
+    pub lore_name: Option<String>,
+    pub lore_wiki_page: Option<String>,
 }
 
-#[instrument(level = "debug", skip(pool))]
+/// A cell edited by one of a mod's plugins
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct PluginCellWithCell {
+    pub plugin_id: i32,
+    pub file_id: i32,
+    pub editor_id: Option<String>,
+    pub form_id: i32,
+    pub master: String,
+    pub x: Option<i32>,
+    pub y: Option<i32>,
+    pub is_persistent: bool,
+    pub is_base_game: bool,
+    pub world_form_id: Option<i32>,
+    pub world_master: Option<String>,
+}
+
+/// A cell edited by a plugin, keyed by the cell so edits from two plugins can be compared
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, FromRow)]
+pub struct PluginCellForDiff {
+    pub cell_id: i32,
+    pub editor_id: Option<String>,
+    pub form_id: i32,
+    pub master: String,
+    pub x: Option<i32>,
+    pub y: Option<i32>,
+    pub is_persistent: bool,
+    pub world_form_id: Option<i32>,
+    pub world_master: Option<String>,
+}
+
+#[instrument(level = "debug", skip(executor))]
 pub async fn insert(
-    pool: &sqlx::Pool<sqlx::Postgres>,
+    executor: impl sqlx::PgExecutor<'_>,
     form_id: i32,
     master: &str,
     x: Option<i32>,
@@ -53,13 +87,14 @@ pub async fn insert(
     world_id: Option<i32>,
     is_persistent: bool,
     is_base_game: bool,
+    game_id: i32,
 ) -> Result<Cell> {
     sqlx::query_as!(
         Cell,
         "INSERT INTO cells
-            (form_id, master, x, y, world_id, is_persistent, is_base_game, created_at, updated_at)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, now(), now())
-            ON CONFLICT (form_id, master, world_id) DO UPDATE
+            (form_id, master, x, y, world_id, is_persistent, is_base_game, game_id, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, now(), now())
+            ON CONFLICT (form_id, master, world_id, game_id) DO UPDATE
             SET (x, y, is_persistent, is_base_game, updated_at) =
             (EXCLUDED.x, EXCLUDED.y, EXCLUDED.is_persistent, EXCLUDED.is_base_game, now())
             RETURNING *",
@@ -69,18 +104,20 @@ pub async fn insert(
         y,
         world_id,
         is_persistent,
-        is_base_game
+        is_base_game,
+        game_id
     )
-    .fetch_one(pool)
+    .fetch_one(executor)
     .await
     .context("Failed to insert cell")
 }
 
-#[instrument(level = "debug", skip(pool))]
+#[instrument(level = "debug", skip(conn))]
 pub async fn batched_insert<'a>(
-    pool: &sqlx::Pool<sqlx::Postgres>,
+    conn: impl sqlx::Acquire<'_, Database = sqlx::Postgres>,
     cells: &[UnsavedCell<'a>],
 ) -> Result<Vec<Cell>> {
+    let mut conn = conn.acquire().await?;
     let mut saved_cells = vec![];
     for batch in cells.chunks(BATCH_SIZE) {
         let mut form_ids: Vec<i32> = vec![];
@@ -90,6 +127,7 @@ pub async fn batched_insert<'a>(
         let mut world_ids: Vec<Option<i32>> = vec![];
         let mut is_persistents: Vec<bool> = vec![];
         let mut is_base_games: Vec<bool> = vec![];
+        let mut game_ids: Vec<i32> = vec![];
         batch.iter().for_each(|unsaved_cell| {
             form_ids.push(unsaved_cell.form_id);
             masters.push(unsaved_cell.master);
@@ -98,13 +136,14 @@ pub async fn batched_insert<'a>(
             world_ids.push(unsaved_cell.world_id);
             is_persistents.push(unsaved_cell.is_persistent);
             is_base_games.push(unsaved_cell.is_base_game);
+            game_ids.push(unsaved_cell.game_id);
         });
         saved_cells.append(
             // sqlx doesn't understand arrays of Options with the query_as! macro
             &mut sqlx::query_as(
-                r#"INSERT INTO cells (form_id, master, x, y, world_id, is_persistent, is_base_game, created_at, updated_at)
-                SELECT *, now(), now() FROM UNNEST($1::int[], $2::text[], $3::int[], $4::int[], $5::int[], $6::bool[], $7::bool[])
-                ON CONFLICT (form_id, master, world_id) DO UPDATE
+                r#"INSERT INTO cells (form_id, master, x, y, world_id, is_persistent, is_base_game, game_id, created_at, updated_at)
+                SELECT *, now(), now() FROM UNNEST($1::int[], $2::text[], $3::int[], $4::int[], $5::int[], $6::bool[], $7::bool[], $8::int[])
+                ON CONFLICT (form_id, master, world_id, game_id) DO UPDATE
                 SET (x, y, is_persistent, is_base_game, updated_at) =
                 (EXCLUDED.x, EXCLUDED.y, EXCLUDED.is_persistent, EXCLUDED.is_base_game, now())
                 RETURNING *"#,
@@ -116,7 +155,8 @@ pub async fn batched_insert<'a>(
             .bind(&world_ids)
             .bind(&is_persistents)
             .bind(&is_base_games)
-            .fetch_all(pool)
+            .bind(&game_ids)
+            .fetch_all(&mut *conn)
             .await
             .context("Failed to insert cells")?,
         );
@@ -124,13 +164,77 @@ pub async fn batched_insert<'a>(
     Ok(saved_cells)
 }
 
-#[instrument(level = "debug", skip(pool))]
+#[instrument(level = "debug", skip(executor))]
+pub async fn get_by_mod_id(
+    executor: impl sqlx::PgExecutor<'_>,
+    mod_id: i32,
+) -> Result<Vec<PluginCellWithCell>> {
+    sqlx::query_as!(
+        PluginCellWithCell,
+        r#"SELECT
+                plugin_cells.plugin_id,
+                plugin_cells.file_id,
+                plugin_cells.editor_id,
+                cells.form_id,
+                cells.master,
+                cells.x,
+                cells.y,
+                cells.is_persistent,
+                cells.is_base_game,
+                worlds.form_id as "world_form_id?",
+                worlds.master as "world_master?"
+            FROM plugin_cells
+            JOIN cells ON cells.id = plugin_cells.cell_id
+            LEFT OUTER JOIN worlds ON worlds.id = cells.world_id
+            WHERE plugin_cells.mod_id = $1
+            ORDER BY plugin_cells.id ASC"#,
+        mod_id,
+    )
+    .fetch_all(executor)
+    .await
+    .context("Failed to get cells by mod_id")
+}
+
+#[instrument(level = "debug", skip(executor))]
+pub async fn get_by_plugin_id(
+    executor: impl sqlx::PgExecutor<'_>,
+    plugin_id: i32,
+) -> Result<Vec<PluginCellForDiff>> {
+    sqlx::query_as!(
+        PluginCellForDiff,
+        r#"SELECT
+                plugin_cells.cell_id,
+                plugin_cells.editor_id,
+                cells.form_id,
+                cells.master,
+                cells.x,
+                cells.y,
+                cells.is_persistent,
+                worlds.form_id as "world_form_id?",
+                worlds.master as "world_master?"
+            FROM plugin_cells
+            JOIN cells ON cells.id = plugin_cells.cell_id
+            LEFT OUTER JOIN worlds ON worlds.id = cells.world_id
+            WHERE plugin_cells.plugin_id = $1
+            ORDER BY plugin_cells.cell_id ASC"#,
+        plugin_id,
+    )
+    .fetch_all(executor)
+    .await
+    .context("Failed to get cells by plugin_id")
+}
+
+#[instrument(level = "debug", skip(executor))]
 pub async fn count_mod_edits(
-    pool: &sqlx::Pool<sqlx::Postgres>,
+    executor: impl sqlx::PgExecutor<'_>,
     master: &str,
     world_id: i32,
     x: i32,
     y: i32,
+    include_translations: bool,
+    include_patches: bool,
+    uploaded_before: Option<NaiveDateTime>,
+    language: Option<&str>,
 ) -> Result<Option<i64>> {
     sqlx::query_scalar!(
         "SELECT COUNT(DISTINCT mods.id)
@@ -139,17 +243,71 @@ pub async fn count_mod_edits(
             JOIN plugins ON plugins.id = plugin_id
             JOIN files ON files.id = plugins.file_id
             JOIN mods ON mods.id = files.mod_id
-            WHERE master = $1 AND world_id = $2 AND x = $3 and y = $4",
+            WHERE master = $1 AND world_id = $2 AND x = $3 and y = $4
+            AND ($5 OR NOT mods.is_translation)
+            AND ($6 OR NOT plugins.is_patch)
+            AND ($7::timestamp(3) IS NULL OR files.uploaded_at < $7)
+            AND ($8::text IS NULL OR mods.language IS NULL OR mods.language = $8)",
         master,
         world_id,
         x,
         y,
+        include_translations,
+        include_patches,
+        uploaded_before,
+        language,
     )
-    .fetch_one(pool)
+    .fetch_one(executor)
     .await
     .context("Failed to count mod edits on cell")
 }
 
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct CellModIds {
+    pub x: Option<i32>,
+    pub y: Option<i32>,
+    pub mod_ids: Option<Vec<i32>>,
+}
+
+/// Returns the ids of the mods that edit each exterior cell in the world, with the same
+/// translation, patch, upload date, and language filters as `count_mod_edits`
+#[instrument(level = "debug", skip(executor))]
+pub async fn get_mod_ids_by_cell(
+    executor: impl sqlx::PgExecutor<'_>,
+    master: &str,
+    world_id: i32,
+    include_translations: bool,
+    include_patches: bool,
+    uploaded_before: Option<NaiveDateTime>,
+    language: Option<&str>,
+) -> Result<Vec<CellModIds>> {
+    sqlx::query_as!(
+        CellModIds,
+        "SELECT cells.x, cells.y, array_agg(DISTINCT plugin_cells.mod_id) AS mod_ids
+            FROM cells
+            JOIN plugin_cells on cells.id = cell_id
+            JOIN plugins ON plugins.id = plugin_cells.plugin_id
+            JOIN files ON files.id = plugin_cells.file_id
+            JOIN mods ON mods.id = plugin_cells.mod_id
+            WHERE master = $1 AND world_id = $2
+            AND cells.x IS NOT NULL and cells.y IS NOT NULL
+            AND ($3 OR NOT mods.is_translation)
+            AND ($4 OR NOT plugins.is_patch)
+            AND ($5::timestamp(3) IS NULL OR files.uploaded_at < $5)
+            AND ($6::text IS NULL OR mods.language IS NULL OR mods.language = $6)
+            GROUP BY cells.x, cells.y",
+        master,
+        world_id,
+        include_translations,
+        include_patches,
+        uploaded_before,
+        language,
+    )
+    .fetch_all(executor)
+    .await
+    .context("Failed to get mod ids by cell")
+}
+
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct CellFileEditCount {
     pub x: Option<i32>,
@@ -157,13 +315,17 @@ pub struct CellFileEditCount {
     pub count: Option<i64>,
 }
 
-#[instrument(level = "debug", skip(pool))]
+#[instrument(level = "debug", skip(executor))]
 pub async fn count_file_edits_in_time_range(
-    pool: &sqlx::Pool<sqlx::Postgres>,
+    executor: impl sqlx::PgExecutor<'_>,
     master: &str,
     world_id: i32,
     start_date: NaiveDateTime,
     end_date: NaiveDateTime,
+    include_translations: bool,
+    include_patches: bool,
+    by_first_seen: bool,
+    language: Option<&str>,
 ) -> Result<Vec<CellFileEditCount>> {
     sqlx::query_as!(
         CellFileEditCount,
@@ -172,25 +334,76 @@ pub async fn count_file_edits_in_time_range(
             JOIN plugin_cells on cells.id = cell_id
             JOIN plugins ON plugins.id = plugin_id
             JOIN files ON files.id = plugins.file_id
+            JOIN mods ON mods.id = files.mod_id
             WHERE master = $1 AND world_id = $2
             AND cells.x IS NOT NULL and cells.y IS NOT NULL
-            AND files.uploaded_at BETWEEN $3 AND $4
+            AND (CASE WHEN $7 THEN files.first_seen_at ELSE files.uploaded_at END) BETWEEN $3 AND $4
+            AND ($5 OR NOT mods.is_translation)
+            AND ($6 OR NOT plugins.is_patch)
+            AND ($8::text IS NULL OR mods.language IS NULL OR mods.language = $8)
             GROUP BY cells.x, cells.y
         ",
         master,
         world_id,
         start_date,
         end_date,
+        include_translations,
+        include_patches,
+        by_first_seen,
+        language,
     )
-    .fetch_all(pool)
+    .fetch_all(executor)
     .await
     .context("Failed to count file-based mod edits on cell")
 }
 
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct CellModEditCount {
+    pub x: Option<i32>,
+    pub y: Option<i32>,
+    pub count: Option<i64>,
+}
+
+/// Counts the mods editing each exterior cell in the rectangle between the min and max cells
+/// (inclusive). Cells no mod edits are left out.
+#[instrument(level = "debug", skip(executor))]
+pub async fn count_mod_edits_in_area(
+    executor: impl sqlx::PgExecutor<'_>,
+    master: &str,
+    world_id: i32,
+    (min_x, min_y): (i32, i32),
+    (max_x, max_y): (i32, i32),
+    include_translations: bool,
+) -> Result<Vec<CellModEditCount>> {
+    sqlx::query_as!(
+        CellModEditCount,
+        "SELECT cells.x, cells.y, COUNT(DISTINCT mods.id)
+            FROM cells
+            JOIN plugin_cells on cells.id = cell_id
+            JOIN plugins ON plugins.id = plugin_id
+            JOIN files ON files.id = plugins.file_id
+            JOIN mods ON mods.id = files.mod_id
+            WHERE master = $1 AND world_id = $2
+            AND cells.x BETWEEN $3 AND $4 AND cells.y BETWEEN $5 AND $6
+            AND ($7 OR NOT mods.is_translation)
+            GROUP BY cells.x, cells.y",
+        master,
+        world_id,
+        min_x,
+        max_x,
+        min_y,
+        max_y,
+        include_translations,
+    )
+    .fetch_all(executor)
+    .await
+    .context("Failed to count mod edits in area")
+}
+
 /// Returns cell properties plus a list of mods that edit the cell
-#[instrument(level = "debug", skip(pool))]
+#[instrument(level = "debug", skip(executor))]
 pub async fn get_cell_data(
-    pool: &sqlx::Pool<sqlx::Postgres>,
+    executor: impl sqlx::PgExecutor<'_>,
     master: &str,
     world_id: i32,
     x: i32,
@@ -208,20 +421,23 @@ pub async fn get_cell_data(
                     COUNT(DISTINCT plugins.id) as plugins_count,
                     COUNT(DISTINCT files.id) as files_count,
                     COUNT(DISTINCT mods.id) as mods_count,
-                    json_agg(DISTINCT mods.*) as mods
+                    json_agg(DISTINCT mods.*) as mods,
+                    cell_lore.name as "lore_name?",
+                    cell_lore.wiki_page as lore_wiki_page
                 FROM cells
                 JOIN plugin_cells on cells.id = cell_id
                 JOIN plugins ON plugins.id = plugin_id
                 JOIN files ON files.id = plugins.file_id
                 JOIN mods ON mods.id = files.mod_id
+                LEFT OUTER JOIN cell_lore ON cell_lore.world_id = cells.world_id AND cell_lore.x = cells.x AND cell_lore.y = cells.y
                 WHERE cells.master = $1 AND cells.world_id = $2 AND cells.x = $3 AND cells.y = $4 AND is_base_game = true
-                GROUP BY cells.x, cells.y, cells.is_persistent, cells.form_id"#,
+                GROUP BY cells.x, cells.y, cells.is_persistent, cells.form_id, cell_lore.name, cell_lore.wiki_page"#,
             master,
             world_id,
             x,
             y
         )
-        .fetch_one(pool)
+        .fetch_one(executor)
         .await
         .context("Failed get cell data")
     } else {
@@ -235,20 +451,23 @@ pub async fn get_cell_data(
                     COUNT(DISTINCT plugins.id) as plugins_count,
                     COUNT(DISTINCT files.id) as files_count,
                     COUNT(DISTINCT mods.id) as mods_count,
-                    json_agg(DISTINCT mods.*) as mods
+                    json_agg(DISTINCT mods.*) as mods,
+                    cell_lore.name as "lore_name?",
+                    cell_lore.wiki_page as lore_wiki_page
                 FROM cells
                 JOIN plugin_cells on cells.id = cell_id
                 JOIN plugins ON plugins.id = plugin_id
                 JOIN files ON files.id = plugins.file_id
                 JOIN mods ON mods.id = files.mod_id
+                LEFT OUTER JOIN cell_lore ON cell_lore.world_id = cells.world_id AND cell_lore.x = cells.x AND cell_lore.y = cells.y
                 WHERE cells.master = $1 AND cells.world_id = $2 AND cells.x = $3 AND cells.y = $4
-                GROUP BY cells.x, cells.y, cells.is_persistent, cells.form_id"#,
+                GROUP BY cells.x, cells.y, cells.is_persistent, cells.form_id, cell_lore.name, cell_lore.wiki_page"#,
             master,
             world_id,
             x,
             y
         )
-        .fetch_one(pool)
+        .fetch_one(executor)
         .await
         .context("Failed get cell data")
 
@@ -0,0 +1,45 @@
+use anyhow::{Context, Result};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use tracing::instrument;
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct CellLore {
+    pub id: i32,
+    pub world_id: i32,
+    pub x: i32,
+    pub y: i32,
+    pub name: String,
+    pub wiki_page: Option<String>,
+    pub updated_at: NaiveDateTime,
+    pub created_at: NaiveDateTime,
+}
+
+#[instrument(level = "debug", skip(executor))]
+pub async fn insert(
+    executor: impl sqlx::PgExecutor<'_>,
+    world_id: i32,
+    x: i32,
+    y: i32,
+    name: &str,
+    wiki_page: Option<&str>,
+) -> Result<CellLore> {
+    sqlx::query_as!(
+        CellLore,
+        "INSERT INTO cell_lore
+            (world_id, x, y, name, wiki_page, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, now(), now())
+            ON CONFLICT (world_id, x, y) DO UPDATE
+            SET (name, wiki_page, updated_at) = (EXCLUDED.name, EXCLUDED.wiki_page, now())
+            RETURNING *",
+        world_id,
+        x,
+        y,
+        name,
+        wiki_page,
+    )
+    .fetch_one(executor)
+    .await
+    .context("Failed to insert cell_lore")
+}
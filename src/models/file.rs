@@ -1,10 +1,13 @@
 use anyhow::{Context, Result};
 use chrono::NaiveDateTime;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
 use sqlx::types::Json;
 use tracing::instrument;
 
 use super::hash_to_string;
+use crate::nexus_api::file_url;
+use crate::nexus_api::files::FileCategory;
+use crate::nexus_api::metadata::{self, ContentPreviewEntry};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct File {
@@ -24,6 +27,13 @@ pub struct File {
     pub downloaded_at: Option<NaiveDateTime>,
     pub has_plugin: bool,
     pub unable_to_extract_plugins: bool,
+    pub metadata_contains_plugin: Option<bool>,
+    pub content_preview: Option<serde_json::Value>,
+    pub skip_reason: Option<String>,
+    pub first_seen_at: NaiveDateTime,
+    pub last_seen_at: NaiveDateTime,
+    pub normalized_category: Option<String>,
+    pub extractor_used: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -33,6 +43,11 @@ pub struct FileWithCells {
     pub file_name: String,
     pub nexus_file_id: i32,
     pub mod_id: i32,
+    pub nexus_mod_id: i32,
+    /// The file on its mod's page on Nexus, or `None` for official content that isn't on Nexus
+    pub nexus_url: Option<String>,
+    /// The Nexus domain of the game the file's mod was scraped from
+    pub game_name: String,
     pub category: Option<String>,
     pub version: Option<String>,
     pub mod_version: Option<String>,
@@ -44,16 +59,120 @@ pub struct FileWithCells {
     pub downloaded_at: Option<NaiveDateTime>,
     pub has_plugin: bool,
     pub unable_to_extract_plugins: bool,
+    pub metadata_contains_plugin: Option<bool>,
+    #[serde(serialize_with = "collapsed_content_preview")]
+    pub content_preview: Option<serde_json::Value>,
+    pub skip_reason: Option<String>,
+    pub first_seen_at: NaiveDateTime,
+    pub last_seen_at: NaiveDateTime,
+    pub normalized_category: Option<String>,
+    pub extractor_used: Option<String>,
     pub cells: Option<serde_json::Value>,
     pub plugins: Option<Json<Vec<FilePlugin>>>,
     pub plugin_count: Option<i64>,
 }
 
+/// A row of `batched_get_with_cells`, before the `nexus_url` is added
+struct FileWithCellsRow {
+    id: i32,
+    name: String,
+    file_name: String,
+    nexus_file_id: i32,
+    mod_id: i32,
+    nexus_mod_id: i32,
+    is_official: bool,
+    game_name: String,
+    category: Option<String>,
+    version: Option<String>,
+    mod_version: Option<String>,
+    size: i64,
+    uploaded_at: NaiveDateTime,
+    has_download_link: bool,
+    updated_at: NaiveDateTime,
+    created_at: NaiveDateTime,
+    downloaded_at: Option<NaiveDateTime>,
+    has_plugin: bool,
+    unable_to_extract_plugins: bool,
+    metadata_contains_plugin: Option<bool>,
+    content_preview: Option<serde_json::Value>,
+    skip_reason: Option<String>,
+    first_seen_at: NaiveDateTime,
+    last_seen_at: NaiveDateTime,
+    normalized_category: Option<String>,
+    extractor_used: Option<String>,
+    cells: Option<serde_json::Value>,
+    plugins: Option<Json<Vec<FilePlugin>>>,
+    plugin_count: Option<i64>,
+}
+
+impl From<FileWithCellsRow> for FileWithCells {
+    fn from(row: FileWithCellsRow) -> Self {
+        FileWithCells {
+            id: row.id,
+            name: row.name,
+            file_name: row.file_name,
+            nexus_file_id: row.nexus_file_id,
+            mod_id: row.mod_id,
+            nexus_url: if row.is_official {
+                None
+            } else {
+                Some(file_url(
+                    &row.game_name,
+                    row.nexus_mod_id,
+                    row.nexus_file_id,
+                ))
+            },
+            nexus_mod_id: row.nexus_mod_id,
+            game_name: row.game_name,
+            category: row.category,
+            version: row.version,
+            mod_version: row.mod_version,
+            size: row.size,
+            uploaded_at: row.uploaded_at,
+            has_download_link: row.has_download_link,
+            updated_at: row.updated_at,
+            created_at: row.created_at,
+            downloaded_at: row.downloaded_at,
+            has_plugin: row.has_plugin,
+            unable_to_extract_plugins: row.unable_to_extract_plugins,
+            metadata_contains_plugin: row.metadata_contains_plugin,
+            content_preview: row.content_preview,
+            skip_reason: row.skip_reason,
+            first_seen_at: row.first_seen_at,
+            last_seen_at: row.last_seen_at,
+            normalized_category: row.normalized_category,
+            extractor_used: row.extractor_used,
+            cells: row.cells,
+            plugins: row.plugins,
+            plugin_count: row.plugin_count,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FilePlugin {
     #[serde(serialize_with = "hash_to_string")]
     pub hash: i64,
     pub file_path: String,
+    pub is_patch: bool,
+}
+
+// The full content preview can be huge, so dumps get the collapsed tree
+fn collapsed_content_preview<S>(
+    content_preview: &Option<serde_json::Value>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let entries = match content_preview {
+        Some(value) => Some(
+            serde_json::from_value::<Vec<ContentPreviewEntry>>(value.clone())
+                .map_err(serde::ser::Error::custom)?,
+        ),
+        None => None,
+    };
+    entries.map(metadata::collapse).serialize(serializer)
 }
 
 #[derive(Debug)]
@@ -63,30 +182,49 @@ pub struct UnsavedFile<'a> {
     pub nexus_file_id: i32,
     pub mod_id: i32,
     pub category: Option<&'a str>,
+    pub normalized_category: Option<FileCategory>,
     pub version: Option<&'a str>,
     pub mod_version: Option<&'a str>,
     pub size: i64,
     pub uploaded_at: NaiveDateTime,
 }
 
-#[instrument(level = "debug", skip(pool))]
+#[instrument(level = "debug", skip(executor))]
 pub async fn get_by_nexus_file_id(
-    pool: &sqlx::Pool<sqlx::Postgres>,
+    executor: impl sqlx::PgExecutor<'_>,
+    game_id: i32,
     nexus_file_id: i32,
 ) -> Result<Option<File>> {
     sqlx::query_as!(
         File,
-        "SELECT * FROM files WHERE nexus_file_id = $1",
+        "SELECT files.* FROM files
+        JOIN mods ON mods.id = files.mod_id
+        WHERE mods.game_id = $1 AND files.nexus_file_id = $2",
+        game_id,
         nexus_file_id,
     )
-    .fetch_optional(pool)
+    .fetch_optional(executor)
     .await
     .context("Failed to get file")
 }
 
-#[instrument(level = "debug", skip(pool))]
+#[instrument(level = "debug", skip(executor))]
+pub async fn get_by_mod_id(executor: impl sqlx::PgExecutor<'_>, mod_id: i32) -> Result<Vec<File>> {
+    sqlx::query_as!(
+        File,
+        "SELECT * FROM files WHERE mod_id = $1 ORDER BY id ASC",
+        mod_id,
+    )
+    .fetch_all(executor)
+    .await
+    .context("Failed to get files")
+}
+
+/// Files of the mod that `update` doesn't need to process again: extracted (files are only marked
+/// downloaded once their archive is extracted), known to have no plugins, or skipped
+#[instrument(level = "debug", skip(executor))]
 pub async fn get_processed_nexus_file_ids_by_mod_id(
-    pool: &sqlx::Pool<sqlx::Postgres>,
+    executor: impl sqlx::PgExecutor<'_>,
     mod_id: i32,
 ) -> Result<Vec<i32>> {
     sqlx::query!(
@@ -94,48 +232,103 @@ pub async fn get_processed_nexus_file_ids_by_mod_id(
             WHERE mod_id = $1 AND (
                 downloaded_at IS NOT NULL OR
                 has_plugin = false OR
-                has_download_link = false
+                has_download_link = false OR
+                skip_reason IS NOT NULL
             )",
         mod_id
     )
     .map(|row| row.nexus_file_id)
-    .fetch_all(pool)
+    .fetch_all(executor)
     .await
     .context("Failed to get files")
 }
 
-#[instrument(level = "debug", skip(pool))]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ModMetadataPluginCounts {
+    pub mod_id: i32,
+    pub checked_count: Option<i64>,
+    pub with_plugin_count: Option<i64>,
+}
+
+/// Counts how many files of each mod had their metadata checked and how many of those contained a
+/// plugin
+#[instrument(level = "debug", skip(executor))]
+pub async fn get_metadata_plugin_counts_by_mod_ids(
+    executor: impl sqlx::PgExecutor<'_>,
+    mod_ids: &[i32],
+) -> Result<Vec<ModMetadataPluginCounts>> {
+    sqlx::query_as!(
+        ModMetadataPluginCounts,
+        "SELECT
+            mod_id,
+            COUNT(metadata_contains_plugin) AS checked_count,
+            COUNT(*) FILTER (WHERE metadata_contains_plugin) AS with_plugin_count
+        FROM files
+        WHERE mod_id = ANY($1::int[])
+        GROUP BY mod_id",
+        mod_ids,
+    )
+    .fetch_all(executor)
+    .await
+    .context("Failed to get file metadata plugin counts")
+}
+
+#[instrument(level = "debug", skip(executor))]
 pub async fn insert<'a>(
-    pool: &sqlx::Pool<sqlx::Postgres>,
+    executor: impl sqlx::PgExecutor<'_>,
     unsaved_file: &UnsavedFile<'a>,
 ) -> Result<File> {
     sqlx::query_as!(
         File,
         "INSERT INTO files
-            (name, file_name, nexus_file_id, mod_id, category, version, mod_version, size, uploaded_at, created_at, updated_at)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, now(), now())
+            (name, file_name, nexus_file_id, mod_id, category, normalized_category, version, mod_version, size, uploaded_at, first_seen_at, last_seen_at, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, now(), now(), now(), now())
             ON CONFLICT (mod_id, nexus_file_id) DO UPDATE
-            SET (name, file_name, category, version, mod_version, uploaded_at, updated_at) =
-            (EXCLUDED.name, EXCLUDED.file_name, EXCLUDED.category, EXCLUDED.version, EXCLUDED.mod_version, EXCLUDED.uploaded_at, now())
+            SET (name, file_name, category, normalized_category, version, mod_version, uploaded_at, last_seen_at, updated_at) =
+            (EXCLUDED.name, EXCLUDED.file_name, EXCLUDED.category, EXCLUDED.normalized_category, EXCLUDED.version, EXCLUDED.mod_version, EXCLUDED.uploaded_at, now(), now())
             RETURNING *",
         unsaved_file.name,
         unsaved_file.file_name,
         unsaved_file.nexus_file_id,
         unsaved_file.mod_id,
         unsaved_file.category,
+        unsaved_file
+            .normalized_category
+            .map(|category| category.as_str()),
         unsaved_file.version,
         unsaved_file.mod_version,
         unsaved_file.size,
-        unsaved_file.uploaded_at
+        unsaved_file.uploaded_at,
     )
-    .fetch_one(pool)
+    .fetch_one(executor)
     .await
     .context("Failed to insert file")
 }
 
-#[instrument(level = "debug", skip(pool))]
+/// Records that the files were listed on the mod, including ones that are not re-inserted because
+/// they were already processed
+#[instrument(level = "debug", skip(executor))]
+pub async fn update_last_seen_at(
+    executor: impl sqlx::PgExecutor<'_>,
+    mod_id: i32,
+    nexus_file_ids: &[i32],
+) -> Result<u64> {
+    Ok(sqlx::query!(
+        "UPDATE files
+            SET last_seen_at = now()
+            WHERE mod_id = $1 AND nexus_file_id = ANY($2::int[])",
+        mod_id,
+        nexus_file_ids,
+    )
+    .execute(executor)
+    .await
+    .context("Failed to update file last_seen_at")?
+    .rows_affected())
+}
+
+#[instrument(level = "debug", skip(executor))]
 pub async fn update_has_download_link(
-    pool: &sqlx::Pool<sqlx::Postgres>,
+    executor: impl sqlx::PgExecutor<'_>,
     id: i32,
     has_download_link: bool,
 ) -> Result<File> {
@@ -148,13 +341,13 @@ pub async fn update_has_download_link(
         id,
         has_download_link,
     )
-    .fetch_one(pool)
+    .fetch_one(executor)
     .await
     .context("Failed to update file")
 }
 
-#[instrument(level = "debug", skip(pool))]
-pub async fn update_downloaded_at(pool: &sqlx::Pool<sqlx::Postgres>, id: i32) -> Result<File> {
+#[instrument(level = "debug", skip(executor))]
+pub async fn update_downloaded_at(executor: impl sqlx::PgExecutor<'_>, id: i32) -> Result<File> {
     sqlx::query_as!(
         File,
         "UPDATE files
@@ -163,14 +356,14 @@ pub async fn update_downloaded_at(pool: &sqlx::Pool<sqlx::Postgres>, id: i32) ->
             RETURNING *",
         id,
     )
-    .fetch_one(pool)
+    .fetch_one(executor)
     .await
     .context("Failed to update file")
 }
 
-#[instrument(level = "debug", skip(pool))]
+#[instrument(level = "debug", skip(executor))]
 pub async fn update_has_plugin(
-    pool: &sqlx::Pool<sqlx::Postgres>,
+    executor: impl sqlx::PgExecutor<'_>,
     id: i32,
     has_plugin: bool,
 ) -> Result<File> {
@@ -183,14 +376,82 @@ pub async fn update_has_plugin(
         id,
         has_plugin,
     )
-    .fetch_one(pool)
+    .fetch_one(executor)
+    .await
+    .context("Failed to update file")
+}
+
+#[instrument(level = "debug", skip(executor))]
+pub async fn get_distinct_categories(executor: impl sqlx::PgExecutor<'_>) -> Result<Vec<String>> {
+    sqlx::query_scalar!(
+        r#"SELECT DISTINCT category AS "category!" FROM files WHERE category IS NOT NULL"#
+    )
+    .fetch_all(executor)
+    .await
+    .context("Failed to get distinct file categories")
+}
+
+#[instrument(level = "debug", skip(executor))]
+pub async fn update_normalized_category_by_category(
+    executor: impl sqlx::PgExecutor<'_>,
+    category: &str,
+    normalized_category: Option<FileCategory>,
+) -> Result<u64> {
+    Ok(sqlx::query!(
+        "UPDATE files SET normalized_category = $2 WHERE category = $1",
+        category,
+        normalized_category.map(|category| category.as_str()),
+    )
+    .execute(executor)
+    .await
+    .context("Failed to update file normalized_category")?
+    .rows_affected())
+}
+
+#[instrument(level = "debug", skip(executor))]
+pub async fn update_skip_reason(
+    executor: impl sqlx::PgExecutor<'_>,
+    id: i32,
+    skip_reason: &str,
+) -> Result<File> {
+    sqlx::query_as!(
+        File,
+        "UPDATE files
+            SET skip_reason = $2
+            WHERE id = $1
+            RETURNING *",
+        id,
+        skip_reason,
+    )
+    .fetch_one(executor)
     .await
     .context("Failed to update file")
 }
 
-#[instrument(level = "debug", skip(pool))]
+#[instrument(level = "debug", skip(executor))]
+pub async fn update_content_preview(
+    executor: impl sqlx::PgExecutor<'_>,
+    id: i32,
+    content_preview: &[ContentPreviewEntry],
+) -> Result<File> {
+    sqlx::query_as!(
+        File,
+        "UPDATE files
+            SET (content_preview, metadata_contains_plugin) = ($2, $3)
+            WHERE id = $1
+            RETURNING *",
+        id,
+        serde_json::to_value(content_preview)?,
+        metadata::contains_plugin(content_preview),
+    )
+    .fetch_one(executor)
+    .await
+    .context("Failed to update file")
+}
+
+#[instrument(level = "debug", skip(executor))]
 pub async fn update_unable_to_extract_plugins(
-    pool: &sqlx::Pool<sqlx::Postgres>,
+    executor: impl sqlx::PgExecutor<'_>,
     id: i32,
     unable_to_extract_plugins: bool,
 ) -> Result<File> {
@@ -203,14 +464,34 @@ pub async fn update_unable_to_extract_plugins(
         id,
         unable_to_extract_plugins,
     )
-    .fetch_one(pool)
+    .fetch_one(executor)
+    .await
+    .context("Failed to update file")
+}
+
+#[instrument(level = "debug", skip(executor))]
+pub async fn update_extractor_used(
+    executor: impl sqlx::PgExecutor<'_>,
+    id: i32,
+    extractor_used: &str,
+) -> Result<File> {
+    sqlx::query_as!(
+        File,
+        "UPDATE files
+            SET extractor_used = $2
+            WHERE id = $1
+            RETURNING *",
+        id,
+        extractor_used,
+    )
+    .fetch_one(executor)
     .await
     .context("Failed to update file")
 }
 
-#[instrument(level = "debug", skip(pool))]
+#[instrument(level = "debug", skip(executor))]
 pub async fn batched_get_with_cells(
-    pool: &sqlx::Pool<sqlx::Postgres>,
+    executor: impl sqlx::PgExecutor<'_>,
     page_size: i64,
     last_id: Option<i32>,
     master: &str,
@@ -220,18 +501,23 @@ pub async fn batched_get_with_cells(
     let last_id = last_id.unwrap_or(0);
     if let Some(updated_after) = updated_after {
         sqlx::query_as!(
-            FileWithCells,
+            FileWithCellsRow,
             r#"SELECT
                 files.*,
                 COALESCE(json_agg(DISTINCT jsonb_build_object('x', cells.x, 'y', cells.y)) FILTER (WHERE cells.x IS NOT NULL AND cells.y IS NOT NULL AND cells.master = $3 AND cells.world_id = $4), '[]') AS cells,
-                COALESCE(json_agg(DISTINCT jsonb_build_object('hash', plugins.hash, 'file_path', plugins.file_path)) FILTER (WHERE plugins.hash IS NOT NULL), '[]') AS "plugins: Json<Vec<FilePlugin>>",
-                COUNT(plugins.*) AS plugin_count
+                COALESCE(json_agg(DISTINCT jsonb_build_object('hash', plugins.hash, 'file_path', plugins.file_path, 'is_patch', plugins.is_patch)) FILTER (WHERE plugins.hash IS NOT NULL), '[]') AS "plugins: Json<Vec<FilePlugin>>",
+                COUNT(plugins.*) AS plugin_count,
+                mods.nexus_mod_id,
+                mods.is_official,
+                games.name AS game_name
             FROM files
+            JOIN mods ON mods.id = files.mod_id
+            JOIN games ON games.id = mods.game_id
             LEFT OUTER JOIN plugin_cells ON plugin_cells.file_id = files.id
             LEFT OUTER JOIN cells ON cells.id = plugin_cells.cell_id
             LEFT OUTER JOIN plugins ON plugins.file_id = files.id
             WHERE files.id > $2 AND files.updated_at > $5
-            GROUP BY files.id
+            GROUP BY files.id, mods.id, games.id
             ORDER BY files.id ASC
             LIMIT $1"#,
             page_size,
@@ -240,23 +526,29 @@ pub async fn batched_get_with_cells(
             world_id,
             updated_after
         )
-        .fetch_all(pool)
+        .fetch_all(executor)
         .await
         .context("Failed to batch get with cells")
+        .map(|rows| rows.into_iter().map(FileWithCells::from).collect())
     } else {
         sqlx::query_as!(
-            FileWithCells,
+            FileWithCellsRow,
             r#"SELECT
                 files.*,
                 COALESCE(json_agg(DISTINCT jsonb_build_object('x', cells.x, 'y', cells.y)) FILTER (WHERE cells.x IS NOT NULL AND cells.y IS NOT NULL AND cells.master = $3 AND cells.world_id = $4), '[]') AS cells,
-                COALESCE(json_agg(DISTINCT jsonb_build_object('hash', plugins.hash, 'file_path', plugins.file_path)) FILTER (WHERE plugins.hash IS NOT NULL), '[]') AS "plugins: Json<Vec<FilePlugin>>",
-                COUNT(plugins.*) AS plugin_count
+                COALESCE(json_agg(DISTINCT jsonb_build_object('hash', plugins.hash, 'file_path', plugins.file_path, 'is_patch', plugins.is_patch)) FILTER (WHERE plugins.hash IS NOT NULL), '[]') AS "plugins: Json<Vec<FilePlugin>>",
+                COUNT(plugins.*) AS plugin_count,
+                mods.nexus_mod_id,
+                mods.is_official,
+                games.name AS game_name
             FROM files
+            JOIN mods ON mods.id = files.mod_id
+            JOIN games ON games.id = mods.game_id
             LEFT OUTER JOIN plugin_cells ON plugin_cells.file_id = files.id
             LEFT OUTER JOIN cells ON cells.id = plugin_cells.cell_id
             LEFT OUTER JOIN plugins ON plugins.file_id = files.id
             WHERE files.id > $2
-            GROUP BY files.id
+            GROUP BY files.id, mods.id, games.id
             ORDER BY files.id ASC
             LIMIT $1"#,
             page_size,
@@ -264,8 +556,9 @@ pub async fn batched_get_with_cells(
             master,
             world_id
         )
-        .fetch_all(pool)
+        .fetch_all(executor)
         .await
         .context("Failed to batch get with cells")
+        .map(|rows| rows.into_iter().map(FileWithCells::from).collect())
     }
 }
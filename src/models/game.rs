@@ -12,9 +12,25 @@ pub struct Game {
     pub created_at: NaiveDateTime,
 }
 
-#[instrument(level = "debug", skip(pool))]
+/// Totals across every mod of a game
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GameStats {
+    #[serde(skip_serializing)]
+    pub game_id: i32,
+    pub mod_count: Option<i64>,
+    pub file_count: Option<i64>,
+    pub plugin_count: Option<i64>,
+    /// Total size of the game's plugins in bytes
+    pub total_plugin_size: Option<i64>,
+    /// Distinct cells edited by any of the game's plugins
+    pub cell_count: Option<i64>,
+    /// Total size of the game's files that were downloaded in bytes
+    pub downloaded_bytes: Option<i64>,
+}
+
+#[instrument(level = "debug", skip(executor))]
 pub async fn insert(
-    pool: &sqlx::Pool<sqlx::Postgres>,
+    executor: impl sqlx::PgExecutor<'_>,
     name: &str,
     nexus_game_id: i32,
 ) -> Result<Game> {
@@ -28,24 +44,64 @@ pub async fn insert(
         name,
         nexus_game_id
     )
-    .fetch_one(pool)
+    .fetch_one(executor)
     .await
     .context("Failed to insert game")
 }
 
-#[instrument(level = "debug", skip(pool))]
-pub async fn get_all(pool: &sqlx::Pool<sqlx::Postgres>) -> Result<Vec<Game>> {
+#[instrument(level = "debug", skip(executor))]
+pub async fn get_all(executor: impl sqlx::PgExecutor<'_>) -> Result<Vec<Game>> {
     sqlx::query_as!(Game, "SELECT * FROM games")
-        .fetch_all(pool)
+        .fetch_all(executor)
         .await
         .context("Failed to fetch games")
 }
 
-#[instrument(level = "debug", skip(pool))]
-pub async fn get_id_by_name(pool: &sqlx::Pool<sqlx::Postgres>, name: &str) -> Result<i32> {
+#[instrument(level = "debug", skip(executor))]
+pub async fn get_id_by_name(executor: impl sqlx::PgExecutor<'_>, name: &str) -> Result<i32> {
     sqlx::query_scalar!("SELECT id FROM games WHERE name = $1", name)
-        .fetch_one(pool)
+        .fetch_one(executor)
         .await
         .context("Failed to fetch game id by name")
 }
 
+#[instrument(level = "debug", skip(executor))]
+pub async fn get_stats(executor: impl sqlx::PgExecutor<'_>) -> Result<Vec<GameStats>> {
+    sqlx::query_as!(
+        GameStats,
+        "SELECT
+            games.id AS game_id,
+            (SELECT COUNT(*) FROM mods WHERE mods.game_id = games.id) AS mod_count,
+            file_stats.file_count,
+            file_stats.downloaded_bytes,
+            plugin_stats.plugin_count,
+            plugin_stats.total_plugin_size,
+            (
+                SELECT COUNT(DISTINCT plugin_cells.cell_id)
+                FROM plugin_cells
+                JOIN mods ON mods.id = plugin_cells.mod_id
+                WHERE mods.game_id = games.id
+            ) AS cell_count
+        FROM games
+        LEFT OUTER JOIN LATERAL (
+            SELECT
+                COUNT(*) AS file_count,
+                COALESCE(SUM(files.size) FILTER (WHERE files.downloaded_at IS NOT NULL), 0)::bigint AS downloaded_bytes
+            FROM files
+            JOIN mods ON mods.id = files.mod_id
+            WHERE mods.game_id = games.id
+        ) AS file_stats ON true
+        LEFT OUTER JOIN LATERAL (
+            SELECT
+                COUNT(*) AS plugin_count,
+                COALESCE(SUM(plugins.size), 0)::bigint AS total_plugin_size
+            FROM plugins
+            JOIN mods ON mods.id = plugins.mod_id
+            WHERE mods.game_id = games.id
+        ) AS plugin_stats ON true
+        ORDER BY games.id",
+    )
+    .fetch_all(executor)
+    .await
+    .context("Failed to get game stats")
+}
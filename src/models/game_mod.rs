@@ -1,11 +1,16 @@
 use anyhow::{Context, Result};
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
-use sqlx::FromRow;
+use sqlx::{Acquire, FromRow};
+use std::collections::HashMap;
 use tracing::instrument;
 
 use crate::nexus_api::game_mod::ExtractedModData;
+use crate::nexus_api::graphql::GraphQLMod;
+use crate::nexus_api::{file_url, mod_url};
 
+use super::category::{self, canonical_category_name};
+use super::game;
 use super::BATCH_SIZE;
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
@@ -26,6 +31,17 @@ pub struct Mod {
     pub last_update_at: NaiveDateTime,
     pub first_upload_at: NaiveDateTime,
     pub last_updated_files_at: Option<NaiveDateTime>,
+    pub is_official: bool,
+    pub is_adult: Option<bool>,
+    pub downloads: Option<i32>,
+    pub nexus_created_at: Option<NaiveDateTime>,
+    pub nexus_updated_at: Option<NaiveDateTime>,
+    pub delisted_at: Option<NaiveDateTime>,
+    pub translation_count: i32,
+    pub files_deferred_at: Option<NaiveDateTime>,
+    pub deferred_file_count: i32,
+    pub metadata_refreshed_at: Option<NaiveDateTime>,
+    pub language: Option<String>,
 }
 
 #[derive(Debug)]
@@ -50,6 +66,7 @@ pub struct ModForSearch {
     pub name: String,
     pub game_id: i32,
     pub nexus_mod_id: i32,
+    pub translation_count: i32,
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
@@ -57,28 +74,82 @@ pub struct ModWithCellsAndFiles {
     pub id: i32,
     pub name: String,
     pub nexus_mod_id: i32,
+    /// The mod's page on Nexus, or `None` for official content that isn't on Nexus
+    pub nexus_url: Option<String>,
     pub author_name: String,
     pub author_id: i32,
+    /// The canonical English name of the category
     pub category_name: Option<String>,
     pub category_id: Option<i32>,
+    /// The name the category was scraped with, if it was localized
+    pub localized_category_name: Option<String>,
     pub description: Option<String>,
     pub thumbnail_link: Option<String>,
     pub game_id: i32,
+    /// The Nexus domain of the game the mod was scraped from
+    pub game_name: String,
     pub is_translation: bool,
     pub updated_at: NaiveDateTime,
     pub created_at: NaiveDateTime,
     pub last_update_at: NaiveDateTime,
     pub first_upload_at: NaiveDateTime,
     pub last_updated_files_at: Option<NaiveDateTime>,
+    pub is_official: bool,
+    pub is_adult: Option<bool>,
+    pub downloads: Option<i32>,
+    pub nexus_created_at: Option<NaiveDateTime>,
+    pub nexus_updated_at: Option<NaiveDateTime>,
+    pub delisted_at: Option<NaiveDateTime>,
+    pub translation_count: i32,
+    pub files_deferred_at: Option<NaiveDateTime>,
+    pub deferred_file_count: i32,
+    pub metadata_refreshed_at: Option<NaiveDateTime>,
+    pub language: Option<String>,
     pub cells: Option<serde_json::Value>,
     pub files: Option<serde_json::Value>,
     pub plugin_count: Option<i64>,
+    pub npc_count: Option<i64>,
+    pub quest_count: Option<i64>,
+    pub dialogue_count: Option<i64>,
+    pub ports: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct DelistedModWithCells {
+    pub id: i32,
+    pub name: String,
+    pub nexus_mod_id: i32,
+    pub author_name: String,
+    pub author_id: i32,
+    pub category_name: Option<String>,
+    pub localized_category_name: Option<String>,
+    pub game_id: i32,
+    pub last_update_at: NaiveDateTime,
+    pub first_upload_at: NaiveDateTime,
+    pub delisted_at: Option<NaiveDateTime>,
+    pub translation_count: i32,
+    pub cells: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct ModCellCount {
     pub nexus_mod_id: i32,
+    /// Exterior cells of the world the counts were made for
     pub cells: Option<i64>,
+    /// Interior cells of any plugin
+    pub interior_cells: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct CategoryCellStats {
+    pub game_id: i32,
+    pub category_id: Option<i32>,
+    pub category_name: Option<String>,
+    /// Other names the category's mods were scraped with
+    pub localized_category_names: Option<Vec<String>>,
+    pub mod_count: Option<i64>,
+    pub total_cells: Option<i64>,
+    pub average_cells_per_mod: Option<f64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
@@ -93,23 +164,41 @@ pub struct ModFiles {
     pub files: Option<serde_json::Value>,
 }
 
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct ModPorts {
+    pub mod_id: i32,
+    pub ports: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct ModForPortMatching {
+    pub id: i32,
+    pub name: String,
+    pub author_id: i32,
+}
+
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct ModPluginCount {
     pub mod_id: i32,
     pub plugin_count: Option<i64>,
+    pub npc_count: Option<i64>,
+    pub quest_count: Option<i64>,
+    pub dialogue_count: Option<i64>,
 }
 
-#[instrument(level = "debug", skip(pool))]
+#[instrument(level = "debug", skip(executor))]
 pub async fn get_by_nexus_mod_id(
-    pool: &sqlx::Pool<sqlx::Postgres>,
+    executor: impl sqlx::PgExecutor<'_>,
+    game_id: i32,
     nexus_mod_id: i32,
 ) -> Result<Option<Mod>> {
     sqlx::query_as!(
         Mod,
-        "SELECT * FROM mods WHERE nexus_mod_id = $1",
+        "SELECT * FROM mods WHERE game_id = $1 AND nexus_mod_id = $2",
+        game_id,
         nexus_mod_id,
     )
-    .fetch_optional(pool)
+    .fetch_optional(executor)
     .await
     .context("Failed to get mod")
 }
@@ -119,9 +208,9 @@ pub struct ModLastUpdatedFilesAt {
     pub last_updated_files_at: NaiveDateTime,
 }
 
-#[instrument(level = "debug", skip(pool))]
+#[instrument(level = "debug", skip(executor))]
 pub async fn bulk_get_last_updated_by_nexus_mod_ids(
-    pool: &sqlx::Pool<sqlx::Postgres>,
+    executor: impl sqlx::PgExecutor<'_>,
     game_id: i32,
     nexus_mod_ids: &[i32],
 ) -> Result<Vec<ModLastUpdatedFilesAt>> {
@@ -139,14 +228,14 @@ pub async fn bulk_get_last_updated_by_nexus_mod_ids(
             .last_updated_files_at
             .expect("last_updated_files_at is null"),
     })
-    .fetch_all(pool)
+    .fetch_all(executor)
     .await
     .context("Failed to bulk get last_updated_files_at by nexus_mod_ids")
 }
 
-#[instrument(level = "debug", skip(pool))]
+#[instrument(level = "debug", skip(executor))]
 pub async fn insert(
-    pool: &sqlx::Pool<sqlx::Postgres>,
+    executor: impl sqlx::PgExecutor<'_>,
     name: &str,
     nexus_mod_id: i32,
     author_name: &str,
@@ -157,17 +246,18 @@ pub async fn insert(
     thumbnail_link: Option<&str>,
     game_id: i32,
     is_translation: bool,
+    is_official: bool,
     last_update_at: NaiveDateTime,
     first_upload_at: NaiveDateTime,
 ) -> Result<Mod> {
     sqlx::query_as!(
         Mod,
         "INSERT INTO mods
-            (name, nexus_mod_id, author_name, author_id, category_name, category_id, description, thumbnail_link, game_id, is_translation, last_update_at, first_upload_at, created_at, updated_at)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, now(), now())
+            (name, nexus_mod_id, author_name, author_id, category_name, category_id, description, thumbnail_link, game_id, is_translation, is_official, last_update_at, first_upload_at, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, now(), now())
             ON CONFLICT (game_id, nexus_mod_id) DO UPDATE
-            SET (name, author_name, author_id, category_name, category_id, description, thumbnail_link, is_translation, last_update_at, first_upload_at, updated_at) =
-            (EXCLUDED.name, EXCLUDED.author_name, EXCLUDED.author_id, EXCLUDED.category_name, EXCLUDED.category_id, EXCLUDED.description, EXCLUDED.thumbnail_link, EXCLUDED.is_translation, EXCLUDED.last_update_at, EXCLUDED.first_upload_at, now())
+            SET (name, author_name, author_id, category_name, category_id, description, thumbnail_link, is_translation, is_official, last_update_at, first_upload_at, updated_at) =
+            (EXCLUDED.name, EXCLUDED.author_name, EXCLUDED.author_id, EXCLUDED.category_name, EXCLUDED.category_id, EXCLUDED.description, EXCLUDED.thumbnail_link, EXCLUDED.is_translation, EXCLUDED.is_official, EXCLUDED.last_update_at, EXCLUDED.first_upload_at, now())
             RETURNING *",
         name,
         nexus_mod_id,
@@ -179,19 +269,21 @@ pub async fn insert(
         thumbnail_link,
         game_id,
         is_translation,
+        is_official,
         last_update_at,
         first_upload_at
     )
-    .fetch_one(pool)
+    .fetch_one(executor)
     .await
     .context("Failed to insert or update mod")
 }
 
-#[instrument(level = "debug", skip(pool))]
+#[instrument(level = "debug", skip(conn))]
 pub async fn batched_insert<'a>(
-    pool: &sqlx::Pool<sqlx::Postgres>,
+    conn: impl sqlx::Acquire<'_, Database = sqlx::Postgres>,
     mods: &[UnsavedMod<'a>],
 ) -> Result<Vec<Mod>> {
+    let mut conn = conn.acquire().await?;
     let mut saved_mods = vec![];
     for batch in mods.chunks(BATCH_SIZE) {
         let mut names: Vec<&str> = vec![];
@@ -228,8 +320,8 @@ pub async fn batched_insert<'a>(
                 SELECT *, now(), now()
                 FROM UNNEST($1::text[], $2::int[], $3::text[], $4::int[], $5::text[], $6::int[], $7::text[], $8::text[], $9::int[], $10::bool[], $11::timestamp(3)[], $12::timestamp(3)[])
                 ON CONFLICT (game_id, nexus_mod_id) DO UPDATE
-                SET (name, author_name, author_id, category_name, category_id, description, thumbnail_link, is_translation, last_update_at, first_upload_at, updated_at) =
-                (EXCLUDED.name, EXCLUDED.author_name, EXCLUDED.author_id, EXCLUDED.category_name, EXCLUDED.category_id, EXCLUDED.description, EXCLUDED.thumbnail_link, EXCLUDED.is_translation, EXCLUDED.last_update_at, EXCLUDED.first_upload_at, now())
+                SET (name, author_name, author_id, category_name, category_id, description, thumbnail_link, is_translation, last_update_at, first_upload_at, delisted_at, updated_at) =
+                (EXCLUDED.name, EXCLUDED.author_name, EXCLUDED.author_id, EXCLUDED.category_name, EXCLUDED.category_id, EXCLUDED.description, EXCLUDED.thumbnail_link, EXCLUDED.is_translation, EXCLUDED.last_update_at, EXCLUDED.first_upload_at, NULL, now())
                 RETURNING *"#,
             )
             .bind(&names)
@@ -244,7 +336,7 @@ pub async fn batched_insert<'a>(
             .bind(&is_translations)
             .bind(&last_update_ats)
             .bind(&first_upload_ats)
-            .fetch_all(pool)
+            .fetch_all(&mut *conn)
             .await
             .context("Failed to insert mods")?,
         );
@@ -252,38 +344,64 @@ pub async fn batched_insert<'a>(
     Ok(saved_mods)
 }
 
-#[instrument(level = "debug", skip(pool))]
-pub async fn get(pool: &sqlx::Pool<sqlx::Postgres>, id: i32) -> Result<Option<Mod>> {
+#[instrument(level = "debug", skip(executor))]
+pub async fn get(executor: impl sqlx::PgExecutor<'_>, id: i32) -> Result<Option<Mod>> {
     sqlx::query_as!(Mod, "SELECT * FROM mods WHERE id = $1", id)
-        .fetch_optional(pool)
+        .fetch_optional(executor)
         .await
         .context("Failed to get mod")
 }
 
-#[instrument(level = "debug", skip(pool))]
+#[instrument(level = "debug", skip(executor))]
 pub async fn update_last_updated_files_at(
-    pool: &sqlx::Pool<sqlx::Postgres>,
+    executor: impl sqlx::PgExecutor<'_>,
     id: i32,
 ) -> Result<Mod> {
     sqlx::query_as!(
         Mod,
         "UPDATE mods
-            SET last_updated_files_at = now()
+            SET
+                last_updated_files_at = now() AT TIME ZONE 'UTC',
+                files_deferred_at = NULL,
+                deferred_file_count = 0
             WHERE id = $1
             RETURNING *",
         id,
     )
-    .fetch_one(pool)
+    .fetch_one(executor)
     .await
     .context("Failed to update mod")
 }
 
-#[instrument(level = "debug", skip(pool, game_mod, mod_data))]
+/// Records that `deferred_file_count` of the mod's files were left for the next update. The mod's
+/// last_updated_files_at is left as is so that the next update processes it again.
+#[instrument(level = "debug", skip(executor))]
+pub async fn update_deferred_files(
+    executor: impl sqlx::PgExecutor<'_>,
+    id: i32,
+    deferred_file_count: i32,
+) -> Result<Mod> {
+    sqlx::query_as!(
+        Mod,
+        "UPDATE mods
+            SET files_deferred_at = now() AT TIME ZONE 'UTC', deferred_file_count = $2
+            WHERE id = $1
+            RETURNING *",
+        id,
+        deferred_file_count,
+    )
+    .fetch_one(executor)
+    .await
+    .context("Failed to update mod deferred files")
+}
+
+#[instrument(level = "debug", skip(conn, game_mod, mod_data))]
 pub async fn update_from_api_response<'a>(
-    pool: &sqlx::Pool<sqlx::Postgres>,
+    conn: impl sqlx::Acquire<'_, Database = sqlx::Postgres>,
     game_mod: &Mod,
     mod_data: &ExtractedModData<'a>,
 ) -> Result<Mod> {
+    let mut conn = conn.acquire().await?;
     let name = mod_data.name.unwrap_or(&game_mod.name);
     let category_id = match mod_data.category_id {
         Some(category_id) => Some(category_id),
@@ -300,7 +418,9 @@ pub async fn update_from_api_response<'a>(
                 author_name = $5,
                 author_id = $6,
                 last_update_at = $7,
-                first_upload_at = $8
+                first_upload_at = $8,
+                metadata_refreshed_at = now(),
+                updated_at = now()
             WHERE id = $1
             RETURNING *",
         game_mod.id,
@@ -312,7 +432,7 @@ pub async fn update_from_api_response<'a>(
         mod_data.last_update_at,
         mod_data.first_upload_at,
     )
-    .fetch_one(pool)
+    .fetch_one(&mut *conn)
     .await
     .context("Failed to update mod from api response")?;
 
@@ -327,7 +447,7 @@ pub async fn update_from_api_response<'a>(
             game_mod.id,
             description,
         )
-        .fetch_one(pool)
+        .fetch_one(&mut *conn)
         .await
         .context("Failed to update mod from api response")?;
     }
@@ -343,7 +463,7 @@ pub async fn update_from_api_response<'a>(
             game_mod.id,
             thumbnail_link,
         )
-        .fetch_one(pool)
+        .fetch_one(&mut *conn)
         .await
         .context("Failed to update mod from api response")?;
     }
@@ -351,12 +471,167 @@ pub async fn update_from_api_response<'a>(
     Ok(ret)
 }
 
-#[instrument(level = "debug", skip(pool))]
+/// Returns the listed mods whose metadata was refreshed (or that were updated) longest ago
+#[instrument(level = "debug", skip(executor))]
+pub async fn get_for_metadata_refresh(
+    executor: impl sqlx::PgExecutor<'_>,
+    limit: i64,
+) -> Result<Vec<Mod>> {
+    sqlx::query_as!(
+        Mod,
+        "SELECT * FROM mods
+            WHERE delisted_at IS NULL AND is_official = false
+            ORDER BY COALESCE(metadata_refreshed_at, updated_at) ASC
+            LIMIT $1",
+        limit,
+    )
+    .fetch_all(executor)
+    .await
+    .context("Failed to get mods for metadata refresh")
+}
+
+/// Returns the listed mods of the game that an update left files of for the next update, the
+/// longest deferred first
+#[instrument(level = "debug", skip(executor))]
+pub async fn get_deferred(executor: impl sqlx::PgExecutor<'_>, game_id: i32) -> Result<Vec<Mod>> {
+    sqlx::query_as!(
+        Mod,
+        "SELECT * FROM mods
+            WHERE game_id = $1 AND files_deferred_at IS NOT NULL AND delisted_at IS NULL
+            ORDER BY files_deferred_at ASC, id ASC",
+        game_id,
+    )
+    .fetch_all(executor)
+    .await
+    .context("Failed to get mods with deferred files")
+}
+
+/// Returns the listed mods of the game whose files were last processed before the mod was last
+/// updated (or never processed), most out of date first. Mods that were never processed are out
+/// of date since they were first uploaded.
+#[instrument(level = "debug", skip(executor))]
+pub async fn get_stale(
+    executor: impl sqlx::PgExecutor<'_>,
+    game_id: i32,
+    limit: i64,
+) -> Result<Vec<Mod>> {
+    sqlx::query_as!(
+        Mod,
+        "SELECT * FROM mods
+            WHERE
+                game_id = $1 AND
+                delisted_at IS NULL AND
+                is_official = false AND
+                (last_updated_files_at IS NULL OR last_updated_files_at < last_update_at)
+            ORDER BY last_update_at - COALESCE(last_updated_files_at, first_upload_at) DESC, id ASC
+            LIMIT $2",
+        game_id,
+        limit,
+    )
+    .fetch_all(executor)
+    .await
+    .context("Failed to get stale mods")
+}
+
+/// Marks the mod as refreshed without changing its metadata, so a mod that fails to refresh does
+/// not hold up the rest
+#[instrument(level = "debug", skip(executor))]
+pub async fn update_metadata_refreshed_at(
+    executor: impl sqlx::PgExecutor<'_>,
+    id: i32,
+) -> Result<()> {
+    sqlx::query!(
+        "UPDATE mods SET metadata_refreshed_at = now() WHERE id = $1",
+        id,
+    )
+    .execute(executor)
+    .await
+    .context("Failed to update mod metadata_refreshed_at")?;
+    Ok(())
+}
+
+#[instrument(level = "debug", skip(executor, graphql_mods))]
+pub async fn batched_update_from_graphql(
+    executor: impl sqlx::PgExecutor<'_>,
+    game_id: i32,
+    graphql_mods: &[GraphQLMod],
+) -> Result<Vec<Mod>> {
+    let mut nexus_mod_ids: Vec<i32> = vec![];
+    let mut is_adults: Vec<bool> = vec![];
+    let mut downloads: Vec<i32> = vec![];
+    let mut nexus_created_ats: Vec<NaiveDateTime> = vec![];
+    let mut nexus_updated_ats: Vec<NaiveDateTime> = vec![];
+    let mut languages: Vec<Option<String>> = vec![];
+    graphql_mods.iter().for_each(|graphql_mod| {
+        nexus_mod_ids.push(graphql_mod.mod_id);
+        is_adults.push(graphql_mod.adult_content);
+        downloads.push(graphql_mod.downloads);
+        nexus_created_ats.push(graphql_mod.created_at.naive_utc());
+        nexus_updated_ats.push(graphql_mod.updated_at.naive_utc());
+        languages.push(graphql_mod.language_name.clone());
+    });
+    // sqlx doesn't understand arrays of Options with the query_as! macro
+    sqlx::query_as(
+        r#"UPDATE mods
+            SET
+                is_adult = graphql_mods.is_adult,
+                downloads = graphql_mods.downloads,
+                nexus_created_at = graphql_mods.nexus_created_at,
+                nexus_updated_at = graphql_mods.nexus_updated_at,
+                language = graphql_mods.language,
+                delisted_at = NULL,
+                updated_at = now()
+            FROM UNNEST(
+                $2::int[],
+                $3::bool[],
+                $4::int[],
+                $5::timestamp(3)[],
+                $6::timestamp(3)[],
+                $7::text[]
+            ) AS graphql_mods(nexus_mod_id, is_adult, downloads, nexus_created_at, nexus_updated_at, language)
+            WHERE mods.game_id = $1 AND mods.nexus_mod_id = graphql_mods.nexus_mod_id
+            RETURNING mods.*"#,
+    )
+    .bind(game_id)
+    .bind(&nexus_mod_ids)
+    .bind(&is_adults)
+    .bind(&downloads)
+    .bind(&nexus_created_ats)
+    .bind(&nexus_updated_ats)
+    .bind(&languages)
+    .fetch_all(executor)
+    .await
+    .context("Failed to update mods from graphql")
+}
+
+#[instrument(level = "debug", skip(executor))]
+pub async fn batched_get(
+    executor: impl sqlx::PgExecutor<'_>,
+    page_size: i64,
+    last_id: Option<i32>,
+) -> Result<Vec<Mod>> {
+    let last_id = last_id.unwrap_or(0);
+    sqlx::query_as!(
+        Mod,
+        "SELECT * FROM mods
+        WHERE id > $2
+        ORDER BY id ASC
+        LIMIT $1",
+        page_size,
+        last_id,
+    )
+    .fetch_all(executor)
+    .await
+    .context("Failed to batch get mods")
+}
+
+#[instrument(level = "debug", skip(executor))]
 pub async fn batched_get_for_search(
-    pool: &sqlx::Pool<sqlx::Postgres>,
-    game: i32,
+    executor: impl sqlx::PgExecutor<'_>,
+    game_ids: &[i32],
     page_size: i64,
     last_id: Option<i32>,
+    include_translations: bool,
 ) -> Result<Vec<ModForSearch>> {
     let last_id = last_id.unwrap_or(0);
     sqlx::query_as!(
@@ -365,29 +640,169 @@ pub async fn batched_get_for_search(
             id,
             name,
             game_id,
-            nexus_mod_id
+            nexus_mod_id,
+            translation_count
         FROM mods
-        WHERE id > $3 AND game_id = $1
+        WHERE id > $3 AND game_id = ANY($1::int[])
+        AND ($4 OR NOT is_translation)
         ORDER BY mods.id ASC
         LIMIT $2",
-        game,
+        game_ids,
         page_size,
         last_id,
+        include_translations,
     )
-    .fetch_all(pool)
+    .fetch_all(executor)
     .await
     .context("Failed to batch get for search")
 }
 
-#[instrument(level = "debug", skip(pool))]
+/// Recounts the translations of every mod. A translation belongs to the first mod (in the same
+/// game) with a plugin of the same file name, since translations replace the original's plugin.
+/// Only mods whose count changed are written, and their `updated_at` is left alone since it tracks
+/// changes scraped from Nexus.
+#[instrument(level = "debug", skip(executor))]
+pub async fn update_translation_counts(executor: impl sqlx::PgExecutor<'_>) -> Result<u64> {
+    Ok(sqlx::query!(
+        "WITH originals AS (
+            SELECT DISTINCT ON (mods.game_id, lower(plugins.file_name))
+                mods.game_id, lower(plugins.file_name) AS file_name, plugins.mod_id
+            FROM plugins
+            JOIN mods ON mods.id = plugins.mod_id
+            WHERE NOT mods.is_translation AND NOT mods.is_official
+            ORDER BY mods.game_id, lower(plugins.file_name), plugins.mod_id
+        ), translations AS (
+            SELECT DISTINCT mods.game_id, lower(plugins.file_name) AS file_name, plugins.mod_id
+            FROM plugins
+            JOIN mods ON mods.id = plugins.mod_id
+            WHERE mods.is_translation
+        ), counts AS (
+            SELECT mods.id, COUNT(DISTINCT translations.mod_id)::int AS count
+            FROM mods
+            LEFT OUTER JOIN originals ON originals.mod_id = mods.id
+            LEFT OUTER JOIN translations ON translations.game_id = originals.game_id
+                AND translations.file_name = originals.file_name
+            GROUP BY mods.id
+        )
+        UPDATE mods
+            SET translation_count = counts.count
+            FROM counts
+            WHERE mods.id = counts.id AND mods.translation_count <> counts.count",
+    )
+    .execute(executor)
+    .await
+    .context("Failed to update mod translation counts")?
+    .rows_affected())
+}
+
+/// Returns the listed mods of the game that could be the original or a port of a mod in another
+/// game (translations and official content are never ports).
+#[instrument(level = "debug", skip(executor))]
+pub async fn get_for_port_matching(
+    executor: impl sqlx::PgExecutor<'_>,
+    game_id: i32,
+) -> Result<Vec<ModForPortMatching>> {
+    sqlx::query_as!(
+        ModForPortMatching,
+        "SELECT id, name, author_id FROM mods
+            WHERE
+                game_id = $1 AND
+                is_translation = false AND
+                is_official = false AND
+                delisted_at IS NULL
+            ORDER BY id",
+        game_id,
+    )
+    .fetch_all(executor)
+    .await
+    .context("Failed to get mods for port matching")
+}
+
+/// Marks the mods as missing from nexus, keeping the time they first went missing
+#[instrument(level = "debug", skip(executor))]
+pub async fn batched_update_delisted(
+    executor: impl sqlx::PgExecutor<'_>,
+    ids: &[i32],
+) -> Result<Vec<Mod>> {
+    sqlx::query_as!(
+        Mod,
+        "UPDATE mods
+            SET delisted_at = COALESCE(delisted_at, now()), updated_at = now()
+            WHERE id = ANY($1::int[])
+            RETURNING *",
+        ids,
+    )
+    .fetch_all(executor)
+    .await
+    .context("Failed to batch update delisted mods")
+}
+
+#[instrument(level = "debug", skip(executor))]
+pub async fn get_delisted_with_cells(
+    executor: impl sqlx::PgExecutor<'_>,
+    master: &str,
+    world_id: i32,
+) -> Result<Vec<DelistedModWithCells>> {
+    sqlx::query_as!(
+        DelistedModWithCells,
+        "SELECT
+            mods.id,
+            mods.name,
+            mods.nexus_mod_id,
+            mods.author_name,
+            mods.author_id,
+            COALESCE(categories.name, mods.category_name) AS category_name,
+            CASE WHEN mods.category_name <> categories.name THEN mods.category_name END AS localized_category_name,
+            mods.game_id,
+            mods.last_update_at,
+            mods.first_upload_at,
+            mods.delisted_at,
+            mods.translation_count,
+            COALESCE(json_agg(DISTINCT jsonb_build_object('x', cells.x, 'y', cells.y)) FILTER (WHERE cells.x IS NOT NULL AND cells.y IS NOT NULL AND cells.master = $1 AND cells.world_id = $2), '[]') AS cells
+        FROM mods
+        LEFT OUTER JOIN categories ON categories.game_id = mods.game_id AND categories.nexus_category_id = mods.category_id
+        LEFT OUTER JOIN plugin_cells ON plugin_cells.mod_id = mods.id
+        LEFT OUTER JOIN cells ON cells.id = plugin_cells.cell_id
+        WHERE mods.delisted_at IS NOT NULL
+        GROUP BY mods.id, categories.id
+        ORDER BY mods.delisted_at ASC, mods.id ASC",
+        master,
+        world_id,
+    )
+    .fetch_all(executor)
+    .await
+    .context("Failed to get delisted mods with cells")
+}
+
+/// Adds a `nexus_url` to each file in a mod's aggregated `files`
+pub fn add_file_urls(
+    mut files: serde_json::Value,
+    game_name: &str,
+    nexus_mod_id: i32,
+) -> serde_json::Value {
+    if let Some(files) = files.as_array_mut() {
+        for file in files.iter_mut().filter_map(|file| file.as_object_mut()) {
+            if let Some(nexus_file_id) = file.get("nexus_file_id").and_then(|id| id.as_i64()) {
+                file.insert(
+                    "nexus_url".to_string(),
+                    file_url(game_name, nexus_mod_id, nexus_file_id as i32).into(),
+                );
+            }
+        }
+    }
+    files
+}
+
+#[instrument(level = "debug", skip(conn))]
 pub async fn batched_get_with_cells_and_files(
-    pool: &sqlx::Pool<sqlx::Postgres>,
+    conn: impl sqlx::Acquire<'_, Database = sqlx::Postgres>,
     page_size: i64,
     last_id: Option<i32>,
     master: &str,
     world_id: i32,
     updated_after: Option<NaiveDateTime>,
 ) -> Result<Vec<ModWithCellsAndFiles>> {
+    let mut conn = conn.acquire().await?;
     let last_id = last_id.unwrap_or(0);
     let mods = if let Some(updated_after) = updated_after {
         sqlx::query_as!(
@@ -403,7 +818,7 @@ pub async fn batched_get_with_cells_and_files(
             last_id,
             updated_after
         )
-        .fetch_all(pool)
+        .fetch_all(&mut *conn)
         .await
         .context("Failed to batch get mods")?
     } else {
@@ -419,7 +834,7 @@ pub async fn batched_get_with_cells_and_files(
             page_size,
             last_id
         )
-        .fetch_all(pool)
+        .fetch_all(&mut *conn)
         .await
         .context("Failed to batch get mods")?
     };
@@ -442,59 +857,159 @@ pub async fn batched_get_with_cells_and_files(
         master,
         world_id
     )
-    .fetch_all(pool)
+    .fetch_all(&mut *conn)
     .await
     .context("Failed to batch get mod cells")?;
     let mod_files = sqlx::query_as!(
         ModFiles,
         "SELECT
             files.mod_id AS mod_id,
-            json_agg(jsonb_build_object('nexus_file_id', nexus_file_id, 'name', name, 'version', version, 'category', category)) AS files
+            json_agg(jsonb_build_object(
+                'nexus_file_id', files.nexus_file_id,
+                'name', files.name,
+                'version', files.version,
+                'category', files.category,
+                'plugin_count', COALESCE(file_plugins.plugin_count, 0),
+                'plugins', COALESCE(file_plugins.plugins, '[]')
+            )) AS files
         FROM files
+        LEFT OUTER JOIN (
+            SELECT
+                plugins.file_id,
+                COUNT(*) AS plugin_count,
+                json_agg(plugins.file_name ORDER BY plugins.file_name) AS plugins
+            FROM plugins
+            WHERE plugins.mod_id = ANY($1::int[])
+            GROUP BY plugins.file_id
+        ) AS file_plugins ON file_plugins.file_id = files.id
         WHERE
             files.mod_id = ANY($1::int[])
         GROUP BY files.mod_id",
         &mod_ids,
     )
-    .fetch_all(pool)
+    .fetch_all(&mut *conn)
     .await
     .context("Failed to batch get mod files")?;
     let plugins_count = sqlx::query_as!(
         ModPluginCount,
         "SELECT
-            mod_id,
-            COUNT(*) as plugin_count
-        FROM plugins
-        WHERE
-            mod_id = ANY($1::int[])
-        GROUP BY mod_id",
+            plugin_counts.mod_id,
+            plugin_counts.plugin_count,
+            record_counts.npc_count,
+            record_counts.quest_count,
+            record_counts.dialogue_count
+        FROM (
+            SELECT mod_id, COUNT(*) AS plugin_count
+            FROM plugins
+            WHERE mod_id = ANY($1::int[])
+            GROUP BY mod_id
+        ) AS plugin_counts
+        JOIN (
+            SELECT
+                mod_id,
+                SUM(npc_count)::bigint AS npc_count,
+                SUM(quest_count)::bigint AS quest_count,
+                SUM(dialogue_count)::bigint AS dialogue_count
+            FROM (
+                -- the same plugin is usually in many files of a mod, so only count its latest version
+                SELECT DISTINCT ON (mod_id, lower(file_name))
+                    mod_id,
+                    npc_count,
+                    quest_count,
+                    dialogue_count
+                FROM plugins
+                WHERE mod_id = ANY($1::int[])
+                ORDER BY mod_id, lower(file_name), updated_at DESC
+            ) AS latest_plugins
+            GROUP BY mod_id
+        ) AS record_counts ON record_counts.mod_id = plugin_counts.mod_id",
         &mod_ids,
     )
-    .fetch_all(pool)
+    .fetch_all(&mut *conn)
     .await
     .context("Failed to batch get mod files")?;
+    let mod_ports = sqlx::query_as!(
+        ModPorts,
+        "SELECT
+            mods.id AS mod_id,
+            json_agg(jsonb_build_object(
+                'nexus_mod_id', other_mods.nexus_mod_id,
+                'game_id', other_mods.game_id,
+                'name', other_mods.name,
+                'is_original', other_mods.id = mod_ports.original_mod_id
+            ) ORDER BY other_mods.id) AS ports
+        FROM mods
+        JOIN mod_ports ON mod_ports.original_mod_id = mods.id OR mod_ports.port_mod_id = mods.id
+        JOIN mods AS other_mods ON other_mods.id = CASE
+            WHEN mod_ports.original_mod_id = mods.id THEN mod_ports.port_mod_id
+            ELSE mod_ports.original_mod_id
+        END
+        WHERE mods.id = ANY($1::int[])
+        GROUP BY mods.id",
+        &mod_ids,
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .context("Failed to batch get mod ports")?;
+    let game_names: HashMap<i32, String> = game::get_all(&mut *conn)
+        .await?
+        .into_iter()
+        .map(|game| (game.id, game.name))
+        .collect();
+    let category_names = category::get_names(&mut *conn).await?;
 
     Ok(mods
         .into_iter()
         .map(|m| {
             let id = m.id;
+            let nexus_mod_id = m.nexus_mod_id;
+            let is_official = m.is_official;
+            let game_name = game_names
+                .get(&m.game_id)
+                .expect("valid mod.game_id")
+                .clone();
+            let game_id = m.game_id;
+            let (category_name, localized_category_name) = canonical_category_name(
+                m.category_name,
+                m.category_id
+                    .and_then(|category_id| category_names.get(&(game_id, category_id)))
+                    .map(String::as_str),
+            );
             ModWithCellsAndFiles {
                 id: m.id,
                 name: m.name,
                 nexus_mod_id: m.nexus_mod_id,
+                nexus_url: if m.is_official {
+                    None
+                } else {
+                    Some(mod_url(&game_name, m.nexus_mod_id))
+                },
                 author_name: m.author_name,
                 author_id: m.author_id,
-                category_name: m.category_name,
+                category_name,
                 category_id: m.category_id,
+                localized_category_name,
                 description: m.description,
                 thumbnail_link: m.thumbnail_link,
                 game_id: m.game_id,
+                game_name: game_name.clone(),
                 is_translation: m.is_translation,
                 updated_at: m.updated_at,
                 created_at: m.created_at,
                 last_update_at: m.last_update_at,
                 first_upload_at: m.first_upload_at,
                 last_updated_files_at: m.last_updated_files_at,
+                is_official: m.is_official,
+                is_adult: m.is_adult,
+                downloads: m.downloads,
+                nexus_created_at: m.nexus_created_at,
+                nexus_updated_at: m.nexus_updated_at,
+                delisted_at: m.delisted_at,
+                translation_count: m.translation_count,
+                files_deferred_at: m.files_deferred_at,
+                deferred_file_count: m.deferred_file_count,
+                metadata_refreshed_at: m.metadata_refreshed_at,
+                language: m.language,
                 cells: mod_cells
                     .iter()
                     .find(|c| c.mod_id == id)
@@ -503,45 +1018,126 @@ pub async fn batched_get_with_cells_and_files(
                 files: mod_files
                     .iter()
                     .find(|f| f.mod_id == id)
-                    .map(|f| f.files.clone())
+                    .map(|f| {
+                        if is_official {
+                            f.files.clone()
+                        } else {
+                            f.files
+                                .clone()
+                                .map(|files| add_file_urls(files, &game_name, nexus_mod_id))
+                        }
+                    })
                     .unwrap_or_else(|| Some(serde_json::Value::Array(vec![]))),
                 plugin_count: plugins_count
                     .iter()
                     .find(|p| p.mod_id == id)
                     .map(|p| p.plugin_count)
                     .unwrap_or(Some(0)),
+                npc_count: plugins_count
+                    .iter()
+                    .find(|p| p.mod_id == id)
+                    .map(|p| p.npc_count)
+                    .unwrap_or(Some(0)),
+                quest_count: plugins_count
+                    .iter()
+                    .find(|p| p.mod_id == id)
+                    .map(|p| p.quest_count)
+                    .unwrap_or(Some(0)),
+                dialogue_count: plugins_count
+                    .iter()
+                    .find(|p| p.mod_id == id)
+                    .map(|p| p.dialogue_count)
+                    .unwrap_or(Some(0)),
+                ports: mod_ports
+                    .iter()
+                    .find(|p| p.mod_id == id)
+                    .map(|p| p.ports.clone())
+                    .unwrap_or_else(|| Some(serde_json::Value::Array(vec![]))),
             }
         })
         .collect())
 }
 
-#[instrument(level = "debug", skip(pool))]
+/// Counts the exterior cells of the world and the interior cells each mod edits, in pages ordered by
+/// nexus mod id
+#[instrument(level = "debug", skip(executor))]
 pub async fn batched_get_cell_counts(
-    pool: &sqlx::Pool<sqlx::Postgres>,
+    executor: impl sqlx::PgExecutor<'_>,
     page_size: i64,
     last_id: Option<i32>,
     master: &str,
     world_id: i32,
+    include_translations: bool,
 ) -> Result<Vec<ModCellCount>> {
     let last_id = last_id.unwrap_or(0);
     sqlx::query_as!(
         ModCellCount,
         "SELECT
             mods.nexus_mod_id,
-            COUNT(DISTINCT cells.*) FILTER (WHERE cells.x IS NOT NULL AND cells.y IS NOT NULL AND cells.master = $3 AND cells.world_id = $4) AS cells
+            COUNT(DISTINCT cells.*) FILTER (WHERE cells.x IS NOT NULL AND cells.y IS NOT NULL AND cells.master = $3 AND cells.world_id = $4) AS cells,
+            COUNT(DISTINCT cells.id) FILTER (WHERE cells.world_id IS NULL) AS interior_cells
         FROM mods
         INNER JOIN plugin_cells ON plugin_cells.mod_id = mods.id
         INNER JOIN cells ON cells.id = plugin_cells.cell_id
         WHERE mods.nexus_mod_id > $2
+        AND ($5 OR NOT mods.is_translation)
         GROUP BY mods.nexus_mod_id
         ORDER BY mods.nexus_mod_id ASC
         LIMIT $1",
         page_size,
         last_id,
         master,
-        world_id
+        world_id,
+        include_translations,
     )
-    .fetch_all(pool)
+    .fetch_all(executor)
     .await
     .context("Failed to batch get mod cell counts")
 }
+
+/// Returns the number of mods in each category of each game, the cells they edit (counting a cell
+/// once per mod that edits it), and the average cells edited per mod (including mods with no
+/// exterior cells)
+#[instrument(level = "debug", skip(executor))]
+pub async fn get_category_cell_stats(
+    executor: impl sqlx::PgExecutor<'_>,
+    master: &str,
+    world_id: i32,
+    include_translations: bool,
+) -> Result<Vec<CategoryCellStats>> {
+    sqlx::query_as!(
+        CategoryCellStats,
+        "SELECT
+            mods.game_id,
+            mods.category_id,
+            COALESCE(categories.name, MIN(mods.category_name)) AS category_name,
+            array_agg(DISTINCT mods.category_name) FILTER (WHERE mods.category_name <> categories.name) AS localized_category_names,
+            COUNT(*) AS mod_count,
+            COALESCE(SUM(mod_cells.cell_count), 0)::bigint AS total_cells,
+            AVG(COALESCE(mod_cells.cell_count, 0))::float8 AS average_cells_per_mod
+        FROM mods
+        LEFT OUTER JOIN (
+            SELECT plugin_cells.mod_id, COUNT(DISTINCT cells.id) AS cell_count
+            FROM plugin_cells
+            INNER JOIN cells ON cells.id = plugin_cells.cell_id
+            WHERE
+                cells.x IS NOT NULL AND
+                cells.y IS NOT NULL AND
+                cells.master = $1 AND
+                cells.world_id = $2
+            GROUP BY plugin_cells.mod_id
+        ) AS mod_cells ON mod_cells.mod_id = mods.id
+        LEFT OUTER JOIN categories ON categories.game_id = mods.game_id AND categories.nexus_category_id = mods.category_id
+        WHERE
+            NOT mods.is_official AND
+            ($3 OR NOT mods.is_translation)
+        GROUP BY mods.game_id, mods.category_id, categories.name
+        ORDER BY mods.game_id, total_cells DESC",
+        master,
+        world_id,
+        include_translations,
+    )
+    .fetch_all(executor)
+    .await
+    .context("Failed to get category cell stats")
+}
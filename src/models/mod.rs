@@ -1,10 +1,15 @@
+pub mod category;
 pub mod cell;
+pub mod cell_lore;
 pub mod file;
 pub mod game;
 pub mod game_mod;
+pub mod mod_merge;
+pub mod mod_port;
 pub mod plugin;
 pub mod plugin_cell;
 pub mod plugin_world;
+pub mod scrape_run;
 pub mod world;
 
 pub const BATCH_SIZE: usize = 50;
@@ -0,0 +1,91 @@
+use anyhow::{Context, Result};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use tracing::instrument;
+
+use super::game_mod::Mod;
+
+/// A duplicate mod row (`removed_mod_id`, since deleted) that was merged into `kept_mod_id`
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct ModMerge {
+    pub id: i32,
+    pub kept_mod_id: i32,
+    pub removed_mod_id: i32,
+    pub removed_game_id: i32,
+    pub removed_nexus_mod_id: i32,
+    pub created_at: NaiveDateTime,
+}
+
+/// A merge with the game names and nexus ids of both mods, where their dumped files are
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct ModMergeWithGames {
+    pub removed_game_name: String,
+    pub removed_nexus_mod_id: i32,
+    pub kept_game_name: String,
+    pub kept_nexus_mod_id: i32,
+    pub merged_at: NaiveDateTime,
+}
+
+#[instrument(level = "debug", skip(executor))]
+pub async fn insert(
+    executor: impl sqlx::PgExecutor<'_>,
+    kept_mod_id: i32,
+    removed_mod: &Mod,
+) -> Result<ModMerge> {
+    sqlx::query_as!(
+        ModMerge,
+        "INSERT INTO mod_merges
+            (kept_mod_id, removed_mod_id, removed_game_id, removed_nexus_mod_id, created_at)
+            VALUES ($1, $2, $3, $4, now())
+            RETURNING *",
+        kept_mod_id,
+        removed_mod.id,
+        removed_mod.game_id,
+        removed_mod.nexus_mod_id,
+    )
+    .fetch_one(executor)
+    .await
+    .context("Failed to insert mod merge")
+}
+
+/// Points merges into `from_mod_id` at `to_mod_id`, for when a kept mod is itself merged away
+#[instrument(level = "debug", skip(executor))]
+pub async fn update_kept_mod_id(
+    executor: impl sqlx::PgExecutor<'_>,
+    from_mod_id: i32,
+    to_mod_id: i32,
+) -> Result<u64> {
+    Ok(sqlx::query!(
+        "UPDATE mod_merges SET kept_mod_id = $2 WHERE kept_mod_id = $1",
+        from_mod_id,
+        to_mod_id,
+    )
+    .execute(executor)
+    .await
+    .context("Failed to update mod merge kept_mod_id")?
+    .rows_affected())
+}
+
+#[instrument(level = "debug", skip(executor))]
+pub async fn get_all_with_games(
+    executor: impl sqlx::PgExecutor<'_>,
+) -> Result<Vec<ModMergeWithGames>> {
+    sqlx::query_as!(
+        ModMergeWithGames,
+        "SELECT
+            removed_games.name AS removed_game_name,
+            mod_merges.removed_nexus_mod_id,
+            kept_games.name AS kept_game_name,
+            kept_mods.nexus_mod_id AS kept_nexus_mod_id,
+            mod_merges.created_at AS merged_at
+        FROM mod_merges
+        JOIN games AS removed_games ON removed_games.id = mod_merges.removed_game_id
+        JOIN mods AS kept_mods ON kept_mods.id = mod_merges.kept_mod_id
+        JOIN games AS kept_games ON kept_games.id = kept_mods.game_id
+        ORDER BY mod_merges.created_at ASC, mod_merges.id ASC",
+    )
+    .fetch_all(executor)
+    .await
+    .context("Failed to get mod merges")
+}
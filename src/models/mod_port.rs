@@ -0,0 +1,93 @@
+use anyhow::{Context, Result};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use tracing::instrument;
+
+use super::BATCH_SIZE;
+
+/// A mod (`port_mod_id`) that is a port of another mod (`original_mod_id`) from a different game.
+/// `match_reason` is "plugin_hash" or "author_and_name".
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct ModPort {
+    pub id: i32,
+    pub original_mod_id: i32,
+    pub port_mod_id: i32,
+    pub match_reason: String,
+    pub updated_at: NaiveDateTime,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug)]
+pub struct UnsavedModPort<'a> {
+    pub original_mod_id: i32,
+    pub port_mod_id: i32,
+    pub match_reason: &'a str,
+}
+
+/// Links every mod of `port_game_id` to the mods of `original_game_id` that have a plugin with
+/// the same hash and file name. Returns the number of new links.
+#[instrument(level = "debug", skip(executor))]
+pub async fn insert_by_plugin_hash(
+    executor: impl sqlx::PgExecutor<'_>,
+    original_game_id: i32,
+    port_game_id: i32,
+) -> Result<u64> {
+    Ok(sqlx::query!(
+        "INSERT INTO mod_ports
+            (original_mod_id, port_mod_id, match_reason, created_at, updated_at)
+            SELECT DISTINCT original_plugins.mod_id, port_plugins.mod_id, 'plugin_hash', now(), now()
+            FROM plugins AS original_plugins
+            JOIN mods AS original_mods ON original_mods.id = original_plugins.mod_id
+            JOIN plugins AS port_plugins ON
+                port_plugins.hash = original_plugins.hash AND
+                lower(port_plugins.file_name) = lower(original_plugins.file_name)
+            JOIN mods AS port_mods ON port_mods.id = port_plugins.mod_id
+            WHERE
+                original_mods.game_id = $1 AND
+                port_mods.game_id = $2 AND
+                original_mods.is_official = false AND
+                port_mods.is_official = false
+            ON CONFLICT (original_mod_id, port_mod_id) DO NOTHING",
+        original_game_id,
+        port_game_id,
+    )
+    .execute(executor)
+    .await
+    .context("Failed to insert mod_ports by plugin hash")?
+    .rows_affected())
+}
+
+/// Saves the links, keeping the reason of links that already exist. Returns the number of new
+/// links.
+#[instrument(level = "debug", skip(conn, mod_ports))]
+pub async fn batched_insert<'a>(
+    conn: impl sqlx::Acquire<'_, Database = sqlx::Postgres>,
+    mod_ports: &[UnsavedModPort<'a>],
+) -> Result<u64> {
+    let mut conn = conn.acquire().await?;
+    let mut inserted = 0;
+    for batch in mod_ports.chunks(BATCH_SIZE) {
+        let mut original_mod_ids: Vec<i32> = vec![];
+        let mut port_mod_ids: Vec<i32> = vec![];
+        let mut match_reasons: Vec<&str> = vec![];
+        batch.iter().for_each(|unsaved_mod_port| {
+            original_mod_ids.push(unsaved_mod_port.original_mod_id);
+            port_mod_ids.push(unsaved_mod_port.port_mod_id);
+            match_reasons.push(unsaved_mod_port.match_reason);
+        });
+        inserted += sqlx::query(
+            r#"INSERT INTO mod_ports (original_mod_id, port_mod_id, match_reason, created_at, updated_at)
+            SELECT *, now(), now() FROM UNNEST($1::int[], $2::int[], $3::text[])
+            ON CONFLICT (original_mod_id, port_mod_id) DO NOTHING"#,
+        )
+        .bind(&original_mod_ids)
+        .bind(&port_mod_ids)
+        .bind(&match_reasons)
+        .execute(&mut *conn)
+        .await
+        .context("Failed to insert mod_ports")?
+        .rows_affected();
+    }
+    Ok(inserted)
+}
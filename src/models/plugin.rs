@@ -2,7 +2,7 @@ use anyhow::{Context, Result};
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 use sqlx::types::Json;
-use sqlx::FromRow;
+use sqlx::{Acquire, FromRow};
 use tracing::instrument;
 
 use super::hash_to_string;
@@ -24,6 +24,15 @@ pub struct Plugin {
     pub file_path: String,
     pub updated_at: NaiveDateTime,
     pub created_at: NaiveDateTime,
+    pub npc_count: i32,
+    pub quest_count: i32,
+    pub dialogue_count: i32,
+    pub is_patch: bool,
+    /// Local to the machine that scraped the plugin, so left out of the dumps
+    #[serde(skip_serializing, default)]
+    pub storage_root: Option<String>,
+    #[serde(skip_serializing, default)]
+    pub tiered_at: Option<NaiveDateTime>,
 }
 
 #[derive(Debug)]
@@ -39,6 +48,11 @@ pub struct UnsavedPlugin<'a> {
     pub masters: &'a [&'a str],
     pub file_name: &'a str,
     pub file_path: &'a str,
+    pub npc_count: i32,
+    pub quest_count: i32,
+    pub dialogue_count: i32,
+    pub is_patch: bool,
+    pub storage_root: Option<&'a str>,
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
@@ -51,19 +65,50 @@ pub struct PluginsByHashWithMods {
     pub cells: Option<serde_json::Value>,
 }
 
-#[instrument(level = "debug", skip(pool))]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PluginVersion {
+    #[serde(serialize_with = "hash_to_string")]
+    pub hash: i64,
+    pub version: f64,
+    pub file_name: String,
+    pub file_path: String,
+    pub file_id: i32,
+    pub mod_id: i32,
+}
+
+/// The parts of a plugin needed to group it into a mod family
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct PluginForFamily {
+    pub mod_id: i32,
+    pub hash: i64,
+    /// lowercased, since masters are matched case-insensitively
+    pub file_name: String,
+    pub masters: Vec<String>,
+    pub is_patch: bool,
+    pub is_translation: bool,
+    pub is_official: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct PluginsByFileNameWithMods {
+    pub file_name: Option<String>,
+    pub plugins: Option<Json<Vec<PluginVersion>>>,
+    pub mods: Option<serde_json::Value>,
+}
+
+#[instrument(level = "debug", skip(executor))]
 pub async fn insert<'a>(
-    pool: &sqlx::Pool<sqlx::Postgres>,
+    executor: impl sqlx::PgExecutor<'_>,
     unsaved_plugin: &UnsavedPlugin<'a>,
 ) -> Result<Plugin> {
     // sqlx doesn't understand slices of &str with the query_as! macro: https://github.com/launchbadge/sqlx/issues/280
     sqlx::query_as(
         r#"INSERT INTO plugins
-            (name, hash, file_id, mod_id, version, size, author, description, masters, file_name, file_path, created_at, updated_at)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, now(), now())
+            (name, hash, file_id, mod_id, version, size, author, description, masters, file_name, file_path, npc_count, quest_count, dialogue_count, is_patch, storage_root, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, now(), now())
             ON CONFLICT (file_id, file_path) DO UPDATE
-            SET (name, hash, mod_id, version, author, description, masters, file_name, updated_at) =
-            (EXCLUDED.name, EXCLUDED.hash, EXCLUDED.mod_id, EXCLUDED.version, EXCLUDED.author, EXCLUDED.description, EXCLUDED.masters, EXCLUDED.file_name, now())
+            SET (name, hash, mod_id, version, author, description, masters, file_name, npc_count, quest_count, dialogue_count, is_patch, storage_root, tiered_at, updated_at) =
+            (EXCLUDED.name, EXCLUDED.hash, EXCLUDED.mod_id, EXCLUDED.version, EXCLUDED.author, EXCLUDED.description, EXCLUDED.masters, EXCLUDED.file_name, EXCLUDED.npc_count, EXCLUDED.quest_count, EXCLUDED.dialogue_count, EXCLUDED.is_patch, COALESCE(EXCLUDED.storage_root, plugins.storage_root), CASE WHEN EXCLUDED.storage_root IS NULL THEN plugins.tiered_at END, now())
             RETURNING *"#,
     )
     .bind(unsaved_plugin.name)
@@ -77,20 +122,164 @@ pub async fn insert<'a>(
     .bind(unsaved_plugin.masters)
     .bind(unsaved_plugin.file_name)
     .bind(unsaved_plugin.file_path)
-    .fetch_one(pool)
+    .bind(unsaved_plugin.npc_count)
+    .bind(unsaved_plugin.quest_count)
+    .bind(unsaved_plugin.dialogue_count)
+    .bind(unsaved_plugin.is_patch)
+    .bind(unsaved_plugin.storage_root)
+    .fetch_one(executor)
     .await
     .context("Failed to insert plugin")
 }
 
-#[instrument(level = "debug", skip(pool))]
+#[instrument(level = "debug", skip(executor))]
+pub async fn get_by_mod_id(
+    executor: impl sqlx::PgExecutor<'_>,
+    mod_id: i32,
+) -> Result<Vec<Plugin>> {
+    sqlx::query_as!(
+        Plugin,
+        "SELECT * FROM plugins WHERE mod_id = $1 ORDER BY id ASC",
+        mod_id,
+    )
+    .fetch_all(executor)
+    .await
+    .context("Failed to get plugins")
+}
+
+/// Returns the most recently saved plugin with the hash. Plugins with the same hash have the same
+/// contents, so any of them will do.
+#[instrument(level = "debug", skip(executor))]
+pub async fn get_by_hash(executor: impl sqlx::PgExecutor<'_>, hash: i64) -> Result<Option<Plugin>> {
+    sqlx::query_as!(
+        Plugin,
+        "SELECT * FROM plugins WHERE hash = $1 ORDER BY id DESC LIMIT 1",
+        hash
+    )
+    .fetch_optional(executor)
+    .await
+    .context("Failed to get plugin by hash")
+}
+
+/// Returns every saved version of a plugin in a mod, oldest file upload first
+#[instrument(level = "debug", skip(executor))]
+pub async fn get_versions_by_file_name(
+    executor: impl sqlx::PgExecutor<'_>,
+    mod_id: i32,
+    file_name: &str,
+) -> Result<Vec<Plugin>> {
+    sqlx::query_as!(
+        Plugin,
+        "SELECT plugins.* FROM plugins
+            JOIN files ON files.id = plugins.file_id
+            WHERE plugins.mod_id = $1 AND lower(plugins.file_name) = lower($2)
+            ORDER BY files.uploaded_at ASC, plugins.id ASC",
+        mod_id,
+        file_name
+    )
+    .fetch_all(executor)
+    .await
+    .context("Failed to get plugin versions by file_name")
+}
+
+/// Where a plugin is stored on disk, for moving it to cold storage
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct PluginForTiering {
+    pub id: i32,
+    pub file_path: String,
+    pub storage_root: Option<String>,
+    pub nexus_file_id: i32,
+    pub nexus_mod_id: i32,
+    pub game_name: String,
+}
+
+/// Returns a page of the plugins not yet in cold storage whose mods were last updated before
+/// `updated_before`
+#[instrument(level = "debug", skip(executor))]
+pub async fn batched_get_for_tiering(
+    executor: impl sqlx::PgExecutor<'_>,
+    updated_before: NaiveDateTime,
+    page_size: i64,
+    last_id: Option<i32>,
+) -> Result<Vec<PluginForTiering>> {
+    let last_id = last_id.unwrap_or(0);
+    sqlx::query_as!(
+        PluginForTiering,
+        "SELECT
+            plugins.id,
+            plugins.file_path,
+            plugins.storage_root,
+            files.nexus_file_id,
+            mods.nexus_mod_id,
+            games.name AS game_name
+        FROM plugins
+        JOIN files ON files.id = plugins.file_id
+        JOIN mods ON mods.id = plugins.mod_id
+        JOIN games ON games.id = mods.game_id
+        WHERE plugins.id > $3
+        AND plugins.tiered_at IS NULL
+        AND mods.last_update_at < $1
+        ORDER BY plugins.id ASC
+        LIMIT $2",
+        updated_before,
+        page_size,
+        last_id,
+    )
+    .fetch_all(executor)
+    .await
+    .context("Failed to batch get plugins for tiering")
+}
+
+#[instrument(level = "debug", skip(executor))]
+pub async fn update_tiered(
+    executor: impl sqlx::PgExecutor<'_>,
+    id: i32,
+    storage_root: &str,
+) -> Result<()> {
+    sqlx::query!(
+        "UPDATE plugins SET storage_root = $2, tiered_at = now() WHERE id = $1",
+        id,
+        storage_root
+    )
+    .execute(executor)
+    .await
+    .context("Failed to update plugin storage root after tiering")?;
+    Ok(())
+}
+
+#[instrument(level = "debug", skip(executor))]
+pub async fn get_all_for_families(
+    executor: impl sqlx::PgExecutor<'_>,
+) -> Result<Vec<PluginForFamily>> {
+    sqlx::query_as!(
+        PluginForFamily,
+        r#"SELECT
+            plugins.mod_id,
+            plugins.hash,
+            lower(plugins.file_name) AS "file_name!",
+            plugins.masters,
+            plugins.is_patch,
+            mods.is_translation,
+            mods.is_official
+        FROM plugins
+        JOIN mods ON mods.id = plugins.mod_id
+        ORDER BY plugins.mod_id ASC"#,
+    )
+    .fetch_all(executor)
+    .await
+    .context("Failed to get plugins for families")
+}
+
+#[instrument(level = "debug", skip(conn))]
 pub async fn batched_get_by_hash_with_mods(
-    pool: &sqlx::Pool<sqlx::Postgres>,
+    conn: impl sqlx::Acquire<'_, Database = sqlx::Postgres>,
     page_size: i64,
     last_hash: Option<i64>,
     master: &str,
     world_id: i32,
     updated_after: Option<NaiveDateTime>,
 ) -> Result<Vec<PluginsByHashWithMods>> {
+    let mut conn = conn.acquire().await?;
     let last_hash = last_hash.unwrap_or(-9223372036854775808); // psql bigint min
     if let Some(updated_after) = updated_after {
         let hashes = sqlx::query!(
@@ -105,7 +294,7 @@ pub async fn batched_get_by_hash_with_mods(
             last_hash,
             updated_after
         )
-        .fetch_all(pool)
+        .fetch_all(&mut *conn)
         .await
         .context("Failed to batch get plugin hashes")?;
         sqlx::query_as!(
@@ -127,7 +316,7 @@ pub async fn batched_get_by_hash_with_mods(
             master,
             world_id
         )
-        .fetch_all(pool)
+        .fetch_all(&mut *conn)
         .await
         .context("Failed to batch get by hash with mods")
     } else {
@@ -153,8 +342,86 @@ pub async fn batched_get_by_hash_with_mods(
             master,
             world_id
         )
-        .fetch_all(pool)
+        .fetch_all(&mut *conn)
         .await
         .context("Failed to batch get by hash with mods")
     }
 }
+
+/// Groups plugins by their lowercased `file_name`, paginated by that normalized file name.
+#[instrument(level = "debug", skip(conn))]
+pub async fn batched_get_by_file_name_with_mods(
+    conn: impl sqlx::Acquire<'_, Database = sqlx::Postgres>,
+    page_size: i64,
+    last_file_name: Option<&str>,
+    updated_after: Option<NaiveDateTime>,
+) -> Result<Vec<PluginsByFileNameWithMods>> {
+    let mut conn = conn.acquire().await?;
+    let last_file_name = last_file_name.unwrap_or("");
+    if let Some(updated_after) = updated_after {
+        let file_names = sqlx::query_scalar!(
+            r#"SELECT
+                lower(plugins.file_name) AS "file_name!"
+            FROM plugins
+            WHERE lower(plugins.file_name) > $2 AND plugins.updated_at > $3
+            GROUP BY lower(plugins.file_name)
+            ORDER BY lower(plugins.file_name) ASC
+            LIMIT $1"#,
+            page_size,
+            last_file_name,
+            updated_after
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .context("Failed to batch get plugin file names")?;
+        sqlx::query_as!(
+            PluginsByFileNameWithMods,
+            r#"SELECT
+                lower(plugins.file_name) AS file_name,
+                json_agg(DISTINCT jsonb_build_object(
+                    'hash', plugins.hash,
+                    'version', plugins.version,
+                    'file_name', plugins.file_name,
+                    'file_path', plugins.file_path,
+                    'file_id', plugins.file_id,
+                    'mod_id', plugins.mod_id
+                )) AS "plugins: Json<Vec<PluginVersion>>",
+                json_agg(DISTINCT mods.*) AS mods
+            FROM plugins
+            LEFT OUTER JOIN mods ON mods.id = plugins.mod_id
+            WHERE lower(plugins.file_name) = ANY($1::text[])
+            GROUP BY lower(plugins.file_name)
+            ORDER BY lower(plugins.file_name) ASC"#,
+            &file_names,
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .context("Failed to batch get by file name with mods")
+    } else {
+        sqlx::query_as!(
+            PluginsByFileNameWithMods,
+            r#"SELECT
+                lower(plugins.file_name) AS file_name,
+                json_agg(DISTINCT jsonb_build_object(
+                    'hash', plugins.hash,
+                    'version', plugins.version,
+                    'file_name', plugins.file_name,
+                    'file_path', plugins.file_path,
+                    'file_id', plugins.file_id,
+                    'mod_id', plugins.mod_id
+                )) AS "plugins: Json<Vec<PluginVersion>>",
+                json_agg(DISTINCT mods.*) AS mods
+            FROM plugins
+            LEFT OUTER JOIN mods ON mods.id = plugins.mod_id
+            WHERE lower(plugins.file_name) > $2
+            GROUP BY lower(plugins.file_name)
+            ORDER BY lower(plugins.file_name) ASC
+            LIMIT $1"#,
+            page_size,
+            last_file_name,
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .context("Failed to batch get by file name with mods")
+    }
+}
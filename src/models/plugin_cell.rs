@@ -1,7 +1,7 @@
 use anyhow::{Context, Result};
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
-use sqlx::FromRow;
+use sqlx::{Acquire, FromRow};
 use tracing::instrument;
 
 use super::BATCH_SIZE;
@@ -27,9 +27,9 @@ pub struct UnsavedPluginCell<'a> {
     pub editor_id: Option<&'a str>,
 }
 
-#[instrument(level = "debug", skip(pool))]
+#[instrument(level = "debug", skip(executor))]
 pub async fn insert(
-    pool: &sqlx::Pool<sqlx::Postgres>,
+    executor: impl sqlx::PgExecutor<'_>,
     plugin_id: i32,
     cell_id: i32,
     file_id: i32,
@@ -50,16 +50,17 @@ pub async fn insert(
         mod_id,
         editor_id,
     )
-    .fetch_one(pool)
+    .fetch_one(executor)
     .await
     .context("Failed to insert plugin_cell")
 }
 
-#[instrument(level = "debug", skip(pool))]
+#[instrument(level = "debug", skip(conn))]
 pub async fn batched_insert<'a>(
-    pool: &sqlx::Pool<sqlx::Postgres>,
+    conn: impl sqlx::Acquire<'_, Database = sqlx::Postgres>,
     plugin_cells: &[UnsavedPluginCell<'a>],
 ) -> Result<Vec<PluginCell>> {
+    let mut conn = conn.acquire().await?;
     let mut saved_plugin_cells = vec![];
     for batch in plugin_cells.chunks(BATCH_SIZE) {
         let mut plugin_ids: Vec<i32> = vec![];
@@ -88,7 +89,7 @@ pub async fn batched_insert<'a>(
             .bind(&file_ids)
             .bind(&mod_ids)
             .bind(&editor_ids)
-            .fetch_all(pool)
+            .fetch_all(&mut *conn)
             .await
             .context("Failed to insert plugin_cells")?,
         );
@@ -1,7 +1,7 @@
 use anyhow::{Context, Result};
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
-use sqlx::FromRow;
+use sqlx::{Acquire, FromRow};
 use tracing::instrument;
 
 use super::BATCH_SIZE;
@@ -23,9 +23,9 @@ pub struct UnsavedPluginWorld<'a> {
     pub editor_id: &'a str,
 }
 
-#[instrument(level = "debug", skip(pool))]
+#[instrument(level = "debug", skip(executor))]
 pub async fn insert(
-    pool: &sqlx::Pool<sqlx::Postgres>,
+    executor: impl sqlx::PgExecutor<'_>,
     plugin_id: i32,
     world_id: i32,
     editor_id: &str,
@@ -42,16 +42,17 @@ pub async fn insert(
         world_id,
         editor_id,
     )
-    .fetch_one(pool)
+    .fetch_one(executor)
     .await
     .context("Failed to insert plugin_world")
 }
 
-#[instrument(level = "debug", skip(pool))]
+#[instrument(level = "debug", skip(conn))]
 pub async fn batched_insert<'a>(
-    pool: &sqlx::Pool<sqlx::Postgres>,
+    conn: impl sqlx::Acquire<'_, Database = sqlx::Postgres>,
     plugin_worlds: &[UnsavedPluginWorld<'a>],
 ) -> Result<Vec<PluginWorld>> {
+    let mut conn = conn.acquire().await?;
     let mut saved_plugin_worlds = vec![];
     for batch in plugin_worlds.chunks(BATCH_SIZE) {
         let mut plugin_ids: Vec<i32> = vec![];
@@ -73,7 +74,7 @@ pub async fn batched_insert<'a>(
             .bind(&plugin_ids)
             .bind(&world_ids)
             .bind(&editor_ids)
-            .fetch_all(pool)
+            .fetch_all(&mut *conn)
             .await
             .context("Failed to insert plugin_worlds")?,
         );
@@ -0,0 +1,104 @@
+use anyhow::{Context, Result};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use tracing::instrument;
+
+/// One pass of `update` through a game's mod list. `page` is the next page to scrape.
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct ScrapeRun {
+    pub id: i32,
+    pub game_id: i32,
+    pub include_translations: bool,
+    pub page: i32,
+    pub finished_at: Option<NaiveDateTime>,
+    pub updated_at: NaiveDateTime,
+    pub created_at: NaiveDateTime,
+}
+
+#[instrument(level = "debug", skip(executor))]
+pub async fn get_unfinished(
+    executor: impl sqlx::PgExecutor<'_>,
+    game_id: i32,
+    include_translations: bool,
+) -> Result<Option<ScrapeRun>> {
+    sqlx::query_as!(
+        ScrapeRun,
+        "SELECT * FROM scrape_runs
+            WHERE game_id = $1 AND include_translations = $2 AND finished_at IS NULL",
+        game_id,
+        include_translations,
+    )
+    .fetch_optional(executor)
+    .await
+    .context("Failed to get unfinished scrape_run")
+}
+
+/// Starts a pass at `page`, restarting the unfinished pass for the same game and translations
+/// setting if there is one.
+#[instrument(level = "debug", skip(executor))]
+pub async fn start(
+    executor: impl sqlx::PgExecutor<'_>,
+    game_id: i32,
+    include_translations: bool,
+    page: i32,
+) -> Result<ScrapeRun> {
+    sqlx::query_as!(
+        ScrapeRun,
+        "INSERT INTO scrape_runs
+            (game_id, include_translations, page, created_at, updated_at)
+            VALUES ($1, $2, $3, now(), now())
+            ON CONFLICT (game_id, include_translations) WHERE finished_at IS NULL DO UPDATE
+            SET (page, updated_at) = (EXCLUDED.page, now())
+            RETURNING *",
+        game_id,
+        include_translations,
+        page,
+    )
+    .fetch_one(executor)
+    .await
+    .context("Failed to insert scrape_run")
+}
+
+#[instrument(level = "debug", skip(executor))]
+pub async fn update_page(
+    executor: impl sqlx::PgExecutor<'_>,
+    id: i32,
+    page: i32,
+) -> Result<ScrapeRun> {
+    sqlx::query_as!(
+        ScrapeRun,
+        "UPDATE scrape_runs
+            SET (page, updated_at) = ($2, now())
+            WHERE id = $1
+            RETURNING *",
+        id,
+        page,
+    )
+    .fetch_one(executor)
+    .await
+    .context("Failed to update scrape_run")
+}
+
+#[instrument(level = "debug", skip(executor))]
+pub async fn finish(executor: impl sqlx::PgExecutor<'_>, id: i32) -> Result<ScrapeRun> {
+    sqlx::query_as!(
+        ScrapeRun,
+        "UPDATE scrape_runs
+            SET (finished_at, updated_at) = (now(), now())
+            WHERE id = $1
+            RETURNING *",
+        id,
+    )
+    .fetch_one(executor)
+    .await
+    .context("Failed to finish scrape_run")
+}
+
+#[instrument(level = "debug", skip(executor))]
+pub async fn get_latest_id(executor: impl sqlx::PgExecutor<'_>) -> Result<Option<i32>> {
+    sqlx::query_scalar!("SELECT MAX(id) FROM scrape_runs")
+        .fetch_one(executor)
+        .await
+        .context("Failed to get latest scrape_run id")
+}
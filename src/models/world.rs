@@ -1,7 +1,7 @@
 use anyhow::{Context, Result};
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
-use sqlx::FromRow;
+use sqlx::{Acquire, FromRow};
 use tracing::instrument;
 
 use super::BATCH_SIZE;
@@ -11,6 +11,7 @@ pub struct World {
     pub id: i32,
     pub form_id: i32,
     pub master: String,
+    pub game_id: i32,
     pub updated_at: NaiveDateTime,
     pub created_at: NaiveDateTime,
 }
@@ -19,55 +20,80 @@ pub struct World {
 pub struct UnsavedWorld<'a> {
     pub form_id: i32,
     pub master: &'a str,
+    pub game_id: i32,
 }
 
-#[instrument(level = "debug", skip(pool))]
+#[instrument(level = "debug", skip(executor))]
 pub async fn insert(
-    pool: &sqlx::Pool<sqlx::Postgres>,
+    executor: impl sqlx::PgExecutor<'_>,
     form_id: i32,
     master: &str,
+    game_id: i32,
 ) -> Result<World> {
     sqlx::query_as!(
         World,
         "INSERT INTO worlds
-            (form_id, master, created_at, updated_at)
-            VALUES ($1, $2, now(), now())
-            ON CONFLICT (form_id, master) DO UPDATE
+            (form_id, master, game_id, created_at, updated_at)
+            VALUES ($1, $2, $3, now(), now())
+            ON CONFLICT (form_id, master, game_id) DO UPDATE
             SET updated_at = now()
             RETURNING *",
         form_id,
-        master
+        master,
+        game_id
     )
-    .fetch_one(pool)
+    .fetch_one(executor)
     .await
     .context("Failed to insert world")
 }
 
-#[instrument(level = "debug", skip(pool))]
+#[instrument(level = "debug", skip(executor))]
+pub async fn get_id(
+    executor: impl sqlx::PgExecutor<'_>,
+    form_id: i32,
+    master: &str,
+    game_id: i32,
+) -> Result<i32> {
+    sqlx::query_scalar!(
+        "SELECT id FROM worlds WHERE form_id = $1 AND master = $2 AND game_id = $3",
+        form_id,
+        master,
+        game_id
+    )
+    .fetch_one(executor)
+    .await
+    .context("Failed to fetch world id")
+}
+
+#[instrument(level = "debug", skip(conn))]
 pub async fn batched_insert<'a>(
-    pool: &sqlx::Pool<sqlx::Postgres>,
+    conn: impl sqlx::Acquire<'_, Database = sqlx::Postgres>,
     worlds: &[UnsavedWorld<'a>],
 ) -> Result<Vec<World>> {
+    let mut conn = conn.acquire().await?;
     let mut saved_worlds = vec![];
     for batch in worlds.chunks(BATCH_SIZE) {
         let mut form_ids: Vec<i32> = vec![];
         let mut masters: Vec<&str> = vec![];
+        let mut game_ids: Vec<i32> = vec![];
         batch.iter().for_each(|unsaved_world| {
             form_ids.push(unsaved_world.form_id);
             masters.push(unsaved_world.master);
+            game_ids.push(unsaved_world.game_id);
         });
         saved_worlds.append(
             // cannot use macro with types that have lifetimes: https://github.com/launchbadge/sqlx/issues/280
             &mut sqlx::query_as(
-                r#"INSERT INTO worlds (form_id, master, created_at, updated_at)
-                SELECT *, now(), now() FROM UNNEST($1::int[], $2::text[])
-                ON CONFLICT (form_id, master) DO UPDATE
+                r#"INSERT INTO worlds (form_id, master, game_id, created_at, updated_at)
+                SELECT *, now(), now() FROM UNNEST($1::int[], $2::text[], $3::int[])
+                ON CONFLICT (form_id, master, game_id) DO UPDATE
                 SET updated_at = now()
                 RETURNING *"#,
             )
             .bind(&form_ids)
             .bind(&masters)
-            .fetch_all(pool)
+            .bind(&game_ids)
+            .fetch_all(&mut *conn)
             .await
             .context("Failed to insert worlds")?,
         );
@@ -2,27 +2,31 @@ use anyhow::{anyhow, Result};
 use futures::TryStreamExt;
 use reqwest::Client;
 use serde_json::Value;
+use std::path::Path;
 use std::{env, time::Duration};
-use tempfile::tempfile;
+use tempfile::tempfile_in;
 use tokio::fs::File;
 use tokio_util::compat::FuturesAsyncReadCompatExt;
 use tracing::{info, instrument};
 
-use super::{rate_limit_wait_duration, warn_and_sleep};
+use super::{rate_limit_wait_duration, warn_and_sleep, RateLimiter};
+use crate::temp_dir;
 
 pub struct DownloadLinkResponse {
     pub wait: Duration,
     json: Value,
 }
 
-#[instrument(skip(client))]
+#[instrument(skip(client, rate_limiter))]
 pub async fn get(
     client: &Client,
+    rate_limiter: &RateLimiter,
     game_name: &str,
     mod_id: i32,
     file_id: i64,
 ) -> Result<DownloadLinkResponse> {
     for attempt in 1..=3 {
+        rate_limiter.wait().await;
         let res = match client
             .get(format!(
                 "https://api.nexusmods.com/v1/games/{}/mods/{}/files/{}/download_link.json",
@@ -47,6 +51,7 @@ pub async fn get(
         };
 
         info!(status = %res.status(), "fetched file download link from API");
+        rate_limiter.record(&res);
         let wait = rate_limit_wait_duration(&res)?;
         let json = res.json::<Value>().await?;
 
@@ -74,8 +79,38 @@ impl DownloadLinkResponse {
 
     #[instrument(skip(self, client))]
     pub async fn download_file(&self, client: &Client) -> Result<File> {
+        self.download(client, || Ok(tempfile_in(temp_dir::get())?))
+            .await
+    }
+
+    /// Downloads the file to `path` instead of a temp file that is removed once closed. It is
+    /// written to a `.part` file next to `path` first, so an interrupted download is never
+    /// mistaken for a whole one.
+    #[instrument(skip(self, client))]
+    pub async fn download_file_to(&self, client: &Client, path: &Path) -> Result<File> {
+        let mut partial_path = path.to_path_buf().into_os_string();
+        partial_path.push(".part");
+        let tokio_file = self
+            .download(client, || {
+                Ok(std::fs::OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .create(true)
+                    .truncate(true)
+                    .open(&partial_path)?)
+            })
+            .await?;
+        tokio::fs::rename(&partial_path, path).await?;
+        Ok(tokio_file)
+    }
+
+    async fn download(
+        &self,
+        client: &Client,
+        create_file: impl Fn() -> Result<std::fs::File>,
+    ) -> Result<File> {
         for attempt in 1..=3 {
-            let mut tokio_file = File::from_std(tempfile()?);
+            let mut tokio_file = File::from_std(create_file()?);
             let res = match client
                 .get(self.link()?)
                 .header("apikey", env::var("NEXUS_API_KEY")?)
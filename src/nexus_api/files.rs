@@ -5,18 +5,118 @@ use serde_json::Value;
 use std::{env, time::Duration};
 use tracing::{info, instrument};
 
-use super::{rate_limit_wait_duration, warn_and_sleep};
+use super::{rate_limit_wait_duration, warn_and_sleep, RateLimiter};
 
 pub struct FilesResponse {
     pub wait: Duration,
     json: Value,
 }
 
+/// The category of a file on a mod's files tab, normalized so that filters don't depend on how the
+/// API spells (or translates) the category name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileCategory {
+    Main,
+    Update,
+    Optional,
+    OldVersion,
+    Miscellaneous,
+    Deleted,
+    Archived,
+}
+
+/// Category names seen in API responses and old scrapes that aren't just a spelling of the
+/// variant's name, after normalizing with `FileCategory::from_name`
+const CATEGORY_NAME_ALIASES: &[(&str, FileCategory)] = &[
+    ("UPDATES", FileCategory::Update),
+    ("PATCH", FileCategory::Update),
+    ("PATCHES", FileCategory::Update),
+    ("OLD", FileCategory::OldVersion),
+    ("OLD_VERSIONS", FileCategory::OldVersion),
+    ("MISC", FileCategory::Miscellaneous),
+    ("REMOVED", FileCategory::Deleted),
+    ("ARCHIVE", FileCategory::Archived),
+    // German
+    ("HAUPTDATEIEN", FileCategory::Main),
+    ("OPTIONALE_DATEIEN", FileCategory::Optional),
+    ("ALTE_VERSIONEN", FileCategory::OldVersion),
+    ("VERSCHIEDENES", FileCategory::Miscellaneous),
+    ("ARCHIVIERT", FileCategory::Archived),
+    // French
+    ("FICHIERS_PRINCIPAUX", FileCategory::Main),
+    ("FICHIERS_OPTIONNELS", FileCategory::Optional),
+    ("ANCIENNES_VERSIONS", FileCategory::OldVersion),
+    ("DIVERS", FileCategory::Miscellaneous),
+    ("ARCHIVES", FileCategory::Archived),
+    // Spanish
+    ("ARCHIVOS_PRINCIPALES", FileCategory::Main),
+    ("ARCHIVOS_OPCIONALES", FileCategory::Optional),
+    ("VERSIONES_ANTIGUAS", FileCategory::OldVersion),
+];
+
+impl FileCategory {
+    /// Value saved in the `normalized_category` column of the files table
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FileCategory::Main => "main",
+            FileCategory::Update => "update",
+            FileCategory::Optional => "optional",
+            FileCategory::OldVersion => "old_version",
+            FileCategory::Miscellaneous => "miscellaneous",
+            FileCategory::Deleted => "deleted",
+            FileCategory::Archived => "archived",
+        }
+    }
+
+    /// From the `category_id` of a file in the API, which doesn't depend on the language
+    pub fn from_id(category_id: i64) -> Option<FileCategory> {
+        match category_id {
+            1 => Some(FileCategory::Main),
+            2 => Some(FileCategory::Update),
+            3 => Some(FileCategory::Optional),
+            4 => Some(FileCategory::OldVersion),
+            5 => Some(FileCategory::Miscellaneous),
+            6 => Some(FileCategory::Deleted),
+            7 => Some(FileCategory::Archived),
+            _ => None,
+        }
+    }
+
+    /// From a category name like "MAIN", "Old versions", "Optional files", or "Hauptdateien".
+    /// Returns `None` for names that aren't recognized.
+    pub fn from_name(name: &str) -> Option<FileCategory> {
+        let name = name
+            .to_uppercase()
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<&str>>()
+            .join("_");
+        let name = name
+            .strip_suffix("_FILES")
+            .or_else(|| name.strip_suffix("_FILE"))
+            .unwrap_or(&name);
+        match name {
+            "MAIN" => Some(FileCategory::Main),
+            "UPDATE" => Some(FileCategory::Update),
+            "OPTIONAL" => Some(FileCategory::Optional),
+            "OLD_VERSION" => Some(FileCategory::OldVersion),
+            "MISCELLANEOUS" => Some(FileCategory::Miscellaneous),
+            "DELETED" => Some(FileCategory::Deleted),
+            "ARCHIVED" => Some(FileCategory::Archived),
+            _ => CATEGORY_NAME_ALIASES
+                .iter()
+                .find(|(alias, _)| *alias == name)
+                .map(|(_, category)| *category),
+        }
+    }
+}
+
 pub struct ApiFile<'a> {
     pub file_id: i64,
     pub name: &'a str,
     pub file_name: &'a str,
     pub category: Option<&'a str>,
+    pub normalized_category: Option<FileCategory>,
     pub version: Option<&'a str>,
     pub mod_version: Option<&'a str>,
     pub size: i64,
@@ -24,9 +124,15 @@ pub struct ApiFile<'a> {
     pub uploaded_at: NaiveDateTime,
 }
 
-#[instrument(skip(client))]
-pub async fn get(client: &Client, game_name: &str, nexus_mod_id: i32) -> Result<FilesResponse> {
+#[instrument(skip(client, rate_limiter))]
+pub async fn get(
+    client: &Client,
+    rate_limiter: &RateLimiter,
+    game_name: &str,
+    nexus_mod_id: i32,
+) -> Result<FilesResponse> {
     for attempt in 1..=3 {
+        rate_limiter.wait().await;
         let res = match client
             .get(format!(
                 "https://api.nexusmods.com/v1/games/{}/mods/{}/files.json",
@@ -51,6 +157,7 @@ pub async fn get(client: &Client, game_name: &str, nexus_mod_id: i32) -> Result<
         };
 
         info!(status = %res.status(), "fetched files for mod from API");
+        rate_limiter.record(&res);
         let wait = rate_limit_wait_duration(&res)?;
         let json = res.json::<Value>().await?;
 
@@ -92,6 +199,11 @@ impl FilesResponse {
                     .get("category_name")
                     .ok_or_else(|| anyhow!("Missing category key in file in API response"))?
                     .as_str();
+                let normalized_category = file
+                    .get("category_id")
+                    .and_then(|category_id| category_id.as_i64())
+                    .and_then(FileCategory::from_id)
+                    .or_else(|| category.and_then(FileCategory::from_name));
                 let version = file
                     .get("version")
                     .ok_or_else(|| anyhow!("Missing version key in file in API response"))?
@@ -136,6 +248,7 @@ impl FilesResponse {
                     name,
                     file_name,
                     category,
+                    normalized_category,
                     version,
                     mod_version,
                     size,
@@ -0,0 +1,103 @@
+use anyhow::{anyhow, Result};
+use reqwest::Client;
+use serde_json::Value;
+use std::{env, time::Duration};
+use tracing::{info, instrument};
+
+use super::{rate_limit_wait_duration, warn_and_sleep, RateLimiter};
+
+pub struct GameResponse {
+    pub wait: Duration,
+    json: Value,
+}
+
+/// A mod category of a game. The API always names categories in English, unlike the mod list,
+/// which names them in the language of the scraping session.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ApiCategory<'a> {
+    pub category_id: i32,
+    pub name: &'a str,
+    pub parent_category_id: Option<i32>,
+}
+
+#[instrument(skip(client, rate_limiter))]
+pub async fn get(
+    client: &Client,
+    rate_limiter: &RateLimiter,
+    game_name: &str,
+) -> Result<GameResponse> {
+    for attempt in 1..=3 {
+        rate_limiter.wait().await;
+        let res = match client
+            .get(format!(
+                "https://api.nexusmods.com/v1/games/{}.json",
+                game_name
+            ))
+            .header("accept", "application/json")
+            .header("apikey", env::var("NEXUS_API_KEY")?)
+            .send()
+            .await
+        {
+            Ok(res) => match res.error_for_status() {
+                Ok(res) => res,
+                Err(err) => {
+                    warn_and_sleep("game::get", anyhow!(err), attempt).await;
+                    continue;
+                }
+            },
+            Err(err) => {
+                warn_and_sleep("game::get", anyhow!(err), attempt).await;
+                continue;
+            }
+        };
+
+        info!(status = %res.status(), "fetched game data from API");
+        rate_limiter.record(&res);
+        let wait = rate_limit_wait_duration(&res)?;
+        let json = res.json::<Value>().await?;
+
+        return Ok(GameResponse { wait, json });
+    }
+    Err(anyhow!("Failed to get game data in three attempts"))
+}
+
+/// Parses the `categories` of a game API response. Top level categories have a `parent_category`
+/// of `false`.
+pub fn parse_categories(json: &Value) -> Result<Vec<ApiCategory>> {
+    json.get("categories")
+        .ok_or_else(|| anyhow!("Missing categories key in API response"))?
+        .as_array()
+        .ok_or_else(|| anyhow!("categories value in API response is not an array"))?
+        .iter()
+        .map(|category| {
+            let category_id = category
+                .get("category_id")
+                .ok_or_else(|| anyhow!("Missing category_id key in category in API response"))?
+                .as_i64()
+                .ok_or_else(|| {
+                    anyhow!("category_id value in API response category is not a number")
+                })? as i32;
+            let name = category
+                .get("name")
+                .ok_or_else(|| anyhow!("Missing name key in category in API response"))?
+                .as_str()
+                .ok_or_else(|| anyhow!("name value in API response category is not a string"))?;
+            let parent_category_id = category
+                .get("parent_category")
+                .and_then(|parent| parent.as_i64())
+                .map(|parent| parent as i32);
+            Ok(ApiCategory {
+                category_id,
+                name,
+                parent_category_id,
+            })
+        })
+        .collect()
+}
+
+impl GameResponse {
+    #[instrument(skip(self))]
+    pub fn categories(&self) -> Result<Vec<ApiCategory>> {
+        parse_categories(&self.json)
+    }
+}
@@ -5,16 +5,22 @@ use serde_json::Value;
 use std::{env, time::Duration};
 use tracing::{info, instrument};
 
-use super::{rate_limit_wait_duration, warn_and_sleep};
+use super::{rate_limit_wait_duration, warn_and_sleep, RateLimiter};
 
 pub struct ModResponse {
     pub wait: Duration,
     json: Value,
 }
 
-#[instrument(skip(client))]
-pub async fn get(client: &Client, game_name: &str, mod_id: i32) -> Result<ModResponse> {
+#[instrument(skip(client, rate_limiter))]
+pub async fn get(
+    client: &Client,
+    rate_limiter: &RateLimiter,
+    game_name: &str,
+    mod_id: i32,
+) -> Result<ModResponse> {
     for attempt in 1..=3 {
+        rate_limiter.wait().await;
         let res = match client
             .get(format!(
                 "https://api.nexusmods.com/v1/games/{}/mods/{}.json",
@@ -39,6 +45,7 @@ pub async fn get(client: &Client, game_name: &str, mod_id: i32) -> Result<ModRes
         };
 
         info!(status = %res.status(), "fetched mod data from API");
+        rate_limiter.record(&res);
         let wait = rate_limit_wait_duration(&res)?;
         let json = res.json::<Value>().await?;
 
@@ -0,0 +1,165 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::env;
+use tracing::{info, instrument};
+
+use super::schema_drift;
+use super::{warn_and_sleep, RateLimiter};
+
+/// Maximum number of mods the GraphQL API returns for one `legacyModsByDomain` query
+pub const GRAPHQL_MODS_PAGE_SIZE: usize = 50;
+
+const MODS_QUERY: &str = "query Mods($ids: [CompositeDomainWithIdInput!]!, $count: Int!) {
+  legacyModsByDomain(ids: $ids, count: $count) {
+    nodes {
+      modId
+      adultContent
+      downloads
+      createdAt
+      updatedAt
+      languageName
+      game {
+        domainName
+      }
+    }
+  }
+}";
+
+/// Top level fields requested for every mod node in `MODS_QUERY`
+const MOD_FIELDS: &[&str] = &[
+    "modId",
+    "adultContent",
+    "downloads",
+    "createdAt",
+    "updatedAt",
+    "languageName",
+    "game",
+];
+
+/// Fields of a mod that only the GraphQL API provides
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphQLMod {
+    pub mod_id: i32,
+    pub adult_content: bool,
+    pub downloads: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Name of the language the mod is in, e.g. "English"
+    #[serde(default)]
+    pub language_name: Option<String>,
+    pub game: GraphQLGame,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphQLGame {
+    pub domain_name: String,
+}
+
+/// Mods in a GraphQL response. Nodes that don't match the schema `GraphQLMod` expects are skipped
+/// (see `schema_drift`) and only their ids are kept, so that they aren't mistaken for mods that
+/// are no longer on nexus.
+#[derive(Debug)]
+pub struct GraphQLMods {
+    pub mods: Vec<GraphQLMod>,
+    /// (game name, nexus mod id) of the skipped nodes that had them
+    pub invalid: Vec<(String, i32)>,
+}
+
+/// Parses the mod nodes of a response, skipping invalid ones
+pub fn parse_mods(query_name: &str, nodes: &Value) -> Result<GraphQLMods> {
+    let nodes = schema_drift::parse_nodes(query_name, nodes, MOD_FIELDS)?;
+    Ok(GraphQLMods {
+        mods: nodes.parsed,
+        invalid: nodes
+            .invalid
+            .iter()
+            .filter_map(|node| {
+                let game_name = node.pointer("/game/domainName")?.as_str()?;
+                let mod_id = node.get("modId")?.as_i64()?;
+                Some((game_name.to_string(), mod_id as i32))
+            })
+            .collect(),
+    })
+}
+
+/// Posts a GraphQL query, retrying failed requests, and returns the `data` of the response
+async fn post_query(
+    client: &Client,
+    rate_limiter: &RateLimiter,
+    name: &str,
+    body: &Value,
+) -> Result<Value> {
+    for attempt in 1..=3 {
+        rate_limiter.wait().await;
+        let res = match client
+            .post("https://api.nexusmods.com/v2/graphql")
+            .header("accept", "application/json")
+            .header("apikey", env::var("NEXUS_API_KEY")?)
+            .json(body)
+            .send()
+            .await
+        {
+            Ok(res) => match res.error_for_status() {
+                Ok(res) => res,
+                Err(err) => {
+                    warn_and_sleep(name, anyhow!(err), attempt).await;
+                    continue;
+                }
+            },
+            Err(err) => {
+                warn_and_sleep(name, anyhow!(err), attempt).await;
+                continue;
+            }
+        };
+
+        info!(status = %res.status(), "fetched mods from GraphQL API");
+        rate_limiter.record(&res);
+        let mut json = res.json::<Value>().await?;
+        if let Some(errors) = json.get("errors") {
+            return Err(anyhow!("GraphQL API returned errors: {}", errors));
+        }
+        return json
+            .get_mut("data")
+            .map(Value::take)
+            .ok_or_else(|| anyhow!("Missing data in GraphQL API response"));
+    }
+    Err(anyhow!(
+        "Failed to get mods from GraphQL API in three attempts"
+    ))
+}
+
+/// Fetches the mods with the given (game name, nexus mod id) pairs. Mods that no longer exist on
+/// nexus are missing from the result (and aren't in its `invalid` either).
+#[instrument(skip(client, rate_limiter, ids), fields(num_ids = ids.len()))]
+pub async fn get_mods(
+    client: &Client,
+    rate_limiter: &RateLimiter,
+    ids: &[(&str, i32)],
+) -> Result<GraphQLMods> {
+    if ids.len() > GRAPHQL_MODS_PAGE_SIZE {
+        return Err(anyhow!(
+            "cannot request more than {} mods at once",
+            GRAPHQL_MODS_PAGE_SIZE
+        ));
+    }
+    let body = json!({
+        "query": MODS_QUERY,
+        "variables": {
+            "ids": ids
+                .iter()
+                .map(|(game_name, mod_id)| json!({ "domainName": game_name, "modId": mod_id }))
+                .collect::<Vec<Value>>(),
+            "count": ids.len(),
+        },
+    });
+    let data = post_query(client, rate_limiter, "graphql::get_mods", &body).await?;
+    let nodes = data
+        .pointer("/legacyModsByDomain/nodes")
+        .ok_or_else(|| anyhow!("Missing nodes in GraphQL API response"))?;
+    parse_mods("graphql::get_mods", nodes)
+}
@@ -1,51 +1,102 @@
 use anyhow::{anyhow, Result};
 use reqwest::Client;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::env;
 use tracing::{info, instrument};
 
 use super::files::ApiFile;
-use super::warn_and_sleep;
+use super::{warn_and_sleep, RateLimiter};
 
-fn has_plugin(json: &Value) -> Result<bool> {
+/// A file or directory in the content preview of an archive, keeping only the fields needed to
+/// browse the archive (the API also includes the full path of every entry).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContentPreviewEntry {
+    pub name: String,
+    pub is_directory: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<ContentPreviewEntry>,
+}
+
+fn normalize_children(json: &Value) -> Result<Vec<ContentPreviewEntry>> {
+    match json.get("children") {
+        None => Ok(vec![]),
+        Some(children) => children
+            .as_array()
+            .ok_or_else(|| anyhow!("children value in metadata is not an array"))?
+            .iter()
+            .map(normalize_entry)
+            .collect(),
+    }
+}
+
+fn normalize_entry(json: &Value) -> Result<ContentPreviewEntry> {
     let node_type = json
         .get("type")
         .ok_or_else(|| anyhow!("Missing type key in metadata API response"))?
         .as_str()
         .ok_or_else(|| anyhow!("type value in metadata is not a string"))?;
+    let name = json
+        .get("name")
+        .ok_or_else(|| anyhow!("Missing name key in metadata API response"))?
+        .as_str()
+        .ok_or_else(|| anyhow!("name value in metadata is not a string"))?;
+    let size = json
+        .get("size")
+        .and_then(|size| size.as_str())
+        .map(str::to_string);
 
-    if node_type == "file" {
-        let name = json
-            .get("name")
-            .ok_or_else(|| anyhow!("Missing name key in metadata API response"))?
-            .as_str()
-            .ok_or_else(|| anyhow!("name value in metadata is not a string"))?;
+    Ok(ContentPreviewEntry {
+        name: name.to_string(),
+        is_directory: node_type == "directory",
+        size,
+        children: normalize_children(json)?,
+    })
+}
 
-        if name.ends_with(".esp") || name.ends_with(".esm") || name.ends_with(".esl") {
-            return Ok(true);
+pub fn contains_plugin(entries: &[ContentPreviewEntry]) -> bool {
+    entries.iter().any(|entry| {
+        if entry.is_directory {
+            contains_plugin(&entry.children)
+        } else {
+            entry.name.ends_with(".esp")
+                || entry.name.ends_with(".esm")
+                || entry.name.ends_with(".esl")
         }
-    }
+    })
+}
 
-    match json.get("children") {
-        None => Ok(false),
-        Some(children) => {
-            let children = children
-                .as_array()
-                .ok_or_else(|| anyhow!("children value in metadata is not an array"))?;
-            for child in children {
-                if has_plugin(child)? {
-                    return Ok(true);
-                }
+/// Merges chains of directories that only contain another directory (e.g. `Data/Meshes/Foo`) into
+/// one entry so deeply nested archives are quicker to browse.
+pub fn collapse(entries: Vec<ContentPreviewEntry>) -> Vec<ContentPreviewEntry> {
+    entries
+        .into_iter()
+        .map(|mut entry| {
+            while entry.is_directory && entry.children.len() == 1 && entry.children[0].is_directory
+            {
+                let child = entry.children.remove(0);
+                entry.name = format!("{}/{}", entry.name, child.name);
+                entry.children = child.children;
             }
-            Ok(false)
-        }
-    }
+            entry.children = collapse(entry.children);
+            entry
+        })
+        .collect()
 }
 
-#[instrument(skip(client, api_file), fields(metadata_link = api_file.content_preview_link.unwrap_or("null")))]
-pub async fn contains_plugin(client: &Client, api_file: &ApiFile<'_>) -> Result<Option<bool>> {
+/// Fetches the content preview of the file's archive. Returns `None` if the file has no
+/// content preview link.
+#[instrument(skip(client, rate_limiter, api_file), fields(metadata_link = api_file.content_preview_link.unwrap_or("null")))]
+pub async fn get_content_preview(
+    client: &Client,
+    rate_limiter: &RateLimiter,
+    api_file: &ApiFile<'_>,
+) -> Result<Option<Vec<ContentPreviewEntry>>> {
     for attempt in 1..=3 {
         if let Some(metadata_link) = api_file.content_preview_link {
+            rate_limiter.wait().await;
             let res = match client
                 .get(metadata_link)
                 .header("accept", "application/json")
@@ -56,33 +107,21 @@ pub async fn contains_plugin(client: &Client, api_file: &ApiFile<'_>) -> Result<
                 Ok(res) => match res.error_for_status() {
                     Ok(res) => res,
                     Err(err) => {
-                        warn_and_sleep("metadata::contains_plugin", anyhow!(err), attempt).await;
+                        warn_and_sleep("metadata::get_content_preview", anyhow!(err), attempt)
+                            .await;
                         continue;
                     }
                 },
                 Err(err) => {
-                    warn_and_sleep("metadata::contains_plugin", anyhow!(err), attempt).await;
+                    warn_and_sleep("metadata::get_content_preview", anyhow!(err), attempt).await;
                     continue;
                 }
             };
 
             info!(status = %res.status(), "fetched file metadata from API");
+            rate_limiter.record(&res);
             let json = res.json::<Value>().await?;
-
-            match json.get("children") {
-                None => return Ok(Some(false)),
-                Some(children) => {
-                    let children = children
-                        .as_array()
-                        .ok_or_else(|| anyhow!("children value in metadata is not an array"))?;
-                    for child in children {
-                        if has_plugin(child)? {
-                            return Ok(Some(true));
-                        }
-                    }
-                    return Ok(Some(false));
-                }
-            }
+            return Ok(Some(normalize_children(&json)?));
         } else {
             return Ok(None);
         }
@@ -8,23 +8,76 @@ use tracing::{info, warn};
 
 pub mod download_link;
 pub mod files;
+pub mod game;
 pub mod game_mod;
+pub mod graphql;
 pub mod metadata;
+pub mod rate_limiter;
+pub mod schema_drift;
+
+pub use rate_limiter::RateLimiter;
 
 pub const SKYRIM_GAME_NAME: &str = "skyrim";
 pub const SKYRIM_GAME_ID: i32 = 110;
 pub const SSE_GAME_NAME: &str = "skyrimspecialedition";
 pub const SSE_GAME_ID: i32 = 1704;
+pub const ENDERAL_GAME_NAME: &str = "enderal";
+pub const ENDERAL_GAME_ID: i32 = 2736;
+pub const ENDERAL_SE_GAME_NAME: &str = "enderalspecialedition";
+pub const ENDERAL_SE_GAME_ID: i32 = 3174;
 pub static USER_AGENT: &str = "mod-mapper/0.1";
 
+/// Every game that can be scraped
+pub const GAME_NAMES: &[&str] = &[
+    SKYRIM_GAME_NAME,
+    SSE_GAME_NAME,
+    ENDERAL_GAME_NAME,
+    ENDERAL_SE_GAME_NAME,
+];
+
+/// Nexus game domains that are the same logical game as another domain, as (alias, canonical)
+/// pairs. Mods scraped from an alias domain are merged into the canonical game when dumping.
+pub const GAME_ALIASES: &[(&str, &str)] = &[(ENDERAL_SE_GAME_NAME, ENDERAL_GAME_NAME)];
+
+/// Games whose mods are often ported to another game, as (original, port) pairs
+pub const PORT_GAMES: &[(&str, &str)] = &[(SKYRIM_GAME_NAME, SSE_GAME_NAME)];
+
 pub fn get_game_id(name: &str) -> Option<i32> {
     match name {
         SKYRIM_GAME_NAME => Some(SKYRIM_GAME_ID),
         SSE_GAME_NAME => Some(SSE_GAME_ID),
+        ENDERAL_GAME_NAME => Some(ENDERAL_GAME_ID),
+        ENDERAL_SE_GAME_NAME => Some(ENDERAL_SE_GAME_ID),
         _ => None,
     }
 }
 
+pub fn get_canonical_game_name(name: &str) -> &str {
+    GAME_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == name)
+        .map(|(_, canonical)| *canonical)
+        .unwrap_or(name)
+}
+
+/// The mod's page on the Nexus website. `game_name` must be the game the mod was scraped from
+/// rather than its canonical game, since mods are only on their own game's domain.
+pub fn mod_url(game_name: &str, nexus_mod_id: i32) -> String {
+    format!(
+        "https://www.nexusmods.com/{}/mods/{}",
+        game_name, nexus_mod_id
+    )
+}
+
+/// The file's entry on the files tab of its mod's page (see `mod_url`)
+pub fn file_url(game_name: &str, nexus_mod_id: i32, nexus_file_id: i32) -> String {
+    format!(
+        "{}?tab=files&file_id={}",
+        mod_url(game_name, nexus_mod_id),
+        nexus_file_id
+    )
+}
+
 pub fn rate_limit_wait_duration(res: &Response) -> Result<std::time::Duration> {
     let daily_remaining: i32 = res
         .headers()
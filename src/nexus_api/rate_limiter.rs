@@ -0,0 +1,102 @@
+use chrono::{DateTime, Duration, Utc};
+use reqwest::Response;
+use std::sync::Mutex;
+use tokio::time::sleep;
+use tracing::info;
+
+/// Minimum time between requests that go through `RateLimiter::wait`
+const MIN_REQUEST_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+#[derive(Debug, Default)]
+struct RateLimitState {
+    daily_remaining: Option<i32>,
+    hourly_remaining: Option<i32>,
+    hourly_reset: Option<DateTime<Utc>>,
+    last_request_at: Option<DateTime<Utc>>,
+}
+
+/// Remaining nexus API quota as last reported by the `x-rl-*` response headers, shared by every
+/// request made during an update so that requests which don't report the quota themselves (e.g.
+/// file metadata) still wait out an exhausted quota.
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    state: Mutex<RateLimitState>,
+}
+
+impl RateLimiter {
+    /// Records the quota reported by an API response. Responses without the rate limit headers are
+    /// ignored.
+    pub fn record(&self, res: &Response) {
+        let header = |name: &str| {
+            res.headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.trim().to_string())
+        };
+        let mut state = self
+            .state
+            .lock()
+            .expect("rate limiter lock is not poisoned");
+        if let Some(daily_remaining) = header("x-rl-daily-remaining").and_then(|v| v.parse().ok()) {
+            state.daily_remaining = Some(daily_remaining);
+        }
+        if let Some(hourly_remaining) = header("x-rl-hourly-remaining").and_then(|v| v.parse().ok())
+        {
+            state.hourly_remaining = Some(hourly_remaining);
+        }
+        if let Some(hourly_reset) = header("x-rl-hourly-reset")
+            .and_then(|v| DateTime::parse_from_str(&v, "%Y-%m-%d %H:%M:%S %z").ok())
+        {
+            state.hourly_reset = Some(hourly_reset.into());
+        }
+    }
+
+    fn wait_duration(&self) -> std::time::Duration {
+        let mut state = self
+            .state
+            .lock()
+            .expect("rate limiter lock is not poisoned");
+        let now = Utc::now();
+        let mut wait = Duration::zero();
+        if let Some(last_request_at) = state.last_request_at {
+            let next_request_at = last_request_at
+                + Duration::from_std(MIN_REQUEST_INTERVAL).expect("interval fits in a Duration");
+            if next_request_at > now {
+                wait = next_request_at - now;
+            }
+        }
+        if let (Some(daily), Some(hourly), Some(hourly_reset)) = (
+            state.daily_remaining,
+            state.hourly_remaining,
+            state.hourly_reset,
+        ) {
+            let hourly_reset = hourly_reset + Duration::seconds(5);
+            if daily <= 1 && hourly <= 1 && hourly_reset - now > wait {
+                wait = hourly_reset - now;
+                // the quota is refilled after the reset
+                state.daily_remaining = None;
+                state.hourly_remaining = None;
+            }
+        }
+        state.last_request_at = Some(now + wait);
+        wait.to_std().unwrap_or_default()
+    }
+
+    /// The last known hourly quota and when it resets, if a response has reported them
+    pub fn hourly_quota(&self) -> Option<(i32, DateTime<Utc>)> {
+        let state = self
+            .state
+            .lock()
+            .expect("rate limiter lock is not poisoned");
+        state.hourly_remaining.zip(state.hourly_reset)
+    }
+
+    /// Sleeps until the next request is allowed by the last known quota
+    pub async fn wait(&self) {
+        let duration = self.wait_duration();
+        if !duration.is_zero() {
+            info!(duration = ?duration, "waiting for rate limit");
+            sleep(duration).await;
+        }
+    }
+}
@@ -0,0 +1,119 @@
+//! Checks GraphQL response nodes against the fields we request, since Nexus changes their schema
+//! without notice. Unknown and missing fields are logged and counted in metrics reported by serve
+//! mode, and nodes that can't be parsed are skipped instead of failing the whole response.
+use anyhow::{anyhow, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tracing::warn;
+
+static UNKNOWN_FIELDS: AtomicU64 = AtomicU64::new(0);
+static MISSING_FIELDS: AtomicU64 = AtomicU64::new(0);
+static INVALID_NODES: AtomicU64 = AtomicU64::new(0);
+
+/// Schema drift seen since the process started
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SchemaDriftMetrics {
+    /// Fields in a response node that weren't requested
+    pub unknown_fields: u64,
+    /// Requested fields that were missing or null in a response node
+    pub missing_fields: u64,
+    /// Response nodes that couldn't be parsed and were skipped
+    pub invalid_nodes: u64,
+}
+
+pub fn metrics() -> SchemaDriftMetrics {
+    SchemaDriftMetrics {
+        unknown_fields: UNKNOWN_FIELDS.load(Ordering::Relaxed),
+        missing_fields: MISSING_FIELDS.load(Ordering::Relaxed),
+        invalid_nodes: INVALID_NODES.load(Ordering::Relaxed),
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct FieldDrift {
+    pub unknown: Vec<String>,
+    pub missing: Vec<String>,
+}
+
+/// Compares the top level fields of a response node with the requested `fields`. A null field
+/// counts as missing, since every field we request is non-null in the schema we were written for.
+pub fn field_drift(node: &Value, fields: &[&str]) -> FieldDrift {
+    let object = match node.as_object() {
+        Some(object) => object,
+        None => {
+            return FieldDrift {
+                unknown: vec![],
+                missing: fields.iter().map(|field| field.to_string()).collect(),
+            }
+        }
+    };
+    FieldDrift {
+        unknown: object
+            .keys()
+            .filter(|key| !fields.contains(&key.as_str()))
+            .cloned()
+            .collect(),
+        missing: fields
+            .iter()
+            .filter(|field| object.get(**field).map_or(true, Value::is_null))
+            .map(|field| field.to_string())
+            .collect(),
+    }
+}
+
+/// A response's nodes, parsed as far as possible
+#[derive(Debug)]
+pub struct ParsedNodes<T> {
+    pub parsed: Vec<T>,
+    /// Nodes that couldn't be parsed, as returned
+    pub invalid: Vec<Value>,
+}
+
+/// Parses each node of a GraphQL response on its own, logging any drift from the requested
+/// `fields` once per response. Only fails if `nodes` isn't a list.
+pub fn parse_nodes<T: DeserializeOwned>(
+    query_name: &str,
+    nodes: &Value,
+    fields: &[&str],
+) -> Result<ParsedNodes<T>> {
+    let nodes = nodes
+        .as_array()
+        .ok_or_else(|| anyhow!("{} response nodes are not a list", query_name))?;
+    let mut unknown = BTreeSet::new();
+    let mut missing = BTreeSet::new();
+    let mut errors = BTreeSet::new();
+    let mut parsed_nodes = ParsedNodes {
+        parsed: vec![],
+        invalid: vec![],
+    };
+    for node in nodes {
+        let drift = field_drift(node, fields);
+        UNKNOWN_FIELDS.fetch_add(drift.unknown.len() as u64, Ordering::Relaxed);
+        MISSING_FIELDS.fetch_add(drift.missing.len() as u64, Ordering::Relaxed);
+        unknown.extend(drift.unknown);
+        missing.extend(drift.missing);
+        match serde_json::from_value(node.clone()) {
+            Ok(parsed) => parsed_nodes.parsed.push(parsed),
+            Err(err) => {
+                INVALID_NODES.fetch_add(1, Ordering::Relaxed);
+                errors.insert(err.to_string());
+                parsed_nodes.invalid.push(node.clone());
+            }
+        }
+    }
+    if !unknown.is_empty() || !missing.is_empty() || !errors.is_empty() {
+        warn!(
+            query_name,
+            ?unknown,
+            ?missing,
+            ?errors,
+            invalid = parsed_nodes.invalid.len(),
+            parsed = parsed_nodes.parsed.len(),
+            "GraphQL API response does not match the expected schema"
+        );
+    }
+    Ok(parsed_nodes)
+}
@@ -1,5 +1,5 @@
 use anyhow::Result;
-use chrono::NaiveDate;
+use chrono::{NaiveDate, NaiveDateTime};
 use reqwest::Client;
 use scraper::{Html, Selector};
 use tracing::{info, instrument};
@@ -22,6 +22,70 @@ pub struct ScrapedMod<'a> {
     pub first_upload_at: NaiveDate,
 }
 
+/// What the mod list is sorted by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModsSortField {
+    UpdatedAt,
+    CreatedAt,
+    Downloads,
+    Endorsements,
+    Name,
+}
+
+impl ModsSortField {
+    /// Value of the mod list's `sort_by` parameter
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ModsSortField::UpdatedAt => "lastupdate",
+            ModsSortField::CreatedAt => "date",
+            ModsSortField::Downloads => "downloads",
+            ModsSortField::Endorsements => "endorsements",
+            ModsSortField::Name => "name",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortDirection {
+    Asc,
+    Desc,
+}
+
+impl SortDirection {
+    /// Value of the mod list's `order` parameter
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SortDirection::Asc => "ASC",
+            SortDirection::Desc => "DESC",
+        }
+    }
+}
+
+/// Url of a page of the mod list of the game, with (or with only) translations depending on
+/// `include_translations`
+pub fn mod_list_url(
+    page: usize,
+    game_id: i32,
+    include_translations: bool,
+    sort: ModsSortField,
+    direction: SortDirection,
+) -> String {
+    format!(
+        "https://www.nexusmods.com/Core/Libs/Common/Widgets/ModList?RH_ModList=nav:true,home:false,type:0,user_id:0,game_id:{},advfilt:true,tags_{}%5B%5D:1428,include_adult:true,page_size:20,show_game_filter:false,open:false,page:{},sort_by:{},order:{}",
+        game_id,
+        match include_translations { true => "yes", false => "no" },
+        page,
+        sort.as_str(),
+        direction.as_str()
+    )
+}
+
+/// The mod list only shows dates (in UTC) without a time of day, so they are stored as the start of
+/// the day in UTC.
+pub fn utc_day_start(date: NaiveDate) -> NaiveDateTime {
+    date.and_hms_opt(0, 0, 0).expect("midnight is a valid time")
+}
+
 pub struct ModListScrape<'a> {
     pub mods: Vec<ScrapedMod<'a>>,
     pub has_next_page: bool,
@@ -34,16 +98,22 @@ pub async fn get_mod_list_page(
     game_name: &str,
     game_id: i32,
     include_translations: bool,
+    sort: ModsSortField,
+    direction: SortDirection,
 ) -> Result<ModListResponse> {
     let res = client
-        .get(format!(
-            "https://www.nexusmods.com/Core/Libs/Common/Widgets/ModList?RH_ModList=nav:true,home:false,type:0,user_id:0,game_id:{},advfilt:true,tags_{}%5B%5D:1428,include_adult:true,page_size:20,show_game_filter:false,open:false,page:{},sort_by:lastupdate",
+        .get(mod_list_url(
+            page,
             game_id,
-            match include_translations { true => "yes", false => "no" },
-            page
+            include_translations,
+            sort,
+            direction,
         ))
         .header("host", "www.nexusmods.com")
-        .header("referrer", format!("https://www.nexusmods.com/{}/mods/", game_name))
+        .header(
+            "referrer",
+            format!("https://www.nexusmods.com/{}/mods/", game_name),
+        )
         .header("sec-fetch-dest", "empty")
         .header("sec-fetch-mode", "cors")
         .header("sec-fetch-site", "same-origin")
@@ -1,12 +1,14 @@
-use anyhow::Result;
-use skyrim_cell_dump::parse_plugin;
+use anyhow::{anyhow, Result};
+use skyrim_cell_dump::{parse_plugin, Plugin};
+use sqlx::Acquire;
 use std::borrow::Borrow;
 use std::convert::TryInto;
-use std::path::{Path, PathBuf};
+use std::path::Path;
 use tokio::fs::create_dir_all;
 use tokio::io::AsyncWriteExt;
 use tracing::{info, warn};
 
+use crate::hooks;
 use crate::models::file::File;
 use crate::models::game_mod::Mod;
 use crate::models::{cell, cell::UnsavedCell};
@@ -14,6 +16,7 @@ use crate::models::{plugin, plugin::UnsavedPlugin};
 use crate::models::{plugin_cell, plugin_cell::UnsavedPluginCell};
 use crate::models::{plugin_world, plugin_world::UnsavedPluginWorld};
 use crate::models::{world, world::UnsavedWorld};
+use crate::plugin_storage;
 
 pub fn get_local_form_id_and_master<'a>(
     form_id: u32,
@@ -28,9 +31,391 @@ pub fn get_local_form_id_and_master<'a>(
     Ok((local_form_id, masters[master_index]))
 }
 
+/// Masters of the base game and its DLCs (lowercased), which every mod may depend on
+const BASE_GAME_MASTERS: [&str; 6] = [
+    "skyrim.esm",
+    "update.esm",
+    "dawnguard.esm",
+    "hearthfires.esm",
+    "dragonborn.esm",
+    "_resourcepack.esl",
+];
+
+/// Creation Club plugins are named like `ccBGSSSE001-Fish.esm`
+fn is_creation_club_plugin(file_name: &str) -> bool {
+    file_name.starts_with("cc") && file_name.get(5..8) == Some("sse")
+}
+
+/// Guesses whether a plugin is a compatibility patch between other mods, which would otherwise
+/// count the cells of the mods it patches a second time. A plugin is a patch if it depends on two
+/// or more community (non base game or Creation Club) plugins, or if it is named like a patch and
+/// depends on at least one (so bug fix mods like the unofficial patch are not patches).
+pub fn is_patch(file_name: &str, masters: &[String]) -> bool {
+    let community_masters = masters
+        .iter()
+        .map(|master| master.to_lowercase())
+        .filter(|master| {
+            !BASE_GAME_MASTERS.contains(&master.as_str()) && !is_creation_club_plugin(master)
+        })
+        .count();
+    community_masters >= 2
+        || (community_masters >= 1 && file_name.to_lowercase().contains("patch"))
+}
+
+/// Size of the header of both records and groups in Skyrim plugins
+const HEADER_SIZE: usize = 24;
+
+/// Number of records a plugin adds (rather than overrides from its masters) of the record types
+/// users most often ask about.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RecordCounts {
+    pub npcs: i32,
+    pub quests: i32,
+    pub dialogues: i32,
+}
+
+fn read_u32(plugin_buf: &[u8], offset: usize) -> Result<u32> {
+    let bytes = plugin_buf
+        .get(offset..offset + 4)
+        .ok_or_else(|| anyhow!("plugin ends unexpectedly at offset {}", offset))?;
+    Ok(u32::from_le_bytes(bytes.try_into()?))
+}
+
+fn read_type(plugin_buf: &[u8], offset: usize) -> Result<&[u8]> {
+    plugin_buf
+        .get(offset..offset + 4)
+        .ok_or_else(|| anyhow!("plugin ends unexpectedly at offset {}", offset))
+}
+
+/// Counts the records of type `label` in the group spanning `start..end` that are new to the
+/// plugin (their form id's master index is past the plugin's masters). Nested groups (e.g. the
+/// topic children of DIAL records) are skipped.
+fn count_new_records_in_group(
+    plugin_buf: &[u8],
+    label: &[u8],
+    start: usize,
+    end: usize,
+    num_masters: usize,
+) -> Result<i32> {
+    let mut count = 0;
+    let mut offset = start;
+    while offset < end {
+        let record_type = read_type(plugin_buf, offset)?;
+        let size = read_u32(plugin_buf, offset + 4)? as usize;
+        if record_type == b"GRUP" {
+            if size < HEADER_SIZE {
+                return Err(anyhow!("invalid group size {} at offset {}", size, offset));
+            }
+            offset += size;
+        } else {
+            if record_type == label {
+                let form_id = read_u32(plugin_buf, offset + 12)?;
+                if (form_id >> 24) as usize >= num_masters {
+                    count += 1;
+                }
+            }
+            offset += HEADER_SIZE + size;
+        }
+    }
+    Ok(count)
+}
+
+/// Walks the top-level groups of the plugin to count new NPC_, QUST, and DIAL records, which
+/// skyrim-cell-dump does not parse.
+pub fn count_new_records(plugin_buf: &[u8], num_masters: usize) -> Result<RecordCounts> {
+    let mut counts = RecordCounts::default();
+    // skip the TES4 header record
+    let mut offset = HEADER_SIZE + read_u32(plugin_buf, 4)? as usize;
+    while offset < plugin_buf.len() {
+        if read_type(plugin_buf, offset)? != b"GRUP" {
+            return Err(anyhow!("expected a top-level group at offset {}", offset));
+        }
+        let size = read_u32(plugin_buf, offset + 4)? as usize;
+        if size < HEADER_SIZE {
+            return Err(anyhow!("invalid group size {} at offset {}", size, offset));
+        }
+        let label = read_type(plugin_buf, offset + 8)?;
+        let count = match label {
+            b"NPC_" | b"QUST" | b"DIAL" => count_new_records_in_group(
+                plugin_buf,
+                label,
+                offset + HEADER_SIZE,
+                offset + size,
+                num_masters,
+            )?,
+            _ => 0,
+        };
+        match label {
+            b"NPC_" => counts.npcs += count,
+            b"QUST" => counts.quests += count,
+            b"DIAL" => counts.dialogues += count,
+            _ => {}
+        }
+        offset += size;
+    }
+    Ok(counts)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedWorld {
+    pub form_id: i32,
+    pub master: String,
+    pub editor_id: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedCell {
+    pub form_id: i32,
+    pub master: String,
+    pub x: Option<i32>,
+    pub y: Option<i32>,
+    /// Local form id and master of the world the cell is in, always one of the plugin's worlds
+    pub world: Option<(i32, String)>,
+    pub is_persistent: bool,
+    pub editor_id: Option<String>,
+}
+
+/// Everything `process_plugin` saves about a plugin, with form ids already resolved to their
+/// local form id and master.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedPlugin {
+    pub hash: u64,
+    pub file_name: String,
+    pub version: f32,
+    pub size: usize,
+    pub author: Option<String>,
+    pub description: Option<String>,
+    pub masters: Vec<String>,
+    pub worlds: Vec<ParsedWorld>,
+    pub cells: Vec<ParsedCell>,
+    pub record_counts: RecordCounts,
+}
+
+fn resolve_plugin(
+    plugin: &Plugin,
+    file_name: String,
+    hash: u64,
+    size: usize,
+    record_counts: RecordCounts,
+) -> Result<ParsedPlugin> {
+    let masters: Vec<&str> = plugin.header.masters.iter().map(|s| s.borrow()).collect();
+
+    let worlds = plugin
+        .worlds
+        .iter()
+        .map(|world| {
+            let (form_id, master) =
+                get_local_form_id_and_master(world.form_id, &masters, &file_name)?;
+            Ok(ParsedWorld {
+                form_id,
+                master: master.to_string(),
+                editor_id: world.editor_id.to_string(),
+            })
+        })
+        .collect::<Result<Vec<ParsedWorld>>>()?;
+
+    let cells = plugin
+        .cells
+        .iter()
+        .map(|cell| {
+            let world = if let Some(world_form_id) = cell.world_form_id {
+                let (form_id, master) =
+                    get_local_form_id_and_master(world_form_id, &masters, &file_name)?;
+                if !worlds
+                    .iter()
+                    .any(|world| world.form_id == form_id && world.master == master)
+                {
+                    return Err(anyhow!(
+                        "cell {:#010x} references world {:#010x} that is not in the plugin",
+                        cell.form_id,
+                        world_form_id
+                    ));
+                }
+                Some((form_id, master.to_string()))
+            } else {
+                None
+            };
+            let (form_id, master) =
+                get_local_form_id_and_master(cell.form_id, &masters, &file_name)?;
+            Ok(ParsedCell {
+                form_id,
+                master: master.to_string(),
+                x: cell.x,
+                y: cell.y,
+                world,
+                is_persistent: cell.is_persistent,
+                editor_id: cell.editor_id.as_ref().map(|id| id.to_string()),
+            })
+        })
+        .collect::<Result<Vec<ParsedCell>>>()?;
+
+    Ok(ParsedPlugin {
+        hash,
+        file_name,
+        version: plugin.header.version,
+        size,
+        author: plugin
+            .header
+            .author
+            .as_ref()
+            .map(|author| author.to_string()),
+        description: plugin
+            .header
+            .description
+            .as_ref()
+            .map(|description| description.to_string()),
+        masters: masters.iter().map(|master| master.to_string()).collect(),
+        worlds,
+        cells,
+        record_counts,
+    })
+}
+
+/// Parses the plugin and resolves its form ids without touching the database or disk, so the
+/// output depends only on the input. Malformed plugins return an error rather than panicking.
+pub fn process_plugin_buf(plugin_buf: &[u8], file_path: &str) -> Result<ParsedPlugin> {
+    let plugin = parse_plugin(plugin_buf)?;
+    let file_name = Path::new(file_path)
+        .file_name()
+        .ok_or_else(|| anyhow!("plugin path {} does not end in a file name", file_path))?
+        .to_string_lossy()
+        .to_string();
+    // The counts are only informational, so a plugin whose records can't be walked is still saved
+    let record_counts = match count_new_records(plugin_buf, plugin.header.masters.len()) {
+        Ok(record_counts) => record_counts,
+        Err(err) => {
+            warn!(error = %err, file_path, "failed to count new records in plugin");
+            RecordCounts::default()
+        }
+    };
+    resolve_plugin(
+        &plugin,
+        file_name,
+        seahash::hash(plugin_buf),
+        plugin_buf.len(),
+        record_counts,
+    )
+}
+
+/// Resolves the form ids of a plugin dumped to JSON by skyrim-cell-dump (e.g. `data/skyrim.json`).
+/// The original plugin is not available, so the hash and size are of the JSON dump instead and the
+/// record counts are left at zero.
+pub fn process_plugin_json(json_buf: &[u8], file_name: &str) -> Result<ParsedPlugin> {
+    let plugin: Plugin = serde_json::from_slice(json_buf)?;
+    resolve_plugin(
+        &plugin,
+        file_name.to_string(),
+        seahash::hash(json_buf),
+        json_buf.len(),
+        RecordCounts::default(),
+    )
+}
+
+/// Saves the plugin and all of its worlds and cells in one transaction so that a failure part-way
+/// through never leaves a plugin with only some of its cells saved.
+pub async fn save_plugin(
+    conn: impl Acquire<'_, Database = sqlx::Postgres>,
+    plugin: &ParsedPlugin,
+    db_file: &File,
+    db_mod: &Mod,
+    file_path: &str,
+    storage_root: Option<&str>,
+) -> Result<()> {
+    let masters: Vec<&str> = plugin.masters.iter().map(String::as_str).collect();
+    let mut tx = conn.begin().await?;
+    let plugin_row = plugin::insert(
+        &mut *tx,
+        &UnsavedPlugin {
+            name: &db_file.name,
+            hash: plugin.hash as i64,
+            file_id: db_file.id,
+            mod_id: db_mod.id,
+            version: plugin.version as f64,
+            size: plugin.size as i64,
+            author: plugin.author.as_deref(),
+            description: plugin.description.as_deref(),
+            masters: &masters,
+            file_name: &plugin.file_name,
+            file_path,
+            npc_count: plugin.record_counts.npcs,
+            quest_count: plugin.record_counts.quests,
+            dialogue_count: plugin.record_counts.dialogues,
+            is_patch: is_patch(&plugin.file_name, &plugin.masters),
+            storage_root,
+        },
+    )
+    .await?;
+
+    let worlds: Vec<UnsavedWorld> = plugin
+        .worlds
+        .iter()
+        .map(|world| UnsavedWorld {
+            form_id: world.form_id,
+            master: &world.master,
+            game_id: db_mod.game_id,
+        })
+        .collect();
+    let db_worlds = world::batched_insert(&mut *tx, &worlds).await?;
+    let plugin_worlds: Vec<UnsavedPluginWorld> = db_worlds
+        .iter()
+        .zip(&plugin.worlds)
+        .map(|(db_world, plugin_world)| UnsavedPluginWorld {
+            plugin_id: plugin_row.id,
+            world_id: db_world.id,
+            editor_id: &plugin_world.editor_id,
+        })
+        .collect();
+    plugin_world::batched_insert(&mut *tx, &plugin_worlds).await?;
+
+    let cells: Vec<UnsavedCell> = plugin
+        .cells
+        .iter()
+        .map(|cell| {
+            let world_id = if let Some((form_id, master)) = &cell.world {
+                Some(
+                    db_worlds
+                        .iter()
+                        .find(|&world| world.form_id == *form_id && &world.master == master)
+                        .ok_or_else(|| anyhow!("cell world was not saved"))?
+                        .id,
+                )
+            } else {
+                None
+            };
+            Ok(UnsavedCell {
+                form_id: cell.form_id,
+                master: &cell.master,
+                x: cell.x,
+                y: cell.y,
+                world_id,
+                is_persistent: cell.is_persistent,
+                is_base_game: false,
+                game_id: db_mod.game_id,
+            })
+        })
+        .collect::<Result<Vec<UnsavedCell>>>()?;
+    let db_cells = cell::batched_insert(&mut *tx, &cells).await?;
+    let plugin_cells: Vec<UnsavedPluginCell> = db_cells
+        .iter()
+        .zip(&plugin.cells)
+        .map(|(db_cell, plugin_cell)| UnsavedPluginCell {
+            plugin_id: plugin_row.id,
+            cell_id: db_cell.id,
+            file_id: db_file.id,
+            mod_id: db_mod.id,
+            editor_id: plugin_cell.editor_id.as_deref(),
+        })
+        .collect();
+    plugin_cell::batched_insert(&mut *tx, &plugin_cells).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Parses and saves the plugin, then writes it to disk in a storage root picked by
+/// `plugin_storage::place`. Plugins that fail to parse are skipped but still written to disk.
 pub async fn process_plugin(
     plugin_buf: &mut [u8],
-    pool: &sqlx::Pool<sqlx::Postgres>,
+    conn: impl Acquire<'_, Database = sqlx::Postgres>,
     db_file: &File,
     db_mod: &Mod,
     file_path: &str,
@@ -41,121 +426,36 @@ pub async fn process_plugin(
         return Ok(());
     }
     info!(bytes = plugin_buf.len(), "parsing plugin");
-    match parse_plugin(&plugin_buf) {
+    let storage_root = plugin_storage::place(plugin_buf.len() as u64)?;
+    match process_plugin_buf(plugin_buf, file_path) {
         Ok(plugin) => {
             info!(
                 num_worlds = plugin.worlds.len(),
                 num_cells = plugin.cells.len(),
                 "parse finished"
             );
-            let hash = seahash::hash(&plugin_buf);
-            let file_name = Path::new(file_path)
-                .file_name()
-                .expect("plugin path ends in a valid file_name")
-                .to_string_lossy();
-            let author = plugin.header.author.as_deref();
-            let description = plugin.header.description.as_deref();
-            let masters: Vec<&str> = plugin.header.masters.iter().map(|s| s.borrow()).collect();
-            let plugin_row = plugin::insert(
-                &pool,
-                &UnsavedPlugin {
-                    name: &db_file.name,
-                    hash: hash as i64,
-                    file_id: db_file.id,
-                    mod_id: db_mod.id,
-                    version: plugin.header.version as f64,
-                    size: plugin_buf.len() as i64,
-                    author,
-                    description,
-                    masters: &masters,
-                    file_name: &file_name,
-                    file_path,
-                },
+            save_plugin(
+                conn,
+                &plugin,
+                db_file,
+                db_mod,
+                file_path,
+                Some(&storage_root),
             )
             .await?;
-
-            let worlds: Vec<UnsavedWorld> = plugin
-                .worlds
-                .iter()
-                .map(|world| {
-                    let (form_id, master) =
-                        get_local_form_id_and_master(world.form_id, &masters, &file_name)
-                            .expect("form_id to be a valid i32");
-                    UnsavedWorld { form_id, master }
-                })
-                .collect();
-            let db_worlds = world::batched_insert(&pool, &worlds).await?;
-            let plugin_worlds: Vec<UnsavedPluginWorld> = db_worlds
-                .iter()
-                .zip(&plugin.worlds)
-                .map(|(db_world, plugin_world)| UnsavedPluginWorld {
-                    plugin_id: plugin_row.id,
-                    world_id: db_world.id,
-                    editor_id: &plugin_world.editor_id,
-                })
-                .collect();
-            plugin_world::batched_insert(&pool, &plugin_worlds).await?;
-
-            let cells: Vec<UnsavedCell> = plugin
-                .cells
-                .iter()
-                .map(|cell| {
-                    let world_id = if let Some(world_form_id) = cell.world_form_id {
-                        let (form_id, master) =
-                            get_local_form_id_and_master(world_form_id, &masters, &file_name)
-                                .expect("form_id to be valid i32");
-                        Some(
-                            db_worlds
-                                .iter()
-                                .find(|&world| world.form_id == form_id && world.master == master)
-                                .expect("cell references world in the plugin worlds")
-                                .id,
-                        )
-                    } else {
-                        None
-                    };
-                    let (form_id, master) =
-                        get_local_form_id_and_master(cell.form_id, &masters, &file_name)
-                            .expect("form_id is a valid i32");
-                    UnsavedCell {
-                        form_id,
-                        master,
-                        x: cell.x,
-                        y: cell.y,
-                        world_id,
-                        is_persistent: cell.is_persistent,
-                        is_base_game: false,
-                    }
-                })
-                .collect();
-            let db_cells = cell::batched_insert(&pool, &cells).await?;
-            let plugin_cells: Vec<UnsavedPluginCell> = db_cells
-                .iter()
-                .zip(&plugin.cells)
-                .map(|(db_cell, plugin_cell)| UnsavedPluginCell {
-                    plugin_id: plugin_row.id,
-                    cell_id: db_cell.id,
-                    file_id: db_file.id,
-                    mod_id: db_mod.id,
-                    editor_id: plugin_cell.editor_id.as_ref().map(|id| id.as_ref()),
-                })
-                .collect();
-            plugin_cell::batched_insert(&pool, &plugin_cells).await?;
+            hooks::plugin_parsed(db_mod, db_file, &plugin);
         }
         Err(err) => {
             warn!(error = %err, "Failed to parse plugin, skipping plugin");
         }
     }
 
-    let plugin_path = [
-        "plugins",
+    let plugin_path = Path::new(&storage_root).join(plugin_storage::relative_path(
         game_name,
-        &format!("{}", db_mod.nexus_mod_id),
-        &format!("{}", db_file.nexus_file_id),
-        &file_path.replace("./", "/"), // NTFS does not like trailing periods in folder names
-    ]
-    .iter()
-    .collect::<PathBuf>();
+        db_mod.nexus_mod_id,
+        db_file.nexus_file_id,
+        file_path,
+    ));
     let plugin_path = plugin_path.as_path();
     if let Some(dir) = plugin_path.parent() {
         create_dir_all(dir).await?;
@@ -0,0 +1,121 @@
+//! A bounded queue between extracting plugins from an archive and parsing and saving them with
+//! `process_plugin`. Extraction runs on a blocking thread and waits whenever the queue is full, so
+//! when Postgres is slow at most `capacity` extracted plugins are held in memory and the slowdown
+//! shows up as time the extractor spends waiting instead of as growing memory.
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use tokio::sync::mpsc::{self, error::TrySendError, Sender};
+use tokio::task::spawn_blocking;
+use tracing::{debug, info_span, Instrument, Span};
+
+use crate::models::file::File;
+use crate::models::game_mod::Mod;
+use crate::plugin_processor::process_plugin;
+
+pub const DEFAULT_CAPACITY: usize = 4;
+
+static CAPACITY: AtomicUsize = AtomicUsize::new(DEFAULT_CAPACITY);
+static DEPTH: AtomicUsize = AtomicUsize::new(0);
+static MAX_DEPTH: AtomicUsize = AtomicUsize::new(0);
+static FULL_WAITS: AtomicU64 = AtomicU64::new(0);
+
+/// Queue depth metrics since the process started, reported by the health endpoints in serve mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct PluginQueueMetrics {
+    pub capacity: usize,
+    /// Extracted plugins waiting to be processed
+    pub depth: usize,
+    pub max_depth: usize,
+    /// Times extraction had to wait for room in the queue
+    pub full_waits: u64,
+}
+
+/// Sets how many extracted plugins can wait to be processed. Call this at startup.
+pub fn set_capacity(capacity: usize) {
+    CAPACITY.store(capacity.max(1), Ordering::Relaxed);
+}
+
+pub fn metrics() -> PluginQueueMetrics {
+    PluginQueueMetrics {
+        capacity: CAPACITY.load(Ordering::Relaxed),
+        depth: DEPTH.load(Ordering::Relaxed),
+        max_depth: MAX_DEPTH.load(Ordering::Relaxed),
+        full_waits: FULL_WAITS.load(Ordering::Relaxed),
+    }
+}
+
+/// The extracting end of the queue
+pub struct PluginSender {
+    sender: Sender<(String, Vec<u8>)>,
+}
+
+impl PluginSender {
+    /// Queues an extracted plugin, blocking the thread while the queue is full. Fails once the
+    /// processing end has stopped (e.g. after a database error).
+    pub fn send(&self, file_path: String, plugin_buf: Vec<u8>) -> Result<()> {
+        let depth = DEPTH.fetch_add(1, Ordering::Relaxed) + 1;
+        MAX_DEPTH.fetch_max(depth, Ordering::Relaxed);
+        let sent = match self.sender.try_send((file_path, plugin_buf)) {
+            Ok(()) => true,
+            Err(TrySendError::Full(plugin)) => {
+                FULL_WAITS.fetch_add(1, Ordering::Relaxed);
+                debug!("plugin queue is full, waiting for plugins to be processed");
+                self.sender.blocking_send(plugin).is_ok()
+            }
+            Err(TrySendError::Closed(_)) => false,
+        };
+        if !sent {
+            DEPTH.fetch_sub(1, Ordering::Relaxed);
+            return Err(anyhow!(
+                "plugin processing stopped before all plugins were extracted"
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Runs `extract` on a blocking thread, processing every plugin it sends as it is extracted. An
+/// error from `extract` is returned after the plugins it sent before failing are processed.
+pub async fn process_plugins<F>(
+    pool: &sqlx::Pool<sqlx::Postgres>,
+    db_file: &File,
+    db_mod: &Mod,
+    game_name: &str,
+    extract: F,
+) -> Result<()>
+where
+    F: FnOnce(&PluginSender) -> Result<()> + Send + 'static,
+{
+    let (sender, mut receiver) = mpsc::channel(CAPACITY.load(Ordering::Relaxed));
+    let span = Span::current();
+    let extractor = spawn_blocking(move || {
+        let _span = span.enter();
+        extract(&PluginSender { sender })
+    });
+    let mut processed = Ok(());
+    while let Some((file_path, mut plugin_buf)) = receiver.recv().await {
+        let depth = DEPTH.fetch_sub(1, Ordering::Relaxed) - 1;
+        let plugin_span = info_span!("plugin", name = ?file_path, queue_depth = depth);
+        processed = process_plugin(
+            &mut plugin_buf,
+            pool,
+            db_file,
+            db_mod,
+            &file_path,
+            game_name,
+        )
+        .instrument(plugin_span)
+        .await;
+        if processed.is_err() {
+            break;
+        }
+    }
+    // stop the extractor and drop the plugins it already queued
+    receiver.close();
+    while receiver.try_recv().is_ok() {
+        DEPTH.fetch_sub(1, Ordering::Relaxed);
+    }
+    processed?;
+    extractor.await?
+}
@@ -0,0 +1,237 @@
+//! Where processed plugins are written to disk. Everything goes in `plugins/` unless other roots
+//! are configured (e.g. one folder per disk once the archive outgrows one), in which case each
+//! plugin is placed in a root picked by the placement policy and the root is saved with the plugin
+//! so it can be found again.
+//!
+//! Under every root plugins are laid out as `{game}/{nexus_mod_id}/{nexus_file_id}/{file_path}`.
+//! Plugins moved to cold storage by `--tier-plugins` keep that layout in the cold storage folder,
+//! gzipped with a `.gz` extension added, and `read` decompresses them transparently.
+use anyhow::{anyhow, Context, Result};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::RwLock;
+
+pub const DEFAULT_ROOT: &str = "plugins";
+/// Free space fill-first placement leaves on a root before moving on to the next one
+pub const MIN_FREE_SPACE: u64 = 1024 * 1024 * 1024;
+/// Extension added to plugins compressed into cold storage
+pub const COLD_EXTENSION: &str = "gz";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    /// Fill the roots in the order they were given
+    FillFirst,
+    /// Place each plugin in the root with the most free space
+    Balanced,
+}
+
+impl FromStr for Placement {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fill-first" => Ok(Placement::FillFirst),
+            "balanced" => Ok(Placement::Balanced),
+            _ => Err(format!("invalid placement: {}", s)),
+        }
+    }
+}
+
+struct Storage {
+    roots: Vec<String>,
+    placement: Placement,
+}
+
+static STORAGE: RwLock<Option<Storage>> = RwLock::new(None);
+
+/// Spreads plugins over `roots` with `placement`. Call this at startup.
+pub fn configure(roots: Vec<String>, placement: Placement) -> Result<()> {
+    if roots.is_empty() {
+        return Err(anyhow!("at least one plugin storage root is required"));
+    }
+    *STORAGE
+        .write()
+        .expect("plugin storage lock is not poisoned") = Some(Storage { roots, placement });
+    Ok(())
+}
+
+/// Configured roots in the order they were given
+pub fn roots() -> Vec<String> {
+    STORAGE
+        .read()
+        .expect("plugin storage lock is not poisoned")
+        .as_ref()
+        .map(|storage| storage.roots.clone())
+        .unwrap_or_else(|| vec![DEFAULT_ROOT.to_string()])
+}
+
+fn placement() -> Placement {
+    STORAGE
+        .read()
+        .expect("plugin storage lock is not poisoned")
+        .as_ref()
+        .map(|storage| storage.placement)
+        .unwrap_or(Placement::FillFirst)
+}
+
+/// Path of a plugin relative to the root it is stored in
+pub fn relative_path(
+    game_name: &str,
+    nexus_mod_id: i32,
+    nexus_file_id: i32,
+    file_path: &str,
+) -> PathBuf {
+    [
+        game_name,
+        &format!("{}", nexus_mod_id),
+        &format!("{}", nexus_file_id),
+        &file_path.replace("./", "/"), // NTFS does not like trailing periods in folder names
+    ]
+    .iter()
+    .collect()
+}
+
+/// Picks the root to place a plugin of `size` bytes in, given each root's free space in bytes.
+/// Only roots that would keep `MIN_FREE_SPACE` free after the plugin is written are considered.
+pub fn choose_root<'a>(
+    roots: &'a [(String, u64)],
+    placement: Placement,
+    size: u64,
+) -> Option<&'a str> {
+    let mut candidates = roots
+        .iter()
+        .filter(|(_, free)| *free >= size.saturating_add(MIN_FREE_SPACE));
+    let root = match placement {
+        Placement::FillFirst => candidates.next(),
+        // max_by_key returns the last max, prefer the first root given on ties instead
+        Placement::Balanced => candidates.rev().max_by_key(|(_, free)| *free),
+    };
+    root.map(|(root, _)| root.as_str())
+}
+
+/// Returns the root to write a plugin of `size` bytes to. With a single root it is always used,
+/// so running out of space fails the write like it did before roots were configurable.
+pub fn place(size: u64) -> Result<String> {
+    let roots = roots();
+    if let [root] = roots.as_slice() {
+        return Ok(root.clone());
+    }
+    let mut free_space = vec![];
+    for root in roots {
+        std::fs::create_dir_all(&root)
+            .with_context(|| format!("Failed to create plugin storage root {}", root))?;
+        let free = fs2::available_space(&root)
+            .with_context(|| format!("Failed to get free space of {}", root))?;
+        free_space.push((root, free));
+    }
+    choose_root(&free_space, placement(), size)
+        .map(str::to_string)
+        .ok_or_else(|| anyhow!("no plugin storage root has room for a {} byte plugin", size))
+}
+
+/// Finds a plugin on disk, looking in the root it was saved with first and then every configured
+/// root, since plugins saved before roots were configured have no root saved and roots can be
+/// moved around by hand.
+pub fn resolve(saved_root: Option<&str>, relative_path: &Path) -> Option<PathBuf> {
+    search_roots(saved_root)
+        .map(|root| Path::new(&root).join(relative_path))
+        .find(|path| path.is_file())
+}
+
+/// Roots `resolve` looks for a plugin in, in order
+fn search_roots(saved_root: Option<&str>) -> impl Iterator<Item = String> {
+    saved_root
+        .map(str::to_string)
+        .into_iter()
+        .chain(roots())
+        .chain(std::iter::once(DEFAULT_ROOT.to_string()))
+}
+
+/// Moves a plugin from `from` to `to` (both relative to the root it is stored in) within the root
+/// `resolve` finds it in, or within the cold storage folder it was saved with. If there is already
+/// a plugin at `to`, the plugin at `from` is a duplicate of it and is removed instead. Returns
+/// whether the plugin was found.
+pub fn relocate(saved_root: Option<&str>, from: &Path, to: &Path) -> Result<bool> {
+    let cold_moves = saved_root.map(|saved_root| {
+        (
+            cold_path(Path::new(saved_root), from),
+            cold_path(Path::new(saved_root), to),
+        )
+    });
+    let mut moves = search_roots(saved_root)
+        .map(|root| (Path::new(&root).join(from), Path::new(&root).join(to)))
+        .chain(cold_moves);
+    let (source, target) = match moves.find(|(source, _)| source.is_file()) {
+        Some(paths) => paths,
+        None => return Ok(false),
+    };
+    if from == to {
+        return Ok(true);
+    }
+    if target.is_file() {
+        std::fs::remove_file(&source)
+            .with_context(|| format!("Failed to remove {}", source.display()))?;
+    } else {
+        if let Some(dir) = target.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::rename(&source, &target).with_context(|| {
+            format!(
+                "Failed to move {} to {}",
+                source.display(),
+                target.display()
+            )
+        })?;
+    }
+    Ok(true)
+}
+
+/// Path a plugin is compressed to in the cold storage folder `cold_root`
+pub fn cold_path(cold_root: &Path, relative_path: &Path) -> PathBuf {
+    let mut path = cold_root.join(relative_path).into_os_string();
+    path.push(".");
+    path.push(COLD_EXTENSION);
+    path.into()
+}
+
+/// Compresses the plugin at `path` into the cold storage folder `cold_root`. The compressed file
+/// is written next to its final path and renamed into place, so an interrupted move never leaves
+/// a truncated plugin behind.
+pub fn compress(path: &Path, cold_root: &Path, relative_path: &Path) -> Result<PathBuf> {
+    let cold_path = cold_path(cold_root, relative_path);
+    if let Some(dir) = cold_path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut partial_path = cold_path.clone().into_os_string();
+    partial_path.push(".part");
+    let partial_path = PathBuf::from(partial_path);
+    let mut encoder = GzEncoder::new(File::create(&partial_path)?, Compression::best());
+    io::copy(&mut File::open(path)?, &mut encoder)?;
+    encoder.finish()?.sync_all()?;
+    std::fs::rename(&partial_path, &cold_path)?;
+    Ok(cold_path)
+}
+
+/// Reads a plugin from whichever root it is in (see `resolve`), or from the cold storage folder it
+/// was saved with, decompressing it. Returns `None` if the plugin can't be found.
+pub fn read(saved_root: Option<&str>, relative_path: &Path) -> Result<Option<Vec<u8>>> {
+    if let Some(path) = resolve(saved_root, relative_path) {
+        return Ok(Some(std::fs::read(path)?));
+    }
+    let cold_path = match saved_root {
+        Some(saved_root) => cold_path(Path::new(saved_root), relative_path),
+        None => return Ok(None),
+    };
+    if !cold_path.is_file() {
+        return Ok(None);
+    }
+    let mut plugin_buf = vec![];
+    GzDecoder::new(File::open(&cold_path)?)
+        .read_to_end(&mut plugin_buf)
+        .with_context(|| format!("Failed to decompress {}", cold_path.display()))?;
+    Ok(Some(plugin_buf))
+}
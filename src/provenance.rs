@@ -0,0 +1,96 @@
+//! When a dump was generated and from which scrape run, so consumers and caches can tell how stale
+//! a dumped file is.
+//!
+//! Dumps of a single document (a mod, cell, file, or plugin) get `generated_at` and `run_id` fields
+//! added to the document itself. Dumps of a whole collection (e.g. `games.json`, the cell edit
+//! counts) keep their shape for existing consumers and get a `{stem}.manifest.json` written next to
+//! them instead.
+use anyhow::Result;
+use chrono::{NaiveDateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use std::path::Path;
+use std::sync::RwLock;
+
+use crate::models::scrape_run;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Provenance {
+    pub generated_at: NaiveDateTime,
+    /// The latest scrape run the dumped data came from
+    pub run_id: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+struct DumpManifest<'a> {
+    file: &'a str,
+    count: usize,
+    #[serde(flatten)]
+    provenance: Provenance,
+}
+
+static PROVENANCE: RwLock<Option<Provenance>> = RwLock::new(None);
+
+/// Looks up the latest scrape run and fixes the generation time, so every file dumped by this
+/// process carries the same provenance
+pub async fn init(executor: impl sqlx::PgExecutor<'_>) -> Result<()> {
+    let run_id = scrape_run::get_latest_id(executor).await?;
+    *PROVENANCE.write().expect("provenance lock is not poisoned") = Some(Provenance {
+        generated_at: Utc::now().naive_utc(),
+        run_id,
+    });
+    Ok(())
+}
+
+pub fn current() -> Provenance {
+    *PROVENANCE
+        .write()
+        .expect("provenance lock is not poisoned")
+        .get_or_insert_with(|| Provenance {
+            generated_at: Utc::now().naive_utc(),
+            run_id: None,
+        })
+}
+
+/// Serializes a document with the provenance fields added. Values that don't serialize to an
+/// object are left as they are.
+pub fn to_json<T: Serialize>(document: &T) -> Result<String> {
+    let mut value = serde_json::to_value(document)?;
+    if let Value::Object(ref mut map) = value {
+        let provenance = current();
+        map.insert(
+            "generated_at".to_string(),
+            serde_json::to_value(provenance.generated_at)?,
+        );
+        map.insert(
+            "run_id".to_string(),
+            serde_json::to_value(provenance.run_id)?,
+        );
+    }
+    Ok(serde_json::to_string(&value)?)
+}
+
+/// `{stem}.manifest.json` next to the dump at `path`
+pub fn manifest_path(path: &Path) -> std::path::PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    path.with_file_name(format!("{}.manifest.json", stem))
+}
+
+/// Writes the manifest of the collection dumped to `path` with `count` entries
+pub fn write_manifest(path: impl AsRef<Path>, count: usize) -> Result<()> {
+    let path = path.as_ref();
+    let file = path
+        .file_name()
+        .map(|file| file.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let manifest = DumpManifest {
+        file: &file,
+        count,
+        provenance: current(),
+    };
+    std::fs::write(manifest_path(path), serde_json::to_string(&manifest)?)?;
+    Ok(())
+}
@@ -0,0 +1,145 @@
+use chrono::{NaiveDateTime, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    Starting,
+    /// Games are being updated, see their own stages
+    Updating,
+    ScrapingModList,
+    FetchingFiles,
+    CheckingMetadata,
+    Downloading,
+    Extracting,
+    RateLimitWait,
+    Idle,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StageSnapshot {
+    pub stage: Stage,
+    pub stage_started_at: NaiveDateTime,
+}
+
+impl StageSnapshot {
+    fn new(stage: Stage) -> Self {
+        StageSnapshot {
+            stage,
+            stage_started_at: Utc::now().naive_utc(),
+        }
+    }
+
+    fn set(&mut self, stage: Stage) {
+        if self.stage != stage {
+            *self = StageSnapshot::new(stage);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusSnapshot {
+    #[serde(flatten)]
+    pub stage: StageSnapshot,
+    pub last_successful_scrape_at: Option<NaiveDateTime>,
+    /// The stage of each game's update by game name
+    pub games: BTreeMap<String, StageSnapshot>,
+}
+
+/// Progress of one game's update. Games are updated at the same time, so each has its own stage.
+#[derive(Debug)]
+pub struct GameStatus {
+    inner: Mutex<StageSnapshot>,
+}
+
+impl Default for GameStatus {
+    fn default() -> Self {
+        GameStatus {
+            inner: Mutex::new(StageSnapshot::new(Stage::Starting)),
+        }
+    }
+}
+
+impl GameStatus {
+    pub fn set_stage(&self, stage: Stage) {
+        self.inner
+            .lock()
+            .expect("status lock is not poisoned")
+            .set(stage);
+    }
+
+    pub fn snapshot(&self) -> StageSnapshot {
+        self.inner
+            .lock()
+            .expect("status lock is not poisoned")
+            .clone()
+    }
+}
+
+/// Progress of the update process, shared with the health endpoints in serve mode so operators
+/// can tell what the scraper is currently doing (e.g. sitting on a rate-limit wait).
+#[derive(Debug)]
+pub struct Status {
+    inner: Mutex<StageSnapshot>,
+    last_successful_scrape_at: Mutex<Option<NaiveDateTime>>,
+    games: Mutex<BTreeMap<String, Arc<GameStatus>>>,
+}
+
+impl Default for Status {
+    fn default() -> Self {
+        Status {
+            inner: Mutex::new(StageSnapshot::new(Stage::Starting)),
+            last_successful_scrape_at: Mutex::new(None),
+            games: Mutex::new(BTreeMap::new()),
+        }
+    }
+}
+
+impl Status {
+    pub fn set_stage(&self, stage: Stage) {
+        self.inner
+            .lock()
+            .expect("status lock is not poisoned")
+            .set(stage);
+    }
+
+    pub fn record_successful_scrape(&self) {
+        *self
+            .last_successful_scrape_at
+            .lock()
+            .expect("status lock is not poisoned") = Some(Utc::now().naive_utc());
+    }
+
+    /// The progress of the game's update, added to the snapshots from now on
+    pub fn game(&self, game_name: &str) -> Arc<GameStatus> {
+        self.games
+            .lock()
+            .expect("status lock is not poisoned")
+            .entry(game_name.to_string())
+            .or_default()
+            .clone()
+    }
+
+    pub fn snapshot(&self) -> StatusSnapshot {
+        StatusSnapshot {
+            stage: self
+                .inner
+                .lock()
+                .expect("status lock is not poisoned")
+                .clone(),
+            last_successful_scrape_at: *self
+                .last_successful_scrape_at
+                .lock()
+                .expect("status lock is not poisoned"),
+            games: self
+                .games
+                .lock()
+                .expect("status lock is not poisoned")
+                .iter()
+                .map(|(game_name, game_status)| (game_name.clone(), game_status.snapshot()))
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,29 @@
+//! The folder downloaded archives are saved to and extracted in. It defaults to the system temp
+//! folder, which is often a small tmpfs that large archives don't fit in.
+use anyhow::{Context, Result};
+use std::env;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+static TEMP_DIR: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// Uses `dir` (created if it doesn't exist) for all downloads and extraction. `TMPDIR` is also set
+/// so that `7z` and libarchive (through compress_tools) spill their own temporary files there.
+///
+/// Call this at startup before any other threads read the environment.
+pub fn set(dir: impl Into<PathBuf>) -> Result<()> {
+    let dir = dir.into();
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("Failed to create temp dir {}", dir.display()))?;
+    env::set_var("TMPDIR", &dir);
+    *TEMP_DIR.write().expect("temp dir lock is not poisoned") = Some(dir);
+    Ok(())
+}
+
+pub fn get() -> PathBuf {
+    TEMP_DIR
+        .read()
+        .expect("temp dir lock is not poisoned")
+        .clone()
+        .unwrap_or_else(env::temp_dir)
+}
@@ -0,0 +1,94 @@
+use anyhow::{anyhow, Result};
+use reqwest::Client;
+use serde_json::Value;
+use std::time::Duration;
+use tokio::time::sleep;
+use tracing::{info, instrument, warn};
+
+use crate::grid;
+pub use crate::grid::CELL_SIZE;
+
+/// Minimum time to wait between requests to the UESP API so we stay well under their rate limits
+pub const REQUEST_INTERVAL: Duration = Duration::from_secs(1);
+
+const LOCATIONS_URL: &str = "https://gamemap.uesp.net/db/gamemap.php";
+
+#[derive(Debug)]
+pub struct UespLocation {
+    pub name: String,
+    pub wiki_page: Option<String>,
+    pub display_level: i64,
+}
+
+/// Fetches all map locations the UESP map has within the bounds of the exterior cell at x, y
+#[instrument(skip(client))]
+pub async fn get_cell_locations(client: &Client, x: i32, y: i32) -> Result<Vec<UespLocation>> {
+    let bounds = grid::cell_bounds(x, y);
+    for attempt in 1..=3 {
+        let res = match client
+            .get(LOCATIONS_URL)
+            .query(&[
+                ("action", "get_locs"),
+                ("db", "sr"),
+                ("world", "skyrim"),
+                ("left", &bounds.left.to_string()),
+                ("right", &bounds.right.to_string()),
+                ("bottom", &bounds.bottom.to_string()),
+                ("top", &bounds.top.to_string()),
+            ])
+            .header("accept", "application/json")
+            .send()
+            .await
+        {
+            Ok(res) => match res.error_for_status() {
+                Ok(res) => res,
+                Err(err) => {
+                    warn!(error = %err, attempt, "uesp get_cell_locations request failed, trying again after 1 second");
+                    sleep(REQUEST_INTERVAL).await;
+                    continue;
+                }
+            },
+            Err(err) => {
+                warn!(error = %err, attempt, "uesp get_cell_locations request failed, trying again after 1 second");
+                sleep(REQUEST_INTERVAL).await;
+                continue;
+            }
+        };
+
+        info!(status = %res.status(), "fetched cell locations from UESP API");
+        let json = res.json::<Value>().await?;
+        let locations = json
+            .get("locations")
+            .ok_or_else(|| anyhow!("Missing locations key in UESP API response"))?
+            .as_array()
+            .ok_or_else(|| anyhow!("locations value in UESP API response is not an array"))?;
+        return locations
+            .iter()
+            .map(|location| {
+                let name = location
+                    .get("name")
+                    .ok_or_else(|| anyhow!("Missing name key in location in UESP API response"))?
+                    .as_str()
+                    .ok_or_else(|| anyhow!("name value in UESP API response location is not a string"))?
+                    .to_string();
+                let wiki_page = location
+                    .get("wikiPage")
+                    .and_then(|wiki_page| wiki_page.as_str())
+                    .filter(|wiki_page| !wiki_page.is_empty())
+                    .map(|wiki_page| wiki_page.to_string());
+                let display_level = location
+                    .get("displayLevel")
+                    .and_then(|display_level| display_level.as_i64())
+                    .unwrap_or(i64::MAX);
+                Ok(UespLocation {
+                    name,
+                    wiki_page,
+                    display_level,
+                })
+            })
+            .collect();
+    }
+    Err(anyhow!(
+        "Failed to get cell locations from UESP in three attempts"
+    ))
+}
@@ -0,0 +1,90 @@
+//! Tests for keeping downloaded archives around for extraction retries.
+mod common;
+
+use mod_mapper::archive_cache::{
+    archive_path, configure, download_path, is_expired, open, prune_dir,
+};
+use mod_mapper::commands::update::extract_archive;
+use mod_mapper::models::file::get_processed_nexus_file_ids_by_mod_id;
+use mod_mapper::nexus_api::SSE_GAME_NAME;
+use mod_mapper::temp_dir;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+use testcontainers::clients::Cli;
+
+use common::{
+    connect, count_plugins, fixture_path, insert_mod_and_file, postgres_image, use_temp_working_dir,
+};
+
+const HOUR: Duration = Duration::from_secs(60 * 60);
+
+#[test]
+fn keys_archives_by_file_id() {
+    assert_eq!(
+        archive_path(Path::new("/tmp/archives"), 42),
+        Path::new("/tmp/archives/42.archive")
+    );
+}
+
+#[test]
+fn expires_archives_older_than_the_retention() {
+    let now = SystemTime::now();
+    assert!(is_expired(now - 2 * HOUR, now, HOUR));
+    assert!(!is_expired(now - HOUR / 2, now, HOUR));
+    // clock skew can put modification times in the future
+    assert!(!is_expired(now + HOUR, now, HOUR));
+}
+
+#[test]
+fn prunes_only_expired_archives() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(archive_path(dir.path(), 1), b"archive").unwrap();
+    assert_eq!(prune_dir(dir.path(), HOUR).unwrap(), 0);
+    assert!(archive_path(dir.path(), 1).is_file());
+    std::thread::sleep(Duration::from_millis(10));
+    assert_eq!(prune_dir(dir.path(), Duration::ZERO).unwrap(), 1);
+    assert!(!archive_path(dir.path(), 1).exists());
+}
+
+#[tokio::test]
+async fn kept_archive_is_reused_after_a_failed_extraction() {
+    use_temp_working_dir();
+    let docker = Cli::default();
+    let node = docker.run(postgres_image());
+    let pool = connect(&node).await;
+    let (db_mod, db_file) = insert_mod_and_file(&pool, 1, "fixture.zip").await;
+    let dir = tempfile::tempdir().unwrap();
+    temp_dir::set(dir.path()).unwrap();
+    configure(HOUR).unwrap();
+
+    // a gzip header followed by data no extractor can read
+    let path = download_path(db_file.id).unwrap();
+    std::fs::write(
+        &path,
+        [0x1F, 0x8B, 0x08, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF],
+    )
+    .unwrap();
+    let mut archive = open(db_file.id).await.unwrap().unwrap();
+    assert!(
+        extract_archive(&pool, &mut archive, &db_file, &db_mod, SSE_GAME_NAME, false)
+            .await
+            .is_err()
+    );
+    let processed = get_processed_nexus_file_ids_by_mod_id(&pool, db_mod.id)
+        .await
+        .unwrap();
+    assert!(!processed.contains(&db_file.nexus_file_id));
+
+    // once the archive can be extracted (say, after an extractor fix), the next update extracts
+    // the kept archive instead of downloading it again
+    std::fs::copy(fixture_path("fixture.zip"), &path).unwrap();
+    let mut archive = open(db_file.id).await.unwrap().unwrap();
+    extract_archive(&pool, &mut archive, &db_file, &db_mod, SSE_GAME_NAME, false)
+        .await
+        .unwrap();
+    assert_eq!(count_plugins(&pool, db_file.id).await, 1);
+    let processed = get_processed_nexus_file_ids_by_mod_id(&pool, db_mod.id)
+        .await
+        .unwrap();
+    assert!(processed.contains(&db_file.nexus_file_id));
+}
@@ -0,0 +1,51 @@
+//! Tests for normalizing scraped category names to the English names from the API.
+use mod_mapper::models::category::canonical_category_name;
+use mod_mapper::nexus_api::game::{parse_categories, ApiCategory};
+use serde_json::json;
+
+#[test]
+fn parses_categories_of_a_game() {
+    let json = json!({
+        "id": 1704,
+        "categories": [
+            { "category_id": 1, "name": "Skyrim Special Edition", "parent_category": false },
+            { "category_id": 2, "name": "Miscellaneous", "parent_category": 1 },
+        ],
+    });
+    assert_eq!(
+        parse_categories(&json).unwrap(),
+        vec![
+            ApiCategory {
+                category_id: 1,
+                name: "Skyrim Special Edition",
+                parent_category_id: None,
+            },
+            ApiCategory {
+                category_id: 2,
+                name: "Miscellaneous",
+                parent_category_id: Some(1),
+            },
+        ]
+    );
+    assert!(parse_categories(&json!({ "id": 1704 })).is_err());
+}
+
+#[test]
+fn replaces_localized_category_names() {
+    assert_eq!(
+        canonical_category_name(Some("Verschiedenes".to_string()), Some("Miscellaneous")),
+        (
+            Some("Miscellaneous".to_string()),
+            Some("Verschiedenes".to_string())
+        )
+    );
+    assert_eq!(
+        canonical_category_name(Some("Miscellaneous".to_string()), Some("Miscellaneous")),
+        (Some("Miscellaneous".to_string()), None)
+    );
+    assert_eq!(
+        canonical_category_name(Some("Divers".to_string()), None),
+        (Some("Divers".to_string()), None)
+    );
+    assert_eq!(canonical_category_name(None, None), (None, None));
+}
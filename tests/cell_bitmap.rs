@@ -0,0 +1,65 @@
+//! Tests for the compact cell bitmap encoding used in mod dumps.
+use mod_mapper::cell_bitmap::{decode, encode, encode_if_smaller};
+use mod_mapper::grid::{MAX_CELL_X, MAX_CELL_Y, MIN_CELL_X, MIN_CELL_Y};
+use proptest::prelude::*;
+
+#[test]
+fn encodes_grid_corners_and_leaves_out_cells_outside() {
+    let cells = vec![
+        (MIN_CELL_X, MIN_CELL_Y),
+        (MAX_CELL_X - 1, MAX_CELL_Y - 1),
+        (0, 0),
+        (MAX_CELL_X, 0),
+        (0, MIN_CELL_Y - 1),
+    ];
+    let (bitmap, outside) = encode(&cells);
+    assert_eq!(outside, vec![(MAX_CELL_X, 0), (0, MIN_CELL_Y - 1)]);
+    assert_eq!(
+        decode(&bitmap).unwrap(),
+        vec![
+            (MIN_CELL_X, MIN_CELL_Y),
+            (0, 0),
+            (MAX_CELL_X - 1, MAX_CELL_Y - 1)
+        ]
+    );
+}
+
+#[test]
+fn empty_bitmap_is_fixed_size() {
+    let (empty, _) = encode(&[]);
+    let (full, _) = encode(
+        &(MIN_CELL_X..MAX_CELL_X)
+            .flat_map(|x| (MIN_CELL_Y..MAX_CELL_Y).map(move |y| (x, y)))
+            .collect::<Vec<_>>(),
+    );
+    assert_eq!(empty.len(), full.len());
+    assert!(decode(&empty).unwrap().is_empty());
+}
+
+#[test]
+fn rejects_bitmaps_of_the_wrong_size() {
+    assert!(decode("AAAA").is_err());
+}
+
+#[test]
+fn only_encodes_when_the_bitmap_is_smaller() {
+    assert_eq!(encode_if_smaller(&[(0, 0), (1, 0)]), None);
+    let many_cells: Vec<(i32, i32)> = (MIN_CELL_X..MAX_CELL_X)
+        .flat_map(|x| (0..10).map(move |y| (x, y)))
+        .chain(std::iter::once((MAX_CELL_X, 0)))
+        .collect();
+    let (bitmap, outside) = encode_if_smaller(&many_cells).unwrap();
+    assert_eq!(outside, vec![(MAX_CELL_X, 0)]);
+    assert_eq!(decode(&bitmap).unwrap().len(), many_cells.len() - 1);
+}
+
+proptest! {
+    #[test]
+    fn decode_inverts_encode(cells in prop::collection::btree_set((MIN_CELL_X..MAX_CELL_X, MIN_CELL_Y..MAX_CELL_Y), 0..200)) {
+        let (bitmap, outside) = encode(&cells.iter().copied().collect::<Vec<_>>());
+        prop_assert!(outside.is_empty());
+        let mut expected: Vec<(i32, i32)> = cells.into_iter().collect();
+        expected.sort_by_key(|&(x, y)| (y, x));
+        prop_assert_eq!(decode(&bitmap).unwrap(), expected);
+    }
+}
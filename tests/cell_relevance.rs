@@ -0,0 +1,44 @@
+//! Tests for scoring how likely mods are to edit cells.
+use mod_mapper::cell_relevance::{score, sort_page};
+
+#[test]
+fn location_categories_score_higher_than_assets() {
+    assert!(score(Some("Locations - New"), 0, 0) > score(Some("Quests and Adventures"), 0, 0));
+    assert!(score(Some("Quests and Adventures"), 0, 0) > score(None, 0, 0));
+    assert!(score(None, 0, 0) > score(Some("Models and Textures"), 0, 0));
+}
+
+#[test]
+fn metadata_checks_outweigh_category() {
+    assert!(score(Some("Models and Textures"), 2, 1) > score(Some("Models and Textures"), 0, 0));
+    assert!(score(Some("Player Homes"), 3, 0) < score(Some("Player Homes"), 0, 0));
+}
+
+#[test]
+fn pages_are_sorted_by_score_keeping_listed_order_of_ties() {
+    let mut page = vec![
+        ("texture", -2),
+        ("quest", 1),
+        ("town", 3),
+        ("other quest", 1),
+    ];
+    sort_page(&mut page, |(_, score)| *score);
+    assert_eq!(
+        page.iter().map(|(name, _)| *name).collect::<Vec<_>>(),
+        vec!["town", "quest", "other quest", "texture"]
+    );
+}
+
+#[test]
+fn mods_are_only_reordered_within_their_page() {
+    let mut first_page = vec![("texture", -2), ("quest", 1)];
+    let mut second_page = vec![("other texture", -2), ("town", 3)];
+    sort_page(&mut first_page, |(_, score)| *score);
+    sort_page(&mut second_page, |(_, score)| *score);
+    let processed: Vec<&str> = first_page
+        .iter()
+        .chain(second_page.iter())
+        .map(|(name, _)| *name)
+        .collect();
+    assert_eq!(processed, vec!["quest", "texture", "town", "other texture"]);
+}
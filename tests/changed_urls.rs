@@ -0,0 +1,38 @@
+//! Tests for listing the urls of changed dump files.
+use chrono::{Duration, Utc};
+use mod_mapper::commands::dump_changed_urls::{changed_urls, parse_dump_url};
+
+#[test]
+fn parses_dump_url_and_trims_trailing_slash() {
+    assert_eq!(
+        parse_dump_url("mods=https://mods.modmapper.com/").unwrap(),
+        ("mods".to_string(), "https://mods.modmapper.com".to_string())
+    );
+    assert!(parse_dump_url("mods").is_err());
+}
+
+#[test]
+fn lists_urls_of_files_written_since() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(dir.path().join("skyrimspecialedition")).unwrap();
+    std::fs::write(dir.path().join("skyrimspecialedition/1.json"), "{}").unwrap();
+    std::fs::write(dir.path().join("games.json"), "[]").unwrap();
+    let dump_urls = vec![(
+        dir.path().to_string_lossy().to_string(),
+        "https://mods.modmapper.com".to_string(),
+    )];
+
+    let an_hour_ago = (Utc::now() - Duration::hours(1)).naive_utc();
+    assert_eq!(
+        changed_urls(&dump_urls, Some(an_hour_ago)).unwrap(),
+        vec![
+            "https://mods.modmapper.com/games.json",
+            "https://mods.modmapper.com/skyrimspecialedition/1.json",
+        ]
+    );
+    let in_an_hour = (Utc::now() + Duration::hours(1)).naive_utc();
+    assert!(changed_urls(&dump_urls, Some(in_an_hour))
+        .unwrap()
+        .is_empty());
+    assert_eq!(changed_urls(&dump_urls, None).unwrap().len(), 2);
+}
@@ -0,0 +1,47 @@
+//! Tests for the completion scripts and man page generated from an argh definition.
+use argh::{ArgsInfo, FromArgs};
+use mod_mapper::commands::completions::{completions, man_page, Shell};
+
+#[derive(FromArgs, ArgsInfo)]
+/// Test command.
+#[allow(dead_code)]
+struct Args {
+    /// name of the game [e.g. "skyrim"]
+    #[argh(option, short = 'g')]
+    game: Vec<String>,
+
+    /// enable full scrape
+    #[argh(switch)]
+    full: bool,
+}
+
+#[test]
+fn bash_completes_flags_and_option_values() {
+    let script = completions(&Args::get_args_info(), "mod-mapper", Shell::Bash);
+    assert!(script.contains("--game|-g)"));
+    assert!(script.contains("--full"));
+    assert!(script.contains("complete -F _mod_mapper mod-mapper"));
+}
+
+#[test]
+fn zsh_escapes_descriptions_and_repeats_vec_options() {
+    let script = completions(&Args::get_args_info(), "mod-mapper", Shell::Zsh);
+    assert!(script.starts_with("#compdef mod-mapper"));
+    assert!(script.contains(r#"'*--game[name of the game \[e.g. "skyrim"\]]:game:_files'"#));
+    assert!(script.contains("'--full[enable full scrape]'"));
+}
+
+#[test]
+fn fish_marks_options_as_requiring_a_value() {
+    let script = completions(&Args::get_args_info(), "mod-mapper", Shell::Fish);
+    assert!(script.contains("complete -c mod-mapper -l game -s g -r -d"));
+    assert!(script.contains("complete -c mod-mapper -l full -d 'enable full scrape'"));
+}
+
+#[test]
+fn man_page_lists_every_flag() {
+    let page = man_page(&Args::get_args_info(), "mod-mapper");
+    assert!(page.starts_with(".TH MOD-MAPPER 1"));
+    assert!(page.contains("\\fB\\-g\\fR, \\fB\\-\\-game\\fR \\fIgame\\fR"));
+    assert!(page.contains("\\fB\\-\\-full\\fR\nenable full scrape"));
+}
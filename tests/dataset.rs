@@ -0,0 +1,45 @@
+//! Tests for the tables and schema description of dataset releases.
+use chrono::NaiveDate;
+use mod_mapper::commands::dump_dataset::{
+    dataset_dir_name, default_version, schema_markdown, DATASET_TABLES,
+};
+use mod_mapper::provenance::Provenance;
+
+#[test]
+fn documents_every_column_each_table_selects() {
+    for table in DATASET_TABLES {
+        let select = table.query.trim_start_matches("SELECT ");
+        let select = &select[..select.find(" FROM ").expect("query selects from a table")];
+        let selected: Vec<&str> = select.split(',').map(|column| column.trim()).collect();
+        let documented: Vec<&str> = table.columns.iter().map(|(column, _)| *column).collect();
+        assert_eq!(selected, documented, "columns of {}", table.name);
+    }
+}
+
+#[test]
+fn describes_each_table_in_the_schema() {
+    let schema = schema_markdown("2023-12-01", DATASET_TABLES);
+    assert!(schema.starts_with("# Modmapper dataset 2023-12-01\n"));
+    for table in DATASET_TABLES {
+        assert!(schema.contains(&format!("## {}.csv.gz\n", table.name)));
+        for (column, description) in table.columns {
+            assert!(schema.contains(&format!("| `{}` | {} |\n", column, description)));
+        }
+    }
+}
+
+#[test]
+fn versions_releases_by_day() {
+    let provenance = Provenance {
+        generated_at: NaiveDate::from_ymd_opt(2023, 12, 1)
+            .unwrap()
+            .and_hms_opt(9, 30, 0)
+            .unwrap(),
+        run_id: Some(3),
+    };
+    assert_eq!(default_version(provenance), "2023-12-01");
+    assert_eq!(
+        dataset_dir_name("2023-12-01"),
+        "modmapper-dataset-2023-12-01"
+    );
+}
@@ -0,0 +1,17 @@
+//! Tests for validating the schema name used for staging runs.
+use mod_mapper::db::is_valid_schema_name;
+
+#[test]
+fn accepts_plain_identifiers() {
+    assert!(is_valid_schema_name("staging"));
+    assert!(is_valid_schema_name("_staging_2"));
+}
+
+#[test]
+fn rejects_names_that_need_quoting() {
+    assert!(!is_valid_schema_name(""));
+    assert!(!is_valid_schema_name("2staging"));
+    assert!(!is_valid_schema_name("Staging"));
+    assert!(!is_valid_schema_name("staging\"; DROP SCHEMA public; --"));
+    assert!(!is_valid_schema_name(&"a".repeat(64)));
+}
@@ -0,0 +1,71 @@
+//! Tests for parsing and answering Discord bot queries.
+use mod_mapper::discord::{cell_reply, parse_query, Query, MAX_LISTED_MODS};
+use mod_mapper::models::cell::CellData;
+use serde_json::json;
+
+#[test]
+fn parses_queries() {
+    assert_eq!(
+        parse_query("!cell 5 -12"),
+        Some(Query::Cell { x: 5, y: -12 })
+    );
+    assert_eq!(
+        parse_query("!mod 12345"),
+        Some(Query::Mod {
+            nexus_mod_id: 12345,
+            game_name: "skyrimspecialedition".to_string(),
+        })
+    );
+    assert_eq!(
+        parse_query("!mod 12345 Skyrim"),
+        Some(Query::Mod {
+            nexus_mod_id: 12345,
+            game_name: "skyrim".to_string(),
+        })
+    );
+}
+
+#[test]
+fn ignores_other_messages() {
+    assert_eq!(parse_query("where is riverwood?"), None);
+    assert_eq!(parse_query("!cells 5 -12"), None);
+    assert_eq!(parse_query(""), None);
+}
+
+#[test]
+fn helps_with_invalid_arguments() {
+    assert_eq!(parse_query("!cell 5"), Some(Query::Help));
+    assert_eq!(parse_query("!cell five -12"), Some(Query::Help));
+    assert_eq!(parse_query("!cell 5 -12 3"), Some(Query::Help));
+    assert_eq!(parse_query("!mod"), Some(Query::Help));
+    assert_eq!(parse_query("!mod 12345 oblivion"), Some(Query::Help));
+}
+
+fn cell_data(mod_count: usize) -> CellData {
+    let mods: Vec<_> = (0..mod_count)
+        .map(|i| json!({ "name": format!("Mod {}", i), "nexus_mod_id": i }))
+        .collect();
+    CellData {
+        form_id: 0x9732,
+        x: Some(5),
+        y: Some(-12),
+        is_persistent: false,
+        plugins_count: Some(mod_count as i64 + 1),
+        files_count: Some(mod_count as i64),
+        mods_count: Some(mod_count as i64),
+        mods: Some(json!(mods)),
+        lore_name: Some("Riverwood".to_string()),
+        lore_wiki_page: None,
+    }
+}
+
+#[test]
+fn replies_with_the_mods_editing_a_cell() {
+    assert_eq!(cell_reply(0, 0, None), "No mods edit cell 0, 0");
+    assert_eq!(
+        cell_reply(5, -12, Some(&cell_data(2))),
+        "Cell 5, -12 (Riverwood) is edited by 2 mods in 3 plugins: Mod 0 (0), Mod 1 (1)"
+    );
+    let reply = cell_reply(5, -12, Some(&cell_data(MAX_LISTED_MODS + 3)));
+    assert!(reply.ends_with("Mod 4 (4), and 3 more"));
+}
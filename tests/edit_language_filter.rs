@@ -0,0 +1,100 @@
+//! Tests for counting only the mods in one language as edits of a cell, with languages saved from
+//! the GraphQL API.
+//!
+//! Requires docker to start the postgres container.
+mod common;
+
+use chrono::Utc;
+use mod_mapper::models::cell::{count_mod_edits, get_mod_ids_by_cell};
+use mod_mapper::models::game_mod::{batched_update_from_graphql, Mod};
+use mod_mapper::nexus_api::graphql::{GraphQLGame, GraphQLMod};
+use mod_mapper::nexus_api::SSE_GAME_NAME;
+use mod_mapper::plugin_processor::process_plugin;
+use testcontainers::clients::Cli;
+
+use common::{connect, fixture_path, insert_mod_and_file, postgres_image, use_temp_working_dir};
+
+fn graphql_mod(nexus_mod_id: i32, language_name: Option<&str>) -> GraphQLMod {
+    GraphQLMod {
+        mod_id: nexus_mod_id,
+        adult_content: false,
+        downloads: 0,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+        language_name: language_name.map(str::to_string),
+        game: GraphQLGame {
+            domain_name: SSE_GAME_NAME.to_string(),
+        },
+    }
+}
+
+/// Saves a mod with a plugin editing the fixture's cell (1, 2)
+async fn insert_mod_editing_cell(pool: &sqlx::Pool<sqlx::Postgres>, nexus_mod_id: i32) -> Mod {
+    let (db_mod, db_file) = insert_mod_and_file(pool, nexus_mod_id, "fixture.esp").await;
+    let mut plugin_buf = std::fs::read(fixture_path("fixture.esp")).unwrap();
+    process_plugin(
+        &mut plugin_buf,
+        pool,
+        &db_file,
+        &db_mod,
+        "fixture.esp",
+        SSE_GAME_NAME,
+    )
+    .await
+    .unwrap();
+    db_mod
+}
+
+#[tokio::test]
+async fn counts_mods_in_the_language_and_mods_without_one() {
+    use_temp_working_dir();
+    let docker = Cli::default();
+    let node = docker.run(postgres_image());
+    let pool = connect(&node).await;
+    let english = insert_mod_editing_cell(&pool, 1).await;
+    let german = insert_mod_editing_cell(&pool, 2).await;
+    let unknown = insert_mod_editing_cell(&pool, 3).await;
+    let updated = batched_update_from_graphql(
+        &pool,
+        english.game_id,
+        &[
+            graphql_mod(english.nexus_mod_id, Some("English")),
+            graphql_mod(german.nexus_mod_id, Some("German")),
+            graphql_mod(unknown.nexus_mod_id, None),
+        ],
+    )
+    .await
+    .unwrap();
+    assert_eq!(updated.len(), 3);
+
+    let (master, world_id): (String, i32) =
+        sqlx::query_as("SELECT master, world_id FROM cells WHERE x = 1 AND y = 2")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    let count = |language: Option<&'static str>| {
+        let pool = pool.clone();
+        let master = master.clone();
+        async move {
+            count_mod_edits(&pool, &master, world_id, 1, 2, true, true, None, language)
+                .await
+                .unwrap()
+        }
+    };
+    assert_eq!(count(None).await, Some(3));
+    // the mods in the language and the mod whose language isn't known
+    assert_eq!(count(Some("English")).await, Some(2));
+    assert_eq!(count(Some("German")).await, Some(2));
+    // only the mod whose language isn't known
+    assert_eq!(count(Some("French")).await, Some(1));
+
+    let mut mod_ids =
+        get_mod_ids_by_cell(&pool, &master, world_id, true, true, None, Some("German"))
+            .await
+            .unwrap()
+            .remove(0)
+            .mod_ids
+            .unwrap();
+    mod_ids.sort_unstable();
+    assert_eq!(mod_ids, vec![german.id, unknown.id]);
+}
@@ -0,0 +1,10 @@
+//! Tests for the names saved in `files.extractor_used`.
+use mod_mapper::extractors::ExtractorUsed;
+
+#[test]
+fn names_each_extractor() {
+    assert_eq!(ExtractorUsed::CompressTools.as_str(), "compress_tools");
+    assert_eq!(ExtractorUsed::SevenZip.as_str(), "7zip");
+    assert_eq!(ExtractorUsed::Unrar.as_str(), "unrar");
+    assert_eq!(ExtractorUsed::None.as_str(), "none");
+}
@@ -0,0 +1,196 @@
+//! Runs the plugin processing and extraction flows against a real postgres database over the
+//! fixture archives in `tests/fixtures` (regenerate them with `tests/fixtures/generate.py`).
+//!
+//! Requires docker to start the postgres container.
+mod common;
+
+use mod_mapper::extractors::{
+    extract_with_7zip, extract_with_compress_tools, extract_with_unrar, ExtractorUsed,
+};
+use mod_mapper::hooks::{self, Hooks};
+use mod_mapper::models::file::File;
+use mod_mapper::models::game_mod::Mod;
+use mod_mapper::nexus_api::SSE_GAME_NAME;
+use mod_mapper::plugin_processor::{process_plugin, ParsedPlugin};
+use std::sync::{Arc, Mutex};
+use testcontainers::clients::Cli;
+
+use common::{
+    connect, count_plugins, fixture_path, get_cell_coords, insert_mod_and_file, postgres_image,
+    use_temp_working_dir,
+};
+
+fn expected_cells() -> Vec<(Option<i32>, Option<i32>, String)> {
+    vec![(Some(1), Some(2), "fixture.esp".to_string())]
+}
+
+#[tokio::test]
+async fn process_plugin_saves_plugin_and_cells() {
+    use_temp_working_dir();
+    let docker = Cli::default();
+    let node = docker.run(postgres_image());
+    let pool = connect(&node).await;
+    let (db_mod, db_file) = insert_mod_and_file(&pool, 1, "fixture.esp").await;
+
+    let mut plugin_buf = std::fs::read(fixture_path("fixture.esp")).unwrap();
+    process_plugin(
+        &mut plugin_buf,
+        &pool,
+        &db_file,
+        &db_mod,
+        "fixture.esp",
+        SSE_GAME_NAME,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(count_plugins(&pool, db_file.id).await, 1);
+    assert_eq!(get_cell_coords(&pool, db_file.id).await, expected_cells());
+}
+
+#[tokio::test]
+async fn process_plugin_skips_unparseable_plugin() {
+    use_temp_working_dir();
+    let docker = Cli::default();
+    let node = docker.run(postgres_image());
+    let pool = connect(&node).await;
+    let (db_mod, db_file) = insert_mod_and_file(&pool, 2, "garbage.esp").await;
+
+    let mut plugin_buf = b"not a plugin".to_vec();
+    process_plugin(
+        &mut plugin_buf,
+        &pool,
+        &db_file,
+        &db_mod,
+        "garbage.esp",
+        SSE_GAME_NAME,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(count_plugins(&pool, db_file.id).await, 0);
+}
+
+/// Extracts the `fixture` archive saved under `nexus_mod_id` with `extractor` and checks that
+/// `plugin_count` plugins were saved. All the fixture archives hold the same `fixture.esp`, so its
+/// cells are expected whenever a plugin is extracted.
+async fn assert_extracts_fixture(
+    fixture: &str,
+    nexus_mod_id: i32,
+    extractor: ExtractorUsed,
+    plugin_count: i64,
+) {
+    use_temp_working_dir();
+    let docker = Cli::default();
+    let node = docker.run(postgres_image());
+    let pool = connect(&node).await;
+    let (db_mod, db_file) = insert_mod_and_file(&pool, nexus_mod_id, fixture).await;
+
+    let mut archive = std::fs::File::open(fixture_path(fixture)).unwrap();
+    match extractor {
+        ExtractorUsed::CompressTools => {
+            extract_with_compress_tools(&mut archive, &pool, &db_file, &db_mod, SSE_GAME_NAME).await
+        }
+        ExtractorUsed::SevenZip => {
+            extract_with_7zip(&mut archive, &pool, &db_file, &db_mod, SSE_GAME_NAME, false).await
+        }
+        ExtractorUsed::Unrar => {
+            extract_with_unrar(&mut archive, &pool, &db_file, &db_mod, SSE_GAME_NAME, false).await
+        }
+        ExtractorUsed::None => panic!("no extractor to extract {} with", fixture),
+    }
+    .unwrap();
+
+    assert_eq!(count_plugins(&pool, db_file.id).await, plugin_count);
+    let cells = if plugin_count > 0 {
+        expected_cells()
+    } else {
+        vec![]
+    };
+    assert_eq!(get_cell_coords(&pool, db_file.id).await, cells);
+}
+
+#[tokio::test]
+async fn compress_tools_extracts_plugin_from_zip() {
+    assert_extracts_fixture("fixture.zip", 3, ExtractorUsed::CompressTools, 1).await;
+}
+
+#[tokio::test]
+async fn seven_zip_extracts_plugin_from_zip() {
+    assert_extracts_fixture("fixture.zip", 4, ExtractorUsed::SevenZip, 1).await;
+}
+
+#[tokio::test]
+async fn nested_archives_are_not_extracted() {
+    assert_extracts_fixture("nested.zip", 5, ExtractorUsed::CompressTools, 0).await;
+}
+
+#[tokio::test]
+async fn compress_tools_extracts_plugin_from_7z() {
+    assert_extracts_fixture("fixture.7z", 7, ExtractorUsed::CompressTools, 1).await;
+}
+
+#[tokio::test]
+async fn seven_zip_extracts_plugin_from_7z() {
+    assert_extracts_fixture("fixture.7z", 8, ExtractorUsed::SevenZip, 1).await;
+}
+
+#[tokio::test]
+async fn unrar_extracts_plugin_from_rar() {
+    assert_extracts_fixture("fixture.rar", 9, ExtractorUsed::Unrar, 1).await;
+}
+
+#[tokio::test]
+async fn compress_tools_extracts_plugin_from_rar() {
+    assert_extracts_fixture("fixture.rar", 10, ExtractorUsed::CompressTools, 1).await;
+}
+
+#[tokio::test]
+async fn plugins_packed_in_bsas_are_not_extracted() {
+    // only the loose fixture.esp, not the packed.esp inside fixture.bsa
+    assert_extracts_fixture("bsa.zip", 11, ExtractorUsed::CompressTools, 1).await;
+}
+
+struct RecordingHooks {
+    parsed: Arc<Mutex<Vec<(i32, String)>>>,
+}
+
+impl Hooks for RecordingHooks {
+    fn on_plugin_parsed(&self, db_mod: &Mod, _db_file: &File, plugin: &ParsedPlugin) {
+        self.parsed
+            .lock()
+            .unwrap()
+            .push((db_mod.nexus_mod_id, plugin.file_name.clone()));
+    }
+}
+
+#[tokio::test]
+async fn process_plugin_calls_registered_hooks() {
+    use_temp_working_dir();
+    let docker = Cli::default();
+    let node = docker.run(postgres_image());
+    let pool = connect(&node).await;
+    let (db_mod, db_file) = insert_mod_and_file(&pool, 6, "fixture.esp").await;
+    let parsed = Arc::new(Mutex::new(vec![]));
+    hooks::register(RecordingHooks {
+        parsed: parsed.clone(),
+    });
+
+    let mut plugin_buf = std::fs::read(fixture_path("fixture.esp")).unwrap();
+    process_plugin(
+        &mut plugin_buf,
+        &pool,
+        &db_file,
+        &db_mod,
+        "fixture.esp",
+        SSE_GAME_NAME,
+    )
+    .await
+    .unwrap();
+
+    // hooks are global, so plugins parsed by the other tests may also be recorded
+    assert!(parsed
+        .lock()
+        .unwrap()
+        .contains(&(6, "fixture.esp".to_string())));
+}
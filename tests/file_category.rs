@@ -0,0 +1,63 @@
+//! Tests for normalizing the file categories in API responses.
+use mod_mapper::nexus_api::files::FileCategory;
+
+#[test]
+fn maps_category_ids() {
+    assert_eq!(FileCategory::from_id(1), Some(FileCategory::Main));
+    assert_eq!(FileCategory::from_id(4), Some(FileCategory::OldVersion));
+    assert_eq!(FileCategory::from_id(7), Some(FileCategory::Archived));
+    assert_eq!(FileCategory::from_id(0), None);
+}
+
+#[test]
+fn maps_spellings_of_category_names() {
+    assert_eq!(FileCategory::from_name("MAIN"), Some(FileCategory::Main));
+    assert_eq!(
+        FileCategory::from_name("Main Files"),
+        Some(FileCategory::Main)
+    );
+    assert_eq!(
+        FileCategory::from_name("OLD_VERSION"),
+        Some(FileCategory::OldVersion)
+    );
+    assert_eq!(
+        FileCategory::from_name("Old versions"),
+        Some(FileCategory::OldVersion)
+    );
+    assert_eq!(
+        FileCategory::from_name("Optional files"),
+        Some(FileCategory::Optional)
+    );
+    assert_eq!(
+        FileCategory::from_name("Miscellaneous"),
+        Some(FileCategory::Miscellaneous)
+    );
+}
+
+#[test]
+fn maps_localized_category_names() {
+    assert_eq!(
+        FileCategory::from_name("Hauptdateien"),
+        Some(FileCategory::Main)
+    );
+    assert_eq!(
+        FileCategory::from_name("Fichiers optionnels"),
+        Some(FileCategory::Optional)
+    );
+    assert_eq!(FileCategory::from_name("Something else"), None);
+}
+
+#[test]
+fn round_trips_through_the_column_value() {
+    for category in [
+        FileCategory::Main,
+        FileCategory::Update,
+        FileCategory::Optional,
+        FileCategory::OldVersion,
+        FileCategory::Miscellaneous,
+        FileCategory::Deleted,
+        FileCategory::Archived,
+    ] {
+        assert_eq!(FileCategory::from_name(category.as_str()), Some(category));
+    }
+}
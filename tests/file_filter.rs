@@ -0,0 +1,84 @@
+//! Tests for the save game and preset heuristics used to skip files before downloading them.
+use mod_mapper::file_filter::{skip_reason, SkipReason};
+use mod_mapper::nexus_api::metadata::ContentPreviewEntry;
+
+fn file(name: &str) -> ContentPreviewEntry {
+    ContentPreviewEntry {
+        name: name.to_string(),
+        is_directory: false,
+        size: None,
+        children: vec![],
+    }
+}
+
+fn directory(name: &str, children: Vec<ContentPreviewEntry>) -> ContentPreviewEntry {
+    ContentPreviewEntry {
+        name: name.to_string(),
+        is_directory: true,
+        size: None,
+        children,
+    }
+}
+
+#[test]
+fn skips_archive_of_saves() {
+    let content_preview = vec![
+        file("Save 12 - Lydia.ess"),
+        file("Save 12 - Lydia.skse"),
+        file("readme.txt"),
+    ];
+    assert_eq!(
+        skip_reason(
+            "My Character",
+            "My Character-1-0.zip",
+            Some(&content_preview)
+        ),
+        Some(SkipReason::SaveGame)
+    );
+}
+
+#[test]
+fn skips_archive_of_bodyslide_presets() {
+    let content_preview = vec![directory(
+        "CalienteTools",
+        vec![directory(
+            "BodySlide",
+            vec![directory("SliderPresets", vec![file("Curvy.xml")])],
+        )],
+    )];
+    assert_eq!(
+        skip_reason("Curvy", "Curvy-1-0.7z", Some(&content_preview)),
+        Some(SkipReason::Preset)
+    );
+}
+
+#[test]
+fn does_not_skip_archive_with_plugin() {
+    let content_preview = vec![file("Save 12 - Lydia.ess"), file("Lydia Follower.esp")];
+    assert_eq!(
+        skip_reason("Lydia savegame", "Lydia-1-0.zip", Some(&content_preview)),
+        None
+    );
+}
+
+#[test]
+fn does_not_skip_xml_outside_preset_folders() {
+    let content_preview = vec![directory("Interface", vec![file("config.xml")])];
+    assert_eq!(
+        skip_reason("UI", "UI-1-0.zip", Some(&content_preview)),
+        None
+    );
+}
+
+#[test]
+fn falls_back_to_names_without_content_preview() {
+    assert_eq!(
+        skip_reason("Level 50 Savegame", "Level 50-1-0.zip", None),
+        Some(SkipReason::SaveGame)
+    );
+    assert_eq!(
+        skip_reason("Lydia", "Lydia.jslot", None),
+        Some(SkipReason::Preset)
+    );
+    assert_eq!(skip_reason("Main File", "Main File-1-0.zip", None), None);
+}
@@ -0,0 +1,203 @@
+#!/usr/bin/env python3
+"""Generates the fixture plugin and archives used by the integration tests.
+
+The plugin is a minimal Skyrim SE plugin with a Tamriel WRLD override and one new exterior CELL at
+1, 2, plus one new and one overridden NPC_, one new QUST, and one new DIAL (with an INFO child). Re-run this from the repository root after changing it: `python3 tests/fixtures/generate.py`
+"""
+import io
+import os
+import struct
+import zipfile
+import zlib
+
+FIXTURES_DIR = os.path.dirname(os.path.abspath(__file__))
+TAMRIEL_FORM_ID = 0x0000003C
+CELL_FORM_ID = 0x01000800
+NPC_FORM_ID = 0x01000801
+PLAYER_FORM_ID = 0x00000007
+QUST_FORM_ID = 0x01000802
+DIAL_FORM_ID = 0x01000803
+INFO_FORM_ID = 0x01000804
+CELL_X = 1
+CELL_Y = 2
+
+
+def subrecord(record_type, data):
+    return record_type + struct.pack("<H", len(data)) + data
+
+
+def record(record_type, form_id, data, flags=0):
+    return record_type + struct.pack("<IIIHHHH", len(data), flags, form_id, 0, 0, 44, 0) + data
+
+
+def group(label, group_type, contents):
+    return b"GRUP" + struct.pack("<I", 24 + len(contents)) + label + struct.pack("<iHHI", group_type, 0, 0, 0) + contents
+
+
+def plugin():
+    header = record(
+        b"TES4",
+        0,
+        subrecord(b"HEDR", struct.pack("<fiI", 1.7, 7, 0x805))
+        + subrecord(b"CNAM", b"modmapper\0")
+        + subrecord(b"SNAM", b"modmapper test fixture\0")
+        + subrecord(b"MAST", b"Skyrim.esm\0")
+        + subrecord(b"DATA", struct.pack("<Q", 0)),
+    )
+    world = record(b"WRLD", TAMRIEL_FORM_ID, subrecord(b"EDID", b"Tamriel\0"))
+    cell = record(
+        b"CELL",
+        CELL_FORM_ID,
+        subrecord(b"EDID", b"ModmapperFixtureCell\0")
+        + subrecord(b"DATA", struct.pack("<H", 0))
+        + subrecord(b"XCLC", struct.pack("<iiI", CELL_X, CELL_Y, 0)),
+    )
+    sub_block = group(struct.pack("<hh", CELL_Y >> 3, CELL_X >> 3), 5, cell)
+    block = group(struct.pack("<hh", CELL_Y >> 5, CELL_X >> 5), 4, sub_block)
+    world_children = group(struct.pack("<I", TAMRIEL_FORM_ID), 1, block)
+    npcs = group(
+        b"NPC_",
+        0,
+        record(b"NPC_", NPC_FORM_ID, subrecord(b"EDID", b"ModmapperFixtureNPC\0"))
+        + record(b"NPC_", PLAYER_FORM_ID, subrecord(b"EDID", b"Player\0")),
+    )
+    quests = group(b"QUST", 0, record(b"QUST", QUST_FORM_ID, subrecord(b"EDID", b"ModmapperFixtureQuest\0")))
+    topic = record(b"DIAL", DIAL_FORM_ID, subrecord(b"EDID", b"ModmapperFixtureTopic\0"))
+    topic_children = group(struct.pack("<I", DIAL_FORM_ID), 7, record(b"INFO", INFO_FORM_ID, b""))
+    dialogues = group(b"DIAL", 0, topic + topic_children)
+    return header + npcs + quests + group(b"WRLD", 0, world + world_children) + dialogues
+
+
+def zip_bytes(entries):
+    buf = io.BytesIO()
+    with zipfile.ZipFile(buf, "w", zipfile.ZIP_DEFLATED) as archive:
+        for name, data in entries:
+            # Fixed timestamps keep the generated archives byte-for-byte reproducible
+            info = zipfile.ZipInfo(name, date_time=(2023, 1, 1, 0, 0, 0))
+            info.compress_type = zipfile.ZIP_DEFLATED
+            archive.writestr(info, data)
+    return buf.getvalue()
+
+
+def seven_zip_number(value):
+    for extra_bytes in range(8):
+        if value < 1 << (7 * (extra_bytes + 1)):
+            first = (0xFF << (8 - extra_bytes)) & 0xFF | value >> (8 * extra_bytes)
+            low = value & ((1 << (8 * extra_bytes)) - 1)
+            return bytes([first]) + low.to_bytes(extra_bytes, "little")
+    return b"\xff" + value.to_bytes(8, "little")
+
+
+def seven_zip_bytes(entries):
+    """A 7z archive with one folder of uncompressed (Copy method) streams, one per entry"""
+    n = seven_zip_number
+    packed = b"".join(data for _, data in entries)
+    names = b"".join(name.encode("utf-16-le") + b"\0\0" for name, _ in entries)
+    header = (
+        b"\x01"  # Header
+        + b"\x04"  # MainStreamsInfo
+        + b"\x06" + n(0) + n(1) + b"\x09" + n(len(packed)) + b"\x00"  # PackInfo
+        + b"\x07\x0b" + n(1) + b"\x00" + n(1) + b"\x01\x00"  # UnpackInfo with a Copy coder
+        + b"\x0c" + n(len(packed)) + b"\x00"
+        + b"\x08\x0d" + n(len(entries))  # SubStreamsInfo
+        + b"\x09" + b"".join(n(len(data)) for _, data in entries[:-1])
+        + b"\x0a\x01" + b"".join(struct.pack("<I", zlib.crc32(data)) for _, data in entries)
+        + b"\x00"
+        + b"\x00"
+        + b"\x05" + n(len(entries))  # FilesInfo
+        + b"\x11" + n(len(names) + 1) + b"\x00" + names
+        + b"\x00"
+        + b"\x00"
+    )
+    start_header = struct.pack("<QQI", len(packed), len(header), zlib.crc32(header))
+    signature = b"7z\xbc\xaf\x27\x1c\x00\x04" + struct.pack("<I", zlib.crc32(start_header))
+    return signature + start_header + packed + header
+
+
+def rar_block(block_type, flags, fields):
+    """A RAR 4 block, whose CRC is the low 16 bits of the CRC32 of the rest of the header"""
+    rest = struct.pack("<BHH", block_type, flags, 7 + len(fields)) + fields
+    return struct.pack("<H", zlib.crc32(rest) & 0xFFFF) + rest
+
+
+def rar_bytes(entries):
+    """A RAR 4 archive of uncompressed (Store method) entries"""
+    dos_time = ((2023 - 1980) << 9 | 1 << 5 | 1) << 16
+    blocks = [b"Rar!\x1a\x07\x00", rar_block(0x73, 0, struct.pack("<HI", 0, 0))]
+    for name, data in entries:
+        encoded_name = name.encode("ascii")
+        fields = struct.pack(
+            "<IIBIIBBHI",
+            len(data),
+            len(data),
+            0,  # MS-DOS host
+            zlib.crc32(data),
+            dos_time,
+            20,  # unpacks with RAR 2.0
+            0x30,  # Store
+            len(encoded_name),
+            0x20,  # archive attribute
+        )
+        # 0x8000: the block is followed by its packed data
+        blocks.append(rar_block(0x74, 0x8000, fields + encoded_name) + data)
+    blocks.append(rar_block(0x7B, 0x4000, b""))
+    return b"".join(blocks)
+
+
+def bsa_hash(path):
+    """The hash BSAs look up folders and files by"""
+    root, ext = os.path.splitext(path.lower().replace("/", "\\"))
+    root, ext = root.encode("ascii"), ext.encode("ascii")
+    hash_low = root[-1] | (root[-2] << 8 if len(root) > 2 else 0) | len(root) << 16 | root[0] << 24
+    hash_low |= {b".kf": 0x80, b".nif": 0x8000, b".dds": 0x8080, b".wav": 0x80000000}.get(ext, 0)
+    hash_high = 0
+    for c in root[1:-2]:
+        hash_high = (hash_high * 0x1003F + c) & 0xFFFFFFFF
+    ext_hash = 0
+    for c in ext:
+        ext_hash = (ext_hash * 0x1003F + c) & 0xFFFFFFFF
+    return ((hash_high + ext_hash) & 0xFFFFFFFF) << 32 | hash_low
+
+
+def bsa_bytes(folder, entries):
+    """An uncompressed Skyrim SE (version 105) BSA of entries in one folder"""
+    folder_name = folder.encode("ascii") + b"\0"
+    file_names = b"".join(name.encode("ascii") + b"\0" for name, _ in entries)
+    header_size = 36
+    folder_records_size = 24
+    file_records_offset = header_size + folder_records_size
+    file_records_size = 1 + len(folder_name) + 16 * len(entries)
+    data_offset = file_records_offset + file_records_size + len(file_names)
+    file_records = b""
+    data = b""
+    for name, file_data in entries:
+        file_records += struct.pack("<QII", bsa_hash(name), len(file_data), data_offset + len(data))
+        data += file_data
+    return (
+        b"BSA\0"
+        + struct.pack("<IIIIIIIHH", 105, header_size, 0x3, 1, len(entries), len(folder_name), len(file_names), 0, 0)
+        + struct.pack("<QIIQ", bsa_hash(folder), len(entries), 0, file_records_offset + len(file_names))
+        + bytes([len(folder_name)])
+        + folder_name
+        + file_records
+        + file_names
+        + data
+    )
+
+
+def write(name, data):
+    with open(os.path.join(FIXTURES_DIR, name), "wb") as f:
+        f.write(data)
+
+
+if __name__ == "__main__":
+    plugin_bytes = plugin()
+    plugin_zip = zip_bytes([("fixture.esp", plugin_bytes), ("readme.txt", b"fixture\n")])
+    write("fixture.esp", plugin_bytes)
+    write("fixture.zip", plugin_zip)
+    write("nested.zip", zip_bytes([("fixture.zip", plugin_zip)]))
+    write("fixture.7z", seven_zip_bytes([("fixture.esp", plugin_bytes), ("readme.txt", b"fixture\n")]))
+    write("fixture.rar", rar_bytes([("fixture.esp", plugin_bytes), ("readme.txt", b"fixture\n")]))
+    # Plugins packed in a BSA aren't loaded by the game, so only the loose plugin should be found
+    bsa = bsa_bytes("plugins", [("packed.esp", plugin_bytes)])
+    write("bsa.zip", zip_bytes([("fixture.esp", plugin_bytes), ("fixture.bsa", bsa)]))
@@ -0,0 +1,108 @@
+//! Tests for converting between cells, worldspace units, and map tiles.
+use mod_mapper::grid::{
+    cell_bounds, cell_grid, cell_to_tile, image_cell, is_on_map, parse_cell_path, tile_bounds,
+    tile_cells, tiles_per_side, world_to_cell, world_to_tile, Tile, WorldBounds, MIN_CELL_X,
+    ZOOM_LEVELS,
+};
+
+#[test]
+fn converts_between_cells_and_worldspace_units() {
+    assert_eq!(
+        cell_bounds(-3, 12),
+        WorldBounds {
+            left: -12288,
+            bottom: 49152,
+            right: -8192,
+            top: 53248,
+        }
+    );
+    assert_eq!(world_to_cell(0, 0), (0, 0));
+    assert_eq!(world_to_cell(4095, -1), (0, -1));
+    assert_eq!(world_to_cell(-4096, -4097), (-1, -2));
+}
+
+#[test]
+fn finds_the_tile_of_a_cell() {
+    // zoom 10 is four tiles, one for each quadrant of the map around cell (0, 0)
+    assert_eq!(cell_to_tile(10, 0, 0), Some(Tile { z: 10, x: 1, y: 0 }));
+    assert_eq!(cell_to_tile(10, -1, -1), Some(Tile { z: 10, x: 0, y: 1 }));
+    assert_eq!(cell_to_tile(10, -64, 63), Some(Tile { z: 10, x: 0, y: 0 }));
+    assert_eq!(
+        cell_to_tile(17, 0, 0),
+        Some(Tile {
+            z: 17,
+            x: 128,
+            y: 127
+        })
+    );
+    assert_eq!(cell_to_tile(10, 64, 0), None);
+    assert_eq!(cell_to_tile(9, 0, 0), None);
+    assert_eq!(world_to_tile(10, 0, 262144), None);
+    assert_eq!(
+        world_to_tile(10, 0, 262143),
+        Some(Tile { z: 10, x: 1, y: 0 })
+    );
+}
+
+#[test]
+fn tiles_cover_the_cells_they_contain() {
+    for z in ZOOM_LEVELS {
+        for tile in [
+            Tile { z, x: 0, y: 0 },
+            Tile {
+                z,
+                x: tiles_per_side(z) - 1,
+                y: tiles_per_side(z) / 2,
+            },
+        ] {
+            let bounds = tile_bounds(tile);
+            assert_eq!(world_to_tile(z, bounds.left, bounds.bottom), Some(tile));
+            assert_eq!(
+                world_to_tile(z, bounds.right - 1, bounds.top - 1),
+                Some(tile)
+            );
+            let ((min_x, min_y), (max_x, max_y)) = tile_cells(tile);
+            assert!(is_on_map(min_x, min_y) && is_on_map(max_x, max_y));
+        }
+    }
+    assert_eq!(tile_cells(Tile { z: 10, x: 1, y: 0 }), ((0, 0), (63, 63)));
+    assert_eq!(
+        tile_cells(Tile {
+            z: 17,
+            x: 128,
+            y: 127
+        }),
+        ((0, 0), (0, 0))
+    );
+}
+
+#[test]
+fn lays_out_images_with_north_up() {
+    assert_eq!(image_cell((-5, 10), 0, 0), (-5, 10));
+    assert_eq!(image_cell((-5, 10), 2, 3), (-3, 7));
+}
+
+#[test]
+fn describes_cells_on_the_map() {
+    assert_eq!(parse_cell_path("-3/12"), Some((-3, 12)));
+    assert_eq!(parse_cell_path("-3"), None);
+    assert_eq!(parse_cell_path("a/12"), None);
+
+    let grid = cell_grid(-3, 12);
+    assert_eq!(grid.bounds, cell_bounds(-3, 12));
+    assert_eq!(grid.tiles.len(), ZOOM_LEVELS.len());
+    assert!(grid
+        .tiles
+        .iter()
+        .zip(ZOOM_LEVELS)
+        .all(|(tile, z)| tile.z == z));
+}
+
+#[test]
+fn cells_off_the_map_have_bounds_but_no_tiles() {
+    assert!(!is_on_map(MIN_CELL_X, 0));
+    let grid = cell_grid(MIN_CELL_X, 0);
+    assert_eq!(grid.bounds, cell_bounds(MIN_CELL_X, 0));
+    assert!(grid.tiles.is_empty());
+    assert!(cell_grid(100, 0).tiles.is_empty());
+}
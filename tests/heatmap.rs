@@ -0,0 +1,71 @@
+//! Tests for rendering mod edit heatmaps of the cells around a cell.
+use mod_mapper::heatmap::{
+    color, parse_heatmap_path, render, Region, CELL_SIZE, DEFAULT_RADIUS, MAX_RADIUS,
+};
+use std::collections::HashMap;
+
+#[test]
+fn parses_heatmap_paths() {
+    assert_eq!(
+        parse_heatmap_path("-3/12.png", None),
+        Some(Region {
+            x: -3,
+            y: 12,
+            radius: DEFAULT_RADIUS
+        })
+    );
+    assert_eq!(
+        parse_heatmap_path("0/0.png", Some("foo=bar&radius=2")),
+        Some(Region {
+            x: 0,
+            y: 0,
+            radius: 2
+        })
+    );
+    assert_eq!(parse_heatmap_path("0/0.jpg", None), None);
+    assert_eq!(parse_heatmap_path("0.png", None), None);
+    assert_eq!(parse_heatmap_path("a/0.png", None), None);
+    assert_eq!(parse_heatmap_path("0/0.png", Some("radius=-1")), None);
+    let too_big = format!("radius={}", MAX_RADIUS + 1);
+    assert_eq!(parse_heatmap_path("0/0.png", Some(&too_big)), None);
+}
+
+#[test]
+fn hotter_cells_are_redder() {
+    assert_eq!(color(0, 10), color(0, 0));
+    let cold = color(1, 100);
+    let warm = color(10, 100);
+    let hot = color(100, 100);
+    assert!(cold[1] > warm[1] && warm[1] > hot[1]);
+    assert_eq!(color(500, 100), hot);
+    assert_eq!(color(1, 1), hot);
+}
+
+#[test]
+fn renders_a_png_of_the_region() {
+    let region = Region {
+        x: 2,
+        y: -1,
+        radius: 1,
+    };
+    let mut counts = HashMap::new();
+    // top left cell, since north is up
+    counts.insert((1, 0), 10);
+    let bytes = render(region, &counts).unwrap();
+
+    let decoder = png::Decoder::new(bytes.as_slice());
+    let mut reader = decoder.read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+    assert_eq!(info.width, 3 * CELL_SIZE);
+    assert_eq!(info.height, 3 * CELL_SIZE);
+
+    let pixel = |px: u32, py: u32| {
+        let index = ((py * info.width + px) * 3) as usize;
+        [pixels[index], pixels[index + 1], pixels[index + 2]]
+    };
+    assert_eq!(pixel(1, 1), color(10, 10));
+    assert_eq!(pixel(CELL_SIZE * 2 + 1, CELL_SIZE * 2 + 1), color(0, 10));
+    // the center cell has a white border
+    assert_eq!(pixel(CELL_SIZE, CELL_SIZE), [255, 255, 255]);
+}
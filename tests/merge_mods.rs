@@ -0,0 +1,84 @@
+//! Tests for merging duplicate mod rows, including the plugins they saved to disk.
+//!
+//! Requires docker to start the postgres container.
+mod common;
+
+use chrono::NaiveDate;
+use mod_mapper::commands::merge_mods;
+use mod_mapper::models::file::{self, File, UnsavedFile};
+use mod_mapper::models::game_mod::Mod;
+use mod_mapper::nexus_api::files::FileCategory;
+use mod_mapper::nexus_api::SSE_GAME_NAME;
+use mod_mapper::plugin_processor::process_plugin;
+use mod_mapper::plugin_storage::{relative_path, resolve};
+use testcontainers::clients::Cli;
+
+use common::{connect, fixture_path, insert_mod_and_file, postgres_image, use_temp_working_dir};
+
+async fn save_fixture_plugin(pool: &sqlx::Pool<sqlx::Postgres>, db_mod: &Mod, db_file: &File) {
+    let mut plugin_buf = std::fs::read(fixture_path("fixture.esp")).unwrap();
+    process_plugin(
+        &mut plugin_buf,
+        pool,
+        db_file,
+        db_mod,
+        "fixture.esp",
+        SSE_GAME_NAME,
+    )
+    .await
+    .unwrap();
+}
+
+fn plugin_path(nexus_mod_id: i32, nexus_file_id: i32) -> std::path::PathBuf {
+    relative_path(SSE_GAME_NAME, nexus_mod_id, nexus_file_id, "fixture.esp")
+}
+
+#[tokio::test]
+async fn merging_mods_that_share_a_plugin_moves_the_removed_mods_plugins() {
+    use_temp_working_dir();
+    let docker = Cli::default();
+    let node = docker.run(postgres_image());
+    let pool = connect(&node).await;
+    let (kept_mod, kept_file) = insert_mod_and_file(&pool, 1, "fixture.zip").await;
+    let (removed_mod, removed_file) = insert_mod_and_file(&pool, 2, "fixture.zip").await;
+    // the removed mod also has the kept mod's file, with the same plugin
+    let duplicate_file = file::insert(
+        &pool,
+        &UnsavedFile {
+            name: "Fixture File",
+            file_name: "fixture.zip",
+            nexus_file_id: kept_file.nexus_file_id,
+            mod_id: removed_mod.id,
+            category: Some("MAIN"),
+            normalized_category: Some(FileCategory::Main),
+            version: None,
+            mod_version: None,
+            size: 0,
+            uploaded_at: NaiveDate::from_ymd_opt(2023, 1, 1)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap(),
+        },
+    )
+    .await
+    .unwrap();
+    save_fixture_plugin(&pool, &kept_mod, &kept_file).await;
+    save_fixture_plugin(&pool, &removed_mod, &removed_file).await;
+    save_fixture_plugin(&pool, &removed_mod, &duplicate_file).await;
+    assert!(resolve(None, &plugin_path(2, duplicate_file.nexus_file_id)).is_some());
+
+    merge_mods(&pool, kept_mod.id, removed_mod.id)
+        .await
+        .unwrap();
+
+    let plugin_count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM plugins WHERE mod_id = $1")
+        .bind(kept_mod.id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(plugin_count, 2);
+    assert!(resolve(None, &plugin_path(1, kept_file.nexus_file_id)).is_some());
+    assert!(resolve(None, &plugin_path(1, removed_file.nexus_file_id)).is_some());
+    assert!(resolve(None, &plugin_path(2, removed_file.nexus_file_id)).is_none());
+    assert!(resolve(None, &plugin_path(2, kept_file.nexus_file_id)).is_none());
+}
@@ -0,0 +1,21 @@
+//! Tests for pausing the background metadata refresh to leave API quota to updates.
+use chrono::{Duration, TimeZone, Utc};
+use mod_mapper::commands::refresh_metadata::{quota_wait, QUOTA_RESERVE};
+
+#[test]
+fn waits_for_reset_when_quota_is_down_to_reserve() {
+    let now = Utc.with_ymd_and_hms(2023, 12, 1, 12, 30, 0).unwrap();
+    let reset = now + Duration::minutes(30);
+    assert_eq!(
+        quota_wait(Some((QUOTA_RESERVE, reset)), now),
+        Some(std::time::Duration::from_secs(30 * 60))
+    );
+    assert_eq!(quota_wait(Some((QUOTA_RESERVE + 1, reset)), now), None);
+}
+
+#[test]
+fn does_not_wait_for_unknown_or_past_quota() {
+    let now = Utc.with_ymd_and_hms(2023, 12, 1, 12, 30, 0).unwrap();
+    assert_eq!(quota_wait(None, now), None);
+    assert_eq!(quota_wait(Some((0, now - Duration::minutes(1))), now), None);
+}
@@ -0,0 +1,21 @@
+//! Tests for the shape of the mod cell counts dump.
+use mod_mapper::commands::dump_mod_cell_counts::CellCounts;
+use mod_mapper::models::game_mod::ModCellCount;
+use serde_json::json;
+
+#[test]
+fn writes_exterior_counts_unless_split_by_type() {
+    let mod_cell_count = ModCellCount {
+        nexus_mod_id: 1,
+        cells: Some(3),
+        interior_cells: Some(40),
+    };
+    assert_eq!(
+        serde_json::to_value(CellCounts::new(&mod_cell_count, false)).unwrap(),
+        json!(3)
+    );
+    assert_eq!(
+        serde_json::to_value(CellCounts::new(&mod_cell_count, true)).unwrap(),
+        json!({ "exterior": 3, "interior": 40 })
+    );
+}
@@ -0,0 +1,58 @@
+//! Tests for grouping mods into families for the deduplicated cell edit counts.
+use mod_mapper::commands::dump_cell_edit_counts::mod_families;
+use mod_mapper::models::plugin::PluginForFamily;
+
+fn plugin(mod_id: i32, hash: i64, file_name: &str, masters: &[&str]) -> PluginForFamily {
+    PluginForFamily {
+        mod_id,
+        hash,
+        file_name: file_name.to_string(),
+        masters: masters.iter().map(|master| master.to_string()).collect(),
+        is_patch: false,
+        is_translation: false,
+        is_official: false,
+    }
+}
+
+#[test]
+fn groups_reuploads_translations_and_patches() {
+    let plugins = vec![
+        plugin(1, 0, "skyrim.esm", &[]),
+        plugin(2, 10, "foo.esp", &["Skyrim.esm"]),
+        plugin(3, 20, "bar.esp", &["Skyrim.esm"]),
+        // reupload of foo
+        plugin(4, 10, "foo.esp", &["Skyrim.esm"]),
+        // translation of bar
+        PluginForFamily {
+            is_translation: true,
+            ..plugin(5, 30, "bar.esp", &["Skyrim.esm"])
+        },
+        // patch between foo and bar
+        PluginForFamily {
+            is_patch: true,
+            ..plugin(
+                6,
+                40,
+                "foo - bar patch.esp",
+                &["Skyrim.esm", "Foo.esp", "Bar.esp"],
+            )
+        },
+        // unrelated mod that depends on the base game
+        plugin(7, 50, "baz.esp", &["Skyrim.esm"]),
+    ];
+    let plugins = plugins
+        .into_iter()
+        .map(|plugin| PluginForFamily {
+            is_official: plugin.mod_id == 1,
+            ..plugin
+        })
+        .collect::<Vec<_>>();
+    let families = mod_families(&plugins);
+    assert_eq!(families.get(&1), None);
+    assert_eq!(families[&2], 2);
+    assert_eq!(families[&3], 3);
+    assert_eq!(families[&4], 2);
+    assert_eq!(families[&5], 3);
+    assert_eq!(families[&6], 2);
+    assert_eq!(families[&7], 7);
+}
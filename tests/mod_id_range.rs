@@ -0,0 +1,30 @@
+//! Tests for parsing the `--mod-id-range` bounds of an update.
+use mod_mapper::commands::ModIdRange;
+
+#[test]
+fn parses_inclusive_ranges() {
+    let range: ModIdRange = "1000-2000".parse().unwrap();
+    assert_eq!(
+        range,
+        ModIdRange {
+            start: 1000,
+            end: 2000
+        }
+    );
+    assert!(range.contains(1000));
+    assert!(range.contains(2000));
+    assert!(!range.contains(999));
+    assert!(!range.contains(2001));
+    assert_eq!(
+        "5-5".parse::<ModIdRange>(),
+        Ok(ModIdRange { start: 5, end: 5 })
+    );
+}
+
+#[test]
+fn rejects_invalid_ranges() {
+    assert!("1000".parse::<ModIdRange>().is_err());
+    assert!("2000-1000".parse::<ModIdRange>().is_err());
+    assert!("a-b".parse::<ModIdRange>().is_err());
+    assert!("1000-".parse::<ModIdRange>().is_err());
+}
@@ -0,0 +1,28 @@
+//! Tests for the sort passed through to the scraped mod list.
+use mod_mapper::nexus_scraper::{mod_list_url, ModsSortField, SortDirection};
+
+#[test]
+fn sorts_the_mod_list_by_last_update_for_update() {
+    let url = mod_list_url(
+        3,
+        1704,
+        false,
+        ModsSortField::UpdatedAt,
+        SortDirection::Desc,
+    );
+    assert!(url.contains("game_id:1704,"));
+    assert!(url.contains("tags_no%5B%5D:1428,"));
+    assert!(url.contains("page:3,"));
+    assert!(url.ends_with(",sort_by:lastupdate,order:DESC"));
+}
+
+#[test]
+fn passes_through_sort_and_direction() {
+    let url = mod_list_url(1, 1704, true, ModsSortField::CreatedAt, SortDirection::Asc);
+    assert!(url.contains("tags_yes%5B%5D:1428,"));
+    assert!(url.ends_with(",sort_by:date,order:ASC"));
+    assert!(
+        mod_list_url(1, 1704, true, ModsSortField::Downloads, SortDirection::Desc)
+            .ends_with(",sort_by:downloads,order:DESC")
+    );
+}
@@ -0,0 +1,40 @@
+//! Tests for matching mods to their ports in other games by author and name.
+use mod_mapper::commands::match_mod_ports::{match_by_author_and_name, normalize_mod_name};
+use mod_mapper::models::game_mod::ModForPortMatching;
+
+fn game_mod(id: i32, name: &str, author_id: i32) -> ModForPortMatching {
+    ModForPortMatching {
+        id,
+        name: name.to_string(),
+        author_id,
+    }
+}
+
+#[test]
+fn strips_edition_words_and_punctuation() {
+    assert_eq!(normalize_mod_name("Immersive Armors"), "immersive armors");
+    assert_eq!(
+        normalize_mod_name("Immersive Armors SSE"),
+        "immersive armors"
+    );
+    assert_eq!(
+        normalize_mod_name("Immersive Armors - Special Edition Port"),
+        "immersive armors"
+    );
+    assert_eq!(normalize_mod_name("SSE"), "");
+}
+
+#[test]
+fn matches_same_author_and_normalized_name() {
+    let originals = vec![
+        game_mod(1, "Immersive Armors", 10),
+        game_mod(2, "Cutting Room Floor", 20),
+        game_mod(3, "SE", 30),
+    ];
+    let ports = vec![
+        game_mod(101, "Immersive Armors SE", 10),
+        game_mod(102, "Cutting Room Floor", 21),
+        game_mod(103, "Port", 30),
+    ];
+    assert_eq!(match_by_author_and_name(&originals, &ports), vec![(1, 101)]);
+}
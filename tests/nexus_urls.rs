@@ -0,0 +1,103 @@
+//! Tests for the Nexus links added to dumped mods and files.
+mod common;
+
+use mod_mapper::models::file;
+use mod_mapper::models::game_mod::{self, add_file_urls};
+use mod_mapper::nexus_api::{file_url, mod_url, SSE_GAME_NAME};
+use serde_json::json;
+use testcontainers::clients::Cli;
+
+use common::{connect, insert_mod_and_file, postgres_image};
+
+#[test]
+fn links_to_the_mod_and_file_on_their_own_game_domain() {
+    assert_eq!(
+        mod_url("enderalspecialedition", 123),
+        "https://www.nexusmods.com/enderalspecialedition/mods/123"
+    );
+    assert_eq!(
+        file_url("skyrimspecialedition", 123, 456),
+        "https://www.nexusmods.com/skyrimspecialedition/mods/123?tab=files&file_id=456"
+    );
+}
+
+#[test]
+fn adds_a_link_to_each_aggregated_file() {
+    let files = json!([
+        { "nexus_file_id": 1, "name": "Main" },
+        { "name": "No id" },
+    ]);
+    assert_eq!(
+        add_file_urls(files, "skyrim", 10),
+        json!([
+            {
+                "nexus_file_id": 1,
+                "name": "Main",
+                "nexus_url": "https://www.nexusmods.com/skyrim/mods/10?tab=files&file_id=1",
+            },
+            { "name": "No id" },
+        ])
+    );
+}
+
+#[tokio::test]
+async fn dumped_mods_and_files_have_their_links() {
+    let docker = Cli::default();
+    let node = docker.run(postgres_image());
+    let pool = connect(&node).await;
+    let (db_mod, db_file) = insert_mod_and_file(&pool, 20, "fixture.zip").await;
+
+    let files = file::batched_get_with_cells(&pool, 10, None, "Skyrim.esm", 1, None)
+        .await
+        .unwrap();
+    assert_eq!(files.len(), 1);
+    let expected_file_url = file_url(SSE_GAME_NAME, db_mod.nexus_mod_id, db_file.nexus_file_id);
+    assert_eq!(files[0].nexus_url, Some(expected_file_url.clone()));
+    assert_eq!(
+        serde_json::to_value(&files[0]).unwrap()["nexus_url"],
+        json!(expected_file_url)
+    );
+
+    let mods = game_mod::batched_get_with_cells_and_files(&pool, 10, None, "Skyrim.esm", 1, None)
+        .await
+        .unwrap();
+    assert_eq!(mods.len(), 1);
+    let mod_json = serde_json::to_value(&mods[0]).unwrap();
+    assert_eq!(
+        mod_json["nexus_url"],
+        json!(mod_url(SSE_GAME_NAME, db_mod.nexus_mod_id))
+    );
+    assert_eq!(mod_json["files"][0]["nexus_url"], json!(expected_file_url));
+}
+
+#[tokio::test]
+async fn dumped_official_mods_and_files_have_no_links() {
+    let docker = Cli::default();
+    let node = docker.run(postgres_image());
+    let pool = connect(&node).await;
+    let (db_mod, _) = insert_mod_and_file(&pool, -20, "fixture.zip").await;
+    sqlx::query("UPDATE mods SET is_official = true WHERE id = $1")
+        .bind(db_mod.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let files = file::batched_get_with_cells(&pool, 10, None, "Skyrim.esm", 1, None)
+        .await
+        .unwrap();
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].nexus_url, None);
+    assert_eq!(
+        serde_json::to_value(&files[0]).unwrap()["nexus_url"],
+        json!(null)
+    );
+
+    let mods = game_mod::batched_get_with_cells_and_files(&pool, 10, None, "Skyrim.esm", 1, None)
+        .await
+        .unwrap();
+    assert_eq!(mods.len(), 1);
+    let mod_json = serde_json::to_value(&mods[0]).unwrap();
+    assert_eq!(mod_json["nexus_url"], json!(null));
+    assert_eq!(mod_json["files"].as_array().unwrap().len(), 1);
+    assert!(mod_json["files"][0].get("nexus_url").is_none());
+}
@@ -0,0 +1,58 @@
+//! Tests for diffing the cells edited by two versions of a plugin.
+use mod_mapper::commands::diff_plugins::{diff_cells, parse_hash, ChangedCell};
+use mod_mapper::models::cell::PluginCellForDiff;
+use mod_mapper::models::format_radix;
+
+fn cell(cell_id: i32, editor_id: Option<&str>) -> PluginCellForDiff {
+    PluginCellForDiff {
+        cell_id,
+        editor_id: editor_id.map(str::to_string),
+        form_id: cell_id,
+        master: "Skyrim.esm".to_string(),
+        x: Some(cell_id),
+        y: Some(-cell_id),
+        is_persistent: false,
+        world_form_id: Some(0x3c),
+        world_master: Some("Skyrim.esm".to_string()),
+    }
+}
+
+#[test]
+fn finds_added_removed_and_changed_cells() {
+    let old = vec![
+        cell(3, Some("Riverwood")),
+        cell(1, None),
+        cell(2, Some("Old")),
+    ];
+    let new = vec![
+        cell(4, None),
+        cell(2, Some("New")),
+        cell(3, Some("Riverwood")),
+    ];
+    let diff = diff_cells(old, new);
+    assert_eq!(diff.added, vec![cell(4, None)]);
+    assert_eq!(diff.removed, vec![cell(1, None)]);
+    assert_eq!(
+        diff.changed,
+        vec![ChangedCell {
+            cell: cell(2, Some("New")),
+            old_editor_id: Some("Old".to_string()),
+        }]
+    );
+}
+
+#[test]
+fn identical_plugins_have_an_empty_diff() {
+    let cells = vec![cell(1, Some("A")), cell(2, None)];
+    let diff = diff_cells(cells.clone(), cells);
+    assert!(diff.added.is_empty());
+    assert!(diff.removed.is_empty());
+    assert!(diff.changed.is_empty());
+}
+
+#[test]
+fn parses_hashes_from_the_dumps() {
+    let hash: i64 = -4_096_123_456_789;
+    assert_eq!(parse_hash(&format_radix(hash as u64, 36)).unwrap(), hash);
+    assert!(parse_hash("not a hash!").is_err());
+}
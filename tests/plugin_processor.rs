@@ -0,0 +1,149 @@
+//! Property tests for the database-free half of plugin processing.
+mod common;
+
+use mod_mapper::plugin_processor::{
+    count_new_records, get_local_form_id_and_master, is_patch, process_plugin_buf,
+    process_plugin_json, RecordCounts,
+};
+use proptest::prelude::*;
+
+use common::fixture_path;
+
+fn master_names() -> impl Strategy<Value = Vec<String>> {
+    prop::collection::vec("[a-zA-Z]{1,8}\\.esm", 0..4)
+}
+
+proptest! {
+    #[test]
+    fn local_form_id_drops_master_index(form_id: u32, masters in master_names()) {
+        let masters: Vec<&str> = masters.iter().map(String::as_str).collect();
+        let (local_form_id, _) =
+            get_local_form_id_and_master(form_id, &masters, "plugin.esp").unwrap();
+        prop_assert!(local_form_id >= 0);
+        prop_assert_eq!(local_form_id as u32, form_id & 0xFFFFFF);
+    }
+
+    #[test]
+    fn master_is_resolved_from_master_index(form_id: u32, masters in master_names()) {
+        let masters: Vec<&str> = masters.iter().map(String::as_str).collect();
+        let (_, master) = get_local_form_id_and_master(form_id, &masters, "plugin.esp").unwrap();
+        let master_index = (form_id >> 24) as usize;
+        if master_index < masters.len() {
+            prop_assert_eq!(master, masters[master_index]);
+        } else {
+            prop_assert_eq!(master, "plugin.esp");
+        }
+    }
+
+    #[test]
+    fn process_plugin_buf_does_not_panic_on_arbitrary_bytes(
+        plugin_buf in prop::collection::vec(any::<u8>(), 0..1024)
+    ) {
+        let _ = process_plugin_buf(&plugin_buf, "plugin.esp");
+    }
+
+    #[test]
+    fn count_new_records_does_not_panic_on_arbitrary_bytes(
+        plugin_buf in prop::collection::vec(any::<u8>(), 0..1024),
+        num_masters in 0usize..4,
+    ) {
+        let _ = count_new_records(&plugin_buf, num_masters);
+    }
+
+    #[test]
+    fn process_plugin_buf_does_not_panic_on_truncated_plugin(len in 0usize..512) {
+        let plugin_buf = std::fs::read(fixture_path("fixture.esp")).unwrap();
+        let len = len.min(plugin_buf.len());
+        let _ = process_plugin_buf(&plugin_buf[..len], "fixture.esp");
+    }
+
+    #[test]
+    fn process_plugin_buf_does_not_panic_on_corrupted_plugin(index in 0usize..512, byte: u8) {
+        let mut plugin_buf = std::fs::read(fixture_path("fixture.esp")).unwrap();
+        let index = index % plugin_buf.len();
+        plugin_buf[index] = byte;
+        let _ = process_plugin_buf(&plugin_buf, "fixture.esp");
+    }
+}
+
+#[test]
+fn process_plugin_buf_resolves_fixture_form_ids() {
+    let plugin_buf = std::fs::read(fixture_path("fixture.esp")).unwrap();
+    let plugin = process_plugin_buf(&plugin_buf, "Data/fixture.esp").unwrap();
+    assert_eq!(plugin.file_name, "fixture.esp");
+    assert_eq!(plugin.masters, vec!["Skyrim.esm".to_string()]);
+    assert_eq!(plugin.worlds.len(), 1);
+    assert_eq!(plugin.worlds[0].form_id, 0x3C);
+    assert_eq!(plugin.worlds[0].master, "Skyrim.esm");
+    assert_eq!(plugin.cells.len(), 1);
+    assert_eq!(plugin.cells[0].form_id, 0x800);
+    assert_eq!(plugin.cells[0].master, "fixture.esp");
+    assert_eq!(plugin.cells[0].x, Some(1));
+    assert_eq!(plugin.cells[0].y, Some(2));
+    assert_eq!(
+        plugin.cells[0].world,
+        Some((0x3C, "Skyrim.esm".to_string()))
+    );
+    assert_eq!(
+        plugin.record_counts,
+        RecordCounts {
+            npcs: 1,
+            quests: 1,
+            dialogues: 1,
+        }
+    );
+}
+
+#[test]
+fn process_plugin_buf_is_deterministic() {
+    let plugin_buf = std::fs::read(fixture_path("fixture.esp")).unwrap();
+    assert_eq!(
+        process_plugin_buf(&plugin_buf, "fixture.esp").unwrap(),
+        process_plugin_buf(&plugin_buf, "fixture.esp").unwrap()
+    );
+}
+
+#[test]
+fn process_plugin_json_matches_process_plugin_buf() {
+    let plugin_buf = std::fs::read(fixture_path("fixture.esp")).unwrap();
+    let json_buf =
+        serde_json::to_vec(&skyrim_cell_dump::parse_plugin(&plugin_buf).unwrap()).unwrap();
+    let from_buf = process_plugin_buf(&plugin_buf, "fixture.esp").unwrap();
+    let from_json = process_plugin_json(&json_buf, "fixture.esp").unwrap();
+    assert_eq!(from_json.file_name, from_buf.file_name);
+    assert_eq!(from_json.masters, from_buf.masters);
+    assert_eq!(from_json.worlds, from_buf.worlds);
+    assert_eq!(from_json.cells, from_buf.cells);
+    assert_eq!(from_json.record_counts, RecordCounts::default());
+}
+
+#[test]
+fn is_patch_ignores_base_game_and_creation_club_masters() {
+    let masters = |names: &[&str]| {
+        names
+            .iter()
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>()
+    };
+    assert!(!is_patch(
+        "unofficial skyrim special edition patch.esp",
+        &masters(&[
+            "Skyrim.esm",
+            "Update.esm",
+            "Dawnguard.esm",
+            "ccBGSSSE001-Fish.esm"
+        ])
+    ));
+    assert!(is_patch(
+        "Immersive Armors - Patch.esp",
+        &masters(&["Skyrim.esm", "Hothtrooper44_ArmorCompilation.esp"])
+    ));
+    assert!(is_patch(
+        "FooBarCompat.esp",
+        &masters(&["Skyrim.esm", "Foo.esp", "Bar.esp"])
+    ));
+    assert!(!is_patch(
+        "Foo Addon.esp",
+        &masters(&["Skyrim.esm", "Foo.esp"])
+    ));
+}
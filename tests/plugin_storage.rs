@@ -0,0 +1,117 @@
+//! Tests for placing plugins in storage roots and cold storage and finding them again.
+use chrono::NaiveDate;
+use mod_mapper::commands::tier_plugins::tier_cutoff;
+use mod_mapper::plugin_storage::{
+    choose_root, cold_path, compress, read, relative_path, resolve, Placement, MIN_FREE_SPACE,
+};
+use std::path::Path;
+
+const GIB: u64 = 1024 * 1024 * 1024;
+
+fn roots() -> Vec<(String, u64)> {
+    vec![
+        ("/mnt/a".to_string(), MIN_FREE_SPACE + 10),
+        ("/mnt/b".to_string(), 5 * GIB),
+        ("/mnt/c".to_string(), 5 * GIB),
+        ("/mnt/d".to_string(), 2 * GIB),
+    ]
+}
+
+#[test]
+fn parses_placements() {
+    assert_eq!("fill-first".parse(), Ok(Placement::FillFirst));
+    assert_eq!("balanced".parse(), Ok(Placement::Balanced));
+    assert!("random".parse::<Placement>().is_err());
+}
+
+#[test]
+fn fill_first_uses_the_first_root_with_room() {
+    let roots = roots();
+    assert_eq!(
+        choose_root(&roots, Placement::FillFirst, 10),
+        Some("/mnt/a")
+    );
+    assert_eq!(
+        choose_root(&roots, Placement::FillFirst, 11),
+        Some("/mnt/b")
+    );
+}
+
+#[test]
+fn balanced_uses_the_root_with_the_most_free_space() {
+    let roots = roots();
+    // ties go to the root given first
+    assert_eq!(choose_root(&roots, Placement::Balanced, 10), Some("/mnt/b"));
+}
+
+#[test]
+fn no_root_when_none_have_room() {
+    let roots = roots();
+    assert_eq!(choose_root(&roots, Placement::FillFirst, 5 * GIB), None);
+    assert_eq!(choose_root(&roots, Placement::Balanced, 5 * GIB), None);
+    assert_eq!(choose_root(&[], Placement::Balanced, 0), None);
+}
+
+#[test]
+fn lays_plugins_out_by_game_mod_and_file() {
+    assert_eq!(
+        relative_path("skyrimspecialedition", 123, 456, "Data./Foo.esp"),
+        Path::new("skyrimspecialedition/123/456/Data/Foo.esp")
+    );
+}
+
+#[test]
+fn resolves_plugins_in_their_saved_root() {
+    let root = tempfile::tempdir().unwrap();
+    let relative = relative_path("skyrim", 1, 2, "Foo.esp");
+    std::fs::create_dir_all(root.path().join(&relative).parent().unwrap()).unwrap();
+    std::fs::write(root.path().join(&relative), b"TES4").unwrap();
+
+    let saved_root = root.path().to_str().unwrap();
+    assert_eq!(
+        resolve(Some(saved_root), &relative),
+        Some(root.path().join(&relative))
+    );
+    assert_eq!(
+        resolve(Some(saved_root), &relative_path("skyrim", 1, 2, "Bar.esp")),
+        None
+    );
+}
+
+#[test]
+fn reads_plugins_back_from_cold_storage() {
+    let root = tempfile::tempdir().unwrap();
+    let cold_root = tempfile::tempdir().unwrap();
+    let relative = relative_path("skyrim", 1, 2, "Foo.esp");
+    let path = root.path().join(&relative);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(&path, b"TES4 plugin contents").unwrap();
+
+    let compressed = compress(&path, cold_root.path(), &relative).unwrap();
+    assert_eq!(compressed, cold_path(cold_root.path(), &relative));
+    assert!(compressed.to_str().unwrap().ends_with("Foo.esp.gz"));
+    std::fs::remove_file(&path).unwrap();
+
+    let cold_root = cold_root.path().to_str().unwrap();
+    assert_eq!(
+        read(Some(cold_root), &relative).unwrap(),
+        Some(b"TES4 plugin contents".to_vec())
+    );
+    assert_eq!(read(None, &relative).unwrap(), None);
+}
+
+#[test]
+fn tiers_mods_not_updated_in_years() {
+    let now = NaiveDate::from_ymd_opt(2024, 1, 1)
+        .unwrap()
+        .and_hms_opt(0, 0, 0)
+        .unwrap();
+    assert_eq!(
+        tier_cutoff(now, 3),
+        NaiveDate::from_ymd_opt(2021, 1, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+    );
+    assert_eq!(tier_cutoff(now, 0), now);
+}
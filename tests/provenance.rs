@@ -0,0 +1,32 @@
+//! Tests for adding provenance to dumped documents.
+use mod_mapper::provenance::{self, manifest_path};
+use serde_json::{json, Value};
+use std::path::Path;
+
+#[test]
+fn adds_provenance_fields_to_objects() {
+    let json = provenance::to_json(&json!({ "nexus_mod_id": 1 })).unwrap();
+    let value: Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["nexus_mod_id"], 1);
+    assert!(value["generated_at"].is_string());
+    assert!(value.get("run_id").is_some());
+}
+
+#[test]
+fn leaves_other_values_as_they_are() {
+    assert_eq!(provenance::to_json(&json!([1, 2])).unwrap(), "[1,2]");
+}
+
+#[test]
+fn writes_manifests_next_to_dumps() {
+    assert_eq!(
+        manifest_path(Path::new("mods/games.json")),
+        Path::new("mods/games.manifest.json")
+    );
+    assert_eq!(
+        manifest_path(Path::new(
+            "cells/edits_over_time/cell_edits_2020-01-01.json"
+        )),
+        Path::new("cells/edits_over_time/cell_edits_2020-01-01.manifest.json")
+    );
+}
@@ -0,0 +1,95 @@
+//! Tests for validating GraphQL API responses against the fields we request.
+use mod_mapper::nexus_api::graphql::parse_mods;
+use mod_mapper::nexus_api::schema_drift::{field_drift, metrics, parse_nodes, FieldDrift};
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Node {
+    id: i32,
+    name: String,
+}
+
+#[test]
+fn finds_unknown_and_missing_fields() {
+    assert_eq!(
+        field_drift(
+            &json!({ "id": 1, "name": null, "summary": "new" }),
+            &["id", "name", "author"]
+        ),
+        FieldDrift {
+            unknown: vec!["summary".to_string()],
+            missing: vec!["name".to_string(), "author".to_string()],
+        }
+    );
+    assert_eq!(
+        field_drift(&json!({ "id": 1, "name": "a" }), &["id", "name"]),
+        FieldDrift::default()
+    );
+}
+
+#[test]
+fn skips_nodes_that_do_not_parse() {
+    let before = metrics();
+    let nodes = json!([
+        { "id": 1, "name": "a", "summary": "new" },
+        { "id": 2 },
+        { "id": 3, "name": "c" },
+    ]);
+    let parsed = parse_nodes::<Node>("test", &nodes, &["id", "name"]).unwrap();
+    assert_eq!(
+        parsed.parsed,
+        vec![
+            Node {
+                id: 1,
+                name: "a".to_string()
+            },
+            Node {
+                id: 3,
+                name: "c".to_string()
+            },
+        ]
+    );
+    assert_eq!(parsed.invalid, vec![json!({ "id": 2 })]);
+
+    // other tests may run concurrently, so only check the counters went up
+    let after = metrics();
+    assert!(after.unknown_fields >= before.unknown_fields + 1);
+    assert!(after.missing_fields >= before.missing_fields + 1);
+    assert!(after.invalid_nodes >= before.invalid_nodes + 1);
+}
+
+#[test]
+fn fails_when_nodes_are_not_a_list() {
+    assert!(parse_nodes::<Node>("test", &json!({ "id": 1 }), &["id"]).is_err());
+}
+
+#[test]
+fn keeps_ids_of_mods_that_do_not_parse() {
+    let nodes = json!([
+        {
+            "modId": 1,
+            "adultContent": false,
+            "downloads": 10,
+            "createdAt": "2023-01-01T00:00:00Z",
+            "updatedAt": "2023-01-02T00:00:00Z",
+            "languageName": "English",
+            "game": { "domainName": "skyrim" },
+        },
+        {
+            "modId": 2,
+            "adultContent": "no",
+            "game": { "domainName": "skyrim" },
+        },
+    ]);
+    let mods = parse_mods("test", &nodes).unwrap();
+    assert_eq!(
+        mods.mods
+            .iter()
+            .map(|graphql_mod| graphql_mod.mod_id)
+            .collect::<Vec<_>>(),
+        vec![1]
+    );
+    assert_eq!(mods.mods[0].language_name.as_deref(), Some("English"));
+    assert_eq!(mods.invalid, vec![("skyrim".to_string(), 2)]);
+}
@@ -0,0 +1,28 @@
+//! Tests for splitting the mod search index into shards.
+use mod_mapper::commands::dump_mod_search_index::{shard_key, SearchIndexSharding};
+
+#[test]
+fn parses_sharding() {
+    assert_eq!("letter".parse(), Ok(SearchIndexSharding::Letter));
+    assert_eq!("500".parse(), Ok(SearchIndexSharding::Chunk(500)));
+    assert!("0".parse::<SearchIndexSharding>().is_err());
+    assert!("word".parse::<SearchIndexSharding>().is_err());
+}
+
+#[test]
+fn shards_by_lowercase_first_letter() {
+    let sharding = SearchIndexSharding::Letter;
+    assert_eq!(shard_key(0, "SkyUI", sharding), "s");
+    assert_eq!(shard_key(1, "skse", sharding), "s");
+    assert_eq!(shard_key(2, "3DNPC", sharding), "_");
+    assert_eq!(shard_key(3, "Élan", sharding), "_");
+    assert_eq!(shard_key(4, "", sharding), "_");
+}
+
+#[test]
+fn shards_by_chunk() {
+    let sharding = SearchIndexSharding::Chunk(2);
+    assert_eq!(shard_key(0, "a", sharding), "0");
+    assert_eq!(shard_key(1, "b", sharding), "0");
+    assert_eq!(shard_key(2, "c", sharding), "1");
+}
@@ -0,0 +1,92 @@
+//! Tests for picking the mods whose files are the most out of date for `update_stale_mods`.
+//!
+//! Requires docker to start the postgres container.
+mod common;
+
+use chrono::{NaiveDate, NaiveDateTime};
+use mod_mapper::models::game;
+use mod_mapper::models::game_mod::{self, get_stale, Mod};
+use mod_mapper::nexus_api::{SSE_GAME_ID, SSE_GAME_NAME};
+use testcontainers::clients::Cli;
+
+use common::{connect, postgres_image};
+
+fn day(day: u32) -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2023, 1, day)
+        .unwrap()
+        .and_hms_opt(0, 0, 0)
+        .unwrap()
+}
+
+/// Saves a mod first uploaded on `first_upload_day` and last updated on `last_update_day` whose
+/// files were last processed on `processed_day`, if ever
+async fn insert_mod(
+    pool: &sqlx::Pool<sqlx::Postgres>,
+    game_id: i32,
+    nexus_mod_id: i32,
+    first_upload_day: u32,
+    last_update_day: u32,
+    processed_day: Option<u32>,
+) -> Mod {
+    let db_mod = game_mod::insert(
+        pool,
+        "Stale Mod",
+        nexus_mod_id,
+        "modmapper",
+        1,
+        None,
+        None,
+        None,
+        None,
+        game_id,
+        false,
+        false,
+        day(last_update_day),
+        day(first_upload_day),
+    )
+    .await
+    .unwrap();
+    sqlx::query("UPDATE mods SET last_updated_files_at = $2 WHERE id = $1")
+        .bind(db_mod.id)
+        .bind(processed_day.map(day))
+        .execute(pool)
+        .await
+        .unwrap();
+    db_mod
+}
+
+#[tokio::test]
+async fn picks_the_most_out_of_date_listed_mods_first() {
+    let docker = Cli::default();
+    let node = docker.run(postgres_image());
+    let pool = connect(&node).await;
+    let game_id = game::insert(&pool, SSE_GAME_NAME, SSE_GAME_ID)
+        .await
+        .unwrap()
+        .id;
+    let up_to_date = insert_mod(&pool, game_id, 1, 1, 10, Some(11)).await;
+    let processed_before_update = insert_mod(&pool, game_id, 2, 1, 20, Some(10)).await;
+    let never_processed = insert_mod(&pool, game_id, 3, 1, 30, None).await;
+    let never_processed_new = insert_mod(&pool, game_id, 4, 25, 28, None).await;
+    let delisted = insert_mod(&pool, game_id, 5, 1, 30, None).await;
+    game_mod::batched_update_delisted(&pool, &[delisted.id])
+        .await
+        .unwrap();
+
+    let ids = |mods: Vec<Mod>| mods.into_iter().map(|m| m.id).collect::<Vec<i32>>();
+    // out of date for 29, 10, and 3 days. Never processed mods are out of date since their first
+    // upload, however long ago they were saved.
+    assert_eq!(
+        ids(get_stale(&pool, game_id, 10).await.unwrap()),
+        vec![
+            never_processed.id,
+            processed_before_update.id,
+            never_processed_new.id
+        ]
+    );
+    assert_eq!(
+        ids(get_stale(&pool, game_id, 1).await.unwrap()),
+        vec![never_processed.id]
+    );
+    assert!(!ids(get_stale(&pool, game_id, 10).await.unwrap()).contains(&up_to_date.id));
+}
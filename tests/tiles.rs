@@ -0,0 +1,78 @@
+//! Tests for the UESP map tile urls and paths shared by download_tiles and the serve tile proxy.
+use mod_mapper::commands::download_tiles::{
+    is_valid_jpeg, is_valid_tile, parse_tile_path, tile_path, tile_url, MissingTiles, TileCache,
+    TileState, JPEG_END_SEARCH_LEN,
+};
+use std::time::Duration;
+use tokio::time::Instant;
+
+#[test]
+fn parses_tile_paths() {
+    assert_eq!(parse_tile_path("10/0/1.jpg"), Some((10, 0, 1)));
+    assert_eq!(parse_tile_path("17/255/255.jpg"), Some((17, 255, 255)));
+    assert_eq!(parse_tile_path("10/0/1.png"), None);
+    assert_eq!(parse_tile_path("10/0"), None);
+    assert_eq!(parse_tile_path("10/0/1.jpg/2"), None);
+    assert_eq!(parse_tile_path("../0/1.jpg"), None);
+}
+
+#[test]
+fn rejects_tiles_outside_the_map() {
+    assert!(is_valid_tile(10, 1, 1));
+    assert!(!is_valid_tile(10, 2, 0));
+    assert!(!is_valid_tile(9, 0, 0));
+    assert!(!is_valid_tile(18, 0, 0));
+}
+
+#[test]
+fn builds_uesp_tile_urls() {
+    assert_eq!(
+        tile_url(12, 3, 4),
+        "https://maps.uesp.net/srmap/color/zoom12/skyrim-3-4-12.jpg"
+    );
+}
+
+#[test]
+fn only_whole_jpegs_are_valid() {
+    assert!(is_valid_jpeg(&[0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0xFF, 0xD9]));
+    assert!(!is_valid_jpeg(&[]));
+    assert!(!is_valid_jpeg(&[0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10]));
+    assert!(!is_valid_jpeg(b"<html>not found</html>"));
+}
+
+#[test]
+fn jpegs_may_have_trailing_bytes_after_the_end_marker() {
+    let mut padded = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0xFF, 0xD9];
+    padded.extend(&[0x00; 16]);
+    assert!(is_valid_jpeg(&padded));
+
+    let mut end_marker_too_early = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0xFF, 0xD9];
+    end_marker_too_early.extend(vec![0x10; JPEG_END_SEARCH_LEN]);
+    assert!(!is_valid_jpeg(&end_marker_too_early));
+}
+
+#[tokio::test]
+async fn checks_saved_tiles_without_fetching() {
+    let dir = tempfile::tempdir().unwrap();
+    let valid = tile_path(dir.path(), 10, 0, 0);
+    std::fs::create_dir_all(valid.parent().unwrap()).unwrap();
+    std::fs::write(&valid, [0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0xFF, 0xD9]).unwrap();
+    std::fs::write(tile_path(dir.path(), 10, 0, 1), b"").unwrap();
+
+    let cache = TileCache::new(dir.path()).unwrap();
+    assert_eq!(cache.check(10, 0, 0).await.unwrap(), TileState::Valid);
+    assert_eq!(cache.check(10, 0, 1).await.unwrap(), TileState::Corrupt);
+    assert_eq!(cache.check(10, 1, 0).await.unwrap(), TileState::Missing);
+}
+
+#[test]
+fn remembers_missing_tiles_until_they_expire() {
+    let missing = MissingTiles::new(Duration::from_secs(60));
+    let now = Instant::now();
+    missing.insert((10, 0, 0), now);
+    assert!(missing.contains((10, 0, 0), now + Duration::from_secs(59)));
+    assert!(!missing.contains((10, 0, 1), now));
+    assert!(!missing.contains((10, 0, 0), now + Duration::from_secs(60)));
+    // expired tiles are forgotten
+    assert!(!missing.contains((10, 0, 0), now));
+}